@@ -0,0 +1,75 @@
+//! Compression benchmark over a realistic print job
+//!
+//! Run with: cargo bench --bench compress
+
+use ptouch::printable_image::{compress_tiff_group4, compress_tiff_group4_into};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+// 36mm tape at 360DPI: 560 pins = 70 bytes per raster line
+const BYTES_PER_LINE: usize = 70;
+
+// About 1m of tape at 360DPI
+const LINES: usize = 14_000;
+
+/// Build raster lines resembling a text label: blank margins,
+/// solid strokes and noisy glyph edges.
+fn realistic_job() -> Vec<Vec<u8>> {
+    let mut state: u32 = 0x9e37_79b9;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+
+    (0..LINES)
+        .map(|x| {
+            (0..BYTES_PER_LINE)
+                .map(|i| match (x / 40 % 4, i) {
+                    (_, 0..10) | (_, 60..) => 0x00,
+                    (0, _) => 0x00,
+                    (1, _) => 0xFF,
+                    _ => next() as u8,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn measure(name: &str, mut f: impl FnMut()) -> Duration {
+    const ITERATIONS: u32 = 20;
+    f(); // warm up
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let elapsed = start.elapsed() / ITERATIONS;
+    println!("{:<32} {:>10.3?} per job", name, elapsed);
+    elapsed
+}
+
+fn main() {
+    let job = realistic_job();
+    // Worst case for literal detection: no two adjacent bytes are equal
+    let pathological: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+
+    measure("compress_tiff_group4", || {
+        for line in &job {
+            black_box(compress_tiff_group4(black_box(line)).unwrap());
+        }
+    });
+
+    measure("compress_tiff_group4_into", || {
+        let mut buf = Vec::new();
+        for line in &job {
+            buf.clear();
+            compress_tiff_group4_into(black_box(line), &mut buf);
+            black_box(&buf);
+        }
+    });
+
+    measure("pathological 64KiB literal", || {
+        black_box(compress_tiff_group4(black_box(&pathological)).unwrap());
+    });
+}
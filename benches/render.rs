@@ -0,0 +1,117 @@
+//! Layout and rendering benchmark over representative labels
+//!
+//! Run with: cargo bench --bench render
+//! Without `--bench` (e.g. cargo test --benches), each case runs once as a smoke test.
+
+use fontdb::Database;
+use ptouch::element::{TextOptions, calculate_logical_bbox, calculate_pixel_bbox};
+use ptouch::layout::{LayoutOptions, parse_layout_script};
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Representative layout scripts: text, QR codes with text, and layers
+const SCRIPTS: &[(&str, &str)] = &[
+    ("single line", "Hello"),
+    ("two lines", "Happy Birthday"),
+    (
+        "qr + text",
+        "Long-Title-On-Top [ qrc:http://example.com + nom@example.com ]",
+    ),
+    (
+        "qr grid",
+        "[ qrc:PORT-01 qrc:PORT-02 ] + [ qrc:PORT-03 qrc:PORT-04 ] + [ qrc:PORT-05 qrc:PORT-06 ]",
+    ),
+    (
+        "overlay",
+        "[ gap:10 + Left ] / [ Right + gap:10 ] / [ box:2x40 + gap:5 + Right ]",
+    ),
+];
+
+fn measure(name: &str, iterations: u32, mut f: impl FnMut()) -> Duration {
+    f(); // warm up (and smoke test)
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let elapsed = start.elapsed() / iterations;
+    println!("{:<32} {:>10.3?} per iteration", name, elapsed);
+    elapsed
+}
+
+/// Any installed font, preferring the default one of the CLI
+fn text_options(fontdb: &Arc<Database>) -> TextOptions {
+    let families: Vec<String> = fontdb
+        .faces()
+        .flat_map(|face| face.families.iter().map(|(name, _)| name.clone()))
+        .collect();
+    let font_name = ["Noto Sans CJK JP", "Noto Sans JP", "DejaVu Sans"]
+        .iter()
+        .map(|name| name.to_string())
+        .find(|name| families.contains(name))
+        .or_else(|| families.first().cloned())
+        .expect("No fonts installed");
+
+    TextOptions {
+        font_name,
+        font_weight: "normal".to_string(),
+        font_size: 64,
+        line_height: 64,
+        fontdb: fontdb.clone(),
+    }
+}
+
+fn main() {
+    let iterations = if std::env::args().any(|arg| arg == "--bench") {
+        20
+    } else {
+        1
+    };
+
+    let mut fontdb = Database::new();
+    fontdb.load_system_fonts();
+    let fontdb = Arc::new(fontdb);
+    let options = LayoutOptions {
+        text: text_options(&fontdb),
+        ..LayoutOptions::new(fontdb.clone())
+    };
+
+    for (name, script) in SCRIPTS {
+        let script: Vec<String> = script.split_whitespace().map(String::from).collect();
+
+        measure(
+            &format!("parse_layout_script: {}", name),
+            iterations,
+            || {
+                let element = parse_layout_script(&script, &options).unwrap();
+                black_box(element);
+            },
+        );
+
+        // Parsing is lazy; bbox is where the text is measured
+        let element = parse_layout_script(&script, &options).unwrap();
+        measure(&format!("bounding_box: {}", name), iterations, || {
+            let bbox = element.bounding_box().unwrap();
+            assert!(!bbox.is_empty());
+            black_box(bbox);
+        });
+        measure(&format!("render: {}", name), iterations, || {
+            black_box(element.render().unwrap());
+        });
+    }
+
+    let svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="2000" height="200"><text x="0" y="100" font-family="{}" font-size="64">Happy Birthday 0123456789</text></svg>"#,
+        options.text.font_name
+    );
+    measure("calculate_pixel_bbox", iterations, || {
+        let bbox = calculate_pixel_bbox(black_box(&svg), &fontdb).unwrap();
+        assert!(bbox.width > 0.0 && bbox.height > 0.0);
+        black_box(bbox);
+    });
+    measure("calculate_logical_bbox", iterations, || {
+        let bbox = calculate_logical_bbox(black_box(&svg), &fontdb).unwrap();
+        assert!(bbox.width > 0.0 && bbox.height > 0.0);
+        black_box(bbox);
+    });
+}
@@ -0,0 +1,45 @@
+//! Parse a layout script, double the font size of its first text and
+//! render it to PNG
+//!
+//! Usage: cargo run --example edit_layout -- OUTPUT.png SCRIPT...
+
+use ptouch::prelude::*;
+
+/// First Text in the tree, depth first
+fn find_text(element: &mut dyn Element) -> Option<&mut Text> {
+    if element.downcast_ref::<Text>().is_some() {
+        return element.downcast_mut::<Text>();
+    }
+    element
+        .children_mut()
+        .into_iter()
+        .find_map(|child| find_text(child.as_mut()))
+}
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [output, script @ ..] = args.as_slice() else {
+        return Err("Usage: edit_layout OUTPUT.png SCRIPT...".into());
+    };
+
+    let fontdb = load_fontdb_with_paths(&[])?;
+    let mut element = parse_layout_script(script, &LayoutOptions::new(fontdb.clone()))?;
+    let before = element.bounding_box()?;
+
+    let text = find_text(element.as_mut()).ok_or("No text in the script")?;
+    let options = text.options_mut();
+    options.font_size *= 2;
+    options.line_height *= 2;
+
+    let after = element.bounding_box()?;
+    println!(
+        "{}x{} -> {}x{}",
+        before.width, before.height, after.width, after.height
+    );
+
+    let options = LabelOptions::new(fontdb).with_tape_spec(TapeSpec::new(Tape::TZe24H));
+    Label::from_element(element, options).save_png(output)?;
+
+    println!("Saved {}", output);
+    Ok(())
+}
@@ -0,0 +1,30 @@
+//! Fake network printer that saves received print jobs
+//!
+//! Usage: cargo run --example fake_printer -- [ADDRESS]
+//!
+//! Listens on ADDRESS [default: 127.0.0.1:9100] and saves each job to
+//! job-N.bin in the current directory. SNMP is not answered, so print
+//! with an assumed tape:
+//!
+//!   ptouch print -H 127.0.0.1 --skip-status -t 12 label.png
+
+use std::io::Read;
+use std::net::TcpListener;
+
+fn main() -> ptouch::Result<()> {
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:9100".to_string());
+    let listener = TcpListener::bind(&address)?;
+    println!("Listening on {}", address);
+
+    for (n, stream) in listener.incoming().enumerate() {
+        let mut job = Vec::new();
+        stream?.read_to_end(&mut job)?;
+
+        let path = format!("job-{}.bin", n + 1);
+        std::fs::write(&path, &job)?;
+        println!("Saved {} bytes to {}", job.len(), path);
+    }
+    Ok(())
+}
@@ -0,0 +1,36 @@
+//! Print a label written in layout script to the tape loaded in a printer
+//!
+//! Usage: cargo run --example print_label -- HOST SCRIPT...
+//!
+//! HOST is the same as `ptouch print -H`: hostname, IP address or vid:pid.
+
+use ptouch::prelude::*;
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [host, script @ ..] = args.as_slice() else {
+        return Err("Usage: print_label HOST SCRIPT...".into());
+    };
+
+    let mut printer = Printer::new(from_host(host)?);
+
+    // Render for the loaded tape
+    let status = printer.get_status()?;
+    if status.has_errors() {
+        return Err("Printer has errors".into());
+    }
+    let tape_spec = TapeSpec::from_width_mm_and_dpi(status.media_width_mm(), status.printer_dpi())
+        .ok_or("Unsupported tape")?;
+
+    let fontdb = load_fontdb_with_paths(&[])?;
+    let element = parse_layout_script(script, &LayoutOptions::new(fontdb.clone()))?;
+    let options = LabelOptions::new(fontdb)
+        .with_tape_spec(tape_spec.clone())
+        .with_auto_scale(true);
+    let png_data = Label::from_element(element, options).to_png()?;
+
+    let printable = PrintableImage::from_png_data(png_data, tape_spec)?;
+    let report = printer.print(&printable, &PrintOptions::default())?;
+    println!("Sent {} raster lines", report.raster_lines);
+    Ok(())
+}
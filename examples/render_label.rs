@@ -0,0 +1,30 @@
+//! Render text with a QR code to PNG
+//!
+//! Usage: cargo run --example render_label -- OUTPUT.png URL TEXT...
+
+use ptouch::prelude::*;
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [output, url, texts @ ..] = args.as_slice() else {
+        return Err("Usage: render_label OUTPUT.png URL TEXT...".into());
+    };
+
+    let fontdb = load_fontdb_with_paths(&[])?;
+
+    // Same as "qrc:URL + TEXT..." in layout script
+    let qr_code = QrCode::with_options(url.clone(), &QrOptions::default())?;
+    let text = Text::new(texts, TextOptions::new(fontdb.clone()))?;
+    let row = Row::new(
+        vec![Box::new(qr_code), Box::new(text)],
+        RowOptions::default(),
+    );
+
+    let options = LabelOptions::new(fontdb)
+        .with_tape_spec(TapeSpec::new(Tape::TZe24H))
+        .with_placement(Placement::Center);
+    Label::from_element(Box::new(row), options).save_png(output)?;
+
+    println!("Saved {}", output);
+    Ok(())
+}
@@ -0,0 +1,486 @@
+//! Minimal Aztec code encoder
+//!
+//! Ported from the ZXing Aztec encoder:
+//! https://github.com/zxing/zxing/tree/master/core/src/main/java/com/google/zxing/aztec/encoder
+//!
+//! Data is always encoded in Binary Shift mode, which is valid for any
+//! bytes but takes some more room than the mode switching of ZXing.
+
+use crate::Result;
+
+/// Default share of error correction codewords in percent, same as ZXing
+pub const DEFAULT_EC_PERCENT: u8 = 33;
+
+/// Word size of the codewords for each number of layers (index)
+const WORD_SIZE: [usize; 33] = [
+    4, 6, 6, 8, 8, 8, 8, 8, 8, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 12, 12, 12,
+    12, 12, 12, 12, 12, 12, 12,
+];
+
+/// Encoded Aztec symbol
+#[derive(Clone, Debug)]
+pub struct AztecCode {
+    compact: bool,
+    layers: usize,
+    /// Number of modules on a side
+    size: usize,
+    /// Modules row by row, true for dark
+    modules: Vec<bool>,
+}
+
+impl AztecCode {
+    /// Encode `data` with `ec_percent` of the symbol for error correction
+    ///
+    /// Picks the smallest symbol that fits, or fails if the data does not
+    /// fit even the largest one (32 layers).
+    pub fn encode(data: &[u8], ec_percent: u8) -> Result<Self> {
+        if !(1..=95).contains(&ec_percent) {
+            return Err(format!("Aztec error correction must be 1 to 95%: {}%", ec_percent).into());
+        }
+        if data.is_empty() {
+            return Err("Empty data in Aztec code".into());
+        }
+        let bits = encode_binary_shift(data);
+        let ecc_bits = bits.len() * ec_percent as usize / 100 + 11;
+
+        let mut word_size = 0;
+        let mut stuffed = Vec::new();
+        let mut chosen = None;
+
+        for i in 0..=32 {
+            let compact = i <= 3;
+            let layers = if compact { i + 1 } else { i };
+            let total_bits = total_bits_in_layer(layers, compact);
+            if word_size != WORD_SIZE[layers] {
+                word_size = WORD_SIZE[layers];
+                stuffed = stuff_bits(&bits, word_size);
+            }
+            let usable_bits = total_bits - total_bits % word_size;
+            // Compact symbols only count up to 64 data words in the mode message
+            if compact && stuffed.len() > word_size * 64 {
+                continue;
+            }
+            if stuffed.len() + ecc_bits <= usable_bits {
+                chosen = Some((compact, layers, total_bits));
+                break;
+            }
+        }
+
+        let Some((compact, layers, total_bits)) = chosen else {
+            return Err(format!(
+                "Data too large for Aztec code: {} bytes with {}% error correction",
+                data.len(),
+                ec_percent
+            )
+            .into());
+        };
+
+        let data_words = stuffed.len() / word_size;
+        let message_bits = generate_check_words(&stuffed, total_bits, word_size);
+        let mode_message = generate_mode_message(compact, layers, data_words);
+
+        Ok(Self::draw(compact, layers, &message_bits, &mode_message))
+    }
+
+    /// Number of modules on a side
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Number of data layers around the bullseye
+    pub fn layers(&self) -> usize {
+        self.layers
+    }
+
+    /// Whether the symbol is a compact one (up to 4 layers, no reference grid)
+    pub fn is_compact(&self) -> bool {
+        self.compact
+    }
+
+    /// Whether the module at column `x` and row `y` is dark
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    fn draw(compact: bool, layers: usize, message_bits: &[bool], mode_message: &[bool]) -> Self {
+        let base_size = if compact { 11 } else { 14 } + layers * 4;
+        let mut alignment_map = vec![0; base_size];
+        let size;
+
+        if compact {
+            size = base_size;
+            for (i, offset) in alignment_map.iter_mut().enumerate() {
+                *offset = i;
+            }
+        } else {
+            // Skip the lines of the reference grid every 16 modules from the center
+            size = base_size + 1 + 2 * ((base_size / 2 - 1) / 15);
+            let orig_center = base_size / 2;
+            let center = size / 2;
+            for i in 0..orig_center {
+                let new_offset = i + i / 15;
+                alignment_map[orig_center - i - 1] = center - new_offset - 1;
+                alignment_map[orig_center + i] = center + new_offset + 1;
+            }
+        }
+
+        let mut symbol = AztecCode {
+            compact,
+            layers,
+            size,
+            modules: vec![false; size * size],
+        };
+        let bit = |index: usize| message_bits.get(index).copied().unwrap_or(false);
+
+        // Data layers spiral inwards, two modules thick, starting at the top-left
+        let mut row_offset = 0;
+        for i in 0..layers {
+            let row_size = (layers - i) * 4 + if compact { 9 } else { 12 };
+            let far = base_size - 1 - i * 2;
+            for j in 0..row_size {
+                let column_offset = j * 2;
+                for k in 0..2 {
+                    let index = row_offset + column_offset + k;
+                    if bit(index) {
+                        symbol.set(alignment_map[i * 2 + k], alignment_map[i * 2 + j]);
+                    }
+                    if bit(index + row_size * 2) {
+                        symbol.set(alignment_map[i * 2 + j], alignment_map[far - k]);
+                    }
+                    if bit(index + row_size * 4) {
+                        symbol.set(alignment_map[far - k], alignment_map[far - j]);
+                    }
+                    if bit(index + row_size * 6) {
+                        symbol.set(alignment_map[far - j], alignment_map[i * 2 + k]);
+                    }
+                }
+            }
+            row_offset += row_size * 8;
+        }
+
+        symbol.draw_mode_message(mode_message);
+
+        let center = size / 2;
+        if compact {
+            symbol.draw_bullseye(center, 5);
+        } else {
+            symbol.draw_bullseye(center, 7);
+            let mut i = 0;
+            let mut j = 0;
+            while i < base_size / 2 - 1 {
+                for k in ((center & 1)..size).step_by(2) {
+                    symbol.set(center - j, k);
+                    symbol.set(center + j, k);
+                    symbol.set(k, center - j);
+                    symbol.set(k, center + j);
+                }
+                i += 15;
+                j += 16;
+            }
+        }
+
+        symbol
+    }
+
+    fn set(&mut self, x: usize, y: usize) {
+        self.modules[y * self.size + x] = true;
+    }
+
+    fn draw_bullseye(&mut self, center: usize, size: usize) {
+        for i in (0..size).step_by(2) {
+            for j in (center - i)..=(center + i) {
+                self.set(j, center - i);
+                self.set(j, center + i);
+                self.set(center - i, j);
+                self.set(center + i, j);
+            }
+        }
+        // Orientation marks at the corners of the mode message ring
+        self.set(center - size, center - size);
+        self.set(center - size + 1, center - size);
+        self.set(center - size, center - size + 1);
+        self.set(center + size, center - size);
+        self.set(center + size, center - size + 1);
+        self.set(center + size, center + size - 1);
+    }
+
+    fn draw_mode_message(&mut self, mode_message: &[bool]) {
+        let center = self.size / 2;
+        if self.compact {
+            for i in 0..7 {
+                let offset = center - 3 + i;
+                if mode_message[i] {
+                    self.set(offset, center - 5);
+                }
+                if mode_message[i + 7] {
+                    self.set(center + 5, offset);
+                }
+                if mode_message[20 - i] {
+                    self.set(offset, center + 5);
+                }
+                if mode_message[27 - i] {
+                    self.set(center - 5, offset);
+                }
+            }
+        } else {
+            for i in 0..10 {
+                let offset = center - 5 + i + i / 5;
+                if mode_message[i] {
+                    self.set(offset, center - 7);
+                }
+                if mode_message[i + 10] {
+                    self.set(center + 7, offset);
+                }
+                if mode_message[29 - i] {
+                    self.set(offset, center + 7);
+                }
+                if mode_message[39 - i] {
+                    self.set(center - 7, offset);
+                }
+            }
+        }
+    }
+}
+
+fn total_bits_in_layer(layers: usize, compact: bool) -> usize {
+    (if compact { 88 } else { 112 } + 16 * layers) * layers
+}
+
+fn append_bits(bits: &mut Vec<bool>, value: usize, count: usize) {
+    for i in (0..count).rev() {
+        bits.push((value >> i) & 1 == 1);
+    }
+}
+
+/// Encode all of `data` with Binary Shift from the initial Upper mode
+fn encode_binary_shift(data: &[u8]) -> Vec<bool> {
+    const BINARY_SHIFT: usize = 31;
+    let mut bits = Vec::new();
+
+    // One shift covers up to 2047 + 31 bytes
+    for chunk in data.chunks(2047 + 31) {
+        append_bits(&mut bits, BINARY_SHIFT, 5);
+        if chunk.len() <= 31 {
+            append_bits(&mut bits, chunk.len(), 5);
+        } else {
+            append_bits(&mut bits, 0, 5);
+            append_bits(&mut bits, chunk.len() - 31, 11);
+        }
+        for &byte in chunk {
+            append_bits(&mut bits, byte as usize, 8);
+        }
+    }
+    bits
+}
+
+/// Split `bits` into words, avoiding words of all 0s or all 1s
+///
+/// A word with all bits but the last equal gets the opposite last bit and
+/// the original last bit moves on to the next word. The last word is
+/// padded with 1s.
+fn stuff_bits(bits: &[bool], word_size: usize) -> Vec<bool> {
+    let mut out = Vec::new();
+    let mask = (1 << word_size) - 2;
+    let mut i = 0;
+
+    while i < bits.len() {
+        let mut word = 0;
+        for j in 0..word_size {
+            if bits.get(i + j).copied().unwrap_or(true) {
+                word |= 1 << (word_size - 1 - j);
+            }
+        }
+        if word & mask == mask {
+            append_bits(&mut out, word & mask, word_size);
+            i += word_size - 1;
+        } else if word & mask == 0 {
+            append_bits(&mut out, word | 1, word_size);
+            i += word_size - 1;
+        } else {
+            append_bits(&mut out, word, word_size);
+            i += word_size;
+        }
+    }
+    out
+}
+
+/// Append Reed-Solomon check words to `bits` to fill `total_bits`
+///
+/// The result starts with zero bits for the remainder of `total_bits`
+/// that does not make up a whole word.
+fn generate_check_words(bits: &[bool], total_bits: usize, word_size: usize) -> Vec<bool> {
+    let total_words = total_bits / word_size;
+    let words: Vec<usize> = bits
+        .chunks(word_size)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .fold(0, |word, &bit| (word << 1) | bit as usize)
+        })
+        .collect();
+    let field = GaloisField::new(word_size);
+    let check_words = field.reed_solomon(&words, total_words - words.len());
+
+    let mut out = vec![false; total_bits % word_size];
+    for word in words.iter().chain(check_words.iter()) {
+        append_bits(&mut out, *word, word_size);
+    }
+    out
+}
+
+/// Number of layers and data words, protected by check words
+fn generate_mode_message(compact: bool, layers: usize, data_words: usize) -> Vec<bool> {
+    let mut bits = Vec::new();
+    if compact {
+        append_bits(&mut bits, layers - 1, 2);
+        append_bits(&mut bits, data_words - 1, 6);
+        generate_check_words(&bits, 28, 4)
+    } else {
+        append_bits(&mut bits, layers - 1, 5);
+        append_bits(&mut bits, data_words - 1, 11);
+        generate_check_words(&bits, 40, 4)
+    }
+}
+
+/// GF(2^m) used for the codewords of `m` bits
+struct GaloisField {
+    exp: Vec<usize>,
+    log: Vec<usize>,
+}
+
+impl GaloisField {
+    fn new(word_size: usize) -> Self {
+        let primitive = match word_size {
+            4 => 0x13,
+            6 => 0x43,
+            8 => 0x12d,
+            10 => 0x409,
+            12 => 0x1069,
+            _ => unreachable!("no Aztec codewords of {} bits", word_size),
+        };
+        let size = 1 << word_size;
+        let mut exp = vec![0; size];
+        let mut log = vec![0; size];
+        let mut x = 1;
+        for (i, value) in exp.iter_mut().enumerate() {
+            *value = x;
+            if i < size - 1 {
+                log[x] = i;
+            }
+            x <<= 1;
+            if x >= size {
+                x ^= primitive;
+            }
+        }
+        GaloisField { exp, log }
+    }
+
+    fn mul(&self, a: usize, b: usize) -> usize {
+        if a == 0 || b == 0 {
+            0
+        } else {
+            self.exp[(self.log[a] + self.log[b]) % (self.exp.len() - 1)]
+        }
+    }
+
+    /// Check words of `data` with the generator roots a^1..a^count
+    fn reed_solomon(&self, data: &[usize], count: usize) -> Vec<usize> {
+        // Generator polynomial, highest degree first
+        let mut generator = vec![1];
+        for d in 1..=count {
+            let root = self.exp[d % (self.exp.len() - 1)];
+            let mut next = vec![0; generator.len() + 1];
+            for (i, &coef) in generator.iter().enumerate() {
+                next[i] ^= coef;
+                next[i + 1] ^= self.mul(coef, root);
+            }
+            generator = next;
+        }
+
+        // Remainder of data * x^count divided by the generator
+        let mut remainder = vec![0; count];
+        for &word in data {
+            let factor = word ^ remainder.first().copied().unwrap_or(0);
+            remainder.rotate_left(1.min(count));
+            if let Some(last) = remainder.last_mut() {
+                *last = 0;
+            }
+            for (r, &g) in remainder.iter_mut().zip(&generator[1..]) {
+                *r ^= self.mul(g, factor);
+            }
+        }
+        remainder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evaluate the codeword polynomial (highest degree first) at a^i
+    fn syndrome(field: &GaloisField, words: &[usize], i: usize) -> usize {
+        let x = field.exp[i];
+        words.iter().fold(0, |acc, &w| field.mul(acc, x) ^ w)
+    }
+
+    #[test]
+    fn test_check_words_have_zero_syndromes() {
+        for word_size in [4, 6, 8, 10, 12] {
+            let field = GaloisField::new(word_size);
+            let data: Vec<usize> = (1..=10).map(|i| (i * 37) % (1 << word_size)).collect();
+            let check = field.reed_solomon(&data, 7);
+            let words: Vec<usize> = data.iter().chain(check.iter()).copied().collect();
+            for i in 1..=7 {
+                assert_eq!(syndrome(&field, &words, i), 0, "word size {}", word_size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bullseye() {
+        // Rings around the center alternate dark and light, the mode message
+        // ring outside them has the orientation marks
+        for (data, compact, rings) in [("HELLO", true, 5), (&*"x".repeat(200), false, 7)] {
+            let symbol = AztecCode::encode(data.as_bytes(), DEFAULT_EC_PERCENT).unwrap();
+            assert_eq!(symbol.is_compact(), compact);
+            let c = symbol.size() / 2;
+            for ring in 0..rings {
+                let dark = ring % 2 == 0;
+                assert_eq!(symbol.is_dark(c + ring, c), dark);
+                assert_eq!(symbol.is_dark(c, c - ring), dark);
+                assert_eq!(symbol.is_dark(c - ring, c + ring), dark);
+            }
+            assert!(symbol.is_dark(c - rings, c - rings));
+            assert!(symbol.is_dark(c - rings + 1, c - rings));
+            assert!(symbol.is_dark(c + rings, c + rings - 1));
+        }
+    }
+
+    #[test]
+    fn test_stuff_bits() {
+        // 6 bits of 0 become 000001, then the moved bit starts the next word
+        let bits = [false; 6];
+        let stuffed = stuff_bits(&bits, 6);
+        let words: Vec<u8> = stuffed
+            .chunks(6)
+            .map(|c| c.iter().fold(0, |w, &b| (w << 1) | b as u8))
+            .collect();
+        assert_eq!(words, vec![0b000001, 0b011111]);
+    }
+
+    #[test]
+    fn test_compact_mode_message() {
+        // 1 layer, 9 data words: 00 001000 and 5 check words of 4 bits
+        let message = generate_mode_message(true, 1, 9);
+        assert_eq!(message.len(), 28);
+        let field = GaloisField::new(4);
+        let words: Vec<usize> = message
+            .chunks(4)
+            .map(|c| c.iter().fold(0, |w, &b| (w << 1) | b as usize))
+            .collect();
+        assert_eq!(words[..2], [0b0000, 0b1000]);
+        for i in 1..=5 {
+            assert_eq!(syndrome(&field, &words, i), 0);
+        }
+    }
+}
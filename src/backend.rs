@@ -1,14 +1,37 @@
 use crate::Result;
 use crate::raster_command::RasterCommand;
-use crate::status::Status;
+use crate::status::{STATUS_HEADER, STATUS_SIZE, Status};
 use snmp2::{SyncSession, Value};
+use std::fs::File;
 use std::io::Write;
 use std::net::TcpStream;
+use std::path::Path;
 use std::time::Duration;
 
 pub trait Backend {
     fn send_command(&mut self, data: &[u8]) -> Result<()>;
     fn get_status(&mut self) -> Result<Status>;
+
+    /// Get ready to send a job, before its first command
+    ///
+    /// Backends that keep a connection open check it here once per job.
+    fn begin_job(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Check that the device is a P-Touch
+    ///
+    /// Backends that can only talk to a P-Touch (or cannot tell) accept any device.
+    fn verify_device(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Human-readable status of the printer, e.g. its panel message
+    ///
+    /// None if the backend has no such text.
+    fn status_text(&mut self) -> Option<String> {
+        None
+    }
 }
 
 impl Backend for Box<dyn Backend> {
@@ -19,15 +42,173 @@ impl Backend for Box<dyn Backend> {
     fn get_status(&mut self) -> Result<Status> {
         (**self).get_status()
     }
+
+    fn begin_job(&mut self) -> Result<()> {
+        (**self).begin_job()
+    }
+
+    fn verify_device(&mut self) -> Result<()> {
+        (**self).verify_device()
+    }
+
+    fn status_text(&mut self) -> Option<String> {
+        (**self).status_text()
+    }
+}
+
+/// SNMP sysDescr (e.g. "Brother NC-8300w, Firmware Ver.1.05 ...")
+pub const OID_SYS_DESCR: &str = "1.3.6.1.2.1.1.1.0";
+
+/// Brother IEEE 1284 device ID (e.g. "MFG:Brother;CMD:PT-CBP;MDL:PT-P900W;CLS:PRINTER;")
+pub const OID_BROTHER_DEVICE_ID: &str = "1.3.6.1.4.1.2435.2.3.9.1.1.7.0";
+
+/// Status packet of Brother P-Touch printers
+pub const OID_BROTHER_STATUS: &str = "1.3.6.1.4.1.2435.3.3.9.1.6.1.0";
+
+/// Printer-MIB prtConsoleDisplayBufferText of the first panel line
+/// (e.g. "READY" or "COVER OPEN")
+pub const OID_CONSOLE_DISPLAY: &str = "1.3.6.1.2.1.43.16.5.1.2.1.1";
+
+/// Text of an SNMP status string without padding
+///
+/// Agents pad the text with spaces or NULs; None if nothing remains.
+pub fn parse_status_text(data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data);
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Source of SNMP values for [`NetworkBackend`]
+///
+/// Printers answer on UDP port 161, which tests cannot bind without
+/// root; they can give canned values instead.
+pub trait SnmpSource {
+    /// Get an OctetString value
+    fn get(&self, oid: &str) -> Result<Vec<u8>>;
+}
+
+/// SNMP v2c agent of a network printer
+pub struct SnmpAgent {
+    address: String,
+}
+
+impl SnmpAgent {
+    /// Agent at `address` (host:port)
+    pub fn new(address: &str) -> Self {
+        SnmpAgent {
+            address: address.to_string(),
+        }
+    }
+}
+
+impl SnmpSource for SnmpAgent {
+    fn get(&self, oid: &str) -> Result<Vec<u8>> {
+        let oid = oid.parse().map_err(|e| format!("Invalid OID: {:?}", e))?;
+        let mut session = SyncSession::new_v2c(&self.address, b"public", None, 0)?;
+
+        let mut response = session.get(&oid)?;
+
+        // Get the first (and should be only) varbind from the response
+        match response.varbinds.next() {
+            Some((_oid, Value::OctetString(data))) => Ok(data.to_vec()),
+            Some(_) => Err("Invalid SNMP response type: expected OctetString".into()),
+            None => Err("No SNMP response received".into()),
+        }
+    }
+}
+
+/// Identification of a network device by SNMP
+#[derive(Clone, Debug, Default)]
+pub struct DeviceInfo {
+    /// SNMP sysDescr
+    pub description: Option<String>,
+    /// IEEE 1284 device ID
+    pub device_id: Option<String>,
+}
+
+impl DeviceInfo {
+    /// Create from raw SNMP responses (None if the OID did not respond)
+    pub fn from_snmp(description: Option<&[u8]>, device_id: Option<&[u8]>) -> Self {
+        let to_string = |data: &[u8]| String::from_utf8_lossy(data).trim().to_string();
+        DeviceInfo {
+            description: description.map(to_string).filter(|s| !s.is_empty()),
+            device_id: device_id.map(to_string).filter(|s| !s.is_empty()),
+        }
+    }
+
+    /// Manufacturer in the device ID, or the first word of sysDescr
+    pub fn manufacturer(&self) -> Option<&str> {
+        self.device_id_field(&["MFG", "MANUFACTURER"]).or_else(|| {
+            self.description
+                .as_deref()
+                .and_then(|s| s.split_whitespace().next())
+        })
+    }
+
+    /// Model name in the device ID (e.g. "PT-P900W")
+    pub fn model(&self) -> Option<&str> {
+        self.device_id_field(&["MDL", "MODEL"])
+    }
+
+    pub fn is_brother(&self) -> bool {
+        self.manufacturer()
+            .is_some_and(|mfg| mfg.to_ascii_lowercase().starts_with("brother"))
+    }
+
+    /// Brother device that is a P-Touch or whose model is unknown
+    pub fn is_ptouch(&self) -> bool {
+        self.is_brother() && self.model().is_none_or(|model| model.starts_with("PT-"))
+    }
+
+    /// Check that the device at `host` is a P-Touch
+    pub fn verify(&self, host: &str) -> Result<()> {
+        if self.is_ptouch() {
+            return Ok(());
+        }
+        let found = match (self.model(), &self.description) {
+            (Some(model), _) => format!("{} {}", self.manufacturer().unwrap_or(""), model),
+            (None, Some(description)) => description.clone(),
+            (None, None) => "no SNMP device information".to_string(),
+        };
+        Err(format!(
+            "Device at {} is not a Brother P-Touch: {}",
+            host,
+            found.trim()
+        )
+        .into())
+    }
+
+    fn device_id_field(&self, keys: &[&str]) -> Option<&str> {
+        self.device_id
+            .as_deref()?
+            .split(';')
+            .filter_map(|field| field.split_once(':'))
+            .find(|(key, _)| keys.contains(&key.trim()))
+            .map(|(_, value)| value.trim())
+    }
 }
 
 pub struct NetworkBackend {
     stream: TcpStream,
     host: String,
+    /// host:port of the raw print port, to reconnect
+    address: String,
+    snmp: Box<dyn SnmpSource>,
 }
 
 impl NetworkBackend {
     pub fn new(host: &str) -> Result<Self> {
+        // Extract hostname for SNMP (remove port if specified)
+        let snmp_host = match host.find(':') {
+            Some(pos) => &host[..pos],
+            None => host,
+        };
+        let snmp = SnmpAgent::new(&format!("{}:161", snmp_host));
+        Self::with_snmp(host, Box::new(snmp))
+    }
+
+    /// Connect to `host` and get status and identification from `snmp`
+    pub fn with_snmp(host: &str, snmp: Box<dyn SnmpSource>) -> Result<Self> {
         // Default to port 9100 for P-Touch printers
         let address = if host.contains(':') {
             host.to_string()
@@ -35,71 +216,340 @@ impl NetworkBackend {
             format!("{}:9100", host)
         };
 
-        let stream = TcpStream::connect(&address)?;
-        stream.set_nodelay(true)?;
-        stream.set_read_timeout(Some(Duration::from_secs(1)))?;
         Ok(NetworkBackend {
-            stream,
+            stream: Self::connect(&address)?,
             host: host.to_string(),
+            address,
+            snmp,
         })
     }
+
+    fn connect(address: &str) -> Result<TcpStream> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+        Ok(stream)
+    }
+
+    /// Check that the printer has not closed the connection
+    ///
+    /// Printers send nothing on the print port, so a readable end of
+    /// stream or an error means it is gone.
+    fn is_connected(&self) -> bool {
+        let mut buf = [0u8; 1];
+        if self.stream.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let alive = match self.stream.peek(&mut buf) {
+            Ok(n) => n > 0,
+            Err(e) => e.kind() == std::io::ErrorKind::WouldBlock,
+        };
+        alive && self.stream.set_nonblocking(false).is_ok()
+    }
+
+    /// Connect again if the printer dropped the idle connection
+    ///
+    /// Some printers close the print port while it waits for the status
+    /// over SNMP, and writing to the closed stream would lose the job.
+    fn reconnect_if_closed(&mut self) -> Result<()> {
+        if !self.is_connected() {
+            crate::info!("Printer closed the connection, reconnecting...");
+            self.stream = Self::connect(&self.address)?;
+        }
+        Ok(())
+    }
+
+    /// Identify the device by SNMP
+    pub fn device_info(&self) -> DeviceInfo {
+        let description = self.snmp.get(OID_SYS_DESCR).ok();
+        let device_id = self.snmp.get(OID_BROTHER_DEVICE_ID).ok();
+        DeviceInfo::from_snmp(description.as_deref(), device_id.as_deref())
+    }
 }
 
 impl Backend for NetworkBackend {
     fn send_command(&mut self, data: &[u8]) -> Result<()> {
+        // Reconnecting here would send the rest of the job alone
+        if !self.is_connected() {
+            return Err("Printer closed the connection in the middle of the job".into());
+        }
         self.stream.write_all(data)?;
         self.stream.flush()?;
         Ok(())
     }
 
+    fn begin_job(&mut self) -> Result<()> {
+        self.reconnect_if_closed()
+    }
+
     fn get_status(&mut self) -> Result<Status> {
         // Use SNMP to get status from Brother P-Touch printer
-        // OID: 1.3.6.1.4.1.2435.3.3.9.1.6.1.0
-        let oid = "1.3.6.1.4.1.2435.3.3.9.1.6.1.0"
-            .parse()
-            .map_err(|e| format!("Invalid OID: {:?}", e))?;
+        let data = self.snmp.get(OID_BROTHER_STATUS)?;
+        Ok(Status::try_from(data.as_slice())?)
+    }
 
-        // Extract hostname for SNMP (remove port if specified)
-        let snmp_host = if let Some(pos) = self.host.find(':') {
-            &self.host[..pos]
-        } else {
-            &self.host
+    fn verify_device(&mut self) -> Result<()> {
+        self.device_info().verify(&self.host)
+    }
+
+    fn status_text(&mut self) -> Option<String> {
+        // Not all agents have it; the status packet is enough to print
+        let data = self.snmp.get(OID_CONSOLE_DISPLAY).ok()?;
+        parse_status_text(&data)
+    }
+}
+
+/// USB interface class of printers
+const USB_CLASS_PRINTER: u8 = 7;
+
+/// Interface (alternate setting) of a USB device, seen while looking for
+/// the printer interface
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UsbInterfaceInfo {
+    pub number: u8,
+    pub setting: u8,
+    pub class_code: u8,
+    /// Endpoint addresses; bit 7 is set for IN (device to host)
+    pub endpoints: Vec<u8>,
+}
+
+impl UsbInterfaceInfo {
+    fn from_descriptor(descriptor: &rusb::InterfaceDescriptor) -> Self {
+        UsbInterfaceInfo {
+            number: descriptor.interface_number(),
+            setting: descriptor.setting_number(),
+            class_code: descriptor.class_code(),
+            endpoints: descriptor
+                .endpoint_descriptors()
+                .map(|endpoint| endpoint.address())
+                .collect(),
+        }
+    }
+
+    fn endpoint_in(&self) -> Option<u8> {
+        self.endpoints
+            .iter()
+            .copied()
+            .find(|address| address & 0x80 != 0)
+    }
+
+    fn endpoint_out(&self) -> Option<u8> {
+        self.endpoints
+            .iter()
+            .copied()
+            .find(|address| address & 0x80 == 0)
+    }
+}
+
+impl std::fmt::Display for UsbInterfaceInfo {
+    /// e.g. "interface 0.0 class 7 (printer): 0x81 IN, 0x02 OUT"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let class_name = match self.class_code {
+            0x01 => "audio",
+            0x02 => "communications",
+            0x03 => "HID",
+            0x07 => "printer",
+            0x08 => "mass storage",
+            0x0A => "CDC data",
+            0xFF => "vendor specific",
+            _ => "other",
         };
+        write!(
+            f,
+            "interface {}.{} class {} ({})",
+            self.number, self.setting, self.class_code, class_name
+        )?;
+        if self.endpoints.is_empty() {
+            return write!(f, ": no endpoints");
+        }
+        let endpoints: Vec<String> = self
+            .endpoints
+            .iter()
+            .map(|address| {
+                let direction = if address & 0x80 != 0 { "IN" } else { "OUT" };
+                format!("0x{:02x} {}", address, direction)
+            })
+            .collect();
+        write!(f, ": {}", endpoints.join(", "))
+    }
+}
 
-        let snmp_addr = format!("{}:161", snmp_host);
-        let mut session = SyncSession::new_v2c(snmp_addr, b"public", None, 0)?;
+/// List interfaces for error messages, e.g. of composite devices
+pub fn describe_usb_interfaces(interfaces: &[UsbInterfaceInfo]) -> String {
+    if interfaces.is_empty() {
+        return "no interfaces".to_string();
+    }
+    interfaces
+        .iter()
+        .map(UsbInterfaceInfo::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
 
-        let mut response = session.get(&oid)?;
+/// Bytes received for a status request that hold no status packet
+///
+/// Carries everything that was read, for bug reports.
+#[derive(Debug)]
+pub struct MalformedStatus {
+    pub raw: Vec<u8>,
+}
 
-        // Get the first (and should be only) varbind from the response
-        if let Some((_oid, value)) = response.varbinds.next() {
-            match value {
-                Value::OctetString(data) => {
-                    if data.len() == 32 {
-                        let mut status_data = [0u8; 32];
-                        status_data.copy_from_slice(data);
-                        Ok(Status::new(status_data))
-                    } else {
-                        Err(format!(
-                            "Invalid status data length: expected 32 bytes, got {}",
-                            data.len()
-                        )
-                        .into())
-                    }
+impl std::fmt::Display for MalformedStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hex: Vec<String> = self.raw.iter().map(|b| format!("{:02X}", b)).collect();
+        write!(
+            f,
+            "Malformed status response ({} bytes): {}",
+            self.raw.len(),
+            hex.join(" ")
+        )
+    }
+}
+
+impl std::error::Error for MalformedStatus {}
+
+/// Bulk reads from the IN endpoint of a USB printer
+///
+/// Tests can replay sequences of reads instead of a device.
+pub trait BulkRead {
+    /// Read into `buf` and return the number of bytes read
+    fn read_bulk(&mut self, buf: &mut [u8]) -> rusb::Result<usize>;
+}
+
+/// Status packet assembled from reads of any size
+///
+/// Some hubs deliver the 32 bytes in several chunks. Bytes before the
+/// header are skipped, so that the packet is found after stray data.
+#[derive(Debug, Default)]
+pub struct StatusAccumulator {
+    buffer: Vec<u8>,
+    /// All bytes received, for [`MalformedStatus`]
+    raw: Vec<u8>,
+}
+
+/// Bytes to receive without finding a status packet before giving up
+const MAX_STATUS_GARBAGE: usize = 4 * STATUS_SIZE;
+
+impl StatusAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add received bytes and return the status once it is complete
+    ///
+    /// Fails with [`MalformedStatus`] when too much data came without a
+    /// status header.
+    pub fn push(&mut self, data: &[u8]) -> Result<Option<Status>> {
+        self.buffer.extend_from_slice(data);
+        self.raw.extend_from_slice(data);
+
+        match self
+            .buffer
+            .windows(STATUS_HEADER.len())
+            .position(|w| w == STATUS_HEADER)
+        {
+            Some(start) => {
+                if start > 0 {
+                    crate::info!("Skipped {} bytes before status header", start);
                 }
-                _ => Err("Invalid SNMP response type: expected OctetString".into()),
+                self.buffer.drain(..start);
+            }
+            None => {
+                // Keep a tail that may be the beginning of a header
+                let keep = self.buffer.len().min(STATUS_HEADER.len() - 1);
+                self.buffer.drain(..self.buffer.len() - keep);
+            }
+        }
+
+        if self.buffer.len() >= STATUS_SIZE && self.buffer.starts_with(&STATUS_HEADER) {
+            return Ok(Some(Status::try_from(&self.buffer[..STATUS_SIZE])?));
+        }
+        if self.raw.len() > MAX_STATUS_GARBAGE + STATUS_SIZE {
+            return Err(self.malformed().into());
+        }
+        Ok(None)
+    }
+
+    /// Whether anything was received
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    /// Error for the bytes received so far
+    pub fn malformed(&self) -> MalformedStatus {
+        MalformedStatus {
+            raw: self.raw.clone(),
+        }
+    }
+}
+
+/// Poll `reader` for a status packet until `timeout`
+///
+/// Incomplete data at the end is reported as [`MalformedStatus`].
+pub fn read_status<R: BulkRead>(reader: &mut R, timeout: Duration) -> Result<Status> {
+    let start_time = std::time::Instant::now();
+    let mut accumulator = StatusAccumulator::new();
+    let mut chunk = [0u8; 64];
+
+    loop {
+        if start_time.elapsed() > timeout {
+            if accumulator.is_empty() {
+                return Err("Status response timeout".into());
+            }
+            return Err(accumulator.malformed().into());
+        }
+
+        match reader.read_bulk(&mut chunk) {
+            Ok(n) => {
+                if let Some(status) = accumulator.push(&chunk[..n])? {
+                    crate::info!("Successfully read status");
+                    return Ok(status);
+                }
+                if n > 0 {
+                    crate::info!("Partial read: {} bytes, continuing...", n);
+                }
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            Err(rusb::Error::Timeout) => {
+                if start_time.elapsed() < timeout * 2 / 3 {
+                    crate::info!("No data yet, waiting...");
+                    std::thread::sleep(Duration::from_millis(100));
+                } else if accumulator.is_empty() {
+                    crate::info!("Connection closed by printer (timeout after no response)");
+                    return Err("Connection closed by printer".into());
+                } else {
+                    return Err(accumulator.malformed().into());
+                }
+            }
+            Err(e) => {
+                crate::info!("Read error: {}", e);
+                std::thread::sleep(Duration::from_millis(5));
             }
-        } else {
-            Err("No SNMP response received".into())
         }
     }
 }
 
+/// IN endpoint of an open USB printer
+struct UsbEndpointIn<'a> {
+    device: &'a rusb::DeviceHandle<rusb::GlobalContext>,
+    endpoint: u8,
+    timeout: Duration,
+}
+
+impl BulkRead for UsbEndpointIn<'_> {
+    fn read_bulk(&mut self, buf: &mut [u8]) -> rusb::Result<usize> {
+        self.device.read_bulk(self.endpoint, buf, self.timeout)
+    }
+}
+
 pub struct UsbBackend {
     device: rusb::DeviceHandle<rusb::GlobalContext>,
     endpoint_in: u8,
     endpoint_out: u8,
     timeout: Duration,
+    interface_number: u8,
+    /// The kernel driver was detached and is reattached on drop
+    kernel_driver_detached: bool,
 }
 
 impl UsbBackend {
@@ -128,66 +578,77 @@ impl UsbBackend {
         let device = target_device.ok_or("Brother P-Touch printer not found via USB")?;
         let handle = device.open()?;
 
-        if handle.kernel_driver_active(0)? {
+        let kernel_driver_detached = handle.kernel_driver_active(0)?;
+        if kernel_driver_detached {
             handle.detach_kernel_driver(0)?;
         }
 
         handle.set_active_configuration(1)?;
 
         let config_desc = device.config_descriptor(0)?;
-        let mut printer_interface = None;
-        let mut interface_number = 0;
-
-        for interface in config_desc.interfaces() {
-            for descriptor in interface.descriptors() {
-                if descriptor.class_code() == 7 {
-                    printer_interface = Some(descriptor);
-                    interface_number = interface.number();
-                    break;
-                }
-            }
-            if printer_interface.is_some() {
-                break;
-            }
-        }
-
-        let interface_desc = printer_interface.ok_or("No printer interface found")?;
+        let interfaces: Vec<UsbInterfaceInfo> = config_desc
+            .interfaces()
+            .flat_map(|interface| interface.descriptors())
+            .map(|descriptor| UsbInterfaceInfo::from_descriptor(&descriptor))
+            .collect();
+
+        let printer_interface = interfaces
+            .iter()
+            .find(|interface| interface.class_code == USB_CLASS_PRINTER)
+            .ok_or_else(|| {
+                format!(
+                    "No printer interface (class 7) found on {}: {}",
+                    device_specifier,
+                    describe_usb_interfaces(&interfaces)
+                )
+            })?;
+        let interface_number = printer_interface.number;
         handle.claim_interface(interface_number)?;
 
-        let mut endpoint_in = 0;
-        let mut endpoint_out = 0;
-
-        for endpoint_desc in interface_desc.endpoint_descriptors() {
-            match endpoint_desc.direction() {
-                rusb::Direction::In => endpoint_in = endpoint_desc.address(),
-                rusb::Direction::Out => endpoint_out = endpoint_desc.address(),
-            }
-        }
-
-        if endpoint_in == 0 || endpoint_out == 0 {
-            return Err("Could not find required USB endpoints".into());
-        }
+        let (Some(endpoint_in), Some(endpoint_out)) = (
+            printer_interface.endpoint_in(),
+            printer_interface.endpoint_out(),
+        ) else {
+            return Err(format!(
+                "Could not find required USB endpoints (IN and OUT) on {}: {}",
+                device_specifier,
+                describe_usb_interfaces(&interfaces)
+            )
+            .into());
+        };
 
-        eprintln!("USB connection established:");
-        eprintln!("  Interface: {}", interface_number);
-        eprintln!("  Endpoint IN: 0x{:02x}", endpoint_in);
-        eprintln!("  Endpoint OUT: 0x{:02x}", endpoint_out);
+        crate::info!("USB connection established:");
+        crate::info!("  Interface: {}", interface_number);
+        crate::info!("  Endpoint IN: 0x{:02x}", endpoint_in);
+        crate::info!("  Endpoint OUT: 0x{:02x}", endpoint_out);
 
         Ok(UsbBackend {
             device: handle,
             endpoint_in,
             endpoint_out,
             timeout: Duration::from_secs(10),
+            interface_number,
+            kernel_driver_detached,
         })
     }
 }
 
+impl Drop for UsbBackend {
+    /// Release the interface so other programs (e.g. CUPS) can use the printer
+    fn drop(&mut self) {
+        let _ = self.device.release_interface(self.interface_number);
+        if self.kernel_driver_detached {
+            let _ = self.device.attach_kernel_driver(0);
+        }
+    }
+}
+
 impl Backend for UsbBackend {
     fn send_command(&mut self, data: &[u8]) -> Result<()> {
         let bytes_written = self
             .device
             .write_bulk(self.endpoint_out, data, self.timeout)?;
-        eprintln!(
+        crate::info!(
             "USB write: {} bytes written out of {} bytes",
             bytes_written,
             data.len()
@@ -209,72 +670,66 @@ impl Backend for UsbBackend {
         cmd.invalidate().initialize().status_information_request();
         let buf = cmd.build();
 
-        eprintln!("Sending command ({} bytes)...", buf.len());
+        crate::info!("Sending command ({} bytes)...", buf.len());
         self.send_command(&buf)?;
 
-        eprintln!("Command sent, waiting for response...");
+        crate::info!("Command sent, waiting for response...");
 
         // Give the printer some time to process the command
         std::thread::sleep(Duration::from_millis(200));
 
         // Read status response with polling
-        let start_time = std::time::Instant::now();
-        let timeout = Duration::from_secs(3);
-        let mut response_buffer = [0u8; 32];
+        let mut endpoint = UsbEndpointIn {
+            device: &self.device,
+            endpoint: self.endpoint_in,
+            timeout: self.timeout,
+        };
+        read_status(&mut endpoint, Duration::from_secs(3))
+    }
+}
 
-        loop {
-            if start_time.elapsed() > timeout {
-                return Err("Status response timeout".into());
-            }
+/// Backend that writes commands to a file instead of a printer
+///
+/// Useful for dry runs and for inspecting the bytes sent to the printer.
+pub struct FileBackend {
+    file: File,
+}
 
-            match self
-                .device
-                .read_bulk(self.endpoint_in, &mut response_buffer, self.timeout)
-            {
-                Ok(n) if n >= 32 => {
-                    eprintln!("Successfully read {} bytes", n);
-                    break;
-                }
-                Ok(n) => {
-                    eprintln!("Partial read: {} bytes, continuing...", n);
-                    std::thread::sleep(Duration::from_millis(5));
-                    continue;
-                }
-                Err(rusb::Error::Timeout) => {
-                    if start_time.elapsed() < Duration::from_secs(2) {
-                        eprintln!("No data yet, waiting...");
-                        std::thread::sleep(Duration::from_millis(100));
-                        continue;
-                    } else {
-                        eprintln!("Connection closed by printer (timeout after no response)");
-                        return Err("Connection closed by printer".into());
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Read error: {}", e);
-                    std::thread::sleep(Duration::from_millis(5));
-                    continue;
-                }
-            }
-        }
+impl FileBackend {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::create(path)?;
+        Ok(FileBackend { file })
+    }
+}
+
+impl Backend for FileBackend {
+    fn send_command(&mut self, data: &[u8]) -> Result<()> {
+        self.file.write_all(data)?;
+        self.file.flush()?;
+        Ok(())
+    }
 
-        Ok(Status::new(response_buffer))
+    fn get_status(&mut self) -> Result<Status> {
+        Err("Status is not available for file backend".into())
     }
 }
 
 /// Create a backend based on the host specifier
 ///
 /// # Arguments
-/// * `host` - Host specifier: hostname for network or vid:pid for USB
+/// * `host` - Host specifier: hostname for network, vid:pid for USB,
+///   or file:PATH to write commands to a file
 ///
 /// # Returns
-/// * Backend implementation (NetworkBackend or UsbBackend)
+/// * Backend implementation (NetworkBackend, UsbBackend or FileBackend)
 pub fn from_host(host: &str) -> Result<Box<dyn Backend>> {
     fn is_usb_specifier(host: &str) -> bool {
         host.contains(':') && host.chars().all(|c| c.is_ascii_hexdigit() || c == ':')
     }
 
-    if is_usb_specifier(host) {
+    if let Some(path) = host.strip_prefix("file:") {
+        Ok(Box::new(FileBackend::new(path)?))
+    } else if is_usb_specifier(host) {
         Ok(Box::new(UsbBackend::new(host)?))
     } else {
         Ok(Box::new(NetworkBackend::new(host)?))
@@ -0,0 +1,97 @@
+//! Compare rendered label images
+//!
+//! Images are compared as printed: a pixel differs only if it is inked
+//! in one image and blank in the other, so antialiasing noise does not
+//! count.
+
+use crate::Result;
+use crate::printable_image::{decode_grayscale, is_ink};
+use std::fmt;
+
+/// Rectangle in pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    pub width: u32,
+    pub height: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}+{}+{}", self.width, self.height, self.x, self.y)
+    }
+}
+
+/// Result of comparing two PNG images
+#[derive(Debug, PartialEq)]
+pub enum ImageDiff {
+    /// Same size and same inked pixels
+    Same { width: u32, height: u32 },
+    /// Sizes (width, height) differ, so pixels are not compared
+    Size { a: (u32, u32), b: (u32, u32) },
+    /// Number of differing pixels and the region containing all of them
+    Pixels { count: usize, region: Region },
+}
+
+impl ImageDiff {
+    pub fn is_same(&self) -> bool {
+        matches!(self, ImageDiff::Same { .. })
+    }
+}
+
+impl fmt::Display for ImageDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageDiff::Same { width, height } => write!(f, "Same: {}x{}", width, height),
+            ImageDiff::Size { a, b } => {
+                write!(f, "Size differs: {}x{} vs {}x{}", a.0, a.1, b.0, b.1)
+            }
+            ImageDiff::Pixels { count, region } => {
+                write!(f, "{} pixels differ in {}", count, region)
+            }
+        }
+    }
+}
+
+/// Compare two PNG images pixel by pixel
+pub fn diff_png(a: &[u8], b: &[u8]) -> Result<ImageDiff> {
+    let (width, height, a_pixels) = decode_grayscale(a)?;
+    let (b_width, b_height, b_pixels) = decode_grayscale(b)?;
+
+    if (width, height) != (b_width, b_height) {
+        return Ok(ImageDiff::Size {
+            a: (width, height),
+            b: (b_width, b_height),
+        });
+    }
+
+    let mut count = 0;
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+
+    for y in 0..height {
+        for x in 0..width {
+            let i = (y * width + x) as usize;
+            if is_ink(a_pixels[i]) != is_ink(b_pixels[i]) {
+                count += 1;
+                min_x = min_x.min(x);
+                min_y = min_y.min(y);
+                max_x = max_x.max(x);
+                max_y = max_y.max(y);
+            }
+        }
+    }
+
+    if count == 0 {
+        return Ok(ImageDiff::Same { width, height });
+    }
+    Ok(ImageDiff::Pixels {
+        count,
+        region: Region {
+            width: max_x - min_x + 1,
+            height: max_y - min_y + 1,
+            x: min_x,
+            y: min_y,
+        },
+    })
+}
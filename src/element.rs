@@ -1,13 +1,19 @@
 use crate::Result;
+use crate::aztec;
+use crate::payload::{self, WifiAuth};
+use crate::tape::TapeSpec;
+use base64::prelude::{BASE64_STANDARD, Engine as _};
 use fontdb::Database;
 use png;
 use qrcode;
 use resvg::{tiny_skia, usvg};
+use std::any::Any;
 use std::fmt::{self, Display};
 use std::fs::File;
 use std::str::FromStr;
 use std::sync::Arc;
 use svg::node::element as svge;
+use unicode_segmentation::UnicodeSegmentation;
 
 #[derive(Clone, Copy, Debug, Default)]
 pub enum VerticalAlign {
@@ -15,6 +21,9 @@ pub enum VerticalAlign {
     Top,
     Center,
     Bottom,
+    /// Line up baselines of the first lines of text;
+    /// elements without text put their bottom on the baseline
+    Baseline,
 }
 
 #[derive(Clone, Debug)]
@@ -23,7 +32,74 @@ pub struct RowOptions {
     pub padding: f32,
 }
 
-#[derive(Clone, Copy, Debug, Default)]
+impl Default for RowOptions {
+    fn default() -> Self {
+        // 20 SVG units ≈ 1.4mm at 360dpi, enough for the quiet zone of QR codes
+        RowOptions {
+            align: VerticalAlign::default(),
+            padding: 20.0,
+        }
+    }
+}
+
+impl RowOptions {
+    pub fn with_align(mut self, align: VerticalAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Space between elements in SVG units
+    pub fn with_padding(mut self, padding: f32) -> Self {
+        self.padding = padding;
+        self
+    }
+}
+
+/// Error correction level of QR codes: share of damaged modules that
+/// can be recovered
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum QrEcLevel {
+    /// About 7%
+    L,
+    /// About 15%
+    #[default]
+    M,
+    /// About 25%
+    Q,
+    /// About 30%
+    H,
+}
+
+impl From<QrEcLevel> for qrcode::EcLevel {
+    fn from(level: QrEcLevel) -> Self {
+        match level {
+            QrEcLevel::L => qrcode::EcLevel::L,
+            QrEcLevel::M => qrcode::EcLevel::M,
+            QrEcLevel::Q => qrcode::EcLevel::Q,
+            QrEcLevel::H => qrcode::EcLevel::H,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct QrOptions {
+    /// Size of one QR module in SVG units
+    pub module_size: f32,
+    /// Error correction level
+    pub ec_level: QrEcLevel,
+}
+
+impl Default for QrOptions {
+    fn default() -> Self {
+        // 5 SVG units ≈ 0.35mm at 360dpi
+        QrOptions {
+            module_size: 5.0,
+            ec_level: QrEcLevel::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct BoundingBox {
     pub width: f32,
     pub height: f32,
@@ -46,22 +122,42 @@ impl BoundingBox {
         self.width == 0.0 && self.height == 0.0
     }
 
-    /// Calculate the union of two bounding boxes for layering.
-    /// The x, y coordinates are always taken from self (base layer).
+    /// Calculate the smallest bounding box containing both boxes
+    ///
+    /// Both boxes must be in the same coordinates; the result may start
+    /// left of or above `self` when `other` does.
     pub fn union(&self, other: &Self) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
         let mx = (self.x + self.width).max(other.x + other.width);
         let my = (self.y + self.height).max(other.y + other.height);
 
         Self {
-            x: self.x, // Base layer coordinates
-            y: self.y, // Base layer coordinates
-            width: mx - self.x,
-            height: my - self.y,
+            x,
+            y,
+            width: mx - x,
+            height: my - y,
         }
     }
 
+    /// Check if two bounding boxes share any area
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+
+    /// Check if this bounding box fully covers the other
+    pub fn covers(&self, other: &Self) -> bool {
+        self.x <= other.x
+            && self.y <= other.y
+            && other.x + other.width <= self.x + self.width
+            && other.y + other.height <= self.y + self.height
+    }
+
     /// Append another bounding box horizontally
-    pub fn h_append(&self, other: Self) -> Self {
+    pub(crate) fn h_append(&self, other: Self) -> Self {
         Self {
             width: self.width + other.width,
             height: self.height.max(other.height),
@@ -71,7 +167,7 @@ impl BoundingBox {
     }
 
     /// Append another bounding box vertically
-    pub fn v_append(&self, other: Self) -> Self {
+    pub(crate) fn v_append(&self, other: Self) -> Self {
         Self {
             width: self.width.max(other.width),
             height: self.height + other.height,
@@ -92,8 +188,68 @@ fn enclose_group(node: impl Into<Box<dyn svg::Node>>) -> svge::Group {
     svge::Group::new().add(node)
 }
 
+/// Child element and its position in the parent's coordinates
+pub type ChildOffset<'a> = (&'a dyn Element, f32, f32);
+
+/// Helper function to render visible children at their positions
+fn render_children(children: Vec<ChildOffset>) -> Result<svge::Group> {
+    let mut group = svge::Group::new();
+
+    for (elm, x, y) in children {
+        // Only render visible elements
+        if elm.is_visible() {
+            group = group.add(elm.render_at(x, y)?);
+        }
+    }
+
+    Ok(group)
+}
+
+/// Element tree with absolute positions
+///
+/// `x`, `y` are where the top-left corner of `bbox` lands when the root
+/// element is rendered by `render_at(0.0, 0.0)`.
+#[derive(Clone, Debug)]
+pub struct LayoutTree {
+    pub name: String,
+    pub bbox: BoundingBox,
+    pub x: f32,
+    pub y: f32,
+    pub visible: bool,
+    pub opaque: bool,
+    pub children: Vec<LayoutTree>,
+}
+
+impl LayoutTree {
+    /// Area of this element in absolute coordinates
+    pub fn rect(&self) -> BoundingBox {
+        BoundingBox::new(self.bbox.width, self.bbox.height, self.x, self.y)
+    }
+}
+
+/// Access to the concrete type of an element, implemented for all elements
+///
+/// Use [`downcast_ref`](trait.Element.html#method.downcast_ref) and
+/// [`downcast_mut`](trait.Element.html#method.downcast_mut) on
+/// `dyn Element` rather than calling these on a `Box`, which would give
+/// the `Box` itself.
+pub trait AsAny {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
 /// Common interface for all renderable elements in the layout system
-pub trait Element: Display {
+pub trait Element: Display + AsAny {
     /// Calculate the bounding box of this element
     fn bounding_box(&self) -> Result<BoundingBox>;
 
@@ -105,6 +261,25 @@ pub trait Element: Display {
         true
     }
 
+    /// Return true if Row/Column put their padding next to this element
+    ///
+    /// Padding goes only between two padded elements, so an invisible
+    /// element in between replaces the padding with its own size.
+    fn is_padded(&self) -> bool {
+        self.is_visible()
+    }
+
+    /// Return true if this element hides everything under its bounding box
+    fn is_opaque(&self) -> bool {
+        false
+    }
+
+    /// Distance from the top of the bounding box to the baseline of text,
+    /// or None for elements without text
+    fn baseline(&self) -> Result<Option<f32>> {
+        Ok(None)
+    }
+
     /// Return the type name of this element
     fn type_name(&self) -> String {
         let s = format!("{}", self);
@@ -114,6 +289,43 @@ pub trait Element: Display {
         }
     }
 
+    /// Return child elements with their positions (used by both render and layout)
+    fn child_offsets(&self) -> Result<Vec<ChildOffset<'_>>> {
+        Ok(Vec::new())
+    }
+
+    /// Return child elements to change or replace them after parsing
+    ///
+    /// Unlike `child_offsets`, the element of Rotated is included.
+    fn children_mut(&mut self) -> Vec<&mut Box<dyn Element>> {
+        Vec::new()
+    }
+
+    /// Calculate positions of this element and all descendants
+    fn layout(&self) -> Result<LayoutTree> {
+        self.layout_at(0.0, 0.0)
+    }
+
+    /// Calculate positions as if rendered by `render_at(x, y)`
+    fn layout_at(&self, x: f32, y: f32) -> Result<LayoutTree> {
+        let bbox = self.bounding_box()?;
+        let children = self
+            .child_offsets()?
+            .into_iter()
+            .map(|(elm, cx, cy)| elm.layout_at(x - bbox.x + cx, y - bbox.y + cy))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(LayoutTree {
+            name: self.to_string(),
+            bbox,
+            x,
+            y,
+            visible: self.is_visible(),
+            opaque: self.is_opaque(),
+            children,
+        })
+    }
+
     /// Render this element at a specific position with proper coordinate transformation
     fn render_at(&self, x: f32, y: f32) -> Result<svge::Group> {
         let bbox = self.bounding_box()?;
@@ -126,12 +338,155 @@ pub trait Element: Display {
     }
 }
 
+/// Boxed elements are elements, so generic code takes both
+///
+/// Boxing a `Box<dyn Element>` again wraps it in another layer, and
+/// `downcast_ref` then finds the inner `Box` rather than its concrete
+/// type; pass boxed elements on as they are.
+impl Element for Box<dyn Element> {
+    fn bounding_box(&self) -> Result<BoundingBox> {
+        (**self).bounding_box()
+    }
+
+    fn render(&self) -> Result<svge::Group> {
+        (**self).render()
+    }
+
+    fn is_visible(&self) -> bool {
+        (**self).is_visible()
+    }
+
+    fn is_padded(&self) -> bool {
+        (**self).is_padded()
+    }
+
+    fn is_opaque(&self) -> bool {
+        (**self).is_opaque()
+    }
+
+    fn baseline(&self) -> Result<Option<f32>> {
+        (**self).baseline()
+    }
+
+    fn type_name(&self) -> String {
+        (**self).type_name()
+    }
+
+    fn child_offsets(&self) -> Result<Vec<ChildOffset<'_>>> {
+        (**self).child_offsets()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Box<dyn Element>> {
+        (**self).children_mut()
+    }
+
+    fn layout(&self) -> Result<LayoutTree> {
+        (**self).layout()
+    }
+
+    fn layout_at(&self, x: f32, y: f32) -> Result<LayoutTree> {
+        (**self).layout_at(x, y)
+    }
+
+    fn render_at(&self, x: f32, y: f32) -> Result<svge::Group> {
+        (**self).render_at(x, y)
+    }
+}
+
+impl dyn Element + '_ {
+    /// Element as its concrete type, e.g. to read a parsed Text
+    ///
+    /// ```
+    /// use ptouch::prelude::*;
+    ///
+    /// let element: Box<dyn Element> = Box::new(Gap::new(10.0, 20.0, true));
+    /// assert_eq!(element.downcast_ref::<Gap>().unwrap().size(), (10.0, 20.0));
+    /// assert!(element.downcast_ref::<Text>().is_none());
+    /// ```
+    pub fn downcast_ref<T: Element + 'static>(&self) -> Option<&T> {
+        AsAny::as_any(self).downcast_ref()
+    }
+
+    /// Element as its concrete type to change it
+    pub fn downcast_mut<T: Element + 'static>(&mut self) -> Option<&mut T> {
+        AsAny::as_any_mut(self).downcast_mut()
+    }
+}
+
+/// How to measure the bounding box of text
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextMeasure {
+    /// Scan the pixels of the rendered text (exact, but slow for long text)
+    #[default]
+    Pixel,
+    /// Bounds of the glyph outlines rounded out to whole pixels (fast)
+    Logical,
+}
+
+impl Display for TextMeasure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextMeasure::Pixel => write!(f, "pixel"),
+            TextMeasure::Logical => write!(f, "logical"),
+        }
+    }
+}
+
+impl FromStr for TextMeasure {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "pixel" => Ok(TextMeasure::Pixel),
+            "logical" => Ok(TextMeasure::Logical),
+            _ => Err(format!("Invalid text measurement: {} (pixel or logical)", s).into()),
+        }
+    }
+}
+
+/// Renderer that the SVG of text is written for
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextRenderer {
+    /// Put the first line far below the top, since ImageMagick `convert`
+    /// does not respect dominant-baseline
+    #[default]
+    ImageMagickCompat,
+    /// Hang the first line from the top with dominant-baseline="hanging"
+    Resvg,
+}
+
+impl Display for TextRenderer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextRenderer::ImageMagickCompat => write!(f, "imagemagick-compat"),
+            TextRenderer::Resvg => write!(f, "resvg"),
+        }
+    }
+}
+
+impl FromStr for TextRenderer {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "imagemagick-compat" => Ok(TextRenderer::ImageMagickCompat),
+            "resvg" => Ok(TextRenderer::Resvg),
+            _ => Err(format!("Invalid renderer: {} (resvg or imagemagick-compat)", s).into()),
+        }
+    }
+}
+
+/// Font of Text elements and the fonts to find it in
+///
+/// Cloning shares `fontdb` instead of copying the font data, which is
+/// large with CJK fonts.
 #[derive(Clone)]
 pub struct TextOptions {
     pub font_name: String,
     pub font_weight: String,
     pub font_size: u32,
     pub line_height: u32,
+    pub fontdb: Arc<Database>,
 }
 
 impl Display for TextOptions {
@@ -144,81 +499,324 @@ impl Display for TextOptions {
     }
 }
 
-impl FromStr for TextOptions {
-    type Err = Box<dyn std::error::Error>;
+impl TextOptions {
+    /// Defaults of the command line with fonts in `fontdb`
+    ///
+    /// Change fields with `with_*` methods.
+    ///
+    /// ```
+    /// use ptouch::prelude::*;
+    /// use std::sync::Arc;
+    ///
+    /// let fontdb = Arc::new(fontdb::Database::new());
+    /// let text = TextOptions::new(fontdb.clone())
+    ///     .with_font_name("DejaVu Sans")
+    ///     .with_font_size(32)
+    ///     .with_line_height(36);
+    /// let options = LayoutOptions::new(fontdb).with_text(text);
+    /// assert_eq!(options.text.to_string(), "DejaVu Sans:normal:32:36");
+    /// ```
+    pub fn new(fontdb: Arc<Database>) -> Self {
+        TextOptions {
+            font_name: "Noto Sans CJK JP".to_string(),
+            font_weight: "normal".to_string(),
+            font_size: 24,
+            line_height: 24,
+            fontdb,
+        }
+    }
 
-    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        let parts: Vec<&str> = s.split(':').collect();
+    pub fn with_font_name(mut self, font_name: impl Into<String>) -> Self {
+        self.font_name = font_name.into();
+        self
+    }
 
-        if parts.len() != 4 {
-            return Err(format!("Invalid font spec format {}", parts.len()).into());
-        }
+    /// Weight such as "bold" or "700"
+    pub fn with_font_weight(mut self, font_weight: impl Into<String>) -> Self {
+        self.font_weight = font_weight.into();
+        self
+    }
 
-        let font_name = parts[0];
-        if font_name.is_empty() {
-            return Err("Font name cannot be empty".into());
-        }
+    /// Font size in pixels; the line height is left as is
+    pub fn with_font_size(mut self, font_size: u32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    pub fn with_line_height(mut self, line_height: u32) -> Self {
+        self.line_height = line_height;
+        self
+    }
 
-        let font_weight = parts[1];
-        if font_weight.is_empty() {
-            return Err("Font weight cannot be empty".into());
+    /// Width of a character cell in pixels
+    ///
+    /// The advance width of '0' from the font metrics, which is the width
+    /// of every character in monospace fonts.
+    pub fn cell_width(&self) -> Result<f32> {
+        self.fontdb
+            .with_face_data(self.query_face()?, |data, index| {
+                let face = ttf_parser::Face::parse(data, index).ok()?;
+                let advance = face.glyph_hor_advance(face.glyph_index('0')?)?;
+                Some(advance as f32 * self.font_size as f32 / face.units_per_em() as f32)
+            })
+            .flatten()
+            .ok_or_else(|| format!("No metrics of '0' in font '{}'", self.font_name).into())
+    }
+
+    /// Height of capital letters in pixels
+    ///
+    /// Taken from the OS/2 table of the font, or approximated as 0.7 em
+    /// when the font or the metric is missing.
+    pub fn cap_height(&self) -> f32 {
+        let metric = self.query_face().ok().and_then(|id| {
+            self.fontdb
+                .with_face_data(id, |data, index| {
+                    let face = ttf_parser::Face::parse(data, index).ok()?;
+                    let cap = face.capital_height().filter(|&cap| cap > 0)?;
+                    Some(cap as f32 * self.font_size as f32 / face.units_per_em() as f32)
+                })
+                .flatten()
+        });
+        metric.unwrap_or(self.font_size as f32 * CAP_HEIGHT_RATIO)
+    }
+
+    /// Warning when the text would print unreadably small or too large
+    ///
+    /// Font sizes are in dots, so the same size prints at half the height
+    /// on a 360 DPI printer as on a 180 DPI one. Warns when the capital
+    /// letters are shorter than `min_mm`, or taller than the printable
+    /// width of the tape.
+    pub fn check_physical_size(&self, tape_spec: &TapeSpec, min_mm: f32) -> Option<String> {
+        let cap = self.cap_height();
+        let cap_mm = cap * 25.4 / tape_spec.dpi as f32;
+        if cap_mm < min_mm {
+            Some(format!(
+                "Font size {} prints capital letters {:.1}mm high at {} DPI, below {}mm",
+                self.font_size, cap_mm, tape_spec.dpi, min_mm
+            ))
+        } else if cap > tape_spec.inner_dots as f32 {
+            Some(format!(
+                "Font size {} prints capital letters {:.1}mm high, beyond the printable width of {} tape",
+                self.font_size, cap_mm, tape_spec.name
+            ))
+        } else {
+            None
         }
+    }
+
+    /// Ascender of the font in pixels, taken from `face` of it
+    fn ascent(&self, face: fontdb::ID) -> Result<f32> {
+        self.fontdb
+            .with_face_data(face, |data, index| {
+                let face = ttf_parser::Face::parse(data, index).ok()?;
+                Some(face.ascender() as f32 * self.font_size as f32 / face.units_per_em() as f32)
+            })
+            .flatten()
+            .ok_or_else(|| format!("No metrics in font '{}'", self.font_name).into())
+    }
 
-        let font_size = parts[2]
-            .parse::<u32>()
-            .map_err(|_| format!("Invalid font size: '{}'", parts[2]))?;
+    /// Face of the font in `fontdb`
+    ///
+    /// Selected by [`crate::select_face`], which does not depend on the
+    /// order the fonts were loaded in.
+    pub fn select_face(&self) -> Option<fontdb::ID> {
+        let weight = match self.font_weight.to_ascii_lowercase().as_str() {
+            "bold" => fontdb::Weight::BOLD,
+            weight => fontdb::Weight(weight.parse().unwrap_or(400)),
+        };
+        crate::select_face(&self.fontdb, &self.font_name, weight, fontdb::Style::Normal)
+    }
 
-        let line_height = parts[3]
-            .parse::<u32>()
-            .map_err(|_| format!("Invalid line height: '{}'", parts[3]))?;
+    fn query_face(&self) -> Result<fontdb::ID> {
+        self.select_face()
+            .ok_or_else(|| format!("Font '{}' not found.", self.font_name).into())
+    }
 
-        Ok(TextOptions {
-            font_name: font_name.to_string(),
-            font_weight: font_weight.to_string(),
-            font_size,
-            line_height,
-        })
+    /// Value of the font-family attribute of SVG
+    ///
+    /// The PostScript name of the selected `face` pins it for the font
+    /// resolver of ptouch; other renderers fall back to the family name.
+    fn font_family(&self, face: Option<fontdb::ID>) -> String {
+        let post_script_name = face
+            .and_then(|id| self.fontdb.face(id))
+            .map(|face| face.post_script_name.as_str())
+            .filter(|name| !name.is_empty());
+        match post_script_name {
+            Some(name) => format!("{}, {}", name, self.font_name),
+            None => self.font_name.clone(),
+        }
+    }
+
+    /// Change the font by a spec of "fnt:" in layout script
+    ///
+    /// The spec is `NAME:WEIGHT:SIZE:LINE_HEIGHT`, where empty or missing
+    /// fields are kept as they are.
+    ///
+    /// ```
+    /// use ptouch::element::TextOptions;
+    /// use std::sync::Arc;
+    ///
+    /// let options = TextOptions::new(Arc::new(fontdb::Database::new()));
+    /// let bold = options.with_spec("DejaVu Sans:bold").unwrap();
+    /// assert_eq!(bold.to_string(), "DejaVu Sans:bold:24:24");
+    /// assert!(bold.with_spec("::large").is_err());
+    /// ```
+    pub fn with_spec(mut self, spec: &str) -> Result<Self> {
+        let parts: Vec<&str> = spec.split(':').collect();
+        let field = |i: usize| parts.get(i).copied().filter(|s| !s.is_empty());
+
+        if let Some(font_name) = field(0) {
+            self.font_name = font_name.to_string();
+        }
+        if let Some(font_weight) = field(1) {
+            self.font_weight = font_weight.to_string();
+        }
+        if let Some(font_size) = field(2) {
+            self.font_size = font_size
+                .parse()
+                .map_err(|_| format!("Invalid font size: '{}'", font_size))?;
+        }
+        if let Some(line_height) = field(3) {
+            self.line_height = line_height
+                .parse()
+                .map_err(|_| format!("Invalid line height: '{}'", line_height))?;
+        }
+        Ok(self)
     }
 }
 
 pub struct Text {
     options: TextOptions,
+    /// Face of the font selected once, or None to select it when needed
+    face: Option<fontdb::ID>,
     texts: Vec<String>,
-    fontdb: Arc<Database>,
+    measure: TextMeasure,
+    renderer: TextRenderer,
+    trim_bearing: bool,
 }
 
 impl Text {
-    pub fn new(texts: &[String], options: TextOptions, fontdb: Arc<Database>) -> Result<Self> {
-        validate_font(&options.font_name, &fontdb)?;
-
+    pub fn new(texts: &[String], options: TextOptions) -> Result<Self> {
+        validate_font(&options.font_name, &options.fontdb)?;
+        let face = options.select_face();
         Ok(Text {
+            face,
+            ..Self::new_unchecked(texts, options)
+        })
+    }
+
+    /// Create Text of lines rendered verbatim
+    ///
+    /// Each string is one line as it is: blank lines keep their line
+    /// height, and leading and repeated spaces are kept. Nothing is split,
+    /// joined, trimmed or normalized, unlike text tokens of a layout
+    /// script. Fails on a line break inside a line, which SVG would turn
+    /// into a space.
+    ///
+    /// Leading spaces count in the bounding box unless
+    /// [`Text::with_trim_bearing`] cuts them off.
+    pub fn from_lines(lines: &[String], options: TextOptions) -> Result<Self> {
+        if let Some(line) = lines.iter().find(|line| line.contains(['\n', '\r'])) {
+            return Err(format!("Line break inside a line: {:?}", line).into());
+        }
+        Self::new(lines, options)
+    }
+
+    /// Create Text without checking that the font is in the fontdb of
+    /// `options`
+    ///
+    /// Measuring or rendering fails if the font is missing.
+    pub fn new_unchecked(texts: &[String], options: TextOptions) -> Self {
+        Text {
             options,
+            face: None,
             texts: texts.to_vec(),
-            fontdb,
-        })
+            measure: TextMeasure::default(),
+            renderer: TextRenderer::default(),
+            trim_bearing: false,
+        }
+    }
+
+    /// Start the bounding box at the ink instead of the left edge of the
+    /// text, cutting off the left side bearing and leading spaces
+    ///
+    /// Tightens standalone labels, but Text elements in a column no longer
+    /// line up at the pen position.
+    pub fn with_trim_bearing(mut self, trim_bearing: bool) -> Self {
+        self.trim_bearing = trim_bearing;
+        self
+    }
+
+    /// Write the SVG of the text for `renderer`
+    pub fn with_renderer(mut self, renderer: TextRenderer) -> Self {
+        self.renderer = renderer;
+        self
+    }
+
+    /// Measure the bounding box by `measure` instead of pixel scanning
+    pub fn with_measure(mut self, measure: TextMeasure) -> Self {
+        self.measure = measure;
+        self
+    }
+
+    /// Lines of text
+    pub fn texts(&self) -> &[String] {
+        &self.texts
+    }
+
+    pub fn set_texts(&mut self, texts: &[String]) {
+        self.texts = texts.to_vec();
+    }
+
+    pub fn options(&self) -> &TextOptions {
+        &self.options
+    }
+
+    /// Font of the text to change it, checked on the next measurement
+    pub fn options_mut(&mut self) -> &mut TextOptions {
+        self.face = None;
+        &mut self.options
+    }
+
+    /// Face of the font, selected once by [`Text::new`]
+    fn face(&self) -> Option<fontdb::ID> {
+        self.face.or_else(|| self.options.select_face())
     }
 }
 
 impl Element for Text {
     fn bounding_box(&self) -> Result<BoundingBox> {
+        // Text created by new_unchecked() is checked on first measurement
+        validate_font(&self.options.font_name, &self.options.fontdb)?;
         calculate_text_bbox(
-            &self.options.font_name,
-            &self.options.font_weight,
-            self.options.font_size,
-            self.options.line_height,
+            &self.options,
+            self.face(),
             &self.texts,
-            &self.fontdb,
+            self.measure,
+            self.renderer,
+            self.trim_bearing,
         )
     }
 
+    fn baseline(&self) -> Result<Option<f32>> {
+        let bbox = self.bounding_box()?;
+        let first_baseline = match self.renderer {
+            // The first line is put at dy = font_size * 2 (see create_text_element)
+            TextRenderer::ImageMagickCompat => self.options.font_size as f32 * 2.0,
+            // resvg hangs the text at 80% of the ascender above the baseline
+            TextRenderer::Resvg => {
+                let face = self
+                    .face()
+                    .ok_or_else(|| format!("Font '{}' not found.", self.options.font_name))?;
+                self.options.ascent(face)? * 0.8
+            }
+        };
+        Ok(Some(first_baseline - bbox.y))
+    }
+
     fn render(&self) -> Result<svge::Group> {
-        let text_element = create_text_element(
-            &self.options.font_name,
-            &self.options.font_weight,
-            self.options.font_size,
-            self.options.line_height,
-            &self.texts,
-        );
+        let text_element =
+            create_text_element(&self.options, self.face(), &self.texts, self.renderer);
         Ok(enclose_group(text_element))
     }
 }
@@ -230,36 +828,35 @@ impl Display for Text {
 }
 
 fn create_text_element(
-    font_name: &str,
-    font_weight: &str,
-    font_size: u32,
-    line_height: u32,
+    options: &TextOptions,
+    face: Option<fontdb::ID>,
     texts: &[String],
+    renderer: TextRenderer,
 ) -> svge::Text {
     let mut text = svge::Text::new("")
-        .set("font-family", font_name)
-        .set("font-weight", font_weight)
-        .set("font-size", font_size)
+        .set("font-family", options.font_family(face))
+        .set("font-weight", options.font_weight.as_str())
+        .set("font-size", options.font_size)
         .set("fill", "black")
         .set("text-anchor", "start")
         .set("xml:space", "preserve")
+        .set("y", 0);
+
+    let mut dy = match renderer {
         // ImageMagick `convert` does not respect dominant-baseline.
         // So, if ptouch creates an SVG with dominant-baseline
         // and convert it by ImageMagick, PNG will be broken.
-        // It is sad.
-        // .set("dominant-baseline", "hanging")
-        .set("y", 0);
-
-    // As for resvg crate, which is used in ptouch,
-    // it renders the text out of ViewBox without dominant-baseline="hanging"
-    //
-    // Therefore, we have to use large `dy` at the first line to
-    // put the whole line in ViewBox. Double the font size will work.
-    //
-    // If you do not care about ImageMagic, enable dominant-baseline="hanging" and:
-    //   let mut dy = 0;
-    // is enough.
-    let mut dy = font_size * 2;
+        //
+        // Without dominant-baseline="hanging", the text is rendered out
+        // of ViewBox. Therefore, we have to use large `dy` at the first
+        // line to put the whole line in ViewBox. Double the font size will
+        // work.
+        TextRenderer::ImageMagickCompat => options.font_size * 2,
+        TextRenderer::Resvg => {
+            text = text.set("dominant-baseline", "hanging");
+            0
+        }
+    };
 
     for line in texts {
         let str = if line.is_empty() {
@@ -271,13 +868,13 @@ fn create_text_element(
         };
         let tspan = svge::TSpan::new(str).set("x", 0).set("dy", dy);
         text = text.add(tspan);
-        dy = line_height; // Subsequent lines use normal line height
+        dy = options.line_height; // Subsequent lines use normal line height
     }
 
     text
 }
 
-fn validate_font(font_name: &str, fontdb: &Database) -> Result<()> {
+pub(crate) fn validate_font(font_name: &str, fontdb: &Database) -> Result<()> {
     // Check if the specified font family exists in the database
     let font_found = fontdb.faces().any(|face| {
         face.families
@@ -292,124 +889,410 @@ fn validate_font(font_name: &str, fontdb: &Database) -> Result<()> {
     Ok(())
 }
 
+/// Check that a font-family attribute of SVG names a font in `fontdb`
+///
+/// Any of the comma-separated names will do, either a family or the
+/// PostScript name of a face.
+pub(crate) fn validate_font_family(value: &str, fontdb: &Database) -> Result<()> {
+    let names: Vec<&str> = value
+        .split(',')
+        .map(|name| name.trim().trim_matches(['\'', '"']))
+        .collect();
+    let found = names.iter().any(|name| {
+        post_script_face(fontdb, name).is_some() || validate_font(name, fontdb).is_ok()
+    });
+    if !found {
+        return Err(format!("Font '{}' not found.", names.join(", ")).into());
+    }
+    Ok(())
+}
+
+/// Face with the PostScript name, the first one by path if duplicated
+fn post_script_face(fontdb: &Database, name: &str) -> Option<fontdb::ID> {
+    let face = fontdb
+        .faces()
+        .filter(|face| face.post_script_name == name)
+        .min_by_key(|face| crate::face_location(face))?;
+    Some(face.id)
+}
+
+/// Select fonts for usvg with [`crate::select_face`]
+///
+/// PostScript names written by [`TextOptions`] pick their face exactly.
+/// Generic families and the fallback are left to the default selector.
+pub(crate) fn font_resolver() -> usvg::FontResolver<'static> {
+    let default_selector = usvg::FontResolver::default_font_selector();
+    usvg::FontResolver {
+        select_font: Box::new(move |font, fontdb| {
+            let style = match font.style() {
+                usvg::FontStyle::Normal => fontdb::Style::Normal,
+                usvg::FontStyle::Italic => fontdb::Style::Italic,
+                usvg::FontStyle::Oblique => fontdb::Style::Oblique,
+            };
+            let weight = fontdb::Weight(font.weight());
+            font.families()
+                .iter()
+                .find_map(|family| match family {
+                    usvg::FontFamily::Named(name) => post_script_face(fontdb, name)
+                        .or_else(|| crate::select_face(fontdb, name, weight, style)),
+                    _ => None,
+                })
+                .or_else(|| default_selector(font, fontdb))
+        }),
+        ..Default::default()
+    }
+}
+
+/// Maximum width and height of the scratch canvas to measure text
+const MAX_CANVAS_SIZE: usize = 16384;
+
+/// Cap height per em of typical Latin fonts, used without font metrics
+const CAP_HEIGHT_RATIO: f32 = 0.7;
+
+/// Error for text that does not fit in the scratch canvas
+#[derive(Debug)]
+pub struct TextTooLarge {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Display for TextTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Text too large: {}x{} pixels exceeds {}x{}. Wrap long lines into multiple lines or use a smaller font size",
+            self.width, self.height, MAX_CANVAS_SIZE, MAX_CANVAS_SIZE
+        )
+    }
+}
+
+impl std::error::Error for TextTooLarge {}
+
+/// Measure text lines as a [`Text`] element without creating one
+///
+/// The bounding box of the ink, e.g. to lay out labels outside of
+/// ptouch. Fails if the font is not in the fontdb of `options`.
+///
+/// ```
+/// use ptouch::element::{TextOptions, measure_text};
+///
+/// // Font shipped for the tests
+/// let fontdb = ptouch::load_fontdb_with_paths(&["attic/fonts".into()]).unwrap();
+/// let options = TextOptions {
+///     font_name: "Noto Sans JP".to_string(),
+///     ..TextOptions::new(fontdb)
+/// };
+/// let bbox = measure_text(&["Hello".to_string()], &options).unwrap();
+/// assert!(bbox.width > 0.0 && bbox.height <= options.font_size as f32 * 1.5);
+/// ```
+pub fn measure_text(texts: &[String], options: &TextOptions) -> Result<BoundingBox> {
+    measure_text_with(
+        texts,
+        options,
+        TextMeasure::default(),
+        TextRenderer::default(),
+        false,
+    )
+}
+
+/// Measure text lines as [`measure_text`] in the way of `measure`,
+/// written for `renderer`
+///
+/// `trim_bearing` starts the box at the ink (see [`Text::with_trim_bearing`]).
+pub fn measure_text_with(
+    texts: &[String],
+    options: &TextOptions,
+    measure: TextMeasure,
+    renderer: TextRenderer,
+    trim_bearing: bool,
+) -> Result<BoundingBox> {
+    validate_font(&options.font_name, &options.fontdb)?;
+    let face = options.select_face();
+    calculate_text_bbox(options, face, texts, measure, renderer, trim_bearing)
+}
+
 fn calculate_text_bbox(
-    font_name: &str,
-    font_weight: &str,
-    font_size: u32,
-    line_height: u32,
+    options: &TextOptions,
+    face: Option<fontdb::ID>,
     texts: &[String],
-    fontdb: &Arc<Database>,
+    measure: TextMeasure,
+    renderer: TextRenderer,
+    trim_bearing: bool,
 ) -> Result<BoundingBox> {
-    // Create a temporary SVG for pre-rendering
-    let max_line_length = texts.iter().map(|s| s.chars().count()).max().unwrap_or(0);
+    let font_size = options.font_size;
+    let line_height = options.line_height;
+    // Create a temporary SVG for pre-rendering. Combining characters
+    // share the cell of their base, so count grapheme clusters
+    let max_line_length = texts
+        .iter()
+        .map(|s| s.graphemes(true).count())
+        .max()
+        .unwrap_or(0);
     let line_count = texts.len();
 
+    // First line starts at dy = font_size * 2 and the following lines
+    // advance by line_height, which may be smaller or larger than font_size
+    let line_advance = line_height.max(font_size) as usize;
     let vw = max_line_length * font_size as usize + 500;
-    let vh = line_count * font_size as usize + 500;
+    let vh = font_size as usize * 2 + line_count * line_advance + 500;
+
+    let fontdb = &options.fontdb;
+    let txt = create_text_element(options, face, texts, renderer);
+    let text_svg = |vw: usize, vh: usize| {
+        svg::Document::new()
+            .set("viewBox", (0, 0, vw, vh))
+            .set("xmlns", "http://www.w3.org/2000/svg")
+            .add(txt.clone())
+            .to_string()
+    };
+    let mut svg = text_svg(vw, vh);
+
+    // The estimate above assumes every glyph is as wide as font_size.
+    // For long lines, shrink the canvas to the logical extent of the text
+    // instead of allocating a huge pixmap.
+    if vw > MAX_CANVAS_SIZE || vh > MAX_CANVAS_SIZE {
+        let tree = usvg::Tree::from_str(&svg, &usvg_options(fontdb, false))?;
+        let extent = tree.root().abs_bounding_box();
+        let margin = font_size as f32;
+        let width = ((extent.right() + margin).ceil() as usize).min(vw);
+        let height = ((extent.bottom() + margin).ceil() as usize).min(vh);
+
+        if width > MAX_CANVAS_SIZE || height > MAX_CANVAS_SIZE {
+            return Err(Box::new(TextTooLarge { width, height }));
+        }
+        svg = text_svg(width, height);
+    }
 
-    let txt = create_text_element(font_name, font_weight, font_size, line_height, texts);
-    let doc = svg::Document::new()
-        .set("viewBox", (0, 0, vw, vh))
-        .set("xmlns", "http://www.w3.org/2000/svg")
-        .add(txt);
-    let svg = doc.to_string();
+    let bbox = match measure {
+        TextMeasure::Pixel => pixel_ink_bbox(&svg, fontdb)?,
+        TextMeasure::Logical => logical_ink_bbox(&svg, fontdb)?,
+    };
+    if trim_bearing {
+        Ok(bbox)
+    } else {
+        Ok(keep_left_bearing(bbox))
+    }
+}
 
-    // let result = calculate_text_logical_bbox(&svg, fontdb)?;
-    let result = calculate_pixel_bbox(&svg, fontdb)?;
+/// How to rasterize SVG into pixels
+#[derive(Clone, Copy, Debug)]
+pub struct RenderOptions {
+    /// Smooth edges with gray pixels
+    pub antialias: bool,
+    /// Pixels per SVG unit (e.g. 2.0 to supersample)
+    pub dpi_scale: f32,
+}
 
-    Ok(result)
+impl RenderOptions {
+    /// Crisp edges to measure ink stably regardless of subpixel positions
+    pub fn measure() -> Self {
+        RenderOptions {
+            antialias: false,
+            dpi_scale: 1.0,
+        }
+    }
+
+    /// Smooth edges for final output, thresholded to 1-bit when printing
+    pub fn output() -> Self {
+        RenderOptions {
+            antialias: true,
+            dpi_scale: 1.0,
+        }
+    }
 }
 
-pub fn render_svg_to_pixmap(
-    svg_data: &str,
-    fontdb: &Arc<Database>,
-    enable_antialiasing: bool,
-) -> Result<tiny_skia::Pixmap> {
-    let options = if enable_antialiasing {
+fn usvg_options(fontdb: &Arc<Database>, antialias: bool) -> usvg::Options<'static> {
+    if antialias {
         usvg::Options {
             fontdb: fontdb.clone(),
+            font_resolver: font_resolver(),
             ..Default::default()
         }
     } else {
         usvg::Options {
             fontdb: fontdb.clone(),
+            font_resolver: font_resolver(),
             text_rendering: usvg::TextRendering::OptimizeSpeed,
             shape_rendering: usvg::ShapeRendering::CrispEdges,
             ..Default::default()
         }
-    };
+    }
+}
+
+/// Convert text of SVG into paths of the glyph outlines
+///
+/// The result shows the same without the fonts, e.g. on other machines.
+/// Only `<text>` elements are rewritten, each on its own, so the rest of
+/// the document (root attributes, classes, ...) stays as it is.
+pub fn text_to_paths(svg_data: &str, fontdb: &Arc<Database>) -> Result<String> {
+    const TEXT_END: &str = "</text>";
+    let options = usvg_options(fontdb, true);
+    let mut result = String::with_capacity(svg_data.len());
+    let mut rest = svg_data;
+    let mut count = 0;
+
+    while let Some(start) = rest.find("<text") {
+        let end = rest[start..]
+            .find(TEXT_END)
+            .map(|len| start + len + TEXT_END.len())
+            .ok_or("Unclosed text element in SVG")?;
+        let text_svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">{}</svg>"#,
+            &rest[start..end]
+        );
+        let tree = usvg::Tree::from_str(&text_svg, &options)?;
+        // IDs of the fragments must not clash in the document
+        let write_options = usvg::WriteOptions {
+            id_prefix: Some(format!("text{}-", count)),
+            ..Default::default()
+        };
+        result.push_str(&rest[..start]);
+        result.push_str(svg_content(&tree.to_string(&write_options)));
+        rest = &rest[end..];
+        count += 1;
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Children of the root element of SVG document `svg_data`
+fn svg_content(svg_data: &str) -> &str {
+    let content = svg_data
+        .find("<svg")
+        .and_then(|root| svg_data[root..].find('>').map(|len| root + len))
+        .filter(|&end| !svg_data[..end].ends_with('/'))
+        .zip(svg_data.rfind("</svg>"));
+    match content {
+        Some((end, close)) => svg_data[end + 1..close].trim(),
+        None => "",
+    }
+}
 
-    let tree = usvg::Tree::from_str(svg_data, &options)?;
+/// Rasterize SVG into a pixmap of its size times `options.dpi_scale`
+pub fn render_svg_to_pixmap(
+    svg_data: &str,
+    fontdb: &Arc<Database>,
+    options: &RenderOptions,
+) -> Result<tiny_skia::Pixmap> {
+    let scale = options.dpi_scale;
+    if !(scale.is_finite() && scale > 0.0) {
+        return Err(format!("DPI scale must be positive: {}", scale).into());
+    }
+
+    let tree = usvg::Tree::from_str(svg_data, &usvg_options(fontdb, options.antialias))?;
     let size = tree.size().to_int_size();
+    let (width, height) = (
+        (size.width() as f32 * scale).ceil() as u32,
+        (size.height() as f32 * scale).ceil() as u32,
+    );
 
-    let mut pixmap =
-        tiny_skia::Pixmap::new(size.width(), size.height()).ok_or("Failed to create pixmap")?;
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or_else(|| {
+        format!(
+            "Failed to create {}x{} pixmap (empty or too large image)",
+            width, height
+        )
+    })?;
 
     // Render SVG to pixmap
     resvg::render(
         &tree,
-        tiny_skia::Transform::identity(),
+        tiny_skia::Transform::from_scale(scale, scale),
         &mut pixmap.as_mut(),
     );
 
     Ok(pixmap)
 }
 
-// Calculate text bounding box using SVG text metrics instead of pixel scanning
-// **doesn't actually work**
-//
-// This is because this function assumes that Text has `dominant-baseline="hanging"`
-// and the first tspan is created with `dy="0"`.  The SVG created by
-// `create_text_element` doesn't satisfy these conditions.
-//
-// Additionally, this function generates a larger bbox than pixel
-// scanning. While it's not suitable for creating a PNG that fits
-// perfectly on tape, I've kept it as it may be useful for debugging
-// purposes.
-//
-#[allow(dead_code)]
-fn calculate_text_logical_bbox(
-    svg_data: &str,
-    fontdb: &Arc<Database>,
-    enable_antialiasing: bool,
-) -> Result<BoundingBox> {
-    let options = if enable_antialiasing {
-        usvg::Options {
-            fontdb: fontdb.clone(),
-            ..Default::default()
-        }
-    } else {
-        usvg::Options {
-            fontdb: fontdb.clone(),
-            text_rendering: usvg::TextRendering::OptimizeSpeed,
-            shape_rendering: usvg::ShapeRendering::CrispEdges,
-            ..Default::default()
+/// Calculate the bounding box of text from its glyph outlines
+///
+/// Same frame and left edge as [`calculate_pixel_bbox`], without
+/// rendering: the outlines are rounded out to whole pixels. The text box
+/// of SVG (`Text::bounding_box` of usvg) spans the ascent and descent of
+/// the font, so the outlines laid out by usvg are used instead.
+pub fn calculate_logical_bbox(svg_data: &str, fontdb: &Arc<Database>) -> Result<BoundingBox> {
+    Ok(keep_left_bearing(logical_ink_bbox(svg_data, fontdb)?))
+}
+
+/// Bounding box of the glyph outlines with the real left edge
+fn logical_ink_bbox(svg_data: &str, fontdb: &Arc<Database>) -> Result<BoundingBox> {
+    fn ink_bounds(group: &usvg::Group, bounds: &mut Option<usvg::Rect>) {
+        for node in group.children() {
+            let rect = match node {
+                usvg::Node::Text(text) => Some(text.flattened().abs_bounding_box()),
+                usvg::Node::Group(group) => {
+                    ink_bounds(group, bounds);
+                    None
+                }
+                _ => Some(node.abs_bounding_box()),
+            };
+            if let Some(rect) = rect {
+                *bounds = Some(match *bounds {
+                    Some(b) => usvg::Rect::from_ltrb(
+                        b.left().min(rect.left()),
+                        b.top().min(rect.top()),
+                        b.right().max(rect.right()),
+                        b.bottom().max(rect.bottom()),
+                    )
+                    .unwrap_or(rect),
+                    None => rect,
+                });
+            }
         }
-    };
+    }
 
-    let tree = usvg::Tree::from_str(svg_data, &options)?;
+    let tree = usvg::Tree::from_str(svg_data, &usvg_options(fontdb, false))?;
+    let mut bounds = None;
+    ink_bounds(tree.root(), &mut bounds);
+
+    match bounds {
+        Some(rect) if rect.width() > 0.0 && rect.height() > 0.0 => {
+            let left = rect.left().floor().max(0.0);
+            let top = rect.top().floor();
+            Ok(BoundingBox {
+                x: left,
+                y: top,
+                width: rect.right().ceil() - left,
+                height: rect.bottom().ceil() - top,
+            })
+        }
+        // Blank text has no outlines
+        _ => pixel_ink_bbox(svg_data, fontdb),
+    }
+}
 
-    // Try to get the overall bounding box of the SVG tree
-    // This should include all elements including text with proper whitespace positioning
-    let bbox = tree.root().abs_bounding_box();
+/// Calculate the bounding box of inked (non-white) pixels of SVG
+///
+/// The left edge is always 0 to keep the left side bearing of text.
+/// This is the hot path of text layout.
+pub fn calculate_pixel_bbox(svg_data: &str, fontdb: &Arc<Database>) -> Result<BoundingBox> {
+    Ok(keep_left_bearing(pixel_ink_bbox(svg_data, fontdb)?))
+}
 
-    // Check if the bounding box has valid dimensions
-    if bbox.width() > 0.0 && bbox.height() > 0.0 {
-        Ok(BoundingBox {
-            width: bbox.width(),
-            height: bbox.height(),
-            x: bbox.left(),
-            y: bbox.top(),
-        })
-    } else {
-        // Fallback to pixel-based calculation if no valid bounding box found
-        calculate_pixel_bbox(svg_data, fontdb)
+/// Extend `bbox` to the left edge of the canvas
+///
+/// A bbox that only cuts out the real drawing area would start at the ink,
+/// but text keeps the left edge at 0 for the following reasons:
+///
+/// 1. preserve the spaces that users put at the beginning of lines.
+/// 2. The size of the left side bearing of the first character differs
+///    for each glyph, so when Text elements are arranged vertically, they
+///    may become uneven.
+///
+/// Therefore, it will result in generating a wider bbox with the
+/// left-side bearing of the first character. [`Text::with_trim_bearing`]
+/// skips this for tight standalone labels.
+fn keep_left_bearing(bbox: BoundingBox) -> BoundingBox {
+    BoundingBox {
+        x: 0.0,
+        width: bbox.x + bbox.width,
+        ..bbox
     }
 }
 
-fn calculate_pixel_bbox(svg_data: &str, fontdb: &Arc<Database>) -> Result<BoundingBox> {
+/// Bounding box of inked pixels with the real left edge
+fn pixel_ink_bbox(svg_data: &str, fontdb: &Arc<Database>) -> Result<BoundingBox> {
     // Use shared rendering logic
-    let pixmap = render_svg_to_pixmap(svg_data, fontdb, false)?;
+    let pixmap = render_svg_to_pixmap(svg_data, fontdb, &RenderOptions::measure())?;
 
     // Find actual pixel bounds (like ImageMagick's %@)
     let pixels = pixmap.data();
@@ -441,19 +1324,6 @@ fn calculate_pixel_bbox(svg_data: &str, fontdb: &Arc<Database>) -> Result<Boundi
         }
     }
 
-    // If you want to get a bbox that only cuts out the real drawing area,
-    // you should not set min_x to 0, but I set it to 0 for the following reasons:
-    //
-    // 1. preserve the spaces that users put at the beginning of lines.
-    // 2. The size of the left side bearing of the first character differs
-    //    for each glyph, so when Text elements are arranged vertically, they
-    //    may become uneven.
-    //
-    // Therefore, it will result in generating a wider bbox with the
-    // left-side bearing of the first character.
-    //
-    min_x = 0;
-
     let result = if found_pixel {
         BoundingBox {
             x: min_x as f32,
@@ -477,69 +1347,164 @@ fn calculate_pixel_bbox(svg_data: &str, fontdb: &Arc<Database>) -> Result<Boundi
 pub struct QrCode {
     data: String,
     module_size: f32,
+    ec_level: QrEcLevel,
+    label: Option<String>,
+    /// Modules of the encoded symbol, row by row
+    modules: Vec<qrcode::Color>,
+    /// Number of modules on a side
+    width: usize,
 }
 
 impl QrCode {
     pub fn new(data: String) -> Result<Self> {
-        // Validate that the data can be encoded as QR code
-        qrcode::QrCode::new(&data)?;
+        Self::with_options(data, &QrOptions::default())
+    }
 
+    pub fn with_options(data: String, options: &QrOptions) -> Result<Self> {
+        // Encoded once here; layout asks for the bounding box many times
+        let (modules, width) = encode_qr(&data, options.ec_level)?;
         Ok(QrCode {
             data,
-            module_size: 5.0, // 5 SVG units ≈ 0.35mm at 360dpi FIXME: 360DPI
+            module_size: options.module_size,
+            ec_level: options.ec_level,
+            label: None,
+            modules,
+            width,
         })
     }
 
+    /// Create QR code of Wi-Fi network config
+    ///
+    /// Displayed as `Wifi(SSID)` to keep the password out of logs.
+    pub fn wifi(ssid: &str, password: &str, auth: WifiAuth, options: &QrOptions) -> Result<Self> {
+        let mut qr_code = Self::with_options(payload::wifi(ssid, password, auth), options)?;
+        qr_code.label = Some(format!("Wifi({})", ssid));
+        Ok(qr_code)
+    }
+
+    /// Data encoded in the QR code
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+
+    /// Change the data, which must be encodable as QR code
+    pub fn set_data(&mut self, data: String) -> Result<()> {
+        (self.modules, self.width) = encode_qr(&data, self.ec_level)?;
+        self.data = data;
+        self.label = None;
+        Ok(())
+    }
+
+    /// Error correction level of the QR code
+    pub fn ec_level(&self) -> QrEcLevel {
+        self.ec_level
+    }
+
     /// Compact version of render with optimized path data
     fn render_compact(&self) -> Result<Box<dyn svg::Node>> {
-        let qr = qrcode::QrCode::new(&self.data)?;
-        let modules = qr.to_colors();
-        let width = qr.width();
-
-        let mut path_data = String::new();
-
-        for y in 0..width {
-            let y_pos = y as f32 * self.module_size;
-            let mut x = 0;
-
-            while x < width {
-                let index = y * width + x;
-
-                if modules[index] == qrcode::Color::Dark {
-                    // Find consecutive dark modules in this row
-                    let start_x = x;
-                    while x < width {
-                        let next_index = y * width + x;
-                        if modules[next_index] != qrcode::Color::Dark {
-                            break;
-                        }
-                        x += 1;
-                    }
-                    let run_length = x - start_x;
-
-                    let x_pos = start_x as f32 * self.module_size;
-                    let width_val = run_length as f32 * self.module_size;
-
-                    // Always use absolute positioning for clarity
-                    path_data.push_str(&format!("M{},{}", x_pos, y_pos));
-
-                    // Draw rectangle: horizontal line, vertical line, horizontal back, close
-                    path_data.push_str(&format!(
-                        "h{}v{}h-{}z",
-                        width_val, self.module_size, width_val
-                    ));
-                } else {
+        let width = self.width;
+        let path = dark_modules_path(width, self.module_size, 0.0, |x, y| {
+            self.modules[y * width + x] == qrcode::Color::Dark
+        });
+        Ok(Box::new(path))
+    }
+}
+
+/// Path of the dark modules of a square 2D code, one rectangle per run in a row
+///
+/// `offset` moves the modules right and down, e.g. to leave a quiet zone.
+fn dark_modules_path(
+    width: usize,
+    module_size: f32,
+    offset: f32,
+    is_dark: impl Fn(usize, usize) -> bool,
+) -> svge::Path {
+    let mut path_data = String::new();
+
+    for y in 0..width {
+        let y_pos = offset + y as f32 * module_size;
+        let mut x = 0;
+
+        while x < width {
+            if is_dark(x, y) {
+                // Find consecutive dark modules in this row
+                let start_x = x;
+                while x < width && is_dark(x, y) {
                     x += 1;
                 }
+                let run_length = x - start_x;
+
+                let x_pos = offset + start_x as f32 * module_size;
+                let width_val = run_length as f32 * module_size;
+
+                // Always use absolute positioning for clarity
+                path_data.push_str(&format!("M{},{}", x_pos, y_pos));
+
+                // Draw rectangle: horizontal line, vertical line, horizontal back, close
+                path_data.push_str(&format!("h{}v{}h-{}z", width_val, module_size, width_val));
+            } else {
+                x += 1;
             }
         }
+    }
+
+    svge::Path::new()
+        .set("d", path_data)
+        .set("fill", "black")
+        .set("fill-rule", "evenodd")
+}
 
-        let path = svge::Path::new()
-            .set("d", path_data)
-            .set("fill", "black")
-            .set("fill-rule", "evenodd");
+/// Encode `data` into QR code modules and the number of them on a side
+fn encode_qr(data: &str, ec_level: QrEcLevel) -> Result<(Vec<qrcode::Color>, usize)> {
+    let qr = qrcode::QrCode::with_error_correction_level(data, ec_level.into())?;
+    let width = qr.width();
+    Ok((qr.into_colors(), width))
+}
 
-        Ok(Box::new(path))
+/// Modules of blank margin around Aztec codes
+///
+/// Aztec codes need no quiet zone to be found, but a small one keeps
+/// neighbouring ink off the outer layer.
+pub const AZTEC_QUIET_ZONE: usize = 2;
+
+pub struct Aztec {
+    data: String,
+    module_size: f32,
+    ec_percent: u8,
+    symbol: aztec::AztecCode,
+}
+
+impl Aztec {
+    pub fn new(data: String) -> Result<Self> {
+        Self::with_options(data, &QrOptions::default(), aztec::DEFAULT_EC_PERCENT)
+    }
+
+    /// Encode `data` with `ec_percent` of the symbol for error correction
+    ///
+    /// Only the module size of `options` applies; Aztec codes have no EC levels.
+    pub fn with_options(data: String, options: &QrOptions, ec_percent: u8) -> Result<Self> {
+        let symbol = aztec::AztecCode::encode(data.as_bytes(), ec_percent)?;
+        Ok(Aztec {
+            data,
+            module_size: options.module_size,
+            ec_percent,
+            symbol,
+        })
+    }
+
+    /// Data encoded in the Aztec code
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+
+    /// Share of the symbol for error correction in percent
+    pub fn ec_percent(&self) -> u8 {
+        self.ec_percent
+    }
+
+    /// Number of modules on a side, without quiet zone
+    pub fn symbol_size(&self) -> usize {
+        self.symbol.size()
     }
 }
 
@@ -552,6 +1517,25 @@ impl Row {
     pub fn new(elements: Vec<Box<dyn Element>>, options: RowOptions) -> Self {
         Row { elements, options }
     }
+
+    pub fn options(&self) -> &RowOptions {
+        &self.options
+    }
+
+    /// Y offsets of elements to line up their baselines
+    fn baseline_offsets(&self) -> Result<Vec<f32>> {
+        let ascents = self
+            .elements
+            .iter()
+            .map(|elm| match elm.baseline()? {
+                Some(baseline) => Ok(baseline),
+                None => Ok(elm.bounding_box()?.height),
+            })
+            .collect::<Result<Vec<f32>>>()?;
+
+        let max_ascent = ascents.iter().copied().fold(0.0, f32::max);
+        Ok(ascents.iter().map(|ascent| max_ascent - ascent).collect())
+    }
 }
 
 impl Element for Row {
@@ -562,38 +1546,71 @@ impl Element for Row {
 
         let padding = BoundingBox::new(self.options.padding, 0.0, 0.0, 0.0);
         let mut combined = BoundingBox::default();
-        let mut prev_was_visible = false;
+        let mut prev_was_padded = false;
 
         for elm in &self.elements {
             let bbox = elm.bounding_box()?;
 
-            // Add padding between visible elements
-            if elm.is_visible() && prev_was_visible {
+            // Add padding between padded elements
+            if elm.is_padded() && prev_was_padded {
                 combined = combined.h_append(padding);
             }
 
             combined = combined.h_append(bbox);
 
             // Update flag for next iteration
-            prev_was_visible = elm.is_visible();
+            prev_was_padded = elm.is_padded();
+        }
+
+        // Elements hang below and above the baseline
+        if let VerticalAlign::Baseline = self.options.align {
+            combined.height = 0.0;
+            for (elm, y) in self.elements.iter().zip(self.baseline_offsets()?) {
+                combined.height = combined.height.max(y + elm.bounding_box()?.height);
+            }
         }
 
         Ok(combined)
     }
 
     fn render(&self) -> Result<svge::Group> {
-        let mut group = svge::Group::new();
+        render_children(self.child_offsets()?)
+    }
+
+    fn baseline(&self) -> Result<Option<f32>> {
+        // Nested rows line up with the shared baseline of their elements
+        if !matches!(self.options.align, VerticalAlign::Baseline) {
+            return Ok(None);
+        }
+        for (elm, y) in self.elements.iter().zip(self.baseline_offsets()?) {
+            if let Some(baseline) = elm.baseline()? {
+                return Ok(Some(y + baseline));
+            }
+        }
+        Ok(None)
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Box<dyn Element>> {
+        self.elements.iter_mut().collect()
+    }
+
+    fn child_offsets(&self) -> Result<Vec<ChildOffset<'_>>> {
+        let mut children = Vec::new();
         let mut x = 0.0;
 
         // Get maximum height from our own bounding box
         let height = self.bounding_box()?.height;
-        let mut prev_was_visible = false;
+        let baseline_offsets = match self.options.align {
+            VerticalAlign::Baseline => self.baseline_offsets()?,
+            _ => Vec::new(),
+        };
+        let mut prev_was_padded = false;
 
-        for elm in &self.elements {
+        for (i, elm) in self.elements.iter().enumerate() {
             let bbox = elm.bounding_box()?;
 
-            // Add padding between visible elements
-            if elm.is_visible() && prev_was_visible {
+            // Add padding between padded elements
+            if elm.is_padded() && prev_was_padded {
                 x += self.options.padding;
             }
 
@@ -602,21 +1619,18 @@ impl Element for Row {
                 VerticalAlign::Top => 0.0,
                 VerticalAlign::Center => (height - bbox.height) / 2.0,
                 VerticalAlign::Bottom => height - bbox.height,
+                VerticalAlign::Baseline => baseline_offsets[i],
             };
 
-            // Only render visible elements
-            if elm.is_visible() {
-                let eg = elm.render_at(x, y)?;
-                group = group.add(eg);
-            }
+            children.push((elm.as_ref(), x, y));
 
             x += bbox.width;
 
             // Update flag for next iteration
-            prev_was_visible = elm.is_visible();
+            prev_was_padded = elm.is_padded();
         }
 
-        Ok(group)
+        Ok(children)
     }
 }
 
@@ -636,6 +1650,11 @@ impl Column {
     pub fn new(elements: Vec<Box<dyn Element>>, padding: f32) -> Self {
         Column { elements, padding }
     }
+
+    /// Vertical space between elements in SVG units
+    pub fn padding(&self) -> f32 {
+        self.padding
+    }
 }
 
 impl Element for Column {
@@ -646,51 +1665,55 @@ impl Element for Column {
 
         let padding = BoundingBox::new(0.0, self.padding, 0.0, 0.0);
         let mut combined = BoundingBox::default();
-        let mut prev_was_visible = false;
+        let mut prev_was_padded = false;
 
         for elm in &self.elements {
             let bbox = elm.bounding_box()?;
 
-            // Add padding between visible elements
-            if elm.is_visible() && prev_was_visible {
+            // Add padding between padded elements
+            if elm.is_padded() && prev_was_padded {
                 combined = combined.v_append(padding);
             }
 
             combined = combined.v_append(bbox);
 
             // Update flag for next iteration
-            prev_was_visible = elm.is_visible();
+            prev_was_padded = elm.is_padded();
         }
 
         Ok(combined)
     }
 
     fn render(&self) -> Result<svge::Group> {
-        let mut group = svge::Group::new();
+        render_children(self.child_offsets()?)
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Box<dyn Element>> {
+        self.elements.iter_mut().collect()
+    }
+
+    fn child_offsets(&self) -> Result<Vec<ChildOffset<'_>>> {
+        let mut children = Vec::new();
         let mut y = 0.0;
-        let mut prev_was_visible = false;
+        let mut prev_was_padded = false;
 
         for elm in &self.elements {
             let bbox = elm.bounding_box()?;
 
-            // Add padding between visible elements
-            if elm.is_visible() && prev_was_visible {
+            // Add padding between padded elements
+            if elm.is_padded() && prev_was_padded {
                 y += self.padding;
             }
 
-            // Only render visible elements
-            if elm.is_visible() {
-                let eg = elm.render_at(0.0, y)?;
-                group = group.add(eg);
-            }
+            children.push((elm.as_ref(), 0.0, y));
 
             y += bbox.height;
 
             // Update flag for next iteration
-            prev_was_visible = elm.is_visible();
+            prev_was_padded = elm.is_padded();
         }
 
-        Ok(group)
+        Ok(children)
     }
 }
 
@@ -703,9 +1726,7 @@ impl Display for Column {
 
 impl Element for QrCode {
     fn bounding_box(&self) -> Result<BoundingBox> {
-        let qr = qrcode::QrCode::new(&self.data)?;
-        let width = qr.width() as f32;
-        let size = width * self.module_size;
+        let size = self.width as f32 * self.module_size;
 
         Ok(BoundingBox {
             width: size,
@@ -719,11 +1740,49 @@ impl Element for QrCode {
         let path = self.render_compact()?;
         Ok(enclose_group(path))
     }
+
+    fn type_name(&self) -> String {
+        "QrCode".to_string()
+    }
+}
+
+impl Element for Aztec {
+    fn bounding_box(&self) -> Result<BoundingBox> {
+        let size = (self.symbol.size() + AZTEC_QUIET_ZONE * 2) as f32 * self.module_size;
+
+        Ok(BoundingBox {
+            width: size,
+            height: size,
+            x: 0.0,
+            y: 0.0,
+        })
+    }
+
+    fn render(&self) -> Result<svge::Group> {
+        let offset = AZTEC_QUIET_ZONE as f32 * self.module_size;
+        let path = dark_modules_path(self.symbol.size(), self.module_size, offset, |x, y| {
+            self.symbol.is_dark(x, y)
+        });
+        Ok(enclose_group(path))
+    }
+
+    fn type_name(&self) -> String {
+        "Aztec".to_string()
+    }
+}
+
+impl Display for Aztec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Aztec({})", self.data)
+    }
 }
 
 impl Display for QrCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "QrCode({})", self.data)
+        match &self.label {
+            Some(label) => write!(f, "{}", label),
+            None => write!(f, "QrCode({})", self.data),
+        }
     }
 }
 
@@ -731,6 +1790,7 @@ pub struct Gap {
     width: f32,
     height: f32,
     visible: bool,
+    padded: bool,
 }
 
 impl Gap {
@@ -739,9 +1799,29 @@ impl Gap {
             width,
             height,
             visible,
+            padded: visible,
         }
     }
 
+    /// Keep the padding of Row/Column around an invisible gap
+    ///
+    /// By default, a gap replaces the padding: `A gap:10 B` puts A and B
+    /// 10 apart. With padding, the gap is added to it: 10 + 2 * padding.
+    pub fn with_padding(mut self, padded: bool) -> Self {
+        self.padded = padded || self.visible;
+        self
+    }
+
+    /// Width and height in SVG units
+    pub fn size(&self) -> (f32, f32) {
+        (self.width, self.height)
+    }
+
+    pub fn set_size(&mut self, width: f32, height: f32) {
+        self.width = width;
+        self.height = height;
+    }
+
     pub fn parse(spec: &str, visible: bool) -> Result<Self> {
         if let Some(x) = spec.find('x') {
             let ws = &spec[..x];
@@ -791,12 +1871,26 @@ impl Element for Gap {
     fn is_visible(&self) -> bool {
         self.visible
     }
+
+    fn is_padded(&self) -> bool {
+        self.padded
+    }
+
+    fn type_name(&self) -> String {
+        if self.visible { "Box" } else { "Gap" }.to_string()
+    }
+
+    fn is_opaque(&self) -> bool {
+        self.visible
+    }
 }
 
 impl Display for Gap {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if self.visible {
             write!(f, "Box({}x{})", self.width, self.height)
+        } else if self.padded {
+            write!(f, "Gap+({}x{})", self.width, self.height)
         } else {
             write!(f, "Gap({}x{})", self.width, self.height)
         }
@@ -814,29 +1908,47 @@ impl Overlay {
 }
 
 impl Element for Overlay {
+    /// Union of the layers in their own coordinates
+    ///
+    /// Layers keep their origins relative to each other, and `render_at`
+    /// translates the whole stack by (-bbox.x, -bbox.y), so a layer
+    /// starting left of or above the others is not clipped.
     fn bounding_box(&self) -> Result<BoundingBox> {
-        self.elements
-            .iter()
-            .map(|e| e.bounding_box())
-            .try_fold(BoundingBox::default(), |acc, bbox| Ok(acc.union(&bbox?)))
+        let mut bboxes = self.elements.iter().map(|e| e.bounding_box());
+        let Some(first) = bboxes.next() else {
+            return Ok(BoundingBox::default());
+        };
+        bboxes.try_fold(first?, |acc, bbox| Ok(acc.union(&bbox?)))
     }
 
     fn render(&self) -> Result<svge::Group> {
-        let mut group = svge::Group::new();
+        render_children(self.child_offsets()?)
+    }
 
-        // Stack layers in order (later layers render on top)
-        for element in &self.elements {
-            let layer_group = element.render_at(0.0, 0.0)?;
-            group = group.add(layer_group);
-        }
+    fn children_mut(&mut self) -> Vec<&mut Box<dyn Element>> {
+        self.elements.iter_mut().collect()
+    }
 
-        Ok(group)
+    fn child_offsets(&self) -> Result<Vec<ChildOffset<'_>>> {
+        // Stack layers in order (later layers render on top), each at
+        // its own origin so that all layers shift by the same amount
+        self.elements
+            .iter()
+            .map(|elm| {
+                let bbox = elm.bounding_box()?;
+                Ok((elm.as_ref(), bbox.x, bbox.y))
+            })
+            .collect()
     }
 
     fn is_visible(&self) -> bool {
         // At least one layer is visible
         self.elements.iter().any(|e| e.is_visible())
     }
+
+    fn is_padded(&self) -> bool {
+        self.elements.iter().any(|e| e.is_padded())
+    }
 }
 
 impl Display for Overlay {
@@ -846,14 +1958,100 @@ impl Display for Overlay {
     }
 }
 
+/// Rotation angle of [`Rotated`] (clockwise)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rotation {
+    Rot90,
+    Rot180,
+    Rot270,
+}
+
+impl Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rotation::Rot90 => write!(f, "Rot90"),
+            Rotation::Rot180 => write!(f, "Rot180"),
+            Rotation::Rot270 => write!(f, "Rot270"),
+        }
+    }
+}
+
+/// Element rotated clockwise about its bounding box
+///
+/// The rotated element is placed so that its new bounding box starts at
+/// (0, 0). Children of the rotated element are not included in `layout()`
+/// since their axes differ from the parent's.
+pub struct Rotated {
+    element: Box<dyn Element>,
+    rotation: Rotation,
+}
+
+impl Rotated {
+    pub fn new(element: Box<dyn Element>, rotation: Rotation) -> Self {
+        Rotated { element, rotation }
+    }
+}
+
+impl Element for Rotated {
+    fn bounding_box(&self) -> Result<BoundingBox> {
+        let bbox = self.element.bounding_box()?;
+        match self.rotation {
+            Rotation::Rot180 => Ok(BoundingBox::new(bbox.width, bbox.height, 0.0, 0.0)),
+            _ => Ok(BoundingBox::new(bbox.height, bbox.width, 0.0, 0.0)),
+        }
+    }
+
+    fn render(&self) -> Result<svge::Group> {
+        let bbox = self.element.bounding_box()?;
+        let group = self.element.render_at(0.0, 0.0)?;
+
+        // Rotate about the origin, then move the result back into (0, 0)-(w, h)
+        let tr = match self.rotation {
+            Rotation::Rot90 => format!("translate({}, 0) rotate(90)", bbox.height),
+            Rotation::Rot180 => format!("translate({}, {}) rotate(180)", bbox.width, bbox.height),
+            Rotation::Rot270 => format!("translate(0, {}) rotate(270)", bbox.width),
+        };
+        // Nested since render_at() sets its own transform on the outer group
+        Ok(enclose_group(
+            svge::Group::new().set("transform", tr).add(group),
+        ))
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Box<dyn Element>> {
+        vec![&mut self.element]
+    }
+
+    fn is_visible(&self) -> bool {
+        self.element.is_visible()
+    }
+
+    fn is_padded(&self) -> bool {
+        self.element.is_padded()
+    }
+
+    fn is_opaque(&self) -> bool {
+        self.element.is_opaque()
+    }
+}
+
+impl Display for Rotated {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.rotation, self.element)
+    }
+}
+
 pub struct Image {
     href: String,
     req_width: Option<f32>,
     req_height: Option<f32>,
     src_width: u32,
     src_height: u32,
+    missing: bool,
 }
 
+/// Size of a placeholder for missing image without requested size
+const PLACEHOLDER_SIZE: u32 = 64;
+
 impl Image {
     pub fn new(href: String, req_width: Option<f32>, req_height: Option<f32>) -> Result<Self> {
         let (src_width, src_height) = read_png_dimensions(&href)?;
@@ -864,9 +2062,79 @@ impl Image {
             req_height,
             src_width,
             src_height,
+            missing: false,
         })
     }
 
+    /// Create a placeholder for a missing image file
+    ///
+    /// Rendered as a crossed-out gray box of the requested size,
+    /// or a square of [`PLACEHOLDER_SIZE`] if no size is given.
+    pub fn placeholder(href: String, req_width: Option<f32>, req_height: Option<f32>) -> Self {
+        Image {
+            href,
+            req_width,
+            req_height,
+            src_width: PLACEHOLDER_SIZE,
+            src_height: PLACEHOLDER_SIZE,
+            missing: true,
+        }
+    }
+
+    /// Create Image from raw RGBA8 pixels (row-major, non-premultiplied)
+    ///
+    /// The raster is encoded as PNG and embedded into the SVG as a data URI,
+    /// so no temporary file is needed.
+    pub fn from_rgba8(width: u32, height: u32, data: &[u8]) -> Result<Self> {
+        if width == 0 || height == 0 {
+            return Err(format!("Invalid image size: {}x{}", width, height).into());
+        }
+
+        let expected = width as usize * height as usize * 4;
+        if data.len() != expected {
+            return Err(format!(
+                "RGBA data length mismatch: {}x{} requires {} bytes, got {}",
+                width,
+                height,
+                expected,
+                data.len()
+            )
+            .into());
+        }
+
+        let mut png_data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut png_data, width, height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(data)?;
+        }
+
+        Ok(Self::from_png_data(&png_data, width, height))
+    }
+
+    /// Create Image from a rendered pixmap
+    pub fn from_pixmap(pixmap: &tiny_skia::Pixmap) -> Result<Self> {
+        let png_data = pixmap.encode_png()?;
+        Ok(Self::from_png_data(
+            &png_data,
+            pixmap.width(),
+            pixmap.height(),
+        ))
+    }
+
+    fn from_png_data(png_data: &[u8], width: u32, height: u32) -> Self {
+        Image {
+            href: format!("data:image/png;base64,{}", BASE64_STANDARD.encode(png_data)),
+            req_width: None,
+            req_height: None,
+            src_width: width,
+            src_height: height,
+            missing: false,
+        }
+    }
+
     fn width(&self) -> f32 {
         let aspect_ratio = self.src_width as f32 / self.src_height as f32;
 
@@ -899,21 +2167,50 @@ impl Element for Image {
     }
 
     fn render(&self) -> Result<svge::Group> {
+        let (width, height) = (self.width(), self.height());
+
+        if self.missing {
+            let rect = svge::Rectangle::new()
+                .set("width", width)
+                .set("height", height)
+                .set("fill", "lightgray")
+                .set("stroke", "gray");
+            let cross = svge::Path::new()
+                .set(
+                    "d",
+                    format!("M0,0 L{w},{h} M{w},0 L0,{h}", w = width, h = height),
+                )
+                .set("stroke", "gray");
+            return Ok(svge::Group::new().add(rect).add(cross));
+        }
+
         let image = svge::Image::new()
             .set("href", self.href.as_str())
-            .set("width", self.width())
-            .set("height", self.height())
+            .set("width", width)
+            .set("height", height)
             .set("preserveAspectRatio", "none")
             .set("x", 0)
             .set("y", 0);
 
         Ok(enclose_group(image))
     }
+
+    fn is_opaque(&self) -> bool {
+        // Labels use images with white background (transparency is not checked)
+        true
+    }
 }
 
 impl Display for Image {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Image({})", self.href)
+        if self.missing {
+            write!(f, "Image(missing:{})", self.href)
+        } else if self.href.starts_with("data:") {
+            // Embedded rasters are too long to show
+            write!(f, "Image(data:{}x{})", self.src_width, self.src_height)
+        } else {
+            write!(f, "Image({})", self.href)
+        }
     }
 }
 
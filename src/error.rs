@@ -0,0 +1,105 @@
+//! Errors tagged with the kind of failure
+//!
+//! Functions still return [`crate::Result`], but errors can be tagged with
+//! an [`ErrorKind`] so the command tells failures apart by exit status:
+//!
+//! | Status | Failure                                        |
+//! |--------|------------------------------------------------|
+//! | 0      | Success                                        |
+//! | 1      | Other errors                                   |
+//! | 2      | Invalid arguments                              |
+//! | 3      | Rendering (fonts, layout scripts, images)      |
+//! | 4      | Communication with the printer                 |
+//! | 5      | Error reported by the printer (e.g. cover open) |
+//! | 6      | Label made for another tape than the loaded one |
+
+use std::fmt;
+
+/// Kind of failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Usage,
+    Render,
+    Communication,
+    Printer,
+    TapeMismatch,
+}
+
+impl ErrorKind {
+    /// Exit status of the command
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Usage => 2,
+            ErrorKind::Render => 3,
+            ErrorKind::Communication => 4,
+            ErrorKind::Printer => 5,
+            ErrorKind::TapeMismatch => 6,
+        }
+    }
+}
+
+/// Error tagged with the kind of failure
+///
+/// Displayed as the wrapped error.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    error: Box<dyn std::error::Error>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, error: impl Into<Box<dyn std::error::Error>>) -> Self {
+        Error {
+            kind,
+            error: error.into(),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// Kind of `error`, None for untagged errors
+pub fn kind_of(error: &(dyn std::error::Error + 'static)) -> Option<ErrorKind> {
+    error.downcast_ref::<Error>().map(Error::kind)
+}
+
+/// Exit status for `error`: 1 for untagged errors
+pub fn exit_code(error: &(dyn std::error::Error + 'static)) -> i32 {
+    kind_of(error).map_or(1, ErrorKind::exit_code)
+}
+
+/// Tag errors of a result
+pub trait ResultExt<T> {
+    /// Tag the error with `kind` unless it is already tagged
+    ///
+    /// Errors keep the kind closest to the cause, e.g. a tape mismatch
+    /// found while printing is not turned into a communication error.
+    fn or_kind(self, kind: ErrorKind) -> crate::Result<T>;
+}
+
+impl<T, E: Into<Box<dyn std::error::Error>>> ResultExt<T> for std::result::Result<T, E> {
+    fn or_kind(self, kind: ErrorKind) -> crate::Result<T> {
+        self.map_err(|e| {
+            let error = e.into();
+            if kind_of(&*error).is_some() {
+                error
+            } else {
+                Error::new(kind, error).into()
+            }
+        })
+    }
+}
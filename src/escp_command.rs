@@ -0,0 +1,101 @@
+use crate::Result;
+use crate::raster_command::CommandMode;
+
+/// Builder for Brother P-Touch ESC/P commands
+///
+/// ESC/P mode prints text with the fonts built into the printer, so no
+/// rendering (and no font files) are needed. Only a minimal subset of the
+/// ESC/P Command Reference is covered; use raster mode for anything else.
+///
+/// # Example
+///
+/// ```
+/// use ptouch::escp_command::EscpCommand;
+///
+/// let mut cmd = EscpCommand::new();
+/// cmd.invalidate()
+///    .initialize()
+///    .select_character_size(48)
+///    .text("HELLO")
+///    .unwrap()
+///    .form_feed();
+///
+/// let command_data = cmd.build();
+/// assert!(command_data.ends_with(b"HELLO\x0C"));
+/// ```
+pub struct EscpCommand {
+    buffer: Vec<u8>,
+}
+
+impl EscpCommand {
+    /// Create a new empty command builder
+    pub fn new() -> Self {
+        EscpCommand { buffer: Vec::new() }
+    }
+
+    /// Add invalidate sequence (200 null bytes)
+    pub fn invalidate(&mut self) -> &mut Self {
+        self.buffer.extend_from_slice(&[0x00; 200]);
+        self
+    }
+
+    /// Add ESC @ (initialize) and switch to ESC/P mode (ESC i a 0)
+    ///
+    /// Initialization resets character settings, so this should be
+    /// called before other commands.
+    pub fn initialize(&mut self) -> &mut Self {
+        self.buffer.extend_from_slice(b"\x1B\x40");
+        self.buffer.extend_from_slice(b"\x1B\x69\x61");
+        self.buffer.push(CommandMode::EscP as u8);
+        self
+    }
+
+    /// Specify character size in dots (ESC X 0 nL nH)
+    pub fn select_character_size(&mut self, dots: u16) -> &mut Self {
+        self.buffer.extend_from_slice(b"\x1B\x58\x00");
+        self.buffer.extend_from_slice(&dots.to_le_bytes());
+        self
+    }
+
+    /// Add text to print
+    ///
+    /// Built-in fonts only have ASCII characters. A newline starts
+    /// a new line (CR LF).
+    ///
+    /// Returns an error for other characters; nothing is added on error.
+    pub fn text(&mut self, text: &str) -> Result<&mut Self> {
+        if let Some(c) = text
+            .chars()
+            .find(|&c| c != '\n' && !(' '..='~').contains(&c))
+        {
+            return Err(format!("Cannot print {:?} in ESC/P mode (ASCII only)", c).into());
+        }
+        for line in text.split_inclusive('\n') {
+            match line.strip_suffix('\n') {
+                Some(line) => {
+                    self.buffer.extend_from_slice(line.as_bytes());
+                    self.buffer.extend_from_slice(b"\r\n");
+                }
+                None => self.buffer.extend_from_slice(line.as_bytes()),
+            }
+        }
+        Ok(self)
+    }
+
+    /// Print and feed (FF)
+    pub fn form_feed(&mut self) -> &mut Self {
+        self.buffer.push(0x0C);
+        self
+    }
+
+    /// Build and return the complete command sequence
+    pub fn build(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+impl Default for EscpCommand {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,18 +1,30 @@
 use crate::Result;
-use crate::element::{Element, render_svg_to_pixmap};
-use crate::tape::TapeSpec;
+use crate::element::{
+    BoundingBox, Element, LayoutTree, RenderOptions, font_resolver, render_svg_to_pixmap,
+    text_to_paths, validate_font_family,
+};
+use crate::tape::{Tape, TapeSpec};
 use fontdb::Database;
-use std::fs::File;
-use std::io::Write;
-use std::path::Path;
+use resvg::{tiny_skia, usvg};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use svg::Document;
 
-#[derive(Clone, Copy, Debug)]
+/// Position of contents across the printable area of the tape
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub enum Placement {
+    #[default]
     Top,
     Center,
     Bottom,
+    /// Fraction of the space left by contents put above them
+    /// (0.0: top, 0.5: center, 1.0: bottom)
+    Fraction(f32),
+    /// Dots from the top of the printable area
+    Offset(f32),
 }
 
 impl std::fmt::Display for Placement {
@@ -21,10 +33,139 @@ impl std::fmt::Display for Placement {
             Placement::Top => write!(f, "top"),
             Placement::Center => write!(f, "center"),
             Placement::Bottom => write!(f, "bottom"),
+            Placement::Fraction(fraction) => write!(f, "{}%", fraction * 100.0),
+            Placement::Offset(dots) => write!(f, "{}", dots),
         }
     }
 }
 
+impl std::str::FromStr for Placement {
+    type Err = Box<dyn std::error::Error>;
+
+    /// Parse "top", "center", "bottom", percentage ("25%") or dots ("10")
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "top" => return Ok(Placement::Top),
+            "center" => return Ok(Placement::Center),
+            "bottom" => return Ok(Placement::Bottom),
+            _ => {}
+        }
+
+        if let Some(percent) = s.strip_suffix('%') {
+            let percent: f32 = percent
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid placement: '{}'", s))?;
+            if !(0.0..=100.0).contains(&percent) {
+                return Err(format!("Placement must be between 0% and 100%: '{}'", s).into());
+            }
+            return Ok(Placement::Fraction(percent / 100.0));
+        }
+
+        let dots: f32 = s
+            .parse()
+            .map_err(|_| format!("Invalid placement: '{}'", s))?;
+        if !dots.is_finite() || dots < 0.0 {
+            return Err(format!("Placement must be a non-negative number of dots: '{}'", s).into());
+        }
+        Ok(Placement::Offset(dots))
+    }
+}
+
+/// Outline framing the printable area of the label, e.g. for name badges
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Border {
+    /// Distance from the edges of the printable area in mm
+    pub inset_mm: f32,
+    /// Thickness of the line in mm
+    pub width_mm: f32,
+    /// Radius of the corners in mm (0: square corners)
+    pub radius_mm: f32,
+    /// Draw only in previews (SVG and annotated PNG), not in the PNG to print
+    pub preview_only: bool,
+}
+
+impl Border {
+    /// Printed border of 0.3mm with square corners, `inset_mm` inside
+    pub fn new(inset_mm: f32) -> Self {
+        Border {
+            inset_mm,
+            width_mm: 0.3,
+            radius_mm: 0.0,
+            preview_only: false,
+        }
+    }
+
+    pub fn with_width(mut self, width_mm: f32) -> Self {
+        self.width_mm = width_mm;
+        self
+    }
+
+    pub fn with_radius(mut self, radius_mm: f32) -> Self {
+        self.radius_mm = radius_mm;
+        self
+    }
+
+    pub fn with_preview_only(mut self, preview_only: bool) -> Self {
+        self.preview_only = preview_only;
+        self
+    }
+}
+
+/// Time spent in the stages of creating a label, e.g. for --timings
+///
+/// Label fills the stages of rendering; `fonts` and `parse` are left to
+/// callers that load fonts and parse layouts. Durations add up over
+/// labels rendered with the same metrics.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RenderMetrics {
+    /// Loading the font database
+    pub fonts: Duration,
+    /// Parsing the layout script into elements
+    pub parse: Duration,
+    /// Bounding box of the whole contents, mostly measuring text
+    pub measure: Duration,
+    /// Building the SVG document (elements measure their children again)
+    pub svg: Duration,
+    /// Rasterizing the SVG into pixels, including supersampling
+    pub rasterize: Duration,
+    /// Encoding the pixels into PNG
+    pub encode: Duration,
+}
+
+impl RenderMetrics {
+    /// Stages with their names, in the order of processing
+    pub fn stages(&self) -> [(&'static str, Duration); 6] {
+        [
+            ("fonts", self.fonts),
+            ("parse", self.parse),
+            ("measure", self.measure),
+            ("svg", self.svg),
+            ("rasterize", self.rasterize),
+            ("encode", self.encode),
+        ]
+    }
+
+    pub fn total(&self) -> Duration {
+        self.stages().iter().map(|(_, duration)| *duration).sum()
+    }
+}
+
+impl std::fmt::Display for RenderMetrics {
+    /// Table of stages in milliseconds, one per line
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, duration) in self.stages() {
+            writeln!(f, "{:<9} {:>9.1} ms", name, duration.as_secs_f64() * 1000.0)?;
+        }
+        write!(
+            f,
+            "{:<9} {:>9.1} ms",
+            "total",
+            self.total().as_secs_f64() * 1000.0
+        )
+    }
+}
+
 pub struct LabelOptions {
     pub fontdb: Arc<Database>,
     pub tape_spec: TapeSpec,
@@ -32,6 +173,176 @@ pub struct LabelOptions {
     pub rotate: bool,
     pub placement: Placement,
     pub debug: bool,
+    /// Omit the white background in SVG output (PNG stays white-backed)
+    pub transparent: bool,
+    /// Checkered background instead of white in previews (SVG output and
+    /// [`Label::to_annotated_png`]), showing white contents. PNG output
+    /// for printing stays white-backed
+    pub preview_checkerboard: bool,
+    /// Convert text to paths in SVG output, so that it looks the same
+    /// without the fonts
+    pub embed_text: bool,
+    /// Fixed length along the tape in element units [default: fit to contents].
+    /// Contents are padded with blank or cut off at the end.
+    pub length: Option<f32>,
+    /// Render PNG at N times the size and reduce it with error diffusion
+    /// into black and white pixels (1: render at the native size)
+    pub supersample: u32,
+    /// Outline around the printable area, over the contents
+    pub border: Option<Border>,
+}
+
+/// Options without the font database, which would list every face
+///
+/// ```
+/// use ptouch::prelude::*;
+/// use std::sync::Arc;
+///
+/// let options = LabelOptions::new(Arc::new(fontdb::Database::new()));
+/// let debug = format!("{:?}", options);
+/// assert!(debug.starts_with("LabelOptions { tape_spec: TapeSpec { name: TZe12H,"));
+/// assert!(!debug.contains("fontdb"));
+/// ```
+impl std::fmt::Debug for LabelOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LabelOptions")
+            .field("tape_spec", &self.tape_spec)
+            .field("auto_scale", &self.auto_scale)
+            .field("rotate", &self.rotate)
+            .field("placement", &self.placement)
+            .field("debug", &self.debug)
+            .field("transparent", &self.transparent)
+            .field("preview_checkerboard", &self.preview_checkerboard)
+            .field("embed_text", &self.embed_text)
+            .field("length", &self.length)
+            .field("supersample", &self.supersample)
+            .field("border", &self.border)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LabelOptions {
+    /// Options for 12mm 360dpi tape with contents at the top
+    ///
+    /// Other fields are public, so set them with `with_*` methods or
+    /// struct update syntax:
+    ///
+    /// ```
+    /// use ptouch::prelude::*;
+    /// use std::sync::Arc;
+    ///
+    /// let fontdb = Arc::new(fontdb::Database::new());
+    /// let options = LabelOptions {
+    ///     debug: true,
+    ///     ..LabelOptions::new(fontdb.clone()).with_tape_spec(TapeSpec::new(Tape::TZe24H))
+    /// };
+    /// assert_eq!(options.tape_spec.width_mm, 24);
+    ///
+    /// let options = LabelOptions::new(fontdb)
+    ///     .with_placement(Placement::Center)
+    ///     .with_length(Some(400.0))
+    ///     .with_supersample(2);
+    /// assert_eq!(options.supersample, 2);
+    /// ```
+    pub fn new(fontdb: Arc<Database>) -> Self {
+        LabelOptions {
+            fontdb,
+            tape_spec: TapeSpec::new(Tape::TZe12H),
+            auto_scale: false,
+            rotate: false,
+            placement: Placement::default(),
+            debug: false,
+            transparent: false,
+            preview_checkerboard: false,
+            embed_text: false,
+            length: None,
+            supersample: 1,
+            border: None,
+        }
+    }
+
+    pub fn with_tape_spec(mut self, tape_spec: TapeSpec) -> Self {
+        self.tape_spec = tape_spec;
+        self
+    }
+
+    /// Scale contents to the printable width of the tape
+    pub fn with_auto_scale(mut self, auto_scale: bool) -> Self {
+        self.auto_scale = auto_scale;
+        self
+    }
+
+    /// Switch to the narrowest tape whose printable area fits `element`
+    ///
+    /// Tapes of the same pin pitch are considered. Overridden DPI and
+    /// high resolution mode are kept.
+    pub fn with_fitting_tape(mut self, element: &dyn Element) -> Result<Self> {
+        let bbox = element.bounding_box()?;
+        let height = if self.rotate { bbox.width } else { bbox.height };
+        let pin_dpi = TapeSpec::new(self.tape_spec.name).dpi;
+        let fitted = TapeSpec::fit_height(height, pin_dpi).ok_or_else(|| {
+            format!(
+                "Contents of {} dots do not fit the printable area of any {}DPI tape",
+                height.ceil(),
+                pin_dpi
+            )
+        })?;
+
+        self.tape_spec = TapeSpec {
+            dpi: self.tape_spec.dpi,
+            feed_dpi: self.tape_spec.feed_dpi,
+            ..fitted
+        };
+        Ok(self)
+    }
+
+    pub fn with_placement(mut self, placement: Placement) -> Self {
+        self.placement = placement;
+        self
+    }
+
+    /// Rotate contents by 90 degrees
+    pub fn with_rotate(mut self, rotate: bool) -> Self {
+        self.rotate = rotate;
+        self
+    }
+
+    /// Draw the bounding box and gray margins
+    pub fn with_debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    pub fn with_transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    pub fn with_preview_checkerboard(mut self, preview_checkerboard: bool) -> Self {
+        self.preview_checkerboard = preview_checkerboard;
+        self
+    }
+
+    pub fn with_embed_text(mut self, embed_text: bool) -> Self {
+        self.embed_text = embed_text;
+        self
+    }
+
+    /// Fixed length along the tape in element units
+    pub fn with_length(mut self, length: Option<f32>) -> Self {
+        self.length = length;
+        self
+    }
+
+    pub fn with_supersample(mut self, supersample: u32) -> Self {
+        self.supersample = supersample;
+        self
+    }
+
+    pub fn with_border(mut self, border: Option<Border>) -> Self {
+        self.border = border;
+        self
+    }
 }
 
 pub struct Label {
@@ -47,47 +358,345 @@ impl Label {
 
     /// Create SVG document
     pub fn to_svg(&self) -> Result<String> {
-        create_label_svg_from_element(&*self.element, &self.options)
+        self.to_svg_with_metrics(&mut RenderMetrics::default())
+    }
+
+    /// Create SVG document, adding time of the stages to `metrics`
+    pub fn to_svg_with_metrics(&self, metrics: &mut RenderMetrics) -> Result<String> {
+        let decorations = Decorations {
+            checkerboard: self.options.preview_checkerboard,
+            preview: true,
+            ..Decorations::debug(self.options.debug)
+        };
+        let svg_data = create_label_svg_from_element(
+            &*self.element,
+            &self.options,
+            self.options.transparent,
+            decorations,
+            true,
+            metrics,
+        )?;
+        if self.options.embed_text {
+            let start = Instant::now();
+            let svg_data = text_to_paths(&svg_data, &self.options.fontdb)?;
+            metrics.svg += start.elapsed();
+            Ok(svg_data)
+        } else {
+            Ok(svg_data)
+        }
+    }
+
+    /// Render to pixels for PNG output
+    pub fn to_pixmap(&self) -> Result<tiny_skia::Pixmap> {
+        self.to_pixmap_with_metrics(&mut RenderMetrics::default())
+    }
+
+    /// Render to pixels, adding time of the stages to `metrics`
+    pub fn to_pixmap_with_metrics(&self, metrics: &mut RenderMetrics) -> Result<tiny_skia::Pixmap> {
+        // Printing requires white background regardless of `transparent`
+        let decorations = Decorations::debug(self.options.debug);
+        let svg_data = create_label_svg_from_element(
+            &*self.element,
+            &self.options,
+            false,
+            decorations,
+            false,
+            metrics,
+        )?;
+
+        let start = Instant::now();
+        let n = self.options.supersample;
+        let pixmap = if n <= 1 {
+            render_svg_to_pixmap(&svg_data, &self.options.fontdb, &RenderOptions::output())
+        } else {
+            let options = RenderOptions {
+                dpi_scale: n as f32,
+                ..RenderOptions::output()
+            };
+            let large = render_svg_to_pixmap(&svg_data, &self.options.fontdb, &options)?;
+            dither(downsample(&large, n))
+        };
+        metrics.rasterize += start.elapsed();
+        pixmap
     }
 
     /// Create PNG data
     pub fn to_png(&self) -> Result<Vec<u8>> {
-        let svg_data = self.to_svg()?;
-        let pixmap = render_svg_to_pixmap(&svg_data, &self.options.fontdb, false)?;
-        Ok(pixmap.encode_png()?)
+        self.to_png_with_metrics(&mut RenderMetrics::default())
+    }
+
+    /// Create PNG data, adding time of the stages to `metrics`
+    pub fn to_png_with_metrics(&self, metrics: &mut RenderMetrics) -> Result<Vec<u8>> {
+        let pixmap = self.to_pixmap_with_metrics(metrics)?;
+        let start = Instant::now();
+        let png_data = self.encode_png(&pixmap);
+        metrics.encode += start.elapsed();
+        png_data
+    }
+
+    /// Encode `pixmap` of [`Label::to_pixmap`] into PNG
+    ///
+    /// The PNG records the DPI of the tape (pHYs chunk) so that image
+    /// editors show and print it at the size on the tape.
+    pub fn encode_png(&self, pixmap: &tiny_skia::Pixmap) -> Result<Vec<u8>> {
+        let pixels: Vec<u8> = pixmap
+            .pixels()
+            .iter()
+            .flat_map(|p| {
+                let c = p.demultiply();
+                [c.red(), c.green(), c.blue(), c.alpha()]
+            })
+            .collect();
+
+        let mut png_data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png_data, pixmap.width(), pixmap.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_pixel_dims(Some(pixel_dims(&self.options.tape_spec)));
+        encoder.write_header()?.write_image_data(&pixels)?;
+        Ok(png_data)
+    }
+
+    /// Create grayscale PNG data of the label marked up for inspection
+    ///
+    /// Shades the non-printable margins (contents bleeding into them stay
+    /// visible), outlines the bounding box of contents, draws a guide on
+    /// the baseline of each text and captions the tape and label length
+    /// below the label. Output of [`Label::to_png`] is left unmarked.
+    pub fn to_annotated_png(&self) -> Result<Vec<u8>> {
+        let svg_data = create_label_svg_from_element(
+            &*self.element,
+            &self.options,
+            false,
+            Decorations {
+                checkerboard: self.options.preview_checkerboard,
+                preview: true,
+                ..Decorations::annotate()
+            },
+            false,
+            &mut RenderMetrics::default(),
+        )?;
+        let pixmap =
+            render_svg_to_pixmap(&svg_data, &self.options.fontdb, &RenderOptions::output())?;
+        encode_grayscale_png(&pixmap, &self.options.tape_spec)
     }
 
     /// Save SVG file
+    ///
+    /// Written through [`write_atomically`], so the file is never seen
+    /// half-written.
     pub fn save_svg<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         let svg_data = self.to_svg()?;
-        let mut file = File::create(path)?;
-        file.write_all(svg_data.as_bytes())?;
-        Ok(())
+        write_atomically(path.as_ref(), svg_data.as_bytes())
     }
 
     /// Save PNG file
+    ///
+    /// Written through [`write_atomically`], so the file is never seen
+    /// half-written.
     pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         let png_data = self.to_png()?;
-        let mut file = File::create(path)?;
-        file.write_all(&png_data)?;
-        Ok(())
+        write_atomically(path.as_ref(), &png_data)
+    }
+
+    /// Save PNG file unless it already has the same content
+    ///
+    /// Returns whether the file was written. Leaving unchanged files
+    /// untouched keeps their modification time for watchers and build
+    /// systems.
+    pub fn save_png_if_changed<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let png_data = self.to_png()?;
+        if fs::read(path.as_ref()).is_ok_and(|current| current == png_data) {
+            return Ok(false);
+        }
+        write_atomically(path.as_ref(), &png_data)?;
+        Ok(true)
+    }
+
+    /// Check the layout for likely mistakes
+    ///
+    /// Reports layers of an Overlay hidden by an opaque upper layer, and
+    /// contents that fall in the non-printable margins of the tape.
+    pub fn lint(&self) -> Result<Vec<String>> {
+        let mut warnings = Vec::new();
+        let tree = self.element.layout()?;
+        lint_overlays(&tree, &mut warnings);
+
+        // Auto-scaled contents always fit in the printable area
+        if !self.options.auto_scale {
+            let tape = &self.options.tape_spec;
+            let (width, height) = if self.options.rotate {
+                (tree.bbox.height, tree.bbox.width)
+            } else {
+                (tree.bbox.width, tree.bbox.height)
+            };
+            let ch = tape.inner_dots as f32;
+            let m = tape.margin_dots() as f32;
+            let y = placement_offset(self.options.placement, m, ch, height);
+
+            let printable = BoundingBox::new(width, ch, 0.0, m);
+            let content = BoundingBox::new(width, height, 0.0, y);
+            if !printable.covers(&content) {
+                warnings.push(format!(
+                    "Contents fall in the margins of {} tape: {} dots at the top, {} dots at the bottom",
+                    tape.name,
+                    (m - y).max(0.0),
+                    (y + height - (m + ch)).max(0.0)
+                ));
+            }
+        }
+
+        Ok(warnings)
+    }
+
+    /// Size of the rendered label in dots: (length along the tape, tape width)
+    ///
+    /// Same as the size of [`Label::to_png`] without rendering, e.g. for
+    /// laying out previews.
+    pub fn dimensions(&self) -> Result<(u32, u32)> {
+        let bbox = self.element.bounding_box()?;
+        let (vw, _, _) = fit_to_tape(&bbox, &self.options);
+        Ok((vw.round() as u32, self.options.tape_spec.width_dots))
+    }
+
+    /// Check if the contents fit in the printable width of the tape
+    ///
+    /// Always true with auto scale, which shrinks the contents to fit.
+    pub fn fits_tape_width(&self) -> Result<bool> {
+        if self.options.auto_scale {
+            return Ok(true);
+        }
+        let bbox = self.element.bounding_box()?;
+        let height = if self.options.rotate {
+            bbox.width
+        } else {
+            bbox.height
+        };
+        Ok(height <= self.options.tape_spec.inner_dots as f32)
     }
 
     /// Access method to Option
     pub fn options(&self) -> &LabelOptions {
         &self.options
     }
+
+    /// Contents of the label
+    pub fn element(&self) -> &dyn Element {
+        &*self.element
+    }
 }
 
-fn create_label_svg_from_element(element: &dyn Element, options: &LabelOptions) -> Result<String> {
-    let tape = &options.tape_spec;
+/// Find layers of Overlays fully hidden by an opaque upper layer
+fn lint_overlays(tree: &LayoutTree, warnings: &mut Vec<String>) {
+    if tree.name.starts_with("Overlay(") {
+        for (i, lower) in tree.children.iter().enumerate() {
+            if !lower.visible {
+                continue;
+            }
+            let upper = tree.children[i + 1..]
+                .iter()
+                .position(|upper| upper.opaque && upper.rect().covers(&lower.rect()));
+            if let Some(j) = upper {
+                let upper = &tree.children[i + 1 + j];
+                warnings.push(format!(
+                    "Layer {} {} is hidden by opaque layer {} {}",
+                    i + 1,
+                    lower.name,
+                    i + 2 + j,
+                    upper.name
+                ));
+            }
+        }
+    }
 
-    // Elementからbounding_boxを取得
-    let bbox = element.bounding_box()?;
+    for child in &tree.children {
+        lint_overlays(child, warnings);
+    }
+}
 
-    let vh = tape.width_dots as f32;
+/// Reduce pixmap by `n` into gray levels (0.0: black, 1.0: white) by
+/// averaging each n x n block
+fn downsample(pixmap: &tiny_skia::Pixmap, n: u32) -> (u32, u32, Vec<f32>) {
+    let width = pixmap.width().div_ceil(n);
+    let height = pixmap.height().div_ceil(n);
+    let mut sums = vec![0.0; (width * height) as usize];
+    let mut counts = vec![0u32; (width * height) as usize];
+
+    for y in 0..pixmap.height() {
+        for x in 0..pixmap.width() {
+            // Background is opaque white, so alpha is always 255
+            let pixel = pixmap.pixel(x, y).unwrap();
+            let gray = (pixel.red() as u32 + pixel.green() as u32 + pixel.blue() as u32) as f32;
+            let i = ((y / n) * width + x / n) as usize;
+            sums[i] += gray / (3.0 * 255.0);
+            counts[i] += 1;
+        }
+    }
+
+    let grays = sums
+        .iter()
+        .zip(counts)
+        .map(|(sum, count)| sum / count as f32);
+    (width, height, grays.collect())
+}
+
+/// Turn gray levels into black and white pixels by Floyd-Steinberg
+/// error diffusion
+fn dither((width, height, mut grays): (u32, u32, Vec<f32>)) -> Result<tiny_skia::Pixmap> {
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| format!("Failed to create {}x{} pixmap", width, height))?;
+    let (w, h) = (width as usize, height as usize);
+
+    for y in 0..h {
+        for x in 0..w {
+            let i = y * w + x;
+            let value = if grays[i] < 0.5 { 0.0 } else { 1.0 };
+            let error = grays[i] - value;
+
+            let mut spread = |dx: isize, dy: usize, weight: f32| {
+                let nx = x as isize + dx;
+                if nx >= 0 && (nx as usize) < w && y + dy < h {
+                    grays[(y + dy) * w + nx as usize] += error * weight;
+                }
+            };
+            spread(1, 0, 7.0 / 16.0);
+            spread(-1, 1, 3.0 / 16.0);
+            spread(0, 1, 5.0 / 16.0);
+            spread(1, 1, 1.0 / 16.0);
+
+            let level = (value * 255.0) as u8;
+            pixmap.pixels_mut()[i] =
+                tiny_skia::PremultipliedColorU8::from_rgba(level, level, level, 255).unwrap();
+        }
+    }
+
+    Ok(pixmap)
+}
+
+/// Vertical position of contents on the tape (`m`: margin, `ch`: printable width)
+fn placement_offset(placement: Placement, m: f32, ch: f32, height: f32) -> f32 {
+    match placement {
+        Placement::Top => m,
+        Placement::Center => m + (ch - height) / 2.0,
+        Placement::Bottom => m + (ch - height),
+        Placement::Fraction(fraction) => m + (ch - height) * fraction,
+        Placement::Offset(dots) => m + dots,
+    }
+}
+
+/// Raster lines per dot along the tape (2.0 in 720DPI mode)
+fn feed_scale(tape: &TapeSpec) -> f32 {
+    tape.feed_dpi as f32 / tape.dpi as f32
+}
+
+/// Fit contents of `bbox` to the tape
+///
+/// Returns the label length in raster lines, the scale of auto-scale and
+/// the offset of contents across the tape.
+fn fit_to_tape(bbox: &BoundingBox, options: &LabelOptions) -> (f32, f32, f32) {
+    let tape = &options.tape_spec;
     let ch = tape.inner_dots as f32;
-    let m = ((tape.width_dots - tape.inner_dots) / 2) as f32;
+    let m = tape.margin_dots() as f32;
 
     // For rotation, we need to consider how the text dimensions map to tape dimensions
     let (effective_width, effective_height) = if options.rotate {
@@ -97,45 +706,425 @@ fn create_label_svg_from_element(element: &dyn Element, options: &LabelOptions)
         (bbox.width, bbox.height)
     };
 
-    let mut vw = effective_width + 2.0;
-    let mut scale = 1.0;
-    let y_offset;
-
-    // Handle auto-scaling
+    let length = options.length.unwrap_or(effective_width);
     if options.auto_scale {
-        y_offset = m;
-        scale = ch / effective_height;
-        vw = effective_width * scale + 2.0;
+        let scale = ch / effective_height;
+        (length * scale * feed_scale(tape) + 2.0, scale, m)
     } else {
-        // Handle placement
-        y_offset = match options.placement {
-            Placement::Top => m,
-            Placement::Center => m + (ch - effective_height) / 2.0,
-            Placement::Bottom => m + (ch - effective_height),
-        };
+        let y_offset = placement_offset(options.placement, m, ch, effective_height);
+        (length * feed_scale(tape) + 2.0, 1.0, y_offset)
+    }
+}
+
+/// Marks drawn over the label for inspection
+#[derive(Clone, Copy, Debug, Default)]
+struct Decorations {
+    /// Opacity of gray shading over the non-printable margins
+    margin_shade: Option<f32>,
+    /// Outline the bounding box of contents
+    bbox: bool,
+    /// Guide lines on the baseline of each text
+    baselines: bool,
+    /// Tape and label length below the label
+    caption: bool,
+    /// Checkered background instead of white, showing white contents
+    checkerboard: bool,
+    /// Output viewed rather than printed, showing preview-only borders
+    preview: bool,
+}
+
+impl Decorations {
+    /// Marks of `--debug`: gray margins hiding contents and the bounding box
+    fn debug(debug: bool) -> Self {
+        Decorations {
+            margin_shade: debug.then_some(1.0),
+            bbox: debug,
+            ..Default::default()
+        }
+    }
+
+    /// All marks, for the annotated preview
+    fn annotate() -> Self {
+        Decorations {
+            margin_shade: Some(0.5),
+            bbox: true,
+            baselines: true,
+            caption: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Size of a square of the checkered preview background in dots
+const CHECKER_SIZE: u32 = 8;
+
+/// Class of marks only for viewing, hidden by [`rasterize_label_svg`]
+const PREVIEW_CLASS: &str = "preview";
+
+/// Add a light checkered background of `width` x `height` dots
+fn add_checkerboard(document: Document, width: u32, height: u32) -> Document {
+    let square = |x: u32, y: u32| {
+        svg::node::element::Rectangle::new()
+            .set("x", x)
+            .set("y", y)
+            .set("width", CHECKER_SIZE)
+            .set("height", CHECKER_SIZE)
+            .set("fill", "#d8d8d8")
+    };
+    let pattern = svg::node::element::Pattern::new()
+        .set("id", "checkerboard")
+        .set("width", CHECKER_SIZE * 2)
+        .set("height", CHECKER_SIZE * 2)
+        .set("patternUnits", "userSpaceOnUse")
+        .add(
+            svg::node::element::Rectangle::new()
+                .set("width", CHECKER_SIZE * 2)
+                .set("height", CHECKER_SIZE * 2)
+                .set("fill", "white"),
+        )
+        .add(square(0, 0))
+        .add(square(CHECKER_SIZE, CHECKER_SIZE));
+    document
+        .add(svg::node::element::Definitions::new().add(pattern))
+        .add(
+            svg::node::element::Rectangle::new()
+                .set("class", format!("background {}", PREVIEW_CLASS))
+                .set("x", 0)
+                .set("y", 0)
+                .set("width", width)
+                .set("height", height)
+                .set("fill", "url(#checkerboard)"),
+        )
+}
+
+/// Height of the caption strip below the label in dots
+const CAPTION_HEIGHT: u32 = 20;
+
+/// Width reserved for the caption of short labels in dots
+const CAPTION_WIDTH: u32 = 240;
+
+/// Baselines of texts in `element` laid out by `render_at(x, y)`
+///
+/// Each baseline is (x, y, width) of a guide line.
+fn text_baselines(
+    element: &dyn Element,
+    x: f32,
+    y: f32,
+    baselines: &mut Vec<(f32, f32, f32)>,
+) -> Result<()> {
+    if !element.is_visible() {
+        return Ok(());
+    }
+    let bbox = element.bounding_box()?;
+    if element.type_name() == "Text"
+        && let Some(baseline) = element.baseline()?
+    {
+        baselines.push((x, y + baseline, bbox.width));
+    }
+    for (child, cx, cy) in element.child_offsets()? {
+        text_baselines(child, x - bbox.x + cx, y - bbox.y + cy, baselines)?;
+    }
+    Ok(())
+}
+
+/// Font family of the caption: sans-serif, or any font available
+fn caption_font_family(fontdb: &Database) -> String {
+    let sans_serif = fontdb.family_name(&fontdb::Family::SansSerif);
+    let query = fontdb::Query {
+        families: &[fontdb::Family::Name(sans_serif)],
+        ..Default::default()
+    };
+    if fontdb.query(&query).is_some() {
+        return sans_serif.to_string();
+    }
+    fontdb
+        .faces()
+        .find_map(|face| face.families.first().map(|(name, _)| name.clone()))
+        .unwrap_or_else(|| sans_serif.to_string())
+}
+
+/// Replace the file at `path` with `data` in one step
+///
+/// Data goes to a temporary file next to `path` that is renamed over it
+/// once complete, so readers see either the old or the new content, never
+/// a truncated file. Permissions of an existing file are kept. IO errors
+/// keep their kind and name the file.
+pub fn write_atomically(path: &Path, data: &[u8]) -> Result<()> {
+    let with_path = |e: io::Error| io::Error::new(e.kind(), format!("{}: {}", path.display(), e));
+    let temp_path = temp_path_for(path).map_err(with_path)?;
+
+    let result = (|| {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(data)?;
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(with_path(e).into());
+    }
+    Ok(())
+}
+
+/// Hidden temporary file in the directory of `path` (same filesystem,
+/// so that it can be renamed over `path`)
+fn temp_path_for(path: &Path) -> io::Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file name"))?;
+    let temp_name = format!(".{}.{}.tmp", name.to_string_lossy(), std::process::id());
+    Ok(path.with_file_name(temp_name))
+}
+
+/// Pixels per meter: raster lines along the tape (x) and pins across it (y)
+fn pixel_dims(tape: &TapeSpec) -> png::PixelDimensions {
+    let per_meter = |dpi: u32| (dpi as f32 / 0.0254).round() as u32;
+    png::PixelDimensions {
+        xppu: per_meter(tape.feed_dpi),
+        yppu: per_meter(tape.dpi),
+        unit: png::Unit::Meter,
+    }
+}
+
+/// Encode pixels on white background into 8-bit grayscale PNG
+fn encode_grayscale_png(pixmap: &tiny_skia::Pixmap, tape: &TapeSpec) -> Result<Vec<u8>> {
+    let pixels: Vec<u8> = pixmap
+        .pixels()
+        .iter()
+        .map(|p| {
+            let luma = 299 * p.red() as u32 + 587 * p.green() as u32 + 114 * p.blue() as u32;
+            (luma / 1000) as u8
+        })
+        .collect();
+
+    let mut png_data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png_data, pixmap.width(), pixmap.height());
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_pixel_dims(Some(pixel_dims(tape)));
+    encoder.write_header()?.write_image_data(&pixels)?;
+    Ok(png_data)
+}
+
+/// Render SVG source of [`Label::to_svg`] back into the pixels to print
+///
+/// The tape comes from the `data-ptouch-tape` attribute, or from the
+/// height of the document in dots at `dpi` for SVG without it. The
+/// document is scaled so that its height is the tape width in dots, and
+/// its length follows at the feed DPI of the tape (720 with
+/// `high_resolution`). Fonts of text must be in `fontdb`, as they are to
+/// create the label. The background is white like [`Label::to_pixmap`],
+/// and marks only for viewing (the checkered background and preview-only
+/// borders) are left out.
+pub fn rasterize_label_svg(
+    svg_data: &str,
+    fontdb: &Arc<Database>,
+    dpi: u32,
+    high_resolution: bool,
+) -> Result<(tiny_skia::Pixmap, TapeSpec)> {
+    let mut tape_name = None;
+    for event in svg::read(svg_data)? {
+        if let svg::parser::Event::Tag(_, _, attributes) = event {
+            if let Some(name) = attributes.get("data-ptouch-tape") {
+                tape_name = Some(name.to_string());
+            }
+            if let Some(family) = attributes.get("font-family") {
+                validate_font_family(family, fontdb)?;
+            }
+        }
+    }
+
+    let options = usvg::Options {
+        fontdb: fontdb.clone(),
+        font_resolver: font_resolver(),
+        style_sheet: Some(format!(".{} {{ display: none }}", PREVIEW_CLASS)),
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_str(svg_data, &options)?;
+    let size = tree.size();
+    let tape_spec = match tape_name {
+        Some(name) => Tape::ALL
+            .into_iter()
+            .find(|tape| tape.to_string() == name)
+            .map(TapeSpec::new)
+            .ok_or_else(|| format!("Unknown tape in SVG: {}", name))?,
+        None => {
+            let dots = size.height().round() as u32;
+            TapeSpec::from_width_dots_and_dpi(dots, dpi)
+                .ok_or_else(|| format!("No {}DPI tape is {} dots wide as the SVG", dpi, dots))?
+        }
+    };
+    let tape_spec = if high_resolution {
+        tape_spec.with_high_resolution()?
+    } else {
+        tape_spec
+    };
+
+    let scale_y = tape_spec.width_dots as f32 / size.height();
+    let width = (size.width() * scale_y * feed_scale(&tape_spec)).round() as u32;
+    let scale_x = width as f32 / size.width();
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, tape_spec.width_dots)
+        .ok_or_else(|| format!("Failed to create {}x{} pixmap", width, tape_spec.width_dots))?;
+    // Printing requires white background, also for transparent SVG
+    pixmap.fill(tiny_skia::Color::WHITE);
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale_x, scale_y),
+        &mut pixmap.as_mut(),
+    );
+    Ok((pixmap, tape_spec))
+}
+
+/// Rectangle of `border` in the printable area of `length` x `ch` dots
+/// starting `m` dots from the top
+///
+/// The stroke is centered on the outline, so the rectangle is inset by
+/// half of the line width to keep the line inside.
+fn border_rect(
+    border: &Border,
+    tape: &TapeSpec,
+    length: f32,
+    ch: f32,
+    m: f32,
+) -> Result<svg::node::element::Rectangle> {
+    let width = tape.mm_to_dots(border.width_mm) as f32;
+    let inset_x = tape.mm_to_feed_dots(border.inset_mm) as f32 + width / 2.0;
+    let inset_y = tape.mm_to_dots(border.inset_mm) as f32 + width / 2.0;
+    let (rect_width, rect_height) = (length - 2.0 * inset_x, ch - 2.0 * inset_y);
+    if rect_width <= 0.0 || rect_height <= 0.0 {
+        return Err(format!(
+            "Border of {}mm inset and {}mm width does not fit the label",
+            border.inset_mm, border.width_mm
+        )
+        .into());
     }
+    let radius = tape.mm_to_dots(border.radius_mm);
+    let class = if border.preview_only {
+        format!("border {}", PREVIEW_CLASS)
+    } else {
+        "border".to_string()
+    };
+    Ok(svg::node::element::Rectangle::new()
+        .set("class", class)
+        .set("x", inset_x)
+        .set("y", m + inset_y)
+        .set("width", rect_width)
+        .set("height", rect_height)
+        .set("rx", radius)
+        .set("ry", radius)
+        .set("fill", "none")
+        .set("stroke", "black")
+        .set("stroke-width", width))
+}
+
+const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+
+/// Physical size of `dots` at `dpi` in SVG length
+fn svg_mm(dots: u32, dpi: u32) -> String {
+    let mm = dots as f32 * 25.4 / dpi as f32;
+    format!("{}mm", (mm * 100.0).round() / 100.0)
+}
 
-    let margin_color = if options.debug { "gray" } else { "white" };
+/// Create SVG document of the label
+///
+/// A `standalone` document is for other programs: it has the XML
+/// declaration and the size in mm on the tape, while the viewBox stays in
+/// dots. Documents to rasterize leave the size out, since resvg would
+/// take the size in mm for the pixmap.
+fn create_label_svg_from_element(
+    element: &dyn Element,
+    options: &LabelOptions,
+    transparent: bool,
+    decorations: Decorations,
+    standalone: bool,
+    metrics: &mut RenderMetrics,
+) -> Result<String> {
+    let tape = &options.tape_spec;
+
+    // Elementからbounding_boxを取得
+    let start = Instant::now();
+    let bbox = element.bounding_box()?;
+    metrics.measure += start.elapsed();
+    let start = Instant::now();
+
+    let vh = tape.width_dots as f32;
+    let ch = tape.inner_dots as f32;
+    let m = tape.margin_dots() as f32;
+    let feed_scale = feed_scale(tape);
+    let (vw, scale, y_offset) = fit_to_tape(&bbox, options);
+
+    let (margin_color, margin_opacity) = match (decorations.margin_shade, transparent) {
+        (Some(opacity), _) => ("gray", opacity),
+        (None, true) => ("none", 1.0),
+        (None, false) => ("white", 1.0),
+    };
+
+    // The caption goes below the label, widened for short labels
+    let (doc_width, doc_height) = if decorations.caption {
+        (
+            (vw.round() as u32).max(CAPTION_WIDTH),
+            vh as u32 + CAPTION_HEIGHT,
+        )
+    } else {
+        (vw.round() as u32, vh as u32)
+    };
 
     let mut document = Document::new()
-        .set("viewBox", (0, 0, vw.round() as u32, vh as u32))
+        .set("viewBox", (0, 0, doc_width, doc_height))
         .set("xmlns", "http://www.w3.org/2000/svg");
+    if standalone {
+        // Across the tape, the nominal tape width rather than the dots
+        // rounded from it
+        let height_mm = tape.width_mm as f32 * doc_height as f32 / vh;
+        document = document
+            .set("width", svg_mm(doc_width, tape.feed_dpi))
+            .set("height", format!("{}mm", height_mm))
+            .set("preserveAspectRatio", "none")
+            .set("data-ptouch-tape", tape.name.to_string());
+    }
 
     // Add white background for the entire label
-    document = document.add(
-        svg::node::element::Rectangle::new()
-            .set("class", "background")
-            .set("x", 0)
-            .set("y", 0)
-            .set("width", vw.round() as u32)
-            .set("height", vh as u32)
-            .set("fill", "white"),
-    );
+    if decorations.checkerboard {
+        document = add_checkerboard(document, doc_width, doc_height);
+    } else if !transparent {
+        document = document.add(
+            svg::node::element::Rectangle::new()
+                .set("class", "background")
+                .set("x", 0)
+                .set("y", 0)
+                .set("width", doc_width)
+                .set("height", doc_height)
+                .set("fill", "white"),
+        );
+    }
 
     let mut content_group = element.render_at(0.0, 0.0)?;
 
+    // Add baseline guides in the same coordinates as the bounding box below
+    if decorations.baselines {
+        let mut baselines = Vec::new();
+        text_baselines(element, bbox.x, bbox.y, &mut baselines)?;
+        for (x, y, width) in baselines {
+            content_group = content_group.add(
+                svg::node::element::Line::new()
+                    .set("class", "baseline")
+                    .set("x1", x)
+                    .set("y1", y)
+                    .set("x2", x + width)
+                    .set("y2", y)
+                    .set("stroke", "gray")
+                    .set("stroke-width", 1)
+                    .set("stroke-dasharray", "4 2"),
+            );
+        }
+    }
+
     // Add debug bounding box (at original bbox position, will be moved by same transform)
-    if options.debug {
+    if decorations.bbox {
         let bbox_rect = svg::node::element::Rectangle::new()
             .set("class", "debug")
             .set("x", bbox.x as i32)
@@ -169,6 +1158,14 @@ fn create_label_svg_from_element(element: &dyn Element, options: &LabelOptions)
             .add(content_group);
     }
 
+    // Stretch along the tape for non-square pixels (720DPI mode)
+    if feed_scale != 1.0 {
+        content_group = svg::node::element::Group::new()
+            .set("class", "high-resolution")
+            .set("transform", format!("scale({}, 1)", feed_scale))
+            .add(content_group);
+    }
+
     // Create main group with translation
     let main_group = svg::node::element::Group::new()
         .set("class", "vertical-align")
@@ -177,26 +1174,47 @@ fn create_label_svg_from_element(element: &dyn Element, options: &LabelOptions)
     document = document.add(main_group);
 
     // Add margin rectangles to mask non-printable areas (after text rendering)
-    // Color: gray for debug, white for normal mode
-    document = document
-        .add(
-            svg::node::element::Rectangle::new()
-                .set("class", "margin")
-                .set("x", 0)
-                .set("y", 0)
-                .set("width", vw.round() as u32)
-                .set("height", m as u32)
-                .set("fill", margin_color),
-        )
-        .add(
-            svg::node::element::Rectangle::new()
-                .set("class", "margin")
-                .set("x", 0)
-                .set("y", (ch + m) as u32)
-                .set("width", vw.round() as u32)
-                .set("height", m as u32)
-                .set("fill", margin_color),
+    // Color: gray for debug, white for normal mode, none for transparent
+    // Annotation shades them half transparent to show contents bleeding in
+    let margin = |y: u32| {
+        let rect = svg::node::element::Rectangle::new()
+            .set("class", "margin")
+            .set("x", 0)
+            .set("y", y)
+            .set("width", vw.round() as u32)
+            .set("height", m as u32)
+            .set("fill", margin_color);
+        if margin_opacity < 1.0 {
+            rect.set("fill-opacity", margin_opacity)
+        } else {
+            rect
+        }
+    };
+    document = document.add(margin(0)).add(margin((ch + m) as u32));
+    if let Some(border) = options.border
+        && (decorations.preview || !border.preview_only)
+    {
+        document = document.add(border_rect(&border, tape, vw, ch, m)?);
+    }
+    if decorations.caption {
+        let length_mm = vw.round() * 25.4 / tape.feed_dpi as f32;
+        let caption = format!("{} tape, {:.1} mm long", tape.name, length_mm);
+        document = document.add(
+            svg::node::element::Text::new(caption)
+                .set("class", "caption")
+                .set("x", 2)
+                .set("y", vh as u32 + CAPTION_HEIGHT - 5)
+                .set("font-family", caption_font_family(&options.fontdb))
+                .set("font-size", 14)
+                .set("fill", "black"),
         );
+    }
 
-    Ok(document.to_string())
+    let svg_data = if standalone {
+        format!("{}{}", XML_DECLARATION, document)
+    } else {
+        document.to_string()
+    };
+    metrics.svg += start.elapsed();
+    Ok(svg_data)
 }
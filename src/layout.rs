@@ -1,10 +1,83 @@
 use crate::Result;
+use crate::aztec;
 use crate::element::{
-    Column, Element, Gap, Image, Overlay, QrCode, Row, RowOptions, Text, TextOptions,
+    Aztec, Column, Element, Gap, Image, LayoutTree, Overlay, QrCode, QrOptions, Rotated, Rotation,
+    Row, RowOptions, Text, TextMeasure, TextOptions, TextRenderer,
 };
+use crate::normalize::Normalization;
+use crate::payload::{IdAlgorithm, WifiAuth, payload_id_with};
 use fontdb::Database;
+use std::fmt::Write;
+use std::path::Path;
 use std::sync::Arc;
 
+/// Options to parse layout script
+///
+/// Fonts are shared through the fontdb of `text` by all Text elements,
+/// so cloning the options does not copy the font data.
+#[derive(Clone)]
+pub struct LayoutOptions {
+    /// Default font, also restored by "fnt:default"
+    pub text: TextOptions,
+    pub row: RowOptions,
+    /// Vertical space between elements of a Column in SVG units
+    pub column_padding: f32,
+    pub qr: QrOptions,
+    /// Digest of the ID text of "qrc+id:"
+    pub id_algorithm: IdAlgorithm,
+    /// Fail on fonts missing in the fontdb; false to parse without fonts
+    pub check_fonts: bool,
+    /// Unicode normalization of text, also of substituted placeholders
+    pub normalization: Normalization,
+    /// Measurement of the bounding box of Text elements
+    pub text_measure: TextMeasure,
+    /// Renderer that the SVG of Text elements is written for
+    pub text_renderer: TextRenderer,
+    /// Cut off the left side bearing and leading spaces of Text elements
+    pub trim_text_bearing: bool,
+}
+
+impl LayoutOptions {
+    /// Default options for 360dpi tapes
+    pub fn new(fontdb: Arc<Database>) -> Self {
+        LayoutOptions {
+            text: TextOptions::new(fontdb),
+            row: RowOptions::default(),
+            // 20 SVG units ≈ 1.4mm at 360dpi
+            column_padding: 20.0,
+            qr: QrOptions::default(),
+            id_algorithm: IdAlgorithm::default(),
+            check_fonts: true,
+            normalization: Normalization::default(),
+            text_measure: TextMeasure::default(),
+            text_renderer: TextRenderer::default(),
+            trim_text_bearing: false,
+        }
+    }
+
+    /// Default font of Text elements
+    pub fn with_text(mut self, text: TextOptions) -> Self {
+        self.text = text;
+        self
+    }
+
+    pub fn with_row(mut self, row: RowOptions) -> Self {
+        self.row = row;
+        self
+    }
+
+    /// Vertical space between elements of a Column in SVG units
+    pub fn with_column_padding(mut self, column_padding: f32) -> Self {
+        self.column_padding = column_padding;
+        self
+    }
+
+    pub fn with_qr(mut self, qr: QrOptions) -> Self {
+        self.qr = qr;
+        self
+    }
+}
+
 /// Parse layout script DSL into Element tree
 ///
 /// Syntax (BNF):
@@ -12,27 +85,40 @@ use std::sync::Arc;
 /// - {LAYER}   := {ROW}                     // pseudo (identity transformation)
 /// - {ROW}     := {COLUMN} ("+" {COLUMN})*
 /// - {COLUMN}  := {FACTOR}+
-/// - {FACTOR}  := {ELEMENT} | "[" {ROW} "]"
-/// - {ELEMENT} := {BAR} | {IMG} | {QRC} | {GAP} | {BOX} | {FNT} | {TXT}
+/// - {FACTOR}  := {ROT}* ({ELEMENT} | {OPEN} {ROW} "]")
+/// - {ELEMENT} := {BAR} | {AZTEC} | {IMG} | {QRC} | {WIFI} | {GAP} | {BOX} | {FNT} | {TXT}
 ///
 /// Note: LAYER is omitted in implementation and ROW is directly reduced to OVERLAY.
 ///
 /// - {BAR} := "bar:"{STRING}
+/// - {AZTEC} := "aztec:"{STRING} | "aztec@"{EC}":"{STRING}   // EC: error correction in % (default 33)
 /// - {IMG} := "img:"{STRING}
-/// - {QRC} := "qrc:"{STRING}
-/// - {GAP} := "gap:"{SPEC}
+/// - {QRC} := "qrc:"{STRING} | "qrc+id:"{STRING}   // with a short ID of STRING below
+/// - {WIFI} := "wifi:"{SSID}[":"{PASSWORD}[":"{AUTH}]]
+/// - {GAP} := ("gap:" | "gap+:"){SPEC}
 /// - {BOX} := "box:"{SPEC}
 /// - {FNT} := "fnt:"{FONT_SPEC}
 /// - {TXT} := ("txt:"{STRING} | {STRING})+
 ///
+/// - {ROT} := "rot90:" | "rot180:" | "rot270:"   // prefix of the token that follows, or alone
+/// - {OPEN} := "[" | "[p="{PADDING}   // PADDING overrides column padding inside the brackets
+///
 /// - {FONT_SPEC} := {FONT_NAME}:{SIZE}:{LINE_HEIGHT} | "default" | "pop"
+/// - {AUTH} := "WPA" | "WEP" | "nopass"  // defaults to WPA, or nopass without PASSWORD
 ///
-/// - Prefixes: "txt:", "qrc:", "bar:", "img:", "fnt:" (defaults to "txt:" if no prefix)
+/// - Prefixes: "txt:", "qrc:", "qrc+id:", "wifi:", "bar:", "aztec:", "aztec@*:", "img:", "fnt:", "rot*:" (defaults to "txt:" if no prefix)
+/// - Rotation (rot90:, rot180:, rot270:) turns the following FACTOR clockwise;
+///   a text FACTOR is the whole run of continuous text
 /// - "+" separates COLUMN, and layouts columns horizontally (creates ROW)
+/// - "[p=8" opens brackets whose columns (also in nested brackets) put 8
+///   between elements instead of the column padding of the options
 /// - Continuous text becomes a single text element.
 /// - Creating Column or Row only when there are multiple elements to contain
 /// - Font operations (fnt:) are side-effects only;
 ///   a COLUMN with only font operations will result in an error
+/// - Padding of ROW and COLUMN goes between visible elements. "gap:" replaces
+///   the padding around it ("A + gap:10 + B" puts A and B exactly 10 apart),
+///   while "gap+:" is added to it (10 plus the padding on both sides)
 ///
 /// Examples:
 /// Happy Birthday
@@ -50,18 +136,69 @@ use std::sync::Arc;
 /// Long-Title-On-Top [ qrc:http://example.com + nom@example.com ]
 /// -> Column(Text(Long-Title-On-Top),Row(QrCode(http://example.com),Text(nom@example.com)))
 ///
-pub fn parse_layout_script(
+/// qrc:example.com + rot270:[ Serial No. ]
+/// -> Row(QrCode(example.com),Rot270(Text(Serial,No.)))
+///
+/// ```
+/// use ptouch::layout::{LayoutOptions, parse_layout_script};
+/// use std::sync::Arc;
+///
+/// let options = LayoutOptions::new(Arc::new(fontdb::Database::new()));
+/// let script = ["qrc:example.com".to_string(), "+".to_string(), "box:10x10".to_string()];
+/// let element = parse_layout_script(&script, &options).unwrap();
+/// assert_eq!(element.type_name(), "Row");
+/// ```
+pub fn parse_layout_script(script: &[String], options: &LayoutOptions) -> Result<Box<dyn Element>> {
+    Ok(parse(script, options, false)?.0)
+}
+
+/// Parse layout script DSL and return warnings about suspicious tokens
+///
+/// The script is accepted as [`parse_layout_script`] does, but tokens
+/// that likely do not print as intended are reported. See
+/// [`ParseWarningKind`] for what is reported.
+///
+/// ```
+/// use ptouch::layout::{LayoutOptions, ParseWarningKind, parse_layout_script_with_warnings};
+/// use std::sync::Arc;
+///
+/// let options = LayoutOptions::new(Arc::new(fontdb::Database::new()));
+/// let script = ["gap:10".to_string(), "+".to_string(), "gap:5".to_string()];
+/// let (_, warnings) = parse_layout_script_with_warnings(&script, &options).unwrap();
+/// assert_eq!(warnings[0].position, 1);
+/// assert_eq!(warnings[0].kind, ParseWarningKind::InvisibleRow);
+/// ```
+pub fn parse_layout_script_with_warnings(
     script: &[String],
-    text_options: &TextOptions,
-    row_options: &RowOptions,
-    fontdb: Arc<Database>,
-) -> Result<Box<dyn Element>> {
+    options: &LayoutOptions,
+) -> Result<(Box<dyn Element>, Vec<ParseWarning>)> {
+    parse(script, options, false)
+}
+
+/// Parse layout script DSL leniently about missing assets
+///
+/// Missing img: files are replaced by placeholders instead of failing,
+/// and reported as [`ParseWarningKind::MissingImage`] along with the
+/// warnings of [`parse_layout_script_with_warnings`].
+pub fn parse_layout_script_lenient(
+    script: &[String],
+    options: &LayoutOptions,
+) -> Result<(Box<dyn Element>, Vec<ParseWarning>)> {
+    parse(script, options, true)
+}
+
+fn parse(
+    script: &[String],
+    options: &LayoutOptions,
+    lenient_assets: bool,
+) -> Result<(Box<dyn Element>, Vec<ParseWarning>)> {
     if script.is_empty() {
         return Err("Empty layout script".into());
     }
 
     let tokens: Vec<&str> = script.iter().map(|s| s.as_str()).collect();
-    let mut tokenizer = Tokenizer::new(tokens, text_options, row_options, fontdb);
+    let mut tokenizer = Tokenizer::new(tokens, options);
+    tokenizer.lenient_assets = lenient_assets;
     let overlay = parse_overlay(&mut tokenizer)?;
 
     // Check for unconsumed tokens (like unmatched ']')
@@ -69,7 +206,289 @@ pub fn parse_layout_script(
         return Err(format!("Syntax error at {}", tokenizer.position_info()).into());
     }
 
-    Ok(overlay)
+    Ok((overlay, tokenizer.warnings))
+}
+
+/// Warning about a token that parses but likely does not print as intended
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseWarning {
+    /// Position of the token in the script, counting from 1
+    pub position: usize,
+    pub kind: ParseWarningKind,
+}
+
+/// Kinds of [`ParseWarning`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseWarningKind {
+    /// `txt:` with nothing after it, printed as an empty line
+    EmptyText,
+    /// Row of invisible gaps only, which prints nothing
+    InvisibleRow,
+    /// qrc: payload with leading or trailing whitespace, encoded as is
+    QrWhitespace(String),
+    /// Text token same as the previous one, stacked as another line
+    DuplicateText(String),
+    /// Missing img: file replaced by a placeholder
+    MissingImage(String),
+}
+
+impl std::fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ParseWarningKind::EmptyText => write!(f, "Empty txt: token")?,
+            ParseWarningKind::InvisibleRow => write!(f, "Row has only invisible gaps")?,
+            ParseWarningKind::QrWhitespace(payload) => {
+                write!(f, "Whitespace around qrc: payload {:?}", payload)?
+            }
+            ParseWarningKind::DuplicateText(text) => write!(f, "Duplicate text line {:?}", text)?,
+            ParseWarningKind::MissingImage(filename) => write!(f, "Missing image: {}", filename)?,
+        }
+        write!(f, " at position {}", self.position)
+    }
+}
+
+/// Describe the element tree, one element per line indented by depth
+///
+/// Each line has the element, the position of the top-left corner of its
+/// bounding box when the root is rendered at (0, 0), its size, and the
+/// options of Text (font spec), Row and Column. Leaves are shown as their
+/// Display and containers by their type name.
+///
+/// ```
+/// use ptouch::layout::{LayoutOptions, explain, parse_layout_script};
+/// use std::sync::Arc;
+///
+/// let options = LayoutOptions::new(Arc::new(fontdb::Database::new()));
+/// let script = ["box:10x10".to_string(), "+".to_string(), "box:20x5".to_string()];
+/// let element = parse_layout_script(&script, &options).unwrap();
+/// assert_eq!(
+///     explain(&*element).unwrap(),
+///     "Row at (0, 0) size 50x10 align=top padding=20\n  \
+///      Box(10x10) at (0, 0) size 10x10\n  \
+///      Box(20x5) at (30, 0) size 20x5\n"
+/// );
+/// ```
+pub fn explain(element: &dyn Element) -> Result<String> {
+    let tree = element.layout()?;
+    let mut output = String::new();
+    explain_node(element, &tree, 0, &mut output)?;
+    Ok(output)
+}
+
+fn explain_node(
+    element: &dyn Element,
+    tree: &LayoutTree,
+    depth: usize,
+    output: &mut String,
+) -> Result<()> {
+    let children = element.child_offsets()?;
+    let name = if children.is_empty() {
+        element.to_string()
+    } else {
+        element.type_name()
+    };
+    write!(
+        output,
+        "{}{} at ({}, {}) size {}x{}",
+        "  ".repeat(depth),
+        name,
+        tree.x,
+        tree.y,
+        tree.bbox.width,
+        tree.bbox.height
+    )?;
+
+    if let Some(text) = element.downcast_ref::<Text>() {
+        write!(output, " font={}", text.options())?;
+    } else if let Some(row) = element.downcast_ref::<Row>() {
+        let options = row.options();
+        let align = format!("{:?}", options.align).to_lowercase();
+        write!(output, " align={} padding={}", align, options.padding)?;
+    } else if let Some(column) = element.downcast_ref::<Column>() {
+        write!(output, " padding={}", column.padding())?;
+    }
+    writeln!(output)?;
+
+    for ((child, _, _), subtree) in children.iter().zip(&tree.children) {
+        explain_node(*child, subtree, depth + 1, output)?;
+    }
+    Ok(())
+}
+
+/// Read layout script file for a single label
+///
+/// Paragraphs are joined into one script. See [`split_script`] for the
+/// format of the file.
+pub fn read_script_file<P: AsRef<Path>>(path: P) -> Result<Vec<String>> {
+    Ok(read_script_paragraphs(path)?.concat())
+}
+
+/// Read layout script file with a label in each paragraph
+pub fn read_script_paragraphs<P: AsRef<Path>>(path: P) -> Result<Vec<Vec<String>>> {
+    let path = path.as_ref();
+    let input = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    split_script(&input).map_err(|e| format!("{}: {}", path.display(), e).into())
+}
+
+/// Split layout script text into paragraphs of tokens
+///
+/// Tokens are split as a shell does with the command line arguments and
+/// unescaped by [`crate::unescape_shell_string`], so a script file works
+/// the same as the arguments of `ptouch image`:
+/// - Whitespace separates tokens, and quotes and backslashes escape it
+/// - "#" at the beginning of a token comments out the rest of the line
+/// - Backslash at the end of a line continues the line
+/// - Blank lines separate paragraphs
+///
+/// CRLF line endings and a UTF-8 BOM are accepted.
+///
+/// ```
+/// use ptouch::layout::split_script;
+///
+/// let script = "# Asset label\nqrc:SN-0001 + 'Serial No.' \\\n  SN-0001\n\nShelf A";
+/// assert_eq!(
+///     split_script(script).unwrap(),
+///     [vec!["qrc:SN-0001", "+", "Serial No.", "SN-0001"], vec!["Shelf", "A"]]
+/// );
+/// ```
+pub fn split_script(input: &str) -> Result<Vec<Vec<String>>> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let input = input.replace("\r\n", "\n");
+    let mut chars = input.chars();
+
+    // Tokens are kept escaped until they are complete
+    let mut paragraphs = Vec::new();
+    let mut tokens = Vec::new();
+    let mut token: Option<String> = None;
+    let mut blank_line = true;
+    let mut line = 1;
+
+    let unescape = |token: String| crate::unescape_shell_string(&token);
+
+    while let Some(ch) = chars.next() {
+        if ch != '\n' && !ch.is_whitespace() {
+            blank_line = false;
+        }
+        match ch {
+            '\n' => {
+                tokens.extend(token.take().map(unescape));
+                if blank_line && !tokens.is_empty() {
+                    paragraphs.push(std::mem::take(&mut tokens));
+                }
+                blank_line = true;
+                line += 1;
+            }
+            ch if ch.is_whitespace() => tokens.extend(token.take().map(unescape)),
+            '#' if token.is_none() => {
+                // Leave the newline to end the line
+                let rest = chars.as_str();
+                let end = rest.find('\n').unwrap_or(rest.len());
+                chars = rest[end..].chars();
+            }
+            '\\' => match chars.next() {
+                Some('\n') => line += 1,
+                next => {
+                    let token = token.get_or_insert_default();
+                    token.push('\\');
+                    token.extend(next);
+                }
+            },
+            '\'' | '"' => {
+                let token = token.get_or_insert_default();
+                let start_line = line;
+                token.push(ch);
+                loop {
+                    let next = chars.next().ok_or_else(|| {
+                        format!("Unterminated quote {} at line {}", ch, start_line)
+                    })?;
+                    if next == '\n' {
+                        line += 1;
+                    }
+                    token.push(next);
+                    if next == ch {
+                        break;
+                    }
+                    // Keep the escaped quote in the token
+                    if next == '\\' && ch == '"' {
+                        token.extend(chars.next());
+                    }
+                }
+            }
+            ch => token.get_or_insert_default().push(ch),
+        }
+    }
+
+    tokens.extend(token.map(unescape));
+    if !tokens.is_empty() {
+        paragraphs.push(tokens);
+    }
+    Ok(paragraphs)
+}
+
+/// Line that marks a label continued from or on another label
+pub const CONTINUATION_MARK: &str = "…";
+
+/// Split tokens of a layout script into scripts of consecutive labels
+///
+/// Each token is a line of text, so a note too long for one label is
+/// broken at its lines. Labels are filled greedily while `fits` accepts
+/// them; a label continued on the next one ends with
+/// [`CONTINUATION_MARK`], and the next one starts with it.
+///
+/// Brackets, "+", "/", rotations and font operations would not survive
+/// the split and are refused.
+///
+/// ```
+/// use ptouch::layout::split_to_fit;
+///
+/// let tokens: Vec<String> = ["a", "b", "c", "d"].map(String::from).into();
+/// let labels = split_to_fit(&tokens, |label| Ok(label.len() <= 3)).unwrap();
+/// assert_eq!(labels, [vec!["a", "b", "…"], vec!["…", "c", "d"]]);
+/// ```
+pub fn split_to_fit(
+    tokens: &[String],
+    mut fits: impl FnMut(&[String]) -> Result<bool>,
+) -> Result<Vec<Vec<String>>> {
+    if let Some(token) = tokens.iter().find(|token| is_structural(token)) {
+        return Err(format!("Cannot split layout structure: '{}'", token).into());
+    }
+
+    let label = |start: usize, end: usize| {
+        let mut label = Vec::new();
+        if start > 0 {
+            label.push(CONTINUATION_MARK.to_string());
+        }
+        label.extend_from_slice(&tokens[start..end]);
+        if end < tokens.len() {
+            label.push(CONTINUATION_MARK.to_string());
+        }
+        label
+    };
+
+    let mut labels = Vec::new();
+    let mut start = 0;
+    while start < tokens.len() {
+        if !fits(&label(start, start + 1))? {
+            return Err(format!("Line does not fit on a label: '{}'", tokens[start]).into());
+        }
+        let mut end = start + 1;
+        while end < tokens.len() && fits(&label(start, end + 1))? {
+            end += 1;
+        }
+        labels.push(label(start, end));
+        start = end;
+    }
+    Ok(labels)
+}
+
+/// Token whose meaning depends on the tokens around it
+///
+/// Rotations also prefix the token they turn (e.g. "rot90:Hello").
+fn is_structural(token: &str) -> bool {
+    matches!(token, "+" | "/" | "]")
+        || parse_open_bracket(token).is_some()
+        || is_rotation(token)
+        || token.starts_with("fnt:")
 }
 
 /// Tokenizer for layout script DSL
@@ -77,23 +496,24 @@ struct Tokenizer<'a> {
     tokens: Vec<&'a str>,
     position: usize,
     font_stack: Vec<TextOptions>,
-    row_options: &'a RowOptions,
-    fontdb: Arc<Database>,
+    /// Column padding of the innermost brackets
+    column_padding: f32,
+    options: &'a LayoutOptions,
+    /// Lenient mode: placeholders instead of failing on missing assets
+    lenient_assets: bool,
+    warnings: Vec<ParseWarning>,
 }
 
 impl<'a> Tokenizer<'a> {
-    fn new(
-        tokens: Vec<&'a str>,
-        text_options: &'a TextOptions,
-        row_options: &'a RowOptions,
-        fontdb: Arc<Database>,
-    ) -> Self {
+    fn new(tokens: Vec<&'a str>, options: &'a LayoutOptions) -> Self {
         Self {
             tokens,
             position: 0,
-            font_stack: vec![text_options.clone()],
-            row_options,
-            fontdb,
+            font_stack: vec![options.text.clone()],
+            column_padding: options.column_padding,
+            options,
+            lenient_assets: false,
+            warnings: Vec::new(),
         }
     }
 
@@ -129,6 +549,25 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    /// Strip `prefix` from the current token and leave the rest as the current token.
+    /// The token is consumed if nothing remains.
+    fn strip_prefix(&mut self, prefix: &str) -> bool {
+        let Some(token) = self.tokens.get(self.position).copied() else {
+            return false;
+        };
+        match token.strip_prefix(prefix) {
+            Some("") => {
+                self.position += 1;
+                true
+            }
+            Some(rest) => {
+                self.tokens[self.position] = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
     fn expect(&mut self, expected: &str) -> bool {
         if self.peek() == Some(expected) {
             self.consume();
@@ -142,6 +581,15 @@ impl<'a> Tokenizer<'a> {
         self.position >= self.tokens.len()
     }
 
+    /// Report `kind` at the position of the current token
+    fn warn(&mut self, kind: ParseWarningKind) {
+        self.warn_at(self.position + 1, kind);
+    }
+
+    fn warn_at(&mut self, position: usize, kind: ParseWarningKind) {
+        self.warnings.push(ParseWarning { position, kind });
+    }
+
     fn position_info(&self) -> String {
         if self.is_empty() {
             "End of input".to_string()
@@ -175,6 +623,7 @@ fn parse_overlay(tokenizer: &mut Tokenizer) -> Result<Box<dyn Element>> {
 /// Parse ROW := COLUMN ("+" COLUMN)*
 fn parse_row(tokenizer: &mut Tokenizer) -> Result<Box<dyn Element>> {
     let mut columns = Vec::new();
+    let position = tokenizer.position + 1;
 
     // Parse first column
     let column = parse_column(tokenizer)?;
@@ -186,7 +635,11 @@ fn parse_row(tokenizer: &mut Tokenizer) -> Result<Box<dyn Element>> {
         columns.push(column);
     }
 
-    create_row_element(columns, tokenizer.row_options.clone())
+    if columns.len() > 1 && columns.iter().all(|column| !column.is_visible()) {
+        tokenizer.warn_at(position, ParseWarningKind::InvisibleRow);
+    }
+
+    create_row_element(columns, tokenizer.options.row.clone())
 }
 
 /// Parse COLUMN := FACTOR+
@@ -205,15 +658,46 @@ fn parse_column(tokenizer: &mut Tokenizer) -> Result<Box<dyn Element>> {
         .into());
     }
 
-    create_column_element(factors)
+    create_column_element(factors, tokenizer.column_padding)
+}
+
+/// Prefixes of FACTOR rotation
+const ROTATIONS: [(&str, Rotation); 3] = [
+    ("rot90:", Rotation::Rot90),
+    ("rot180:", Rotation::Rot180),
+    ("rot270:", Rotation::Rot270),
+];
+
+fn is_rotation(token: &str) -> bool {
+    ROTATIONS
+        .iter()
+        .any(|(prefix, _)| token.starts_with(prefix))
 }
 
-/// Parse FACTOR := ELEMENT | "[" ROW "]"
+/// Parse FACTOR := ROT* (ELEMENT | OPEN ROW "]")
 fn parse_factor(tokenizer: &mut Tokenizer) -> Result<Option<Box<dyn Element>>> {
+    for (prefix, rotation) in ROTATIONS {
+        if tokenizer.strip_prefix(prefix) {
+            let Some(factor) = parse_factor(tokenizer)? else {
+                return Err(format!(
+                    "Expected element after '{}' at {}",
+                    prefix,
+                    tokenizer.position_info()
+                )
+                .into());
+            };
+            return Ok(Some(Box::new(Rotated::new(factor, rotation))));
+        }
+    }
+
     if let Some(token) = tokenizer.peek() {
-        if token == "[" {
+        if let Some(padding) = parse_open_bracket(token) {
+            let padding = padding?;
             tokenizer.consume(); // consume "["
+            let outer_padding = tokenizer.column_padding;
+            tokenizer.column_padding = padding.unwrap_or(outer_padding);
             let row = parse_row(tokenizer)?;
+            tokenizer.column_padding = outer_padding;
             if !tokenizer.expect("]") {
                 return Err(format!("Expected ']' at {}", tokenizer.position_info()).into());
             }
@@ -226,28 +710,61 @@ fn parse_factor(tokenizer: &mut Tokenizer) -> Result<Option<Box<dyn Element>>> {
     }
 }
 
-/// Parse ELEMENT := BAR_ELEMENT | IMG_ELEMENT | QRC_ELEMENT | GAP_ELEMENT | BOX_ELEMENT
-///                  | TXT_ELEMENT
+/// Parse OPEN := "[" | "[p=" PADDING
+///
+/// Returns None if `token` does not open brackets, or the padding override.
+fn parse_open_bracket(token: &str) -> Option<Result<Option<f32>>> {
+    if token == "[" {
+        return Some(Ok(None));
+    }
+    let spec = token.strip_prefix("[p=")?;
+    Some(match spec.parse::<f32>() {
+        Ok(padding) if padding.is_finite() && padding >= 0.0 => Ok(Some(padding)),
+        _ => Err(format!("Invalid column padding '{}'", token).into()),
+    })
+}
+
+/// Parse ELEMENT := BAR_ELEMENT | AZTEC_ELEMENT | IMG_ELEMENT | QRC_ELEMENT | WIFI_ELEMENT | GAP_ELEMENT
+///                  | BOX_ELEMENT | TXT_ELEMENT
 fn parse_element(tokenizer: &mut Tokenizer) -> Result<Option<Box<dyn Element>>> {
     if let Some(token) = tokenizer.peek() {
         if let Some(content) = token.strip_prefix("bar:") {
             let content = content.to_string();
             tokenizer.consume();
             Err(format!("Barcode not yet implemented: {}", content).into())
+        } else if is_aztec(token) {
+            let token = token.to_string();
+            tokenizer.consume();
+            parse_aztec_element(&token, &tokenizer.options.qr)
         } else if let Some(content) = token.strip_prefix("img:") {
             let content = content.to_string();
-            tokenizer.consume();
-            parse_img_element(&content)
+            parse_img_element(tokenizer, &content)
         } else if let Some(content) = token.strip_prefix("qrc:") {
             let content = content.to_string();
+            if content.trim() != content {
+                tokenizer.warn(ParseWarningKind::QrWhitespace(content.clone()));
+            }
             tokenizer.consume();
-            let qr_code = QrCode::new(content)?;
+            let qr_code = QrCode::with_options(content, &tokenizer.options.qr)?;
             Ok(Some(Box::new(qr_code)))
+        } else if let Some(content) = token.strip_prefix("qrc+id:") {
+            let content = content.to_string();
+            tokenizer.consume();
+            parse_qrc_id_element(tokenizer, content)
+        } else if let Some(content) = token.strip_prefix("wifi:") {
+            let content = content.to_string();
+            tokenizer.consume();
+            parse_wifi_element(&content, &tokenizer.options.qr)
         } else if let Some(content) = token.strip_prefix("gap:") {
             let content = content.to_string();
             tokenizer.consume();
             let gap = Gap::parse(&content, false)?;
             Ok(Some(Box::new(gap)))
+        } else if let Some(content) = token.strip_prefix("gap+:") {
+            let content = content.to_string();
+            tokenizer.consume();
+            let gap = Gap::parse(&content, false)?.with_padding(true);
+            Ok(Some(Box::new(gap)))
         } else if let Some(content) = token.strip_prefix("box:") {
             let content = content.to_string();
             tokenizer.consume();
@@ -275,36 +792,103 @@ fn parse_txt_element(tokenizer: &mut Tokenizer) -> Result<Option<Box<dyn Element
     while let Some(token) = tokenizer.peek() {
         // Stop if we hit a non-text element or separator or brackets
         if token.starts_with("bar:")
+            || is_aztec(token)
             || token.starts_with("img:")
             || token.starts_with("qrc:")
+            || token.starts_with("qrc+id:")
+            || token.starts_with("wifi:")
             || token.starts_with("gap:")
+            || token.starts_with("gap+:")
             || token.starts_with("box:")
             || token.starts_with("fnt:")
+            || is_rotation(token)
             || token == "+"
             || token == "/"
-            || token == "["
+            || parse_open_bracket(token).is_some()
             || token == "]"
         {
             break;
         }
 
-        let txt = tokenizer.consume().unwrap();
-        if let Some(content) = txt.strip_prefix("txt:") {
-            texts.push(content.to_string());
-        } else {
-            texts.push(txt.to_string());
+        let content = token.strip_prefix("txt:").unwrap_or(token).to_string();
+        if token == "txt:" {
+            tokenizer.warn(ParseWarningKind::EmptyText);
+        } else if texts.last() == Some(&content) {
+            tokenizer.warn(ParseWarningKind::DuplicateText(content.clone()));
         }
+        tokenizer.consume();
+        texts.push(content);
     }
 
     if texts.is_empty() {
         return Ok(None);
     }
 
-    Ok(Some(Box::new(Text::new(
-        &texts,
-        tokenizer.current_font(),
-        tokenizer.fontdb.clone(),
-    )?)))
+    Ok(Some(create_text(tokenizer, &texts)?))
+}
+
+/// Create Text in the current font
+fn create_text(tokenizer: &Tokenizer, texts: &[String]) -> Result<Box<dyn Element>> {
+    let normalization = tokenizer.options.normalization;
+    let texts: Vec<String> = texts.iter().map(|text| normalization.apply(text)).collect();
+    let font = tokenizer.current_font();
+    let text = if tokenizer.options.check_fonts {
+        Text::new(&texts, font)?
+    } else {
+        Text::new_unchecked(&texts, font)
+    };
+    Ok(Box::new(
+        text.with_measure(tokenizer.options.text_measure)
+            .with_renderer(tokenizer.options.text_renderer)
+            .with_trim_bearing(tokenizer.options.trim_text_bearing),
+    ))
+}
+
+/// Whether `token` is an AZTEC element
+fn is_aztec(token: &str) -> bool {
+    token.starts_with("aztec:") || parse_aztec_spec(token).is_some()
+}
+
+/// Split "aztec@EC:DATA" into EC and DATA
+fn parse_aztec_spec(token: &str) -> Option<(&str, &str)> {
+    let spec = token.strip_prefix("aztec@")?;
+    let (ec, data) = spec.split_once(':')?;
+    ec.trim_end_matches('%')
+        .bytes()
+        .all(|b| b.is_ascii_digit())
+        .then_some((ec, data))
+}
+
+/// Parse aztec:DATA or aztec@EC:DATA element
+fn parse_aztec_element(token: &str, qr_options: &QrOptions) -> Result<Option<Box<dyn Element>>> {
+    let (ec_percent, data) = match token.strip_prefix("aztec:") {
+        Some(data) => (aztec::DEFAULT_EC_PERCENT, data),
+        None => {
+            let (ec, data) =
+                parse_aztec_spec(token).ok_or_else(|| format!("Invalid Aztec code '{}'", token))?;
+            let ec_percent = ec
+                .trim_end_matches('%')
+                .parse::<u8>()
+                .map_err(|_| format!("Invalid Aztec error correction '{}'", ec))?;
+            (ec_percent, data)
+        }
+    };
+    let aztec = Aztec::with_options(data.to_string(), qr_options, ec_percent)?;
+    Ok(Some(Box::new(aztec)))
+}
+
+/// Parse qrc+id:PAYLOAD element into a Column of QrCode and its ID text
+fn parse_qrc_id_element(
+    tokenizer: &mut Tokenizer,
+    content: String,
+) -> Result<Option<Box<dyn Element>>> {
+    let id = payload_id_with(&content, tokenizer.options.id_algorithm);
+    let qr_code = QrCode::with_options(content, &tokenizer.options.qr)?;
+    let text = create_text(tokenizer, &[id])?;
+    Ok(Some(Box::new(Column::new(
+        vec![Box::new(qr_code), text],
+        tokenizer.column_padding,
+    ))))
 }
 
 /// Create Row element or return single element if columns.len() == 1
@@ -321,12 +905,15 @@ fn create_row_element(
 }
 
 /// Create Column element or return single element if elements.len() == 1
-fn create_column_element(elements: Vec<Box<dyn Element>>) -> Result<Box<dyn Element>> {
+fn create_column_element(
+    elements: Vec<Box<dyn Element>>,
+    padding: f32,
+) -> Result<Box<dyn Element>> {
     let mut elements = elements;
     match elements.len() {
         0 => Err("No effective elements found".into()),
         1 => Ok(elements.pop().unwrap()),
-        _ => Ok(Box::new(Column::new(elements, 20.0))), // FIXME: 360DPI
+        _ => Ok(Box::new(Column::new(elements, padding))),
     }
 }
 
@@ -360,25 +947,46 @@ fn parse_font_element(tokenizer: &mut Tokenizer, spec: &str) -> Result<()> {
 
 /// Parse font specification and create new TextOptions
 fn parse_font_spec(base_font: &TextOptions, spec: &str) -> Result<TextOptions> {
-    let base_string = base_font.to_string();
-    let base_parts: Vec<&str> = base_string.split(':').collect();
-    let spec_parts: Vec<&str> = spec.split(':').collect();
+    base_font.clone().with_spec(spec)
+}
+
+/// Parse wifi:SSID[:PASSWORD[:AUTH]] element
+///
+/// The last field is taken as AUTH only if it is a known auth type,
+/// so that the password may contain ':'.
+fn parse_wifi_element(spec: &str, qr_options: &QrOptions) -> Result<Option<Box<dyn Element>>> {
+    let (rest, auth) = match spec.rsplit_once(':') {
+        Some((rest, auth)) if rest.contains(':') && auth.parse::<WifiAuth>().is_ok() => {
+            (rest, Some(auth.parse::<WifiAuth>()?))
+        }
+        _ => (spec, None),
+    };
+    let (ssid, password) = rest.split_once(':').unwrap_or((rest, ""));
 
-    let merged_spec = (0..4)
-        .map(|i| {
-            *spec_parts
-                .get(i)
-                .filter(|s| !s.is_empty())
-                .unwrap_or(&base_parts[i])
-        })
-        .collect::<Vec<&str>>()
-        .join(":");
+    if ssid.is_empty() {
+        return Err("Empty SSID in wifi element".into());
+    }
+    let auth = auth.unwrap_or(if password.is_empty() {
+        WifiAuth::NoPass
+    } else {
+        WifiAuth::Wpa
+    });
+    if auth != WifiAuth::NoPass && password.is_empty() {
+        return Err(format!("Password required for {} in wifi element", auth).into());
+    }
 
-    merged_spec.parse()
+    Ok(Some(Box::new(QrCode::wifi(
+        ssid, password, auth, qr_options,
+    )?)))
 }
 
 /// Parse img:filename:width:height element
-fn parse_img_element(spec: &str) -> Result<Option<Box<dyn Element>>> {
+///
+/// In lenient mode, a missing file becomes a placeholder with a warning.
+fn parse_img_element(tokenizer: &mut Tokenizer, spec: &str) -> Result<Option<Box<dyn Element>>> {
+    let position = tokenizer.position + 1;
+    tokenizer.consume();
+
     let parts: Vec<&str> = spec.split(':').collect();
 
     if parts.is_empty() {
@@ -404,6 +1012,64 @@ fn parse_img_element(spec: &str) -> Result<Option<Box<dyn Element>>> {
         .transpose()
         .map_err(|_| format!("Invalid height: '{}'", parts.get(2).unwrap_or(&"")))?;
 
+    if tokenizer.lenient_assets && !Path::new(filename).exists() {
+        tokenizer.warn_at(
+            position,
+            ParseWarningKind::MissingImage(filename.to_string()),
+        );
+        let image = Image::placeholder(filename.to_string(), req_width, req_height);
+        return Ok(Some(Box::new(image)));
+    }
+
     let image = Image::new(filename.to_string(), req_width, req_height)?;
     Ok(Some(Box::new(image)))
 }
+
+/// Warnings collected over labels in a batch
+#[derive(Debug, Default)]
+pub struct WarningSummary {
+    warnings: Vec<(i64, String)>,
+}
+
+impl WarningSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add warnings of label number `n`
+    pub fn add(&mut self, n: i64, warnings: Vec<String>) {
+        self.warnings.extend(warnings.into_iter().map(|w| (n, w)));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    /// Number of warnings
+    pub fn count(&self) -> usize {
+        self.warnings.len()
+    }
+
+    /// Number of labels with warnings
+    pub fn label_count(&self) -> usize {
+        let mut labels: Vec<i64> = self.warnings.iter().map(|(n, _)| *n).collect();
+        labels.sort_unstable();
+        labels.dedup();
+        labels.len()
+    }
+}
+
+impl std::fmt::Display for WarningSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} warning(s) in {} label(s)",
+            self.count(),
+            self.label_count()
+        )?;
+        for (n, warning) in &self.warnings {
+            write!(f, "\n  Label {}: {}", n, warning)?;
+        }
+        Ok(())
+    }
+}
@@ -1,18 +1,55 @@
+pub mod aztec;
 pub mod backend;
+pub mod diff;
 pub mod element;
+pub mod error;
+pub mod escp_command;
 pub mod label;
 pub mod layout;
+pub mod normalize;
+pub mod pattern;
+pub mod payload;
+pub mod prelude;
 pub mod printable_image;
 pub mod printer;
 pub mod raster_command;
+pub mod serve;
 pub mod status;
+pub mod status_cache;
 pub mod tape;
+pub mod template;
 
 use fontdb::Database;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Suppress informational messages of [`info!`]
+///
+/// Warnings and errors are printed anyway.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Print an informational message to stderr unless quiet
+///
+/// Stdout is left for data such as PNG images and JSON results.
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if !$crate::is_quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
 /// Remove shell escaping from a string
 ///
 /// Handles common escape sequences: `\<space>`, `\<tab>`, `\<newline>`, `\\`, `\'`, `\"`
@@ -113,31 +150,165 @@ pub fn load_fontdb_with_paths(font_paths: &[PathBuf]) -> Result<std::sync::Arc<D
     Ok(std::sync::Arc::new(fontdb))
 }
 
+/// Font tables of color glyphs: layered, bitmap and SVG glyphs
+const COLOR_FONT_TABLES: [&[u8; 4]; 4] = [b"COLR", b"CBDT", b"sbix", b"SVG "];
+
+/// Check if the face has color glyphs
+///
+/// Color glyphs threshold poorly to 1-bit: light colors vanish and
+/// dark ones fill the whole shape.
+pub fn is_color_font(fontdb: &Database, id: fontdb::ID) -> bool {
+    fontdb
+        .with_face_data(id, |data, index| {
+            let face = ttf_parser::RawFace::parse(data, index).ok()?;
+            Some(
+                COLOR_FONT_TABLES
+                    .iter()
+                    .any(|tag| face.table(ttf_parser::Tag::from_bytes(tag)).is_some()),
+            )
+        })
+        .flatten()
+        .unwrap_or(false)
+}
+
+/// Remove fonts with color glyphs from fontdb
+///
+/// Emoji then fall back to a monochrome font (e.g. Noto Emoji) and print
+/// as clean black outlines. Returns the number of removed faces.
+pub fn remove_color_fonts(fontdb: &mut Database) -> usize {
+    let ids: Vec<fontdb::ID> = fontdb
+        .faces()
+        .map(|face| face.id)
+        .filter(|&id| is_color_font(fontdb, id))
+        .collect();
+    for &id in &ids {
+        fontdb.remove_face(id);
+    }
+    ids.len()
+}
+
+/// Select a face of `family` the same way on every machine
+///
+/// Several installed fonts may share a family name (e.g. variants of
+/// Noto Sans CJK), and fontdb picks the first loaded one, which depends
+/// on the order of directory entries. Faces are chosen by:
+/// 1. Family name matching exactly, or ignoring case if none does
+/// 2. Requested style, then the weight closest to the requested one
+///    (the lighter one on a tie)
+/// 3. Path of the font file, then the index of the face in the file
+///
+/// # Examples
+///
+/// ```
+/// use fontdb::{Database, Style, Weight};
+///
+/// let fontdb = Database::new();
+/// assert_eq!(ptouch::select_face(&fontdb, "DejaVu Sans", Weight::NORMAL, Style::Normal), None);
+/// ```
+pub fn select_face(
+    fontdb: &Database,
+    family: &str,
+    weight: fontdb::Weight,
+    style: fontdb::Style,
+) -> Option<fontdb::ID> {
+    let has_family = |face: &&fontdb::FaceInfo, exact: bool| {
+        face.families.iter().any(|(name, _)| {
+            if exact {
+                name == family
+            } else {
+                name.eq_ignore_ascii_case(family)
+            }
+        })
+    };
+    let mut candidates: Vec<&fontdb::FaceInfo> = fontdb
+        .faces()
+        .filter(|face| has_family(face, true))
+        .collect();
+    if candidates.is_empty() {
+        candidates = fontdb
+            .faces()
+            .filter(|face| has_family(face, false))
+            .collect();
+    }
+
+    candidates
+        .into_iter()
+        .min_by_key(|face| {
+            (
+                face.style != style,
+                face.weight.0.abs_diff(weight.0),
+                face.weight.0,
+                face_location(face),
+            )
+        })
+        .map(|face| face.id)
+}
+
+/// Path of the font file and index of the face in it, to order faces
+/// independently of the load order
+///
+/// The path is empty for fonts loaded from memory.
+pub(crate) fn face_location(face: &fontdb::FaceInfo) -> (PathBuf, u32) {
+    let path = match &face.source {
+        fontdb::Source::File(path) => path.clone(),
+        fontdb::Source::SharedFile(path, _) => path.clone(),
+        fontdb::Source::Binary(_) => PathBuf::new(),
+    };
+    (path, face.index)
+}
+
 /// Get available font names from font paths
 ///
 /// # Arguments
 /// * `font_paths` - Vector of paths to search for fonts
 ///
 /// # Returns
-/// * Vector of font names sorted alphabetically
+/// * Vector of unique font names sorted alphabetically (see [`sort_font_names`])
 pub fn get_font_names(font_paths: &[PathBuf]) -> Vec<String> {
     let fontdb = match load_fontdb_with_paths(font_paths) {
         Ok(db) => db,
         Err(_) => return vec![],
     };
 
-    let mut font_names = std::collections::HashSet::new();
+    // Collect font family names
+    let names = fontdb
+        .faces()
+        .flat_map(|face| face.families.iter().map(|(name, _)| name.clone()));
+    sort_font_names(names)
+}
+
+/// Sort font names ignoring case and remove case-insensitive duplicates
+///
+/// Fonts are loaded in the order of directory entries, which varies
+/// between systems, so the result must not depend on the input order.
+/// Of names differing only in case, the first one in byte order is kept.
+///
+/// # Examples
+///
+/// ```
+/// use ptouch::sort_font_names;
+///
+/// let names = ["noto Sans", "DejaVu Sans", "Noto Sans", "DejaVu Sans"];
+/// assert_eq!(
+///     sort_font_names(names.map(String::from)),
+///     ["DejaVu Sans", "Noto Sans"]
+/// );
+/// ```
+pub fn sort_font_names(names: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut font_names = std::collections::BTreeMap::<String, String>::new();
 
-    // Collect unique font family names
-    for face in fontdb.faces() {
-        for (family_name, _) in &face.families {
-            font_names.insert(family_name.clone());
+    for name in names {
+        let key = name.to_lowercase();
+        match font_names.get_mut(&key) {
+            Some(kept) if name < *kept => *kept = name,
+            Some(_) => {}
+            None => {
+                font_names.insert(key, name);
+            }
         }
     }
 
-    let mut names: Vec<String> = font_names.into_iter().collect();
-    names.sort();
-    names
+    font_names.into_values().collect()
 }
 
 /// Parse font name and weight from a font specification string
@@ -1,20 +1,38 @@
+use chrono::{Local, NaiveDateTime};
 use clap::{Args, CommandFactory, Parser, Subcommand};
 use clap_complete::engine::ArgValueCompleter;
 use clap_complete::{CompleteEnv, CompletionCandidate, generate};
 use std::io::{self, Read, Write};
-use std::path::PathBuf;
-
-use ptouch::backend;
-use ptouch::element::TextOptions;
-use ptouch::element::{RowOptions, VerticalAlign};
-use ptouch::label::{Label, LabelOptions, Placement as LabelPlacement};
-use ptouch::layout;
-use ptouch::printable_image::PrintableImage;
-use ptouch::printer::Printer;
-use ptouch::tape::{self, Tape, TapeSpec};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ptouch::backend::{self, Backend};
+use ptouch::diff;
+use ptouch::element::{QrEcLevel, QrOptions, RowOptions, VerticalAlign};
+use ptouch::element::{TextMeasure, TextOptions, TextRenderer};
+use ptouch::error::{self, Error, ErrorKind, ResultExt};
+use ptouch::escp_command::EscpCommand;
+use ptouch::label::{
+    Border, Label, LabelOptions, Placement as LabelPlacement, RenderMetrics, rasterize_label_svg,
+    write_atomically,
+};
+use ptouch::layout::{self, LayoutOptions, ParseWarningKind, WarningSummary};
+use ptouch::normalize::Normalization;
+use ptouch::pattern;
+use ptouch::payload::IdAlgorithm as PayloadIdAlgorithm;
+use ptouch::printable_image::{PrintableImage, RasterOptions};
+use ptouch::printer::{
+    Compression, CutPolicy, PrintOptions, PrintReport, Printer, Quality as PrintQuality, check_job,
+};
+use ptouch::serve::{Renderer, Server};
+use ptouch::status::Status;
+use ptouch::status_cache::StatusCache;
+use ptouch::tape::{self, Tape, TapeMismatch, TapeSpec};
+use ptouch::template::{self, Counter, Placeholders};
 use ptouch::{
-    Result, get_font_names, load_fontdb_with_paths, parse_font_name_and_weight,
-    unescape_shell_string,
+    Result, get_font_names, info, load_fontdb_with_paths, parse_font_name_and_weight,
+    remove_color_fonts, unescape_shell_string,
 };
 
 #[derive(clap::ValueEnum, Clone, Copy, Debug)]
@@ -57,6 +75,175 @@ impl From<Placement> for VerticalAlign {
     }
 }
 
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+#[clap(rename_all = "lowercase")]
+enum Cut {
+    Each,
+    End,
+    None,
+}
+
+impl std::fmt::Display for Cut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        CutPolicy::from(*self).fmt(f)
+    }
+}
+
+impl From<Cut> for CutPolicy {
+    fn from(cut: Cut) -> Self {
+        match cut {
+            Cut::Each => CutPolicy::Each,
+            Cut::End => CutPolicy::EndOnly,
+            Cut::None => CutPolicy::None,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+#[clap(rename_all = "lowercase")]
+enum QrEc {
+    L,
+    M,
+    Q,
+    H,
+}
+
+impl std::fmt::Display for QrEc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QrEc::L => write!(f, "l"),
+            QrEc::M => write!(f, "m"),
+            QrEc::Q => write!(f, "q"),
+            QrEc::H => write!(f, "h"),
+        }
+    }
+}
+
+impl From<QrEc> for QrEcLevel {
+    fn from(level: QrEc) -> Self {
+        match level {
+            QrEc::L => QrEcLevel::L,
+            QrEc::M => QrEcLevel::M,
+            QrEc::Q => QrEcLevel::Q,
+            QrEc::H => QrEcLevel::H,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+#[clap(rename_all = "lowercase")]
+enum Quality {
+    Draft,
+    Normal,
+    High,
+}
+
+impl std::fmt::Display for Quality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        PrintQuality::from(*self).fmt(f)
+    }
+}
+
+impl From<Quality> for PrintQuality {
+    fn from(quality: Quality) -> Self {
+        match quality {
+            Quality::Draft => PrintQuality::Draft,
+            Quality::Normal => PrintQuality::Normal,
+            Quality::High => PrintQuality::High,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+#[clap(rename_all = "lowercase")]
+enum IdAlgorithm {
+    Sha256,
+    Crc32,
+}
+
+impl std::fmt::Display for IdAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        PayloadIdAlgorithm::from(*self).fmt(f)
+    }
+}
+
+impl From<IdAlgorithm> for PayloadIdAlgorithm {
+    fn from(algorithm: IdAlgorithm) -> Self {
+        match algorithm {
+            IdAlgorithm::Sha256 => PayloadIdAlgorithm::Sha256,
+            IdAlgorithm::Crc32 => PayloadIdAlgorithm::Crc32,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+#[clap(rename_all = "lowercase")]
+enum Normalize {
+    None,
+    Nfc,
+    Nfkc,
+}
+
+impl std::fmt::Display for Normalize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Normalization::from(*self).fmt(f)
+    }
+}
+
+impl From<Normalize> for Normalization {
+    fn from(normalize: Normalize) -> Self {
+        match normalize {
+            Normalize::None => Normalization::None,
+            Normalize::Nfc => Normalization::Nfc,
+            Normalize::Nfkc => Normalization::Nfkc,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+#[clap(rename_all = "lowercase")]
+enum Measure {
+    Pixel,
+    Logical,
+}
+
+impl std::fmt::Display for Measure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        TextMeasure::from(*self).fmt(f)
+    }
+}
+
+impl From<Measure> for TextMeasure {
+    fn from(measure: Measure) -> Self {
+        match measure {
+            Measure::Pixel => TextMeasure::Pixel,
+            Measure::Logical => TextMeasure::Logical,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum RenderTarget {
+    Resvg,
+    #[value(name = "imagemagick-compat")]
+    ImageMagickCompat,
+}
+
+impl std::fmt::Display for RenderTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        TextRenderer::from(*self).fmt(f)
+    }
+}
+
+impl From<RenderTarget> for TextRenderer {
+    fn from(target: RenderTarget) -> Self {
+        match target {
+            RenderTarget::Resvg => TextRenderer::Resvg,
+            RenderTarget::ImageMagickCompat => TextRenderer::ImageMagickCompat,
+        }
+    }
+}
+
 #[derive(clap::ValueEnum, Clone, Copy, Debug)]
 enum TapeName {
     #[value(name = "3.5")]
@@ -95,6 +282,8 @@ enum Resolution {
     Dpi180,
     #[value(name = "360")]
     Dpi360,
+    #[value(name = "720")]
+    Dpi720,
 }
 
 impl Resolution {
@@ -102,6 +291,7 @@ impl Resolution {
         match self {
             Resolution::Dpi180 => 180,
             Resolution::Dpi360 => 360,
+            Resolution::Dpi720 => 720,
         }
     }
 }
@@ -114,23 +304,27 @@ impl std::fmt::Display for Resolution {
 
 impl TapeName {
     fn to_tape(self, resolution: Resolution) -> Result<Tape> {
+        // 720DPI uses 360DPI tapes with doubled raster lines
         match (self, resolution) {
-            (TapeName::Tape3_5, Resolution::Dpi360) => Ok(Tape::TZe3H),
-            (TapeName::Tape6, Resolution::Dpi360) => Ok(Tape::TZe6H),
-            (TapeName::Tape9, Resolution::Dpi360) => Ok(Tape::TZe9H),
-            (TapeName::Tape12, Resolution::Dpi360) => Ok(Tape::TZe12H),
-            (TapeName::Tape18, Resolution::Dpi360) => Ok(Tape::TZe18H),
-            (TapeName::Tape24, Resolution::Dpi360) => Ok(Tape::TZe24H),
-            (TapeName::Tape36, Resolution::Dpi360) => Ok(Tape::TZe36H),
+            (TapeName::Tape3_5, Resolution::Dpi360 | Resolution::Dpi720) => Ok(Tape::TZe3H),
+            (TapeName::Tape6, Resolution::Dpi360 | Resolution::Dpi720) => Ok(Tape::TZe6H),
+            (TapeName::Tape9, Resolution::Dpi360 | Resolution::Dpi720) => Ok(Tape::TZe9H),
+            (TapeName::Tape12, Resolution::Dpi360 | Resolution::Dpi720) => Ok(Tape::TZe12H),
+            (TapeName::Tape18, Resolution::Dpi360 | Resolution::Dpi720) => Ok(Tape::TZe18H),
+            (TapeName::Tape24, Resolution::Dpi360 | Resolution::Dpi720) => Ok(Tape::TZe24H),
+            (TapeName::Tape36, Resolution::Dpi360 | Resolution::Dpi720) => Ok(Tape::TZe36H),
             (TapeName::Tape3_5, Resolution::Dpi180) => Ok(Tape::TZe3L),
             (TapeName::Tape6, Resolution::Dpi180) => Ok(Tape::TZe6L),
             (TapeName::Tape9, Resolution::Dpi180) => Ok(Tape::TZe9L),
             (TapeName::Tape12, Resolution::Dpi180) => Ok(Tape::TZe12L),
             (TapeName::Tape18, Resolution::Dpi180) => Ok(Tape::TZe18L),
             (TapeName::Tape24, Resolution::Dpi180) => Ok(Tape::TZe24L),
-            (TapeName::Tape36, Resolution::Dpi180) => {
-                Err("36mm tape not supported on 180DPI printers".into())
-            }
+            (TapeName::Tape36, Resolution::Dpi180) => Err(Error::new(
+                ErrorKind::Usage,
+                "36mm tape not supported on 180DPI printers; use -r 360 or -r 720, \
+                 or a tape of 3.5, 6, 9, 12, 18 or 24mm",
+            )
+            .into()),
         }
     }
 }
@@ -143,6 +337,14 @@ impl TapeName {
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Suppress informational messages
+    #[arg(
+        long = "quiet",
+        global = true,
+        long_help = "Suppress informational messages and warnings about the layout script. Other warnings and errors are still printed to stderr. Informational messages always go to stderr, leaving stdout for data."
+    )]
+    quiet: bool,
 }
 
 #[derive(Subcommand)]
@@ -151,22 +353,125 @@ enum Commands {
     Image(ImageArgs),
     /// Send raster image to P-Touch
     Print(PrintArgs),
+    /// Create or print numbered labels from a template
+    Batch(BatchArgs),
+    /// Print jobs sent over a local socket
+    Serve(ServeArgs),
     /// Get status information from P-Touch
     Status(StatusArgs),
+    /// Print text with built-in fonts of P-Touch (ESC/P mode)
+    Text(TextArgs),
+    /// Check layout script syntax without rendering
+    Check(CheckArgs),
+    /// Compare two label images pixel by pixel
+    Diff(DiffArgs),
+    /// Print a calibration pattern for the loaded tape
+    TestPattern(TestPatternArgs),
+    /// Send a raw command file to P-Touch unmodified
+    Raw(RawArgs),
+    /// Decode a raw 32-byte status packet from hex
+    DecodeStatus(DecodeStatusArgs),
     /// Generate shell completion scripts
     Completion(CompletionArgs),
 }
 
 #[derive(Args)]
 struct ImageArgs {
+    /// Align elements in a row by text baseline
+    #[arg(
+        long = "align-baseline",
+        long_help = "Align elements in a row by the baseline of their first text line instead of --placement. Elements without text put their bottom on the baseline."
+    )]
+    align_baseline: bool,
+
+    /// Also write a preview with margins and guides marked
+    #[arg(
+        long = "annotate",
+        value_name = "FILE",
+        long_help = "Also write a grayscale PNG preview to FILE with the margins shaded, the bounding box of contents, baseline guides of text and a caption of the tape and length. The output itself is left unmarked. With 'ptouch batch', FILE takes {n} placeholders as --output."
+    )]
+    annotate: Option<PathBuf>,
+
     /// Auto scale contents to the tape width
     #[arg(short = 'a', long = "auto-scale")]
     auto_scale: bool,
 
+    /// Use the narrowest tape that fits contents
+    #[arg(
+        long = "auto-tape",
+        conflicts_with = "auto_scale",
+        long_help = "Use the narrowest tape whose printable area fits contents instead of --tape-name. Tapes of the --resolution are considered. It fails if even the widest tape is too narrow."
+    )]
+    auto_tape: bool,
+
+    /// Frame the label with an outline MM inside the printable area
+    #[arg(
+        long = "border",
+        value_name = "MM",
+        value_parser = parse_mm,
+        long_help = "Frame the label with an outline MM inside the edges of the printable area, drawn over the contents, e.g. for name badges. See --border-width and --border-radius."
+    )]
+    border: Option<f32>,
+
+    /// Draw the --border only in previews
+    #[arg(
+        long = "border-preview",
+        requires = "border",
+        long_help = "Draw the --border only in SVG output and --annotate previews, e.g. to check the layout against a frame that is not printed"
+    )]
+    border_preview: bool,
+
+    /// Corner radius of the --border in mm
+    #[arg(long = "border-radius", value_name = "MM", default_value_t = 0.0,
+          value_parser = parse_mm, requires = "border")]
+    border_radius: f32,
+
+    /// Line width of the --border in mm
+    #[arg(long = "border-width", value_name = "MM", default_value_t = 0.3,
+          value_parser = parse_mm, requires = "border")]
+    border_width: f32,
+
+    /// Space between stacked elements in dots [default: 1.4mm]
+    #[arg(long = "column-gap", value_name = "DOTS", value_parser = parse_column_gap,
+          long_help = "Space between elements stacked in a column in dots. Brackets can override it as '[p=DOTS ... ]'. [default: 1.4mm, 20 dots at 360DPI]")]
+    column_gap: Option<f32>,
+
+    /// Label length in character cells of the font
+    #[arg(long = "columns", value_name = "N", conflicts_with = "rotate",
+          value_parser = clap::value_parser!(u32).range(1..),
+          long_help = "Label length in N character cells (advance width of '0') of the font. Shorter contents are padded with blank, and longer ones are cut off.")]
+    columns: Option<u32>,
+
     /// Show alignment marks for debug
     #[arg(short = 'd', long = "debug")]
     debug: bool,
 
+    /// Convert text to paths in SVG source
+    #[arg(
+        long = "embed-text",
+        requires = "source",
+        long_help = "Convert text to paths of the glyph outlines in SVG source (--source), so that it looks the same on machines without the fonts. The SVG gets larger."
+    )]
+    embed_text: bool,
+
+    /// Print the tree of parsed elements instead of the image
+    #[arg(
+        long = "explain",
+        conflicts_with_all = ["output", "source", "annotate"],
+        long_help = "Print the parsed elements to stdout as an indented tree, with the position and size of each element and the options of text, rows and columns. No image is written."
+    )]
+    explain: bool,
+
+    /// Date for {date} placeholders [default: now]
+    #[arg(long = "date", value_parser = parse_date,
+          long_help = "Date and time for {date}, {time} and {datetime} placeholders: YYYY-MM-DD[THH:MM[:SS]] [default: now]")]
+    date: Option<NaiveDateTime>,
+
+    /// Override DPI for layout [default: tape resolution]
+    #[arg(long = "dpi", value_parser = clap::value_parser!(u32).range(1..),
+          long_help = "Override DPI used for mm-to-dots conversions and QR module sizing. [default: tape resolution]")]
+    dpi: Option<u32>,
+
     /// Font name
     #[arg(short = 'f', long = "font", default_value = "Noto Sans CJK JP",
           add = ArgValueCompleter::new(font_completer))]
@@ -181,10 +486,71 @@ struct ImageArgs {
     )]
     font_paths: Vec<PathBuf>,
 
+    /// Digest of the ID text of qrc+id: elements
+    #[arg(long = "id-algorithm", value_name = "ALGORITHM", default_value_t = IdAlgorithm::Sha256,
+          long_help = "Digest of the short ID text printed below qrc+id: QR codes. [possible values: sha256, crc32]",
+          hide_possible_values = true)]
+    id_algorithm: IdAlgorithm,
+
+    /// Render missing img: files as placeholders
+    #[arg(
+        long = "lenient-assets",
+        long_help = "Render missing img: files as crossed-out gray boxes with warnings instead of failing"
+    )]
+    lenient_assets: bool,
+
+    /// Warn about hidden layers and contents in the margins
+    #[arg(
+        long = "lint",
+        long_help = "Warn about Overlay layers hidden by an opaque upper layer and contents falling in the non-printable margins"
+    )]
+    lint: bool,
+
+    /// Fail on warnings about the layout script
+    #[arg(
+        long = "strict-script",
+        long_help = "Fail on warnings about the layout script, e.g. an empty txt: token, a row of invisible gaps only, whitespace around a qrc: payload, or the same text twice in a row. Without this, they are printed unless --quiet."
+    )]
+    strict_script: bool,
+
     /// Line height in pixels [default: font-size]
     #[arg(short = 'l', long = "line-height")]
     line_height: Option<u32>,
 
+    /// Line height as a ratio of font size (e.g. 1.2)
+    #[arg(long = "line-height-ratio", value_name = "RATIO", conflicts_with = "line_height",
+          value_parser = parse_line_height_ratio)]
+    line_height_ratio: Option<f32>,
+
+    /// Warn when capital letters print shorter than this
+    #[arg(
+        long = "min-text-mm",
+        value_name = "MM",
+        default_value_t = 1.5,
+        value_parser = parse_mm,
+        long_help = "Warn when capital letters of the default font print shorter than MM millimeters at the DPI of the tape, e.g. --font-size 12 taken as points. Also warns when they are taller than the printable width. 0 disables the lower bound. Checked only with an explicit --font-size and without --auto-scale."
+    )]
+    min_text_mm: f32,
+
+    /// Print emoji as black outlines instead of color glyphs
+    #[arg(
+        long = "monochrome-emoji",
+        long_help = "Ignore color fonts so that emoji fall back to a monochrome font (e.g. Noto Emoji) and print as clean black shapes. Color glyphs threshold poorly to 1-bit."
+    )]
+    monochrome_emoji: bool,
+
+    /// Unicode normalization of text
+    #[arg(long = "normalize", value_name = "FORM", default_value_t = Normalize::None,
+          long_help = "Unicode normalization of text before layout, also of substituted placeholders. nfkc folds full-width ASCII (１２３) and halfwidth kana (ｶﾅ) into their usual forms, nfc composes accents with their base characters. [possible values: none, nfc, nfkc]",
+          hide_possible_values = true)]
+    normalize: Normalize,
+
+    /// Measure text by pixels or glyph outlines
+    #[arg(long = "text-measure", value_name = "MODE", default_value_t = Measure::Pixel,
+          long_help = "Measure the bounding box of text by scanning rendered pixels (pixel), or from the glyph outlines rounded out to whole dots (logical). logical skips rendering and is faster for long text; the boxes agree within a dot or two. [possible values: pixel, logical]",
+          hide_possible_values = true)]
+    text_measure: Measure,
+
     /// Output to file [default: stdout]
     #[arg(short = 'o', long = "output")]
     output: Option<PathBuf>,
@@ -195,9 +561,52 @@ struct ImageArgs {
           hide_possible_values = true)]
     placement: Placement,
 
+    /// Place contents at a percentage or dots from the top
+    #[arg(long = "placement-offset", value_name = "OFFSET", value_parser = parse_placement_offset,
+          conflicts_with = "auto_scale",
+          long_help = "Place contents at OFFSET: N% of the space they leave on the tape (0%: top, 50%: center, 100%: bottom), or DOTS below the top of the printable area. Overrides --placement for the label; elements in a row are still aligned by --placement.")]
+    placement_offset: Option<LabelPlacement>,
+
+    /// Checkered background in SVG source and --annotate preview
+    #[arg(
+        long = "preview-checkerboard",
+        conflicts_with = "transparent",
+        long_help = "Draw a light checkered background instead of white in SVG source (--source) and the --annotate preview, so that white (knocked-out) contents stay visible while designing. PNG output for printing is unaffected."
+    )]
+    preview_checkerboard: bool,
+
+    /// Error correction level of QR codes
+    #[arg(long = "qr-ec", value_name = "LEVEL", default_value_t = QrEc::M, hide_possible_values = true,
+          long_help = "Error correction level of all QR codes (qrc:, qrc+id:, wifi:): l (7%), m (15%), q (25%) or h (30% of the code recoverable). Higher levels make larger codes.")]
+    qr_ec: QrEc,
+
+    /// Size of a QR code module in dots
+    #[arg(long = "qr-module-size", value_name = "DOTS", default_value_t = QrOptions::default().module_size,
+          value_parser = parse_qr_module_size,
+          long_help = "Size of a module (the smallest square) of all QR and Aztec codes in dots of the tape's native DPI. The physical size is kept with --dpi.")]
+    qr_module_size: f32,
+
+    /// Renderer that text is written for
+    #[arg(long = "renderer", value_name = "RENDERER", default_value_t = RenderTarget::ImageMagickCompat,
+          long_help = "Write text in the SVG for RENDERER. resvg, which renders the PNG of ptouch, hangs text from the top by dominant-baseline=\"hanging\" and measures it tightly. imagemagick-compat puts the first line far below the top instead, so that ImageMagick, which ignores dominant-baseline, can convert the SVG of -S/--source. [possible values: resvg, imagemagick-compat]",
+          hide_possible_values = true)]
+    renderer: RenderTarget,
+
+    /// Cut off blank space on the left of text
+    #[arg(
+        long = "trim",
+        overrides_with = "no_trim",
+        long_help = "Start text at its ink, cutting off the left side bearing of the first character and leading spaces. Tightens labels of a single text, but lines of text stacked in a column (A B) no longer line up on the left, as each is cut by its own first character."
+    )]
+    trim: bool,
+
+    /// Keep blank space on the left of text (default)
+    #[arg(long = "no-trim", overrides_with = "trim")]
+    no_trim: bool,
+
     /// Printer resolution in DPI
     #[arg(short = 'r', long = "resolution", default_value_t = Resolution::Dpi360,
-          long_help = "Printer resolution in DPI. [possible values: 180, 360]",
+          long_help = "Printer resolution in DPI. 720 renders 360DPI tape with doubled raster lines along the tape (high resolution mode). [possible values: 180, 360, 720]",
           hide_possible_values = true)]
     resolution: Resolution,
 
@@ -205,9 +614,9 @@ struct ImageArgs {
     #[arg(short = 'R', long = "rotate")]
     rotate: bool,
 
-    /// Font size in pixels
-    #[arg(short = 's', long = "font-size", default_value = "24")]
-    font_size: u32,
+    /// Font size in pixels [default: 24]
+    #[arg(short = 's', long = "font-size")]
+    font_size: Option<u32>,
 
     /// Tape size in mm
     #[arg(short = 't', long = "tape-name", default_value_t = TapeName::Tape12,
@@ -215,10 +624,39 @@ struct ImageArgs {
           hide_possible_values = true)]
     tape_name: TapeName,
 
+    /// Read layout script from file
+    #[arg(
+        long = "script-file",
+        value_name = "FILE",
+        conflicts_with = "text",
+        long_help = "Read layout script from FILE instead of arguments. Tokens are split as in the shell. '#' starts a comment, and backslash at the end of a line continues it. With 'ptouch batch', each paragraph separated by blank lines is a separate label."
+    )]
+    script_file: Option<PathBuf>,
+
     /// Output SVG source instead of PNG
     #[arg(short = 'S', long = "source")]
     source: bool,
 
+    /// Render at N times the size and reduce to the tape
+    #[arg(long = "supersample", value_name = "N", default_value_t = 1,
+          value_parser = clap::value_parser!(u32).range(1..),
+          long_help = "Render at N times the size, average N x N blocks and diffuse the error into black and white dots. Keeps thin strokes of small text even. 1 turns it off.")]
+    supersample: u32,
+
+    /// Print time taken by each stage to stderr
+    #[arg(
+        long = "timings",
+        long_help = "Print wall-clock time of each stage (fonts, parse, measure, svg, rasterize, encode) to stderr after rendering. batch sums the stages over all labels."
+    )]
+    timings: bool,
+
+    /// Transparent background in SVG source
+    #[arg(
+        long = "transparent",
+        long_help = "Omit the white background in SVG source (--source). PNG output is always white-backed."
+    )]
+    transparent: bool,
+
     /// Text lines to print [default: stdin]
     text: Vec<String>,
 }
@@ -227,201 +665,1515 @@ struct ImageArgs {
 struct PrintArgs {
     /// Printer host: hostname.local (network) or vid:pid (USB)
     /// Examples: ptouch.local, 192.168.1.100, 04f9:2085
-    #[arg(short = 'H', long = "host", required = true)]
-    host: String,
+    #[arg(
+        short = 'H',
+        long = "host",
+        required = true,
+        verbatim_doc_comment,
+        long_help = "Printer host: hostname.local (network) or vid:pid (USB)\nExamples: ptouch.local, 192.168.1.100, 04f9:2085\nRepeat to print the same label on several printers. Failures are reported per host without stopping the others."
+    )]
+    host: Vec<String>,
 
-    /// Enable continuous printing (no cutting)
-    #[arg(short = 'c', long = "continuous")]
-    continuous: bool,
+    #[command(flatten)]
+    cut: CutArgs,
+
+    /// Print in high resolution mode (720DPI along the tape)
+    #[arg(
+        long = "high-resolution",
+        long_help = "Print in high resolution mode (720DPI along the tape). The image must be created with --resolution 720."
+    )]
+    high_resolution: bool,
+
+    /// Print the result as JSON
+    #[arg(
+        long = "json",
+        long_help = "Print the result as a JSON object to stdout: host, tape, length_mm, raster_lines, bytes_sent and duration_ms. One line per host."
+    )]
+    json: bool,
+
+    /// Print quality
+    #[arg(short = 'q', long = "quality", default_value_t = Quality::Normal,
+          long_help = "Print quality. draft prints faster, high gives priority to quality. [possible values: draft, normal, high]",
+          hide_possible_values = true)]
+    quality: Quality,
+
+    /// Send raster lines without TIFF compression
+    #[arg(
+        long = "no-compress",
+        long_help = "Send raster lines without TIFF compression (for firmwares that misbehave with compressed data)"
+    )]
+    no_compress: bool,
+
+    /// Disable half-cut between labels
+    #[arg(
+        long = "no-half-cut",
+        long_help = "Disable half-cut between labels. Half-cut is also disabled on models without a half cutter."
+    )]
+    no_half_cut: bool,
+
+    /// Printer resolution in DPI for --skip-status
+    #[arg(short = 'r', long = "resolution", default_value_t = Resolution::Dpi360,
+          long_help = "Printer resolution in DPI assumed with --skip-status. [possible values: 180, 360, 720]",
+          hide_possible_values = true)]
+    resolution: Resolution,
+
+    /// Tape size in mm for --skip-status
+    #[arg(
+        short = 't',
+        long = "tape-name",
+        long_help = "Tape size in mm assumed with --skip-status. [possible values: 3.5, 6, 9, 12, 18, 24, 36]",
+        hide_possible_values = true
+    )]
+    tape_name: Option<TapeName>,
+
+    #[command(flatten)]
+    raster: RasterArgs,
+
+    /// Send long labels in chunks
+    #[arg(
+        long = "stream",
+        long_help = "Send raster lines to the printer in chunks while building the job instead of the whole job at once. Keeps memory low for very long (e.g. continuous) labels."
+    )]
+    stream: bool,
+
+    /// Print N copies of the label
+    #[arg(long = "repeat", value_name = "N", default_value_t = 1,
+          value_parser = clap::value_parser!(u32).range(1..),
+          long_help = "Print N copies of the label. Each copy is sent as a job of its own unless --reuse-buffer is given.")]
+    repeat: u32,
+
+    /// Send the label once for --repeat
+    #[arg(
+        long = "reuse-buffer",
+        long_help = "Send raster data once for --repeat and print the other copies from the printer's buffer, saving transfer time on slow links. Printers without the feature (and unknown ones, e.g. with --skip-status) get the label sent for each copy."
+    )]
+    reuse_buffer: bool,
+
+    /// Cut off blank space at both ends of the label
+    #[arg(
+        long = "trim",
+        long_help = "Cut off white or transparent columns at both ends of the image along the tape, e.g. padding around screenshots, and report how much was trimmed. The height must still match the tape. Fails for a blank image."
+    )]
+    trim: bool,
+
+    /// Print SVG source of 'ptouch image -S' instead of PNG
+    #[arg(
+        long = "svg",
+        value_name = "FILE",
+        conflicts_with = "png_file",
+        long_help = "Print SVG source written by 'ptouch image -S' instead of PNG, rendering it here. The tape is taken from the SVG, or from its height in dots at --resolution for SVG without it. Fonts of the text must be installed or given by --font-path."
+    )]
+    svg: Option<PathBuf>,
+
+    /// Additional font path for --svg
+    #[arg(
+        short = 'F',
+        long = "font-path",
+        value_name = "FONT_PATH",
+        requires = "svg",
+        long_help = "Additional font path for --svg: directory or font file (can be specified multiple times)"
+    )]
+    font_paths: Vec<PathBuf>,
+
+    #[command(flatten)]
+    status_options: StatusOptions,
 
     /// PNG file to print [default: stdin]
     png_file: Option<PathBuf>,
 }
 
 #[derive(Args)]
-struct StatusArgs {
+struct BatchArgs {
+    /// Number of labels
+    #[arg(long = "count", default_value = "1", value_parser = clap::value_parser!(u32).range(1..),
+          long_help = "Number of labels. With --script-file of several paragraphs, the paragraphs are repeated COUNT times and each label gets the next number.")]
+    count: u32,
+
+    /// First label number
+    #[arg(long = "start", default_value = "1", allow_negative_numbers = true)]
+    start: i64,
+
+    /// Increment of label number
+    #[arg(long = "step", default_value = "1", allow_negative_numbers = true)]
+    step: i64,
+
+    /// Print to P-Touch instead of writing files
+    #[arg(
+        short = 'H',
+        long = "host",
+        long_help = "Print to P-Touch instead of writing files: hostname.local (network) or vid:pid (USB)\nExamples: ptouch.local, 192.168.1.100, 04f9:2085"
+    )]
+    host: Option<String>,
+
+    #[command(flatten)]
+    cut: CutArgs,
+
+    #[command(flatten)]
+    raster: RasterArgs,
+
+    /// Split long text across several labels
+    #[arg(
+        long = "split",
+        long_help = "Split text too long for one label at its lines into consecutive labels, each fitting the printable width of the tape and --max-length. Labels continued on the next one end with a \"…\" line, and the next one starts with it. Brackets, +, /, rotations and fnt: cannot be split."
+    )]
+    split: bool,
+
+    #[command(flatten)]
+    status_options: StatusOptions,
+
+    #[command(flatten)]
+    image: ImageArgs,
+}
+
+#[derive(Args)]
+struct ServeArgs {
     /// Printer host: hostname.local (network) or vid:pid (USB)
     /// Examples: ptouch.local, 192.168.1.100, 04f9:2085
-    #[arg(short = 'H', long = "host", required = true)]
+    #[arg(short = 'H', long = "host")]
     host: String,
 
-    /// Show verbose information
-    #[arg(short = 'v', long = "verbose")]
-    verbose: bool,
+    /// Address to accept jobs on
+    #[arg(
+        long = "listen",
+        value_name = "ADDR",
+        default_value = "127.0.0.1:7777",
+        long_help = "Address to accept jobs on. Each line from a client is a job in JSON: {\"layout\": \"...\", \"copies\": 2}, {\"png_base64\": \"...\"} or {\"shutdown\": true}. Each job is answered with a line of JSON."
+    )]
+    listen: String,
+
+    #[command(flatten)]
+    raster: RasterArgs,
+
+    #[command(flatten)]
+    status_options: StatusOptions,
+
+    #[command(flatten)]
+    image: ImageArgs,
 }
 
+/// When to cut the tape
 #[derive(Args)]
-struct CompletionArgs {
-    /// Shell type
-    #[arg(value_enum)]
-    shell: clap_complete::Shell,
-}
+struct CutArgs {
+    /// When to cut the tape
+    #[arg(long = "cut", value_name = "POLICY", default_value_t = Cut::Each, hide_possible_values = true,
+          long_help = "When to cut the tape: each (after every label), end (only after the last label, leaving the labels as one strip), none (never; the strip stays in the printer and the next job continues on it).")]
+    cut: Cut,
+
+    /// Enable continuous printing (same as --cut none)
+    #[arg(short = 'c', long = "continuous", conflicts_with = "cut")]
+    continuous: bool,
 
-fn handle_image_command(args: ImageArgs) -> Result<()> {
-    // Get text input
-    let texts = if args.text.is_empty() {
-        let mut input = String::new();
-        io::stdin().read_to_string(&mut input)?;
-        input.lines().map(|s| s.to_string()).collect()
-    } else {
-        args.text.clone()
-    };
+    /// Cut after every N labels
+    #[arg(long = "cut-every", value_name = "N", default_value_t = 1,
+          value_parser = clap::value_parser!(u8).range(1..),
+          long_help = "Cut after every N labels, leaving strips of N labels. Copies and batch labels are counted across their jobs. 1 to 255; not to cut at all, use --cut none instead.")]
+    cut_every: u8,
+}
 
-    if texts.is_empty() {
-        return Err("No text input provided".into());
+impl CutArgs {
+    fn policy(&self) -> CutPolicy {
+        if self.continuous {
+            CutPolicy::None
+        } else {
+            self.cut.into()
+        }
     }
+}
 
-    // Create fontdb from font paths
-    let fontdb = load_fontdb_with_paths(&args.font_paths)?;
+/// How to turn labels into raster lines
+#[derive(Args)]
+struct RasterArgs {
+    /// Shift contents across the tape in dots
+    #[arg(
+        long = "v-offset",
+        value_name = "DOTS",
+        default_value_t = 0,
+        allow_negative_numbers = true,
+        long_help = "Shift contents across the tape by DOTS print head pins to correct labels printed off-center. Positive values move contents toward the bottom of the image. Find the value with 'ptouch test-pattern'."
+    )]
+    v_offset: i32,
 
-    // Parse font name and weight from font argument
+    /// Dead pins of the print head to keep contents off
+    #[arg(
+        long = "mask-pins",
+        value_name = "PINS",
+        value_delimiter = ',',
+        long_help = "Comma-separated print head pins never to be set, e.g. dead pins leaving white stripes. Contents are shifted across the tape to avoid them where blank rows permit. 'ptouch test-pattern' shows the pin numbers."
+    )]
+    mask_pins: Vec<u32>,
+
+    /// Print white on black
+    #[arg(
+        long = "negative",
+        long_help = "Print white on black: swap printed and blank dots in the printable area, knocking contents out of a black background. The margins stay blank."
+    )]
+    negative: bool,
+}
+
+impl From<&RasterArgs> for RasterOptions {
+    fn from(args: &RasterArgs) -> Self {
+        RasterOptions {
+            v_offset: args.v_offset,
+            mask_pins: args.mask_pins.clone(),
+            negative: args.negative,
+        }
+    }
+}
+
+/// How to check the printer before printing
+#[derive(Args)]
+struct StatusOptions {
+    /// Print even if the device is not identified as a P-Touch
+    #[arg(
+        long = "force",
+        long_help = "Print even if the network device is not identified as a Brother P-Touch by SNMP"
+    )]
+    force: bool,
+
+    /// Do not query printer status
+    #[arg(
+        long = "skip-status",
+        conflicts_with = "status_cache",
+        long_help = "Do not query printer status. The tape is assumed from --tape-name and --resolution, and printer errors are not detected."
+    )]
+    skip_status: bool,
+
+    /// Stop on printer warnings such as weak batteries
+    #[arg(
+        long = "strict",
+        long_help = "Stop on printer warnings: weak batteries, or jobs longer than 200 mm without the AC adapter"
+    )]
+    strict: bool,
+
+    /// Reuse printer status queried within SECONDS
+    #[arg(
+        long = "status-cache",
+        value_name = "SECONDS",
+        long_help = "Reuse printer status queried within SECONDS, cached in $XDG_STATE_HOME/ptouch. The status is checked again on tape mismatch, and the cache is cleared on print errors."
+    )]
+    status_cache: Option<u64>,
+
+    /// Refuse labels longer than MM
+    #[arg(
+        long = "max-length",
+        value_name = "MM",
+        default_value_t = 500,
+        long_help = "Refuse labels longer than MM along the tape before sending anything to the printer, as a guard against absurd jobs. 0 for unlimited."
+    )]
+    max_length: u32,
+}
+
+impl StatusOptions {
+    /// Longest label to print in mm, None for unlimited
+    fn max_length_mm(&self) -> Option<f32> {
+        (self.max_length > 0).then_some(self.max_length as f32)
+    }
+}
+
+#[derive(Args)]
+struct StatusArgs {
+    /// Printer host: hostname.local (network) or vid:pid (USB)
+    /// Examples: ptouch.local, 192.168.1.100, 04f9:2085
+    #[arg(short = 'H', long = "host", required = true)]
+    host: String,
+
+    /// Show verbose information
+    #[arg(short = 'v', long = "verbose")]
+    verbose: bool,
+}
+
+#[derive(Args)]
+struct TextArgs {
+    /// Printer host: hostname.local (network) or vid:pid (USB)
+    /// Examples: ptouch.local, 192.168.1.100, 04f9:2085
+    #[arg(short = 'H', long = "host", required = true)]
+    host: String,
+
+    /// Character size in dots
+    #[arg(short = 's', long = "size", default_value = "32",
+          value_parser = clap::value_parser!(u16).range(1..))]
+    size: u16,
+
+    /// Text lines to print (ASCII only)
+    #[arg(required = true)]
+    text: Vec<String>,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// PNG file to compare
+    a: PathBuf,
+
+    /// PNG file to compare with
+    b: PathBuf,
+}
+
+#[derive(Args)]
+struct CheckArgs {
+    /// Font name
+    #[arg(short = 'f', long = "font", default_value = "Noto Sans CJK JP",
+          add = ArgValueCompleter::new(font_completer))]
+    font: String,
+
+    /// Additional font path
+    #[arg(
+        short = 'F',
+        long = "font-path",
+        value_name = "FONT_PATH",
+        long_help = "Additional font path: directory or font file (can be specified multiple times)"
+    )]
+    font_paths: Vec<PathBuf>,
+
+    /// Skip loading fonts and checking font names
+    #[arg(long = "no-font-check")]
+    no_font_check: bool,
+
+    /// Layout script to check [default: stdin]
+    #[arg(value_name = "SCRIPT")]
+    script: Vec<String>,
+}
+
+#[derive(Args)]
+struct TestPatternArgs {
+    /// Printer host: hostname.local (network) or vid:pid (USB)
+    /// Examples: ptouch.local, 192.168.1.100, 04f9:2085
+    #[arg(short = 'H', long = "host", required_unless_present = "output")]
+    host: Option<String>,
+
+    /// Font name for the ruler
+    #[arg(short = 'f', long = "font", default_value = "Noto Sans CJK JP",
+          add = ArgValueCompleter::new(font_completer))]
+    font: String,
+
+    /// Additional font path
+    #[arg(
+        short = 'F',
+        long = "font-path",
+        value_name = "FONT_PATH",
+        long_help = "Additional font path: directory or font file (can be specified multiple times)"
+    )]
+    font_paths: Vec<PathBuf>,
+
+    /// Save PNG to file instead of printing
+    #[arg(short = 'o', long = "output", conflicts_with = "host")]
+    output: Option<PathBuf>,
+
+    /// Printer resolution in DPI for --output or --skip-status
+    #[arg(short = 'r', long = "resolution", default_value_t = Resolution::Dpi360,
+          long_help = "Printer resolution in DPI assumed with --output or --skip-status. [possible values: 180, 360, 720]",
+          hide_possible_values = true)]
+    resolution: Resolution,
+
+    /// Tape size in mm for --output or --skip-status
+    #[arg(short = 't', long = "tape-name", default_value_t = TapeName::Tape12,
+          long_help = "Tape size in mm assumed with --output or --skip-status. [possible values: 3.5, 6, 9, 12, 18, 24, 36]",
+          hide_possible_values = true)]
+    tape_name: TapeName,
+
+    #[command(flatten)]
+    raster: RasterArgs,
+
+    #[command(flatten)]
+    status_options: StatusOptions,
+}
+
+#[derive(Args)]
+struct RawArgs {
+    /// Printer host: hostname.local (network), vid:pid (USB) or file:PATH
+    /// Examples: ptouch.local, 192.168.1.100, 04f9:2085, file:out.bin
+    #[arg(short = 'H', long = "host", required = true)]
+    host: String,
+
+    /// Send without confirmation
+    #[arg(short = 'f', long = "force")]
+    force: bool,
+
+    /// Get status after sending
+    #[arg(short = 's', long = "status")]
+    status: bool,
+
+    /// Show verbose status information
+    #[arg(short = 'v', long = "verbose")]
+    verbose: bool,
+
+    /// Command file to send
+    file: PathBuf,
+}
+
+#[derive(Args)]
+struct DecodeStatusArgs {
+    /// Show verbose information
+    #[arg(short = 'v', long = "verbose")]
+    verbose: bool,
+
+    /// Status packet in hex (whitespace is ignored)
+    /// Example: 80 20 42 30 71 30 00 00 00 00 0C 01 ...
+    #[arg(required = true)]
+    hex: Vec<String>,
+}
+
+#[derive(Args)]
+struct CompletionArgs {
+    /// Shell type
+    #[arg(value_enum)]
+    shell: clap_complete::Shell,
+}
+
+fn parse_line_height_ratio(s: &str) -> std::result::Result<f32, String> {
+    let ratio: f32 = s.parse().map_err(|_| format!("Invalid ratio: '{}'", s))?;
+    if !ratio.is_finite() || ratio < 0.0 {
+        return Err(format!("Ratio must be a non-negative number: '{}'", s));
+    }
+    Ok(ratio)
+}
+
+fn parse_mm(s: &str) -> std::result::Result<f32, String> {
+    let mm: f32 = s.parse().map_err(|_| format!("Invalid length: '{}'", s))?;
+    if !mm.is_finite() || mm < 0.0 {
+        return Err(format!("Length must be a non-negative number: '{}'", s));
+    }
+    Ok(mm)
+}
+
+fn parse_column_gap(s: &str) -> std::result::Result<f32, String> {
+    let gap: f32 = s.parse().map_err(|_| format!("Invalid gap: '{}'", s))?;
+    if !gap.is_finite() || gap < 0.0 {
+        return Err(format!("Gap must be a non-negative number: '{}'", s));
+    }
+    Ok(gap)
+}
+
+fn parse_qr_module_size(s: &str) -> std::result::Result<f32, String> {
+    let size: f32 = s
+        .parse()
+        .map_err(|_| format!("Invalid module size: '{}'", s))?;
+    if !size.is_finite() || size <= 0.0 {
+        return Err(format!("Module size must be a positive number: '{}'", s));
+    }
+    Ok(size)
+}
+
+fn parse_placement_offset(s: &str) -> std::result::Result<LabelPlacement, String> {
+    s.parse()
+        .map_err(|e: Box<dyn std::error::Error>| e.to_string())
+}
+
+fn parse_date(s: &str) -> std::result::Result<NaiveDateTime, String> {
+    template::parse_datetime(s).map_err(|e| e.to_string())
+}
+
+fn handle_image_command(args: ImageArgs) -> Result<()> {
+    // Get text input
+    let texts = read_texts(&args)?;
+    let values = Placeholders {
+        counter: None,
+        datetime: Some(args.date.unwrap_or_else(|| Local::now().naive_local())),
+    };
+    let mut metrics = RenderMetrics::default();
+    let mut warnings = Vec::new();
+    let fontdb = load_fonts(&args, &mut metrics)?;
+    let label = create_label(&args, &fontdb, &texts, &values, &mut warnings, &mut metrics)?;
+    for warning in &warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    if args.explain {
+        let tree = layout::explain(label.element()).or_kind(ErrorKind::Render)?;
+        print!("{}", tree);
+        return Ok(());
+    }
+    write_label(&label, args.source, args.output.as_ref(), &mut metrics)?;
+    if let Some(path) = &args.annotate {
+        write_annotation(&label, path)?;
+    }
+    print_timings(args.timings, &metrics);
+    Ok(())
+}
+
+/// Print the table of stages for --timings
+fn print_timings(enabled: bool, metrics: &RenderMetrics) {
+    if enabled {
+        for line in metrics.to_string().lines() {
+            eprintln!("Timing: {}", line);
+        }
+    }
+}
+
+/// Get layout script from arguments, script file or stdin
+fn read_texts(args: &ImageArgs) -> Result<Vec<String>> {
+    let texts: Vec<String> = if let Some(path) = &args.script_file {
+        layout::read_script_file(path)?
+    } else if args.text.is_empty() {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        input.lines().map(|s| s.to_string()).collect()
+    } else {
+        args.text.clone()
+    };
+
+    if texts.is_empty() {
+        return Err(Error::new(ErrorKind::Usage, "No text input provided").into());
+    }
+    Ok(texts)
+}
+
+/// Load system fonts and --font-path, once for all labels of a command
+fn load_fonts(args: &ImageArgs, metrics: &mut RenderMetrics) -> Result<Arc<fontdb::Database>> {
+    let start = Instant::now();
+    let mut fontdb = load_fontdb_with_paths(&args.font_paths).or_kind(ErrorKind::Render)?;
+    if args.monochrome_emoji {
+        remove_color_fonts(Arc::make_mut(&mut fontdb));
+    }
+    metrics.fonts += start.elapsed();
+    Ok(fontdb)
+}
+
+fn create_label(
+    args: &ImageArgs,
+    fontdb: &Arc<fontdb::Database>,
+    texts: &[String],
+    values: &Placeholders,
+    warnings: &mut Vec<String>,
+    metrics: &mut RenderMetrics,
+) -> Result<Label> {
+    // Expand placeholders before parsing so that they also work in qrc: payloads
+    let texts = template::substitute_script(texts, values).or_kind(ErrorKind::Render)?;
+    let start = Instant::now();
+
+    // Parse font name and weight from font argument
     let (font_name, font_weight) = parse_font_name_and_weight(&args.font);
 
     // Create text options for layout parsing
+    let defaults = TextOptions::new(fontdb.clone());
+    let font_size = args.font_size.unwrap_or(defaults.font_size);
     let text_options = TextOptions {
         font_name,
         font_weight,
-        font_size: args.font_size,
-        line_height: args.line_height.unwrap_or(args.font_size),
+        font_size,
+        line_height: match (args.line_height, args.line_height_ratio) {
+            (Some(line_height), _) => line_height,
+            (None, Some(ratio)) => (font_size as f32 * ratio).round() as u32,
+            (None, None) => font_size,
+        },
+        ..defaults
     };
 
     // Create label options (simplified)
-    let tape_spec = TapeSpec::new(args.tape_name.to_tape(args.resolution)?);
+    let mut tape_spec = TapeSpec::new(args.tape_name.to_tape(args.resolution)?);
+    let native_dpi = tape_spec.dpi;
+    if let Some(dpi) = args.dpi {
+        tape_spec = tape_spec.with_dpi(dpi)?;
+    }
+    if let Resolution::Dpi720 = args.resolution {
+        tape_spec = tape_spec.with_high_resolution()?;
+    }
 
     // At 360 DPI, 14.0 is 1mm, 20.0 is 1.4mm
     // Note: This depends on ""quiet zone" of QR code
     let row_padding = tape_spec.mm_to_dots(1.4) as f32;
 
+    // QR modules keep their physical size when DPI is overridden
+    let qr_options = QrOptions {
+        module_size: args.qr_module_size * tape_spec.dpi as f32 / native_dpi as f32,
+        ec_level: args.qr_ec.into(),
+    };
+
+    // --columns counts character cells of the default font
+    let length = match args.columns {
+        Some(n) => Some(text_options.cell_width().or_kind(ErrorKind::Render)? * n as f32),
+        None => None,
+    };
+
     let label_options = LabelOptions {
         fontdb: fontdb.clone(),
         tape_spec,
         auto_scale: args.auto_scale,
         rotate: args.rotate,
-        placement: args.placement.into(),
+        placement: args.placement_offset.unwrap_or(args.placement.into()),
         debug: args.debug,
+        transparent: args.transparent,
+        preview_checkerboard: args.preview_checkerboard,
+        embed_text: args.embed_text,
+        length,
+        supersample: args.supersample,
+        border: args.border.map(|inset_mm| {
+            Border::new(inset_mm)
+                .with_width(args.border_width)
+                .with_radius(args.border_radius)
+                .with_preview_only(args.border_preview)
+        }),
     };
 
-    // Create row options from placement
-    let row_options = RowOptions {
-        align: args.placement.into(),
-        padding: row_padding,
+    let layout_options = LayoutOptions {
+        text: text_options,
+        // Create row options from placement
+        row: RowOptions {
+            align: if args.align_baseline {
+                VerticalAlign::Baseline
+            } else {
+                args.placement.into()
+            },
+            padding: row_padding,
+        },
+        // Same 1.4mm as rows, 20 dots at 360 DPI
+        column_padding: args.column_gap.unwrap_or(row_padding),
+        qr: qr_options,
+        id_algorithm: args.id_algorithm.into(),
+        check_fonts: true,
+        normalization: args.normalize.into(),
+        text_measure: args.text_measure.into(),
+        text_renderer: args.renderer.into(),
+        trim_text_bearing: args.trim && !args.no_trim,
     };
 
     // Create label using layout script parsing
-    let element = layout::parse_layout_script(&texts, &text_options, &row_options, fontdb)?;
+    let (element, script_warnings) = if args.lenient_assets {
+        layout::parse_layout_script_lenient(&texts, &layout_options)
+    } else {
+        layout::parse_layout_script_with_warnings(&texts, &layout_options)
+    }
+    .or_kind(ErrorKind::Render)?;
+    metrics.parse += start.elapsed();
+    // Missing images of --lenient-assets are always reported, unlike
+    // warnings about the script itself
+    let (asset_warnings, script_warnings): (Vec<_>, Vec<_>) = script_warnings
+        .into_iter()
+        .partition(|w| matches!(w.kind, ParseWarningKind::MissingImage(_)));
+    warnings.extend(asset_warnings.iter().map(|w| w.to_string()));
+    if args.strict_script && !script_warnings.is_empty() {
+        let messages: Vec<String> = script_warnings.iter().map(|w| w.to_string()).collect();
+        let message = format!("Layout script has warnings: {}", messages.join("; "));
+        return Err(Error::new(ErrorKind::Usage, message).into());
+    }
+    if !ptouch::is_quiet() {
+        warnings.extend(script_warnings.iter().map(|w| w.to_string()));
+    }
+
+    let label_options = if args.auto_tape {
+        let label_options = label_options
+            .with_fitting_tape(&*element)
+            .or_kind(ErrorKind::Render)?;
+        info!("Selected {} tape", label_options.tape_spec.name);
+        label_options
+    } else {
+        label_options
+    };
+
+    // Font sizes are in dots; catch sizes meant as points
+    if args.font_size.is_some() && !args.auto_scale {
+        warnings.extend(
+            layout_options
+                .text
+                .check_physical_size(&label_options.tape_spec, args.min_text_mm),
+        );
+    }
+
     let label = Label::from_element(element, label_options);
+    if args.lint {
+        warnings.extend(label.lint().or_kind(ErrorKind::Render)?);
+    }
+    Ok(label)
+}
 
-    if args.source {
+fn write_label(
+    label: &Label,
+    source: bool,
+    output: Option<&PathBuf>,
+    metrics: &mut RenderMetrics,
+) -> Result<()> {
+    let data = if source {
         // Output source (SVG)
-        match &args.output {
-            Some(path) => {
-                label.save_svg(path)?;
-            }
-            None => {
-                print!("{}", label.to_svg()?);
-            }
-        }
+        let svg_data = label
+            .to_svg_with_metrics(metrics)
+            .or_kind(ErrorKind::Render)?;
+        svg_data.into_bytes()
     } else {
-        // Output PNG
-        match &args.output {
-            Some(path) => {
-                label.save_png(path)?;
-            }
-            None => {
-                let png_data = label.to_png()?;
-                io::stdout().write_all(&png_data)?;
-            }
-        }
+        render_png(label, metrics)?
+    };
+
+    match output {
+        Some(path) => write_atomically(path, &data)?,
+        None => io::stdout().write_all(&data)?,
     }
+    Ok(())
+}
 
+/// Write the annotated preview of the label
+fn write_annotation(label: &Label, path: &Path) -> Result<()> {
+    let png_data = label.to_annotated_png().or_kind(ErrorKind::Render)?;
+    write_atomically(path, &png_data)?;
+    Ok(())
+}
+
+/// Render label into PNG data, adding time of the stages to `metrics`
+fn render_png(label: &Label, metrics: &mut RenderMetrics) -> Result<Vec<u8>> {
+    label
+        .to_png_with_metrics(metrics)
+        .or_kind(ErrorKind::Render)
+}
+
+fn handle_serve_command(args: ServeArgs) -> Result<()> {
+    if !args.image.text.is_empty() || args.image.script_file.is_some() {
+        let message = "Serve takes layouts from clients, not from arguments";
+        return Err(Error::new(ErrorKind::Usage, message).into());
+    }
+    if args.image.output.is_some()
+        || args.image.source
+        || args.image.annotate.is_some()
+        || args.image.explain
+    {
+        let message = "Serve prints labels and cannot write them to files";
+        return Err(Error::new(ErrorKind::Usage, message).into());
+    }
+
+    // The printer is kept open and its status is checked for each job
+    let assumed_tape = TapeSpec::new(args.image.tape_name.to_tape(args.image.resolution)?);
+    let connection = Connection::open(&args.host, &args.status_options, Some(assumed_tape))?;
+    let tape_spec = connection.tape_spec()?;
+    let print_options = PrintOptions {
+        half_cut: connection.supports_half_cut(),
+        raster: (&args.raster).into(),
+        ..Default::default()
+    };
+
+    let image = args.image;
+    let fontdb = load_fonts(&image, &mut RenderMetrics::default())?;
+    let render: Renderer = Box::new(move |script| {
+        let values = Placeholders {
+            counter: None,
+            datetime: Some(Local::now().naive_local()),
+        };
+        let mut warnings = Vec::new();
+        let mut metrics = RenderMetrics::default();
+        let label = create_label(
+            &image,
+            &fontdb,
+            script,
+            &values,
+            &mut warnings,
+            &mut metrics,
+        )?;
+        for warning in &warnings {
+            eprintln!("Warning: {}", warning);
+        }
+        let png_data = render_png(&label, &mut metrics)?;
+        print_timings(image.timings, &metrics);
+        Ok(png_data)
+    });
+
+    let listener = std::net::TcpListener::bind(&args.listen)?;
+    info!(
+        "Serving {} mm tape on {}",
+        tape_spec.width_mm,
+        listener.local_addr()?
+    );
+    let mut server = Server::new(connection.printer, tape_spec, print_options, render)
+        .with_check_status(!args.status_options.skip_status)
+        .with_max_length(args.status_options.max_length_mm());
+    server.serve(&listener)?;
+
+    // Dropping the server closes the printer
+    info!("Server stopped");
     Ok(())
 }
 
 fn handle_print_command(args: PrintArgs) -> Result<()> {
     // Read PNG data
-    let png_data = match &args.png_file {
-        Some(path) => std::fs::read(path)?,
-        None => {
+    let png_data = match (&args.svg, &args.png_file) {
+        (Some(path), _) => render_svg_file(path, &args)?,
+        (None, Some(path)) => std::fs::read(path)?,
+        (None, None) => {
             let mut buffer = Vec::new();
             io::stdin().read_to_end(&mut buffer)?;
             buffer
         }
     };
 
-    // Get PNG dimensions
-    let decoder = png::Decoder::new(png_data.as_slice());
-    let reader = decoder.read_info()?;
-    let png_info = reader.info();
-    let png_height = png_info.height;
+    if let [host] = args.host.as_slice() {
+        return print_to_host(&args, host, png_data);
+    }
 
-    // Check printer status to get DPI and tape width
-    println!("Checking printer status...");
+    // Keep printing to the other hosts when one fails
+    let mut failures = Vec::new();
+    for host in &args.host {
+        info!("Printing to {}...", host);
+        if let Err(e) = print_to_host(&args, host, png_data.clone()) {
+            eprintln!("Error: {}: {}", host, e);
+            failures.push((host.as_str(), error::kind_of(&*e)));
+        }
+    }
 
-    let backend = backend::from_host(&args.host)?;
-    let mut printer = Printer::new(backend);
-    let status = printer.get_status()?;
+    // Exit with the status of the first failure
+    if let Some((_, kind)) = failures.first() {
+        let hosts: Vec<&str> = failures.iter().map(|(host, _)| *host).collect();
+        let message = format!(
+            "Failed to print on {} of {} printers: {}",
+            failures.len(),
+            args.host.len(),
+            hosts.join(", ")
+        );
+        return Err(Error::new(kind.unwrap_or(ErrorKind::Communication), message).into());
+    }
+    Ok(())
+}
+
+/// Render SVG source of a label into PNG data to print
+fn render_svg_file(path: &Path, args: &PrintArgs) -> Result<Vec<u8>> {
+    let svg_data = std::fs::read_to_string(path)?;
+    let fontdb = Arc::new(load_fontdb_with_paths(&args.font_paths).or_kind(ErrorKind::Render)?);
+    // 720DPI is the high resolution mode of 360DPI tapes
+    let dpi = args.resolution.to_dpi().min(360);
+    let (pixmap, tape_spec) = rasterize_label_svg(&svg_data, &fontdb, dpi, args.high_resolution)
+        .or_kind(ErrorKind::Render)?;
+    info!("Rendered {} for {} tape", path.display(), tape_spec.name);
+    pixmap.encode_png().or_kind(ErrorKind::Render)
+}
+
+/// Print the label of `png_data` on `host`
+fn print_to_host(args: &PrintArgs, host: &str, png_data: Vec<u8>) -> Result<()> {
+    let start = Instant::now();
+
+    let assumed_tape = args
+        .tape_name
+        .map(|tape_name| tape_name.to_tape(args.resolution))
+        .transpose()?
+        .map(TapeSpec::new);
+    let mut connection = Connection::open(host, &args.status_options, assumed_tape)?;
+
+    if args.high_resolution
+        && let Some(status) = &connection.status
+        && !status.supports_high_resolution()
+    {
+        let message = format!(
+            "High resolution printing is not supported by this printer (model code 0x{:02X})",
+            status.model_code()
+        );
+        return Err(Error::new(ErrorKind::Printer, message).into());
+    }
+
+    let mut printable = connection.create_printable(png_data)?;
+    if args.trim {
+        let (trimmed, trim) = printable.trimmed().or_kind(ErrorKind::Render)?;
+        let trimmed_mm = (printable.length_mm() - trimmed.length_mm()).max(0.0);
+        info!(
+            "Trimmed {} blank lines at the start and {} at the end ({:.1} mm)",
+            trim.leading, trim.trailing, trimmed_mm
+        );
+        printable = trimmed;
+    }
 
-    // Check for errors first
-    if status.has_errors() {
-        println!("Printer error detected:");
-        status.print_status_info(false);
-        return Err("Cannot print due to printer errors".into());
+    let print_options = PrintOptions {
+        cut: args.cut.policy(),
+        cut_every: args.cut.cut_every,
+        compression: if args.no_compress {
+            Compression::None
+        } else {
+            Compression::Tiff
+        },
+        high_resolution: args.high_resolution,
+        quality: args.quality.into(),
+        half_cut: !args.no_half_cut && connection.supports_half_cut(),
+        raster: (&args.raster).into(),
+        stream: args.stream,
+        reuse_buffer: args.reuse_buffer && connection.supports_buffer_reuse(),
+    };
+    if args.reuse_buffer && !print_options.reuse_buffer {
+        info!("Buffer reuse is not supported by this printer; sending each copy");
     }
 
-    // Get printer DPI and tape width
-    let printer_dpi = status.printer_dpi();
-    let actual_tape_width = status.media_width_mm();
+    info!("Print quality: {}", print_options.quality);
+    info!("Starting print...");
+
+    let report = connection.print_repeat(&printable, &print_options, args.repeat)?;
+
+    if args.json {
+        let result = serde_json::json!({
+            "host": host,
+            "tape": printable.tape_spec().name.to_string(),
+            "length_mm": printable.length_mm(),
+            "raster_lines": report.raster_lines,
+            "bytes_sent": report.bytes_sent,
+            "duration_ms": start.elapsed().as_millis() as u64,
+        });
+        println!("{}", result);
+    }
+    Ok(())
+}
 
-    // Get tape spec from PNG dimensions using printer's DPI
-    let png_tape_spec = tape::TapeSpec::from_width_dots_and_dpi(png_height, printer_dpi)
-        .ok_or_else(|| {
-            format!(
-                "Unsupported PNG height: {} pixels at {}DPI",
-                png_height, printer_dpi
-            )
-        })?;
+/// Connected printer and the tape loaded in it
+struct Connection {
+    printer: Printer<Box<dyn Backend>>,
+    host: String,
+    /// Printer status, None with --skip-status
+    status: Option<Status>,
+    /// Tape assumed with --skip-status
+    assumed_tape: Option<TapeSpec>,
+    /// Status cache and max age of entries for --status-cache
+    cache: Option<(StatusCache, Duration)>,
+    /// `status` was taken from the cache
+    cached: bool,
+    /// Print to unidentified devices (--force)
+    force: bool,
+    /// Stop on printer warnings (--strict)
+    strict: bool,
+    /// Longest label to print in mm (--max-length), None for unlimited
+    max_length_mm: Option<f32>,
+}
+
+impl Connection {
+    /// Connect to the printer and check that it is ready to print
+    fn open(host: &str, options: &StatusOptions, assumed_tape: Option<TapeSpec>) -> Result<Self> {
+        let cache = options.status_cache.and_then(|seconds| {
+            Some((
+                StatusCache::new(StatusCache::default_dir()?),
+                Duration::from_secs(seconds),
+            ))
+        });
+        let mut connection = Connection {
+            printer: Printer::new(backend::from_host(host).or_kind(ErrorKind::Communication)?),
+            host: host.to_string(),
+            status: None,
+            assumed_tape: None,
+            cache,
+            cached: false,
+            force: options.force,
+            strict: options.strict,
+            max_length_mm: options.max_length_mm(),
+        };
+
+        if options.skip_status {
+            let tape_spec = assumed_tape
+                .ok_or("--skip-status requires --tape-name")
+                .or_kind(ErrorKind::Usage)?;
+            info!(
+                "Skipping status check: assuming {} mm tape at {}DPI",
+                tape_spec.width_mm, tape_spec.dpi
+            );
+            connection.assumed_tape = Some(tape_spec);
+            return Ok(connection);
+        }
 
-    // Get printer tape spec using the same DPI
-    let printer_tape_spec = tape::TapeSpec::from_width_mm_and_dpi(actual_tape_width, printer_dpi)
-        .ok_or_else(|| {
-        format!(
-            "Unsupported tape width: {} mm at {}DPI",
-            actual_tape_width, printer_dpi
-        )
-    })?;
+        if let Some((cache, max_age)) = &connection.cache
+            && let Some((status, age)) = cache.load(host, *max_age)
+        {
+            info!("Using cached printer status ({}s old)", age.as_secs());
+            connection.status = Some(status);
+            connection.cached = true;
+            return Ok(connection);
+        }
 
-    // Verify PNG tape spec matches printer tape spec
-    if png_tape_spec.width_dots != printer_tape_spec.width_dots {
-        return Err(format!(
-            "Tape specification mismatch: PNG expects {}mm tape ({}px width), but printer has {}mm tape ({}px width)",
-            png_tape_spec.width_mm, png_tape_spec.width_dots,
-            printer_tape_spec.width_mm, printer_tape_spec.width_dots
-        ).into());
+        connection.check_status()?;
+        Ok(connection)
     }
 
-    println!("Verified tape compatibility: {} mm", actual_tape_width);
-    println!("Starting print...");
+    /// Query printer status and fail if the printer has errors
+    ///
+    /// The device is also identified, since a wrong host may accept
+    /// print jobs and silently discard or misprint them.
+    fn check_status(&mut self) -> Result<()> {
+        info!("Checking printer status...");
+        if let Err(e) = self.printer.verify_device() {
+            if !self.force {
+                let message = format!("{} (use --force to print anyway)", e);
+                return Err(Error::new(ErrorKind::Communication, message).into());
+            }
+            eprintln!("Warning: {}", e);
+        }
+
+        let status = self
+            .printer
+            .get_status()
+            .or_kind(ErrorKind::Communication)?;
+
+        if let Some((cache, _)) = &self.cache
+            && let Err(e) = cache.store(&self.host, &status)
+        {
+            eprintln!("Warning: Failed to cache printer status: {}", e);
+        }
+
+        // The error names the conditions
+        status.check_errors()?;
 
-    // Create PrintableImage and print
-    let printable = PrintableImage::from_png_data(png_data, printer_tape_spec)?;
-    printer.print(&printable, args.continuous)?;
+        self.status = Some(status);
+        self.cached = false;
+        Ok(())
+    }
 
+    /// Tape loaded in the printer
+    fn tape_spec(&self) -> Result<TapeSpec> {
+        let Some(status) = &self.status else {
+            return self
+                .assumed_tape
+                .clone()
+                .ok_or_else(|| "Printer status is not available".into());
+        };
+
+        let printer_dpi = status.printer_dpi();
+        let actual_tape_width = status.media_width_mm();
+        tape::TapeSpec::from_width_mm_and_dpi(actual_tape_width, printer_dpi).ok_or_else(|| {
+            let message = format!(
+                "Unsupported tape width: {} mm at {}DPI",
+                actual_tape_width, printer_dpi
+            );
+            Error::new(ErrorKind::TapeMismatch, message).into()
+        })
+    }
+
+    /// Verify PNG against the loaded tape and create printable image
+    ///
+    /// A cached status is checked again on mismatch since the tape may
+    /// have been changed after it was cached.
+    fn create_printable(&mut self, png_data: Vec<u8>) -> Result<PrintableImage> {
+        // Get PNG dimensions
+        let decoder = png::Decoder::new(png_data.as_slice());
+        let reader = decoder.read_info()?;
+        let png_height = reader.info().height;
+
+        if let Err(e) = verify_tape(&self.tape_spec()?, png_height) {
+            if !self.cached {
+                return Err(e);
+            }
+            info!("Cached status does not match: {}", e);
+            self.check_status()?;
+            verify_tape(&self.tape_spec()?, png_height)?;
+        }
+
+        PrintableImage::from_png_data(png_data, self.tape_spec()?).or_kind(ErrorKind::TapeMismatch)
+    }
+
+    /// Print and clear the cached status on failure
+    fn print(&mut self, printable: &PrintableImage, options: &PrintOptions) -> Result<PrintReport> {
+        self.print_repeat(printable, options, 1)
+    }
+
+    /// Print `times` copies and clear the cached status on failure
+    fn print_repeat(
+        &mut self,
+        printable: &PrintableImage,
+        options: &PrintOptions,
+        times: u32,
+    ) -> Result<PrintReport> {
+        let status = self.status.as_ref();
+        for warning in check_job(printable, times, status, self.max_length_mm, self.strict)? {
+            eprintln!("Warning: {}", warning);
+        }
+
+        let options = &dodge_pins(printable, options)?;
+        let result = self
+            .printer
+            .print_repeat(printable, options, times)
+            .or_kind(ErrorKind::Communication);
+
+        if result.is_err()
+            && let Some((cache, _)) = &self.cache
+            && let Err(e) = cache.remove(&self.host)
+        {
+            eprintln!("Warning: Failed to clear cached printer status: {}", e);
+        }
+        result
+    }
+
+    /// Models without status are assumed to have a half cutter
+    fn supports_half_cut(&self) -> bool {
+        self.status.as_ref().is_none_or(Status::supports_half_cut)
+    }
+
+    /// Models without status are not trusted to keep the buffer
+    fn supports_buffer_reuse(&self) -> bool {
+        self.status
+            .as_ref()
+            .is_some_and(Status::supports_buffer_reuse)
+    }
+}
+
+/// Shift contents to keep ink off the masked pins of `options`
+fn dodge_pins(printable: &PrintableImage, options: &PrintOptions) -> Result<PrintOptions> {
+    let (raster, warning) = printable.dodge_masked_pins(&options.raster)?;
+    if let Some(warning) = warning {
+        eprintln!("Warning: {}", warning);
+    }
+    if raster.v_offset != options.raster.v_offset {
+        info!(
+            "Shifted contents by {} dots to avoid masked pins",
+            raster.v_offset - options.raster.v_offset
+        );
+    }
+    Ok(PrintOptions {
+        raster,
+        ..options.clone()
+    })
+}
+
+/// Verify that PNG of `png_height` pixels is made for the printer tape
+fn verify_tape(printer_tape_spec: &TapeSpec, png_height: u32) -> Result<()> {
+    let printer = printer_tape_spec;
+
+    if let Some(mismatch) = tape::diagnose_png_height(png_height, printer) {
+        let message = match mismatch {
+            TapeMismatch::Dpi(png) => format!(
+                "This PNG looks like {}mm at {}dpi, but the printer is {}dpi; regenerate it with -r {}",
+                png.width_name(),
+                png.dpi,
+                printer.dpi,
+                printer.dpi
+            ),
+            TapeMismatch::Width(png) => format!(
+                "Tape specification mismatch: PNG expects {}mm tape ({}px width), but printer has {}mm tape ({}px width)",
+                png.width_name(),
+                png.width_dots,
+                printer.width_name(),
+                printer.width_dots
+            ),
+            TapeMismatch::WidthAndDpi(png) => format!(
+                "This PNG looks like {}mm at {}dpi, but the printer has {}mm tape at {}dpi; regenerate it with -t {} -r {}",
+                png.width_name(),
+                png.dpi,
+                printer.width_name(),
+                printer.dpi,
+                printer.width_name(),
+                printer.dpi
+            ),
+            TapeMismatch::Unsupported => format!(
+                "Unsupported PNG height: {} pixels; {}mm tape at {}dpi needs {} pixels",
+                png_height,
+                printer.width_name(),
+                printer.dpi,
+                printer.width_dots
+            ),
+        };
+        return Err(Error::new(ErrorKind::TapeMismatch, message).into());
+    }
+
+    info!(
+        "Verified tape compatibility: {} mm",
+        printer_tape_spec.width_mm
+    );
     Ok(())
 }
 
+fn handle_batch_command(args: BatchArgs) -> Result<()> {
+    // Each paragraph of a script file is a label
+    let templates = match &args.image.script_file {
+        Some(path) => layout::read_script_paragraphs(path)?,
+        None => vec![read_texts(&args.image)?],
+    };
+    if templates.is_empty() {
+        return Err(Error::new(ErrorKind::Usage, "No text input provided").into());
+    }
+    if args.image.explain {
+        let message = "Batch cannot --explain; explain a label with 'ptouch image'";
+        return Err(Error::new(ErrorKind::Usage, message).into());
+    }
+
+    // All labels share the same date
+    let datetime = args
+        .image
+        .date
+        .unwrap_or_else(|| Local::now().naive_local());
+
+    let mut metrics = RenderMetrics::default();
+    let fontdb = load_fonts(&args.image, &mut metrics)?;
+    let templates = if args.split {
+        split_templates(&args, &fontdb, &templates, datetime, &mut metrics)?
+    } else {
+        templates
+    };
+    let counter = Counter {
+        start: args.start,
+        step: args.step,
+        count: args.count * templates.len() as u32,
+    };
+
+    let output = args.image.output.as_ref().map(|p| p.to_string_lossy());
+    if args.host.is_none() {
+        match &output {
+            None => {
+                return Err(
+                    Error::new(ErrorKind::Usage, "Batch requires --host or --output").into(),
+                );
+            }
+            Some(path) if counter.count > 1 && !path.contains("{n") => {
+                let message = format!("Output path must contain {{n}} placeholder: {}", path);
+                return Err(Error::new(ErrorKind::Usage, message).into());
+            }
+            _ => {}
+        }
+    }
+
+    // Status is queried once for all labels
+    let mut connection = match &args.host {
+        Some(host) => {
+            let tape_name = args.image.tape_name;
+            let assumed_tape = TapeSpec::new(tape_name.to_tape(args.image.resolution)?);
+            Some(Connection::open(
+                host,
+                &args.status_options,
+                Some(assumed_tape),
+            )?)
+        }
+        None => None,
+    };
+
+    let mut summary = WarningSummary::new();
+
+    // Render per index since each label differs
+    for (i, (n, template)) in (0..).zip(counter.values().zip(templates.iter().cycle())) {
+        let values = Placeholders {
+            counter: Some(n),
+            datetime: Some(datetime),
+        };
+        let mut warnings = Vec::new();
+        let label = create_label(
+            &args.image,
+            &fontdb,
+            template,
+            &values,
+            &mut warnings,
+            &mut metrics,
+        )?;
+        summary.add(n, warnings);
+
+        if let Some(connection) = &mut connection {
+            let printable = connection.create_printable(render_png(&label, &mut metrics)?)?;
+            // Each label is a job, so the cuts are counted over the batch
+            let print_options = PrintOptions {
+                cut: args.cut.policy(),
+                cut_every: args.cut.cut_every,
+                half_cut: connection.supports_half_cut(),
+                raster: (&args.raster).into(),
+                ..Default::default()
+            }
+            .for_label(i, counter.count);
+            info!("Printing label {}...", n);
+            connection.print(&printable, &print_options)?;
+        } else if let Some(path) = &output {
+            let path = PathBuf::from(template::substitute(path, &values)?);
+            write_label(&label, args.image.source, Some(&path), &mut metrics)?;
+        }
+        if let Some(path) = &args.image.annotate {
+            let path = template::substitute(&path.to_string_lossy(), &values)?;
+            write_annotation(&label, Path::new(&path))?;
+        }
+    }
+
+    if !summary.is_empty() {
+        eprintln!("Warning: {}", summary);
+    }
+    print_timings(args.image.timings, &metrics);
+    Ok(())
+}
+
+/// Split each template into labels that fit the tape and --max-length
+///
+/// Measured with the first label number; a wider number in a later
+/// label may still overflow.
+fn split_templates(
+    args: &BatchArgs,
+    fontdb: &Arc<fontdb::Database>,
+    templates: &[Vec<String>],
+    datetime: NaiveDateTime,
+    metrics: &mut RenderMetrics,
+) -> Result<Vec<Vec<String>>> {
+    let values = Placeholders {
+        counter: Some(args.start),
+        datetime: Some(datetime),
+    };
+    let max_length_mm = args.status_options.max_length_mm();
+    let mut labels = Vec::new();
+    for template in templates {
+        let split = layout::split_to_fit(template, |tokens| {
+            let mut warnings = Vec::new();
+            let label = create_label(&args.image, fontdb, tokens, &values, &mut warnings, metrics)?;
+            let short_enough = match max_length_mm {
+                Some(mm) => label.dimensions()?.0 <= label.options().tape_spec.mm_to_dots(mm),
+                None => true,
+            };
+            Ok(short_enough && label.fits_tape_width()?)
+        })
+        .or_kind(ErrorKind::Render)?;
+        info!("Split into {} labels", split.len());
+        labels.extend(split);
+    }
+    Ok(labels)
+}
+
 fn handle_status_command(args: StatusArgs) -> Result<()> {
-    let backend = backend::from_host(&args.host)?;
+    let backend = backend::from_host(&args.host).or_kind(ErrorKind::Communication)?;
     let mut printer = Printer::new(backend);
 
-    match printer.get_status() {
-        Ok(status) => {
-            status.print_status_info(args.verbose);
+    let status = printer.get_status().or_kind(ErrorKind::Communication)?;
+    status.print_status_info(args.verbose);
+    if args.verbose
+        && let Some(text) = printer.get_status_text()
+    {
+        println!("Printer message: {}", text);
+    }
+    Ok(())
+}
+
+fn handle_text_command(args: TextArgs) -> Result<()> {
+    // No rendering: the printer lays out text with its built-in fonts
+    let mut cmd = EscpCommand::new();
+    cmd.invalidate()
+        .initialize()
+        .select_character_size(args.size)
+        .text(&args.text.join("\n"))?
+        .form_feed();
+    let data = cmd.build();
+
+    let mut backend = backend::from_host(&args.host).or_kind(ErrorKind::Communication)?;
+    backend
+        .begin_job()
+        .and_then(|()| backend.send_command(&data))
+        .or_kind(ErrorKind::Communication)?;
+    info!("Sent {} bytes", data.len());
+    Ok(())
+}
+
+fn handle_diff_command(args: DiffArgs) -> Result<()> {
+    let read = |path: &PathBuf| {
+        std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+    };
+    let diff = diff::diff_png(&read(&args.a)?, &read(&args.b)?)?;
+
+    println!("{}", diff);
+    if !diff.is_same() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn handle_check_command(args: CheckArgs) -> Result<()> {
+    let script = if args.script.is_empty() {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        input.lines().map(|s| s.to_string()).collect()
+    } else {
+        args.script
+    };
+
+    // Any counter and date will do to check the syntax of placeholders
+    let values = Placeholders {
+        counter: Some(1),
+        datetime: Some(Local::now().naive_local()),
+    };
+    let script = template::substitute_script(&script, &values)?;
+
+    let fontdb = if args.no_font_check {
+        Arc::new(fontdb::Database::new())
+    } else {
+        load_fontdb_with_paths(&args.font_paths)?
+    };
+    let (font_name, font_weight) = parse_font_name_and_weight(&args.font);
+    let options = LayoutOptions {
+        text: TextOptions {
+            font_name,
+            font_weight,
+            ..TextOptions::new(fontdb.clone())
+        },
+        check_fonts: !args.no_font_check,
+        ..LayoutOptions::new(fontdb)
+    };
+
+    let element = layout::parse_layout_script(&script, &options)?;
+    println!("OK: {}", element);
+    Ok(())
+}
+
+fn handle_test_pattern_command(args: TestPatternArgs) -> Result<()> {
+    let assumed_tape = TapeSpec::new(args.tape_name.to_tape(args.resolution)?);
+    let mut connection = match &args.host {
+        Some(host) => Some(Connection::open(
+            host,
+            &args.status_options,
+            Some(assumed_tape.clone()),
+        )?),
+        None => None,
+    };
+    let tape_spec = match &connection {
+        Some(connection) => connection.tape_spec()?,
+        None => assumed_tape,
+    };
+    info!(
+        "Test pattern for {} mm tape at {}DPI",
+        tape_spec.width_mm, tape_spec.dpi
+    );
+
+    let fontdb = load_fontdb_with_paths(&args.font_paths)?;
+    let (font_name, font_weight) = parse_font_name_and_weight(&args.font);
+    let text_options = TextOptions {
+        font_name,
+        font_weight,
+        ..TextOptions::new(fontdb.clone())
+    };
+    let element = pattern::test_pattern(&tape_spec, Some(&text_options));
+    let label = Label::from_element(element, LabelOptions::new(fontdb).with_tape_spec(tape_spec));
+    let png_data = label.to_png().or_kind(ErrorKind::Render)?;
+
+    match &mut connection {
+        Some(connection) => {
+            let printable = connection.create_printable(png_data)?;
+            let print_options = PrintOptions {
+                half_cut: connection.supports_half_cut(),
+                raster: (&args.raster).into(),
+                ..Default::default()
+            };
+            info!("Starting print...");
+            connection.print(&printable, &print_options)?;
+            Ok(())
+        }
+        None => {
+            let path = args
+                .output
+                .as_ref()
+                .ok_or("Test pattern requires --host or --output")
+                .or_kind(ErrorKind::Usage)?;
+            std::fs::write(path, png_data)?;
+            Ok(())
         }
-        Err(e) => {
-            println!("Error getting printer status: {}", e);
+    }
+}
+
+fn handle_raw_command(args: RawArgs) -> Result<()> {
+    let data = std::fs::read(&args.file)?;
+
+    // Raw commands bypass all checks such as tape width verification
+    if !args.force {
+        let prompt = format!(
+            "Send {} bytes to {} unmodified? [y/N] ",
+            data.len(),
+            args.host
+        );
+        if !confirm(&prompt)? {
+            return Err("Aborted (use --force to skip confirmation)".into());
         }
     }
 
+    let mut backend = backend::from_host(&args.host).or_kind(ErrorKind::Communication)?;
+    backend
+        .begin_job()
+        .and_then(|()| backend.send_command(&data))
+        .or_kind(ErrorKind::Communication)?;
+    info!("Sent {} bytes", data.len());
+
+    if args.status {
+        backend.get_status()?.print_status_info(args.verbose);
+    }
+
+    Ok(())
+}
+
+fn confirm(prompt: &str) -> Result<bool> {
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn handle_decode_status_command(args: DecodeStatusArgs) -> Result<()> {
+    let status = Status::from_hex(&args.hex.join(" "))?;
+    status.print_status_info(args.verbose);
     Ok(())
 }
 
@@ -522,16 +2274,32 @@ fn font_completer(current: &std::ffi::OsStr) -> Vec<CompletionCandidate> {
     completions
 }
 
-fn main() -> Result<()> {
+fn main() {
     // Check for dynamic completion first
     CompleteEnv::with_factory(Cli::command).complete();
 
+    // Exit status tells the kind of failure (see ptouch::error)
     let cli = Cli::parse();
+    ptouch::set_quiet(cli.quiet);
+    if let Err(e) = run(cli) {
+        eprintln!("Error: {}", e);
+        std::process::exit(error::exit_code(&*e));
+    }
+}
 
+fn run(cli: Cli) -> Result<()> {
     match cli.command {
         Commands::Image(args) => handle_image_command(args)?,
         Commands::Print(args) => handle_print_command(args)?,
+        Commands::Batch(args) => handle_batch_command(args)?,
+        Commands::Serve(args) => handle_serve_command(args)?,
         Commands::Status(args) => handle_status_command(args)?,
+        Commands::Text(args) => handle_text_command(args)?,
+        Commands::Check(args) => handle_check_command(args)?,
+        Commands::Diff(args) => handle_diff_command(args)?,
+        Commands::TestPattern(args) => handle_test_pattern_command(args)?,
+        Commands::Raw(args) => handle_raw_command(args)?,
+        Commands::DecodeStatus(args) => handle_decode_status_command(args)?,
         Commands::Completion(args) => handle_completion_command(args)?,
     }
 
@@ -0,0 +1,64 @@
+//! Unicode normalization of label text
+//!
+//! Text pasted from spreadsheets often mixes full-width digits (１２３)
+//! and halfwidth kana (ｶﾀｶﾅ), which look inconsistent on a label.
+//! NFKC folds them into ASCII digits and full-width kana.
+
+use crate::Result;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use unicode_normalization::UnicodeNormalization;
+
+/// Normalization form applied to text before creating elements
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Normalization {
+    /// Keep text as is
+    #[default]
+    None,
+    /// Compose accents with their base characters (e.g. e + ◌́ → é)
+    Nfc,
+    /// NFC and fold compatibility characters, e.g. full-width ASCII and
+    /// halfwidth kana
+    Nfkc,
+}
+
+impl Normalization {
+    /// Normalize `text`
+    ///
+    /// ```
+    /// use ptouch::normalize::Normalization;
+    ///
+    /// assert_eq!(Normalization::Nfkc.apply("ＡＢＣ１２３ ｶﾅ"), "ABC123 カナ");
+    /// assert_eq!(Normalization::Nfc.apply("Cafe\u{301}"), "Café");
+    /// ```
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            Normalization::None => text.to_string(),
+            Normalization::Nfc => text.nfc().collect(),
+            Normalization::Nfkc => text.nfkc().collect(),
+        }
+    }
+}
+
+impl Display for Normalization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Normalization::None => write!(f, "none"),
+            Normalization::Nfc => write!(f, "nfc"),
+            Normalization::Nfkc => write!(f, "nfkc"),
+        }
+    }
+}
+
+impl FromStr for Normalization {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Normalization::None),
+            "nfc" => Ok(Normalization::Nfc),
+            "nfkc" => Ok(Normalization::Nfkc),
+            _ => Err(format!("Invalid normalization: {} (nfkc, nfc or none)", s).into()),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Calibration pattern to check a new tape cassette and the print head
+//!
+//! The pattern shows where the printer actually puts ink: bars across
+//! the tape every 5mm show the feed, and ticks every 1mm along both
+//! edges of the printable area show the head alignment.
+//!
+//! After the ruler come sections to find dead pins: full black, a
+//! gradient of stripes, lines of single pins and a ruler of pin numbers.
+
+use crate::element::{Column, Element, Gap, Overlay, Row, RowOptions, Text, TextOptions};
+use crate::tape::TapeSpec;
+
+/// Length of the ruler part of the pattern along the tape in mm
+pub const PATTERN_LENGTH_MM: u32 = 50;
+
+/// Length of the whole pattern along the tape in mm
+pub const TOTAL_LENGTH_MM: u32 = 100;
+
+/// Start of the full black section in mm
+pub const BLACK_MM: u32 = 55;
+
+/// Start of the gradient section in mm: 4 steps of 3mm
+pub const GRADIENT_MM: u32 = 62;
+
+/// Start of the single pin lines in mm: even pins, then odd pins for 5mm each
+pub const PIN_LINES_MM: u32 = 76;
+
+/// Start of the pin number ruler in mm
+pub const PIN_RULER_MM: u32 = 88;
+
+/// Width of the bars every 5mm in dots
+pub const BAR_WIDTH: f32 = 2.0;
+
+/// Width of the ticks every 1mm in dots
+pub const TICK_WIDTH: f32 = 1.0;
+
+/// Create the calibration pattern for `tape_spec`
+///
+/// The pattern fills the printable width of the tape, so render it with
+/// top placement and without auto-scaling. The bar for `n` mm starts at
+/// `tape_spec.mm_to_dots(n)`. `ruler_font` adds the mm numbers next to the
+/// bars every 10mm and the pin numbers; None omits them (e.g. without
+/// fonts). The pattern is `TOTAL_LENGTH_MM` long.
+pub fn test_pattern(tape_spec: &TapeSpec, ruler_font: Option<&TextOptions>) -> Box<dyn Element> {
+    let height = tape_spec.inner_dots as f32;
+    let tick_length = tape_spec.mm_to_dots(1.0) as f32;
+
+    let bars = marks(tape_spec, 5, BAR_WIDTH, height);
+    let ticks = Column::new(
+        vec![
+            marks(tape_spec, 1, TICK_WIDTH, tick_length),
+            Box::new(Gap::new(TICK_WIDTH, height - 2.0 * tick_length, false)),
+            marks(tape_spec, 1, TICK_WIDTH, tick_length),
+        ],
+        0.0,
+    );
+    let mut layers: Vec<Box<dyn Element>> = vec![bars, Box::new(ticks)];
+    layers.extend(head_check(tape_spec));
+    // Fix the length regardless of the ink at the end
+    let length = tape_spec.mm_to_dots(TOTAL_LENGTH_MM as f32) as f32;
+    layers.push(Box::new(Gap::new(length, height, false)));
+
+    if let Some(text_options) = ruler_font {
+        // Two digits fit between bars
+        let space = tape_spec.mm_to_dots(5.0) as f32 - BAR_WIDTH * 4.0;
+        let font_size = (height / 4.0).min(space / 1.3).round() as u32;
+        let options = TextOptions {
+            font_size,
+            line_height: font_size,
+            ..text_options.clone()
+        };
+        for mm in (10..PATTERN_LENGTH_MM).step_by(10) {
+            let text = Text::new_unchecked(&[mm.to_string()], options.clone());
+            layers.push(place(
+                Box::new(text),
+                tape_spec.mm_to_dots(mm as f32) as f32 + BAR_WIDTH * 2.0,
+                (height - font_size as f32) / 2.0,
+            ));
+        }
+        layers.extend(pin_numbers(tape_spec, text_options));
+    }
+
+    Box::new(Overlay::new(layers))
+}
+
+/// Sections to find dead pins, placed at BLACK_MM and later
+///
+/// A dead pin leaves a white streak along the tape in the black and
+/// gradient sections, and a missing line in the single pin lines.
+fn head_check(tape_spec: &TapeSpec) -> Vec<Box<dyn Element>> {
+    let height = tape_spec.inner_dots as f32;
+    let dots = |mm: u32| tape_spec.mm_to_dots(mm as f32) as f32;
+    let mut layers = Vec::new();
+
+    let black = Gap::new(dots(BLACK_MM + 5) - dots(BLACK_MM), height, true);
+    layers.push(place(Box::new(black), dots(BLACK_MM), 0.0));
+
+    // Stripes of 1/8, 1/4, 1/2 and 3/4 ink
+    for (i, (on, off)) in [(1.0, 7.0), (1.0, 3.0), (1.0, 1.0), (3.0, 1.0)]
+        .into_iter()
+        .enumerate()
+    {
+        let start = GRADIENT_MM + 3 * i as u32;
+        let stripes = stripes(on, off, dots(start + 3) - dots(start), height);
+        layers.push(place(stripes, dots(start), 0.0));
+    }
+
+    for parity in 0..2 {
+        let start = PIN_LINES_MM + 5 * parity;
+        let lines = pin_lines(tape_spec, parity, dots(start + 5) - dots(start));
+        layers.push(place(lines, dots(start), 0.0));
+    }
+
+    // Ticks every 8 pins: long ones every 32 pins
+    for (y, pin) in pin_rows(tape_spec, 8) {
+        let length = if pin.is_multiple_of(32) {
+            dots(2)
+        } else {
+            dots(1)
+        };
+        let tick = Gap::new(length, TICK_WIDTH, true);
+        layers.push(place(Box::new(tick), dots(PIN_RULER_MM), y));
+    }
+
+    layers
+}
+
+/// Printable rows of pins numbered a multiple of `step`: (y, pin)
+///
+/// Pins are numbered from the right edge of the head as in the raster
+/// data, so a dead pin can be found in `ptouch raw` dumps.
+fn pin_rows(tape_spec: &TapeSpec, step: u32) -> impl Iterator<Item = (f32, u32)> {
+    let right_pins = tape_spec.right_pins;
+    (0..tape_spec.inner_dots)
+        .filter(move |y| (right_pins + y).is_multiple_of(step))
+        .map(move |y| (y as f32, right_pins + y))
+}
+
+/// Numbers of pins every 32 pins next to the long ticks
+fn pin_numbers(tape_spec: &TapeSpec, text_options: &TextOptions) -> Vec<Box<dyn Element>> {
+    let height = tape_spec.inner_dots as f32;
+    let font_size = 24.min(tape_spec.inner_dots / 4);
+    let options = TextOptions {
+        font_size,
+        line_height: font_size,
+        ..text_options.clone()
+    };
+    let x = tape_spec.mm_to_dots((PIN_RULER_MM + 2) as f32) as f32 + BAR_WIDTH * 2.0;
+
+    // Numbers centered on the ticks, except those sticking out of the tape
+    pin_rows(tape_spec, 32)
+        .map(|(y, pin)| (y - font_size as f32 / 2.0, pin))
+        .filter(|&(y, _)| y >= 0.0 && y + font_size as f32 <= height)
+        .map(|(y, pin)| {
+            let text = Text::new_unchecked(&[pin.to_string()], options.clone());
+            place(Box::new(text), x, y)
+        })
+        .collect()
+}
+
+/// Vertical lines of `on` dots every `on + off` dots across the tape
+fn stripes(on: f32, off: f32, length: f32, height: f32) -> Box<dyn Element> {
+    let mut elements: Vec<Box<dyn Element>> = Vec::new();
+    let mut x = 0.0;
+
+    while x + on <= length {
+        elements.push(Box::new(Gap::new(on, height, true)));
+        elements.push(Box::new(Gap::new(off, height, false)));
+        x += on + off;
+    }
+
+    Box::new(Row::new(
+        elements,
+        RowOptions {
+            padding: 0.0,
+            ..RowOptions::default()
+        },
+    ))
+}
+
+/// Lines along the tape printed by single pins of `parity` (0: even)
+fn pin_lines(tape_spec: &TapeSpec, parity: u32, length: f32) -> Box<dyn Element> {
+    let elements = (0..tape_spec.inner_dots)
+        .map(|y| {
+            let inked = (tape_spec.right_pins + y) % 2 == parity;
+            Box::new(Gap::new(length, 1.0, inked)) as Box<dyn Element>
+        })
+        .collect();
+    Box::new(Column::new(elements, 0.0))
+}
+
+/// Row of marks every `step_mm` from 0 to PATTERN_LENGTH_MM
+fn marks(tape_spec: &TapeSpec, step_mm: u32, width: f32, height: f32) -> Box<dyn Element> {
+    let mut elements: Vec<Box<dyn Element>> = Vec::new();
+    let mut x = 0.0;
+
+    for mm in (0..=PATTERN_LENGTH_MM).step_by(step_mm as usize) {
+        let position = tape_spec.mm_to_dots(mm as f32) as f32;
+        if position > x {
+            elements.push(Box::new(Gap::new(position - x, height, false)));
+        }
+        elements.push(Box::new(Gap::new(width, height, true)));
+        x = position + width;
+    }
+
+    Box::new(Row::new(
+        elements,
+        RowOptions {
+            padding: 0.0,
+            ..RowOptions::default()
+        },
+    ))
+}
+
+/// Put `element` at (x, y) in an Overlay layer
+fn place(element: Box<dyn Element>, x: f32, y: f32) -> Box<dyn Element> {
+    let row = Row::new(
+        vec![Box::new(Gap::new(x, 1.0, false)), element],
+        RowOptions {
+            padding: 0.0,
+            ..RowOptions::default()
+        },
+    );
+    Box::new(Column::new(
+        vec![Box::new(Gap::new(1.0, y, false)), Box::new(row)],
+        0.0,
+    ))
+}
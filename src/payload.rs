@@ -0,0 +1,194 @@
+//! Helpers to build well-known QR code payloads
+//!
+//! Escaping follows the ZXing barcode contents conventions:
+//! https://github.com/zxing/zxing/wiki/Barcode-Contents
+
+use crate::Result;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// Authentication type of a Wi-Fi network
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WifiAuth {
+    #[default]
+    Wpa,
+    Wep,
+    NoPass,
+}
+
+impl Display for WifiAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WifiAuth::Wpa => write!(f, "WPA"),
+            WifiAuth::Wep => write!(f, "WEP"),
+            WifiAuth::NoPass => write!(f, "nopass"),
+        }
+    }
+}
+
+impl FromStr for WifiAuth {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "wpa" | "wpa2" => Ok(WifiAuth::Wpa),
+            "wep" => Ok(WifiAuth::Wep),
+            "nopass" | "none" => Ok(WifiAuth::NoPass),
+            _ => Err(format!("Invalid Wi-Fi authentication: {} (WPA, WEP or nopass)", s).into()),
+        }
+    }
+}
+
+/// Escape special characters `\ ; , " :` with a backslash
+fn escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for ch in s.chars() {
+        if matches!(ch, '\\' | ';' | ',' | '"' | ':') {
+            result.push('\\');
+        }
+        result.push(ch);
+    }
+    result
+}
+
+/// Build a Wi-Fi network config payload
+///
+/// The password is omitted for [`WifiAuth::NoPass`].
+///
+/// # Examples
+///
+/// ```
+/// use ptouch::payload::{WifiAuth, wifi};
+///
+/// assert_eq!(wifi("home", "secret", WifiAuth::Wpa), "WIFI:T:WPA;S:home;P:secret;;");
+/// assert_eq!(wifi("a;b", "", WifiAuth::NoPass), r"WIFI:T:nopass;S:a\;b;;");
+/// ```
+pub fn wifi(ssid: &str, password: &str, auth: WifiAuth) -> String {
+    match auth {
+        WifiAuth::NoPass => format!("WIFI:T:{};S:{};;", auth, escape(ssid)),
+        _ => format!(
+            "WIFI:T:{};S:{};P:{};;",
+            auth,
+            escape(ssid),
+            escape(password)
+        ),
+    }
+}
+
+/// Build an e-mail payload
+///
+/// ```
+/// assert_eq!(ptouch::payload::mailto("nom@example.com"), "mailto:nom@example.com");
+/// ```
+pub fn mailto(address: &str) -> String {
+    format!("mailto:{}", address)
+}
+
+/// Build a telephone number payload
+///
+/// Spaces, hyphens and parentheses are dropped as dialers do not need them.
+///
+/// ```
+/// assert_eq!(ptouch::payload::tel("+81 (3) 1234-5678"), "tel:+81312345678");
+/// ```
+pub fn tel(number: &str) -> String {
+    let number: String = number
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '(' | ')'))
+        .collect();
+    format!("tel:{}", number)
+}
+
+/// Build a MECARD contact payload
+///
+/// Empty fields are omitted.
+///
+/// ```
+/// use ptouch::payload::mecard;
+///
+/// assert_eq!(
+///     mecard("Yoshinari Nomura", "", "nom@example.com"),
+///     "MECARD:N:Yoshinari Nomura;EMAIL:nom@example.com;;"
+/// );
+/// ```
+pub fn mecard(name: &str, tel: &str, email: &str) -> String {
+    let mut result = String::from("MECARD:");
+    for (key, value) in [("N", name), ("TEL", tel), ("EMAIL", email)] {
+        if !value.is_empty() {
+            result.push_str(&format!("{}:{};", key, escape(value)));
+        }
+    }
+    result.push(';');
+    result
+}
+
+/// Digest algorithm of payload IDs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IdAlgorithm {
+    #[default]
+    Sha256,
+    Crc32,
+}
+
+impl Display for IdAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdAlgorithm::Sha256 => write!(f, "sha256"),
+            IdAlgorithm::Crc32 => write!(f, "crc32"),
+        }
+    }
+}
+
+impl FromStr for IdAlgorithm {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "sha256" => Ok(IdAlgorithm::Sha256),
+            "crc32" => Ok(IdAlgorithm::Crc32),
+            _ => Err(format!("Invalid ID algorithm: {} (sha256 or crc32)", s).into()),
+        }
+    }
+}
+
+/// Crockford's base32 alphabet: no I, L, O and U to be read aloud safely
+const BASE32_ALPHABET: &[u8; 32] = b"0123456789abcdefghjkmnpqrstvwxyz";
+
+/// Number of characters in a payload ID (30 bits)
+const ID_LENGTH: usize = 6;
+
+/// Short human-readable digest of a payload
+///
+/// People can read it aloud to confirm that a QR code is on the right
+/// item. See [`payload_id_with`] for the algorithm.
+///
+/// ```
+/// assert_eq!(ptouch::payload::payload_id("https://example.com"), "20381b");
+/// ```
+pub fn payload_id(payload: &str) -> String {
+    payload_id_with(payload, IdAlgorithm::default())
+}
+
+/// Short human-readable digest of a payload by `algorithm`
+///
+/// The leading 30 bits of the digest (big endian) are encoded in 6
+/// lowercase characters of Crockford's base32.
+///
+/// ```
+/// use ptouch::payload::{IdAlgorithm, payload_id_with};
+///
+/// assert_eq!(payload_id_with("https://example.com", IdAlgorithm::Crc32), "zwmnsp");
+/// ```
+pub fn payload_id_with(payload: &str, algorithm: IdAlgorithm) -> String {
+    use sha2::Digest;
+
+    let digest: Vec<u8> = match algorithm {
+        IdAlgorithm::Sha256 => sha2::Sha256::digest(payload.as_bytes()).to_vec(),
+        IdAlgorithm::Crc32 => crc32fast::hash(payload.as_bytes()).to_be_bytes().to_vec(),
+    };
+
+    let bits = u32::from_be_bytes(digest[..4].try_into().unwrap()) >> 2;
+    (0..ID_LENGTH)
+        .map(|i| BASE32_ALPHABET[((bits >> (5 * (ID_LENGTH - 1 - i))) & 31) as usize] as char)
+        .collect()
+}
@@ -0,0 +1,18 @@
+//! Commonly used types for building and printing labels
+//!
+//! ```
+//! use ptouch::prelude::*;
+//! ```
+
+pub use crate::Result;
+pub use crate::backend::{Backend, from_host};
+pub use crate::element::{
+    Column, Element, Gap, Image, Overlay, QrCode, QrEcLevel, QrOptions, Rotated, Rotation, Row,
+    RowOptions, Text, TextOptions, VerticalAlign,
+};
+pub use crate::label::{Border, Label, LabelOptions, Placement};
+pub use crate::layout::{LayoutOptions, parse_layout_script};
+pub use crate::load_fontdb_with_paths;
+pub use crate::printable_image::PrintableImage;
+pub use crate::printer::{PrintOptions, Printer};
+pub use crate::tape::{Tape, TapeSpec};
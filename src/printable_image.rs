@@ -1,10 +1,51 @@
 use crate::Result;
+use crate::error::{Error, ErrorKind};
 use crate::tape::TapeSpec;
 use png::ColorType;
 
+/// Blank raster lines cut off by [`PrintableImage::trimmed`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Trim {
+    /// Lines cut off at the start (left edge of the PNG)
+    pub leading: u32,
+    /// Lines cut off at the end (right edge of the PNG)
+    pub trailing: u32,
+}
+
+/// How pixels of the printable area are mapped to the pins of the print head
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RasterOptions {
+    /// Shift of contents across the tape in pins (positive: toward the
+    /// bottom of the image) to correct off-center printing; pins shifted
+    /// off the print head are dropped
+    pub v_offset: i32,
+    /// Pins never to be set, e.g. dead pins of the print head
+    pub mask_pins: Vec<u32>,
+    /// Swap ink and blank in the printable area (white on black); pins
+    /// outside it stay unset, so the margins are not printed
+    pub negative: bool,
+}
+
+/// PNG of a label validated against the tape to print on
+///
+/// Debug output leaves out the PNG data, showing its size instead:
+///
+/// ```
+/// use ptouch::prelude::*;
+/// use std::sync::Arc;
+///
+/// let options = LabelOptions::new(Arc::new(fontdb::Database::new()));
+/// let label = Label::from_element(Box::new(Gap::new(100.0, 10.0, true)), options);
+/// let png_data = label.to_png().unwrap();
+/// let image = PrintableImage::from_png_data(png_data, TapeSpec::new(Tape::TZe12H)).unwrap();
+/// assert_eq!((image.length_px(), image.width_px()), (label.dimensions().unwrap().0, 170));
+/// assert!(format!("{:?}", image).starts_with("PrintableImage { tape_spec: TapeSpec { name: TZe12H,"));
+/// ```
 pub struct PrintableImage {
     png_data: Vec<u8>,
     tape_spec: TapeSpec,
+    /// Number of raster lines (PNG width)
+    length: u32,
 }
 
 impl PrintableImage {
@@ -14,6 +55,7 @@ impl PrintableImage {
         let reader = decoder.read_info()?;
         let png_info = reader.info();
         let png_height = png_info.height;
+        let length = png_info.width;
 
         if png_height != tape_spec.width_dots {
             return Err(format!(
@@ -26,28 +68,179 @@ impl PrintableImage {
         Ok(PrintableImage {
             png_data,
             tape_spec,
+            length,
         })
     }
 
     pub fn to_raster_lines(&self) -> Result<Vec<Vec<u8>>> {
-        png_to_raster_lines(&self.png_data, &self.tape_spec)
+        self.to_raster_lines_with(&RasterOptions::default())
+    }
+
+    /// Raster lines mapped to the pins by `options`
+    pub fn to_raster_lines_with(&self, options: &RasterOptions) -> Result<Vec<Vec<u8>>> {
+        png_to_raster_lines(&self.png_data, &self.tape_spec, options)
+    }
+
+    /// Find the offset nearest to `v_offset` that keeps ink off `mask_pins`
+    ///
+    /// Contents are moved only within the printable area, using blank
+    /// rows at its edges and between lines. Returns None if every offset
+    /// puts ink on a masked pin.
+    pub fn dodge_pins(&self, v_offset: i32, mask_pins: &[u32]) -> Result<Option<i32>> {
+        let (width, height, gray_buf) = decode_grayscale(&self.png_data)?;
+        let (width, height) = (width as usize, height as usize);
+        let margin = self.tape_spec.margin_dots() as usize;
+        let inner = self.tape_spec.inner_dots as i64;
+
+        // Rows of the printable area with ink
+        let ink_rows: Vec<i64> = (margin..(margin + inner as usize).min(height))
+            .filter(|y| (0..width).any(|x| is_ink(gray_buf[y * width + x])))
+            .map(|y| (y - margin) as i64)
+            .collect();
+        let first_pin = self.tape_spec.right_pins as i64 + v_offset as i64;
+        let fits = |shift: i64| {
+            ink_rows.iter().all(|row| {
+                let row = row + shift;
+                (0..inner).contains(&row) && !mask_pins.contains(&((first_pin + row) as u32))
+            })
+        };
+
+        let shift = (0..inner)
+            .flat_map(|distance| [distance, -distance])
+            .find(|&shift| fits(shift));
+        Ok(shift.map(|shift| v_offset + shift as i32))
+    }
+
+    /// Options with the offset moved by [`Self::dodge_pins`] to keep ink
+    /// off the masked pins
+    ///
+    /// Also tells why the masked pins cannot be avoided, if so: the
+    /// contents overlap them at every offset, or the background of the
+    /// negative covers all pins. They print blank then.
+    pub fn dodge_masked_pins(
+        &self,
+        options: &RasterOptions,
+    ) -> Result<(RasterOptions, Option<String>)> {
+        let mut options = options.clone();
+        if options.mask_pins.is_empty() {
+            return Ok((options, None));
+        }
+        let pins: Vec<String> = options.mask_pins.iter().map(u32::to_string).collect();
+
+        // The background is printed on all pins, leaving nowhere to dodge
+        if options.negative {
+            let warning = format!(
+                "Masked pins {} print blank stripes in the negative",
+                pins.join(",")
+            );
+            return Ok((options, Some(warning)));
+        }
+
+        match self.dodge_pins(options.v_offset, &options.mask_pins)? {
+            Some(v_offset) => {
+                options.v_offset = v_offset;
+                Ok((options, None))
+            }
+            None => {
+                let warning = format!(
+                    "Contents overlap masked pins {}, which print blank",
+                    pins.join(",")
+                );
+                Ok((options, Some(warning)))
+            }
+        }
+    }
+
+    /// Cut off blank lines at both ends of the label
+    ///
+    /// Columns without ink (white or transparent) are removed along the
+    /// tape, e.g. padding around screenshots. The tape width is kept.
+    /// Fails if the image has no ink at all.
+    pub fn trimmed(&self) -> Result<(Self, Trim)> {
+        let (width, height, gray_buf) = decode_grayscale(&self.png_data)?;
+        let (width, height) = (width as usize, height as usize);
+        let has_ink = |x: usize| (0..height).any(|y| is_ink(gray_buf[y * width + x]));
+
+        let first = (0..width)
+            .find(|&x| has_ink(x))
+            .ok_or("Nothing to print: the image is blank")?;
+        let last = (first..width).rev().find(|&x| has_ink(x)).unwrap();
+        let trim = Trim {
+            leading: first as u32,
+            trailing: (width - 1 - last) as u32,
+        };
+
+        let new_width = last + 1 - first;
+        let pixels: Vec<u8> = (0..height)
+            .flat_map(|y| {
+                gray_buf[y * width + first..=y * width + last]
+                    .iter()
+                    .copied()
+            })
+            .collect();
+        let mut png_data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png_data, new_width as u32, height as u32);
+        encoder.set_color(ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&pixels)?;
+
+        Ok((Self::from_png_data(png_data, self.tape_spec.clone())?, trim))
+    }
+
+    /// Length along the tape in pixels (PNG width), i.e. the number of raster lines
+    pub fn length_px(&self) -> u32 {
+        self.length
+    }
+
+    /// Width across the tape in pixels (PNG height), margins included
+    pub fn width_px(&self) -> u32 {
+        self.tape_spec.width_dots
     }
 
     pub fn tape_spec(&self) -> &TapeSpec {
         &self.tape_spec
     }
+
+    /// Length of the label along the tape in mm (without feed margins)
+    pub fn length_mm(&self) -> f32 {
+        self.length as f32 * 25.4 / self.tape_spec.feed_dpi as f32
+    }
+
+    /// Fail if the label is longer than `max_length_mm` along the tape
+    pub fn check_length(&self, max_length_mm: f32) -> Result<()> {
+        if self.length_mm() > max_length_mm {
+            let message = format!(
+                "Label is {:.1} mm long ({} raster lines at {}DPI), longer than the maximum {} mm",
+                self.length_mm(),
+                self.length,
+                self.tape_spec.feed_dpi,
+                max_length_mm
+            );
+            return Err(Error::new(ErrorKind::Usage, message).into());
+        }
+        Ok(())
+    }
 }
 
-fn png_to_raster_lines(png_data: &[u8], tape_spec: &TapeSpec) -> Result<Vec<Vec<u8>>> {
-    let decoder = png::Decoder::new(png_data);
-    let mut reader = decoder.read_info()?;
-    let mut buf = vec![0; reader.output_buffer_size()];
-    let info = reader.next_frame(&mut buf)?;
+impl std::fmt::Debug for PrintableImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrintableImage")
+            .field("tape_spec", &self.tape_spec)
+            .field("length", &self.length)
+            .field("png_bytes", &self.png_data.len())
+            .finish()
+    }
+}
 
-    let gray_buf = convert_to_grayscale(&buf, info.color_type)?;
+fn png_to_raster_lines(
+    png_data: &[u8],
+    tape_spec: &TapeSpec,
+    options: &RasterOptions,
+) -> Result<Vec<Vec<u8>>> {
+    let (width, height, gray_buf) = decode_grayscale(png_data)?;
 
-    let width = info.width as usize;
-    let height = info.height as usize;
+    let width = width as usize;
+    let height = height as usize;
     let bytes_per_raster = (tape_spec.total_pins / 8) as usize;
     let mut raster_lines = Vec::new();
 
@@ -55,19 +248,22 @@ fn png_to_raster_lines(png_data: &[u8], tape_spec: &TapeSpec) -> Result<Vec<Vec<
         let mut raster_line = vec![0u8; bytes_per_raster];
 
         // Mapping the Y-range (margin, margin+inner-1) of the PNG to
-        // (right_pin, right_pin+inner-1)
-        let margin = ((tape_spec.width_dots - tape_spec.inner_dots) / 2) as usize;
+        // (right_pin, right_pin+inner-1) shifted by v_offset
+        let margin = tape_spec.margin_dots() as usize;
         let inner = tape_spec.inner_dots as usize;
-        let right_pin = tape_spec.right_pins as usize;
+        let right_pin = tape_spec.right_pins as i64 + options.v_offset as i64;
 
         for y in margin..(margin + inner).min(height) {
-            let pin = right_pin + (y - margin);
+            let pin = right_pin + (y - margin) as i64;
 
-            if pin < tape_spec.total_pins as usize {
+            if let Ok(pin) = usize::try_from(pin)
+                && pin < tape_spec.total_pins as usize
+                && !options.mask_pins.contains(&(pin as u32))
+            {
                 let pixel_idx = y * width + x;
                 if pixel_idx < gray_buf.len() {
                     let pixel = gray_buf[pixel_idx];
-                    if pixel < 127 {
+                    if is_ink(pixel) != options.negative {
                         let byte_idx = pin / 8;
                         let bit_idx = 7 - (pin % 8);
                         raster_line[byte_idx] |= 1 << bit_idx;
@@ -82,6 +278,22 @@ fn png_to_raster_lines(png_data: &[u8], tape_spec: &TapeSpec) -> Result<Vec<Vec<
     Ok(raster_lines)
 }
 
+/// Decode PNG into width, height and 8-bit grayscale pixels
+pub(crate) fn decode_grayscale(png_data: &[u8]) -> Result<(u32, u32, Vec<u8>)> {
+    let decoder = png::Decoder::new(png_data);
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+
+    let gray_buf = convert_to_grayscale(&buf, info.color_type)?;
+    Ok((info.width, info.height, gray_buf))
+}
+
+/// Check if the grayscale pixel is printed
+pub(crate) fn is_ink(pixel: u8) -> bool {
+    pixel < 127
+}
+
 fn convert_to_grayscale(buf: &[u8], color_type: ColorType) -> Result<Vec<u8>> {
     match color_type {
         ColorType::Grayscale => Ok(buf.to_vec()),
@@ -103,75 +315,300 @@ fn convert_to_grayscale(buf: &[u8], color_type: ColorType) -> Result<Vec<u8>> {
     }
 }
 
-fn take_consecutive_run(data: &[u8]) -> &[u8] {
-    if data.len() < 2 || data[0] != data[1] {
-        return &[];
-    }
+/// Longest run encodable in one PackBits header (-127 => 128 bytes)
+const MAX_RUN: usize = 128;
+
+/// Longest literal sequence emitted in one PackBits header
+const MAX_LITERAL: usize = 127;
 
-    let first_byte = data[0];
-    let mut len = 1;
+pub fn compress_tiff_group4(data: &[u8]) -> Result<Vec<u8>> {
+    let mut compressed = Vec::new();
+    compress_tiff_group4_into(data, &mut compressed);
+    Ok(compressed)
+}
 
-    while len < data.len() && data[len] == first_byte && len < 255 {
-        len += 1;
+/// Compress one raster line and append the result to `out`
+///
+/// Same encoding as [`compress_tiff_group4`], but lets the caller reuse
+/// one output buffer across raster lines.
+pub fn compress_tiff_group4_into(data: &[u8], out: &mut Vec<u8>) {
+    // TIFF Group 4 Run Length Encoding for Brother P-Touch
+    // Based on cv_ptp900_eng_raster_102.pdf "Select compression mode" example:
+    // - Run data (consecutive same bytes): negative count (two's complement) + byte
+    // - Literal data (non-consecutive): positive count + raw bytes
+    //
+    // Single forward pass: `start` is the beginning of the current
+    // run or literal sequence, and the mode is decided by whether
+    // the first two bytes are the same.
+
+    let len = data.len();
+    let is_run_at = |i: usize| i + 1 < len && data[i] == data[i + 1];
+    let mut start = 0;
+
+    while start < len {
+        let mut end = start + 1;
+
+        if is_run_at(start) {
+            let byte = data[start];
+            while end < len && data[end] == byte && end - start < MAX_RUN {
+                end += 1;
+            }
+            out.push((257 - (end - start)) as u8);
+            out.push(byte);
+        } else {
+            while end < len && end - start < MAX_LITERAL && !is_run_at(end) {
+                end += 1;
+            }
+            out.push((end - start - 1) as u8);
+            out.extend_from_slice(&data[start..end]);
+        }
+
+        start = end;
+    }
+}
+
+/// Decompress one raster line compressed by [`compress_tiff_group4`]
+///
+/// Accepts any valid PackBits data, including the no-op header (0x80)
+/// and runs not produced by our encoder.
+pub fn decompress_tiff_group4(data: &[u8]) -> Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let header = data[pos] as i8;
+        pos += 1;
+
+        match header {
+            0..=127 => {
+                // Literal data: header + 1 raw bytes
+                let end = pos + header as usize + 1;
+                let literal = data
+                    .get(pos..end)
+                    .ok_or_else(|| format!("Truncated literal data at offset {}", pos - 1))?;
+                decompressed.extend_from_slice(literal);
+                pos = end;
+            }
+            -127..=-1 => {
+                // Run data: 1 - header copies of the next byte
+                let byte = *data
+                    .get(pos)
+                    .ok_or_else(|| format!("Truncated run data at offset {}", pos - 1))?;
+                let count = (1 - header as isize) as usize;
+                decompressed.resize(decompressed.len() + count, byte);
+                pos += 1;
+            }
+            -128 => {} // No operation
+        }
     }
 
-    &data[..len]
+    Ok(decompressed)
 }
 
-fn take_literal_run(data: &[u8]) -> &[u8] {
-    let mut len = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Previous (two-helper, rescanning) encoder kept as a reference.
+    // Its runs are capped at 255 which is not valid PackBits beyond 128,
+    // so comparisons only use data whose runs fit in 128 bytes.
+    fn reference_compress(data: &[u8]) -> Vec<u8> {
+        fn take_consecutive_run(data: &[u8]) -> &[u8] {
+            if data.len() < 2 || data[0] != data[1] {
+                return &[];
+            }
+            let mut len = 1;
+            while len < data.len() && data[len] == data[0] && len < 255 {
+                len += 1;
+            }
+            &data[..len]
+        }
+
+        fn take_literal_run(data: &[u8]) -> &[u8] {
+            let mut len = 0;
+            while len < data.len() && len < 127 {
+                if !take_consecutive_run(&data[len..]).is_empty() {
+                    break;
+                }
+                len += 1;
+            }
+            &data[..len]
+        }
 
-    while len < data.len() && len < 127 {
-        let remaining = &data[len..];
-        if !take_consecutive_run(remaining).is_empty() {
-            break;
+        let mut compressed = Vec::new();
+        let mut remaining = data;
+
+        while !remaining.is_empty() {
+            let consecutive_run = take_consecutive_run(remaining);
+            let count = if !consecutive_run.is_empty() {
+                let count = consecutive_run.len();
+                compressed.push((256 - (count - 1)) as u8);
+                compressed.push(consecutive_run[0]);
+                count
+            } else {
+                let literal_run = take_literal_run(remaining);
+                let count = literal_run.len();
+                compressed.push((count - 1) as u8);
+                compressed.extend_from_slice(literal_run);
+                count
+            };
+            remaining = &remaining[count..];
         }
-        len += 1;
+        compressed
     }
 
-    &data[..len]
-}
+    // Small xorshift PRNG to avoid a dev-dependency
+    fn xorshift(state: &mut u32) -> u32 {
+        *state ^= *state << 13;
+        *state ^= *state >> 17;
+        *state ^= *state << 5;
+        *state
+    }
 
-pub fn compress_tiff_group4(data: &[u8]) -> Result<Vec<u8>> {
-    // TIFF Group 4 Run Length Encoding for Brother P-Touch
-    // Based on cv_ptp900_eng_raster_102.pdf "Select compression mode" example:
-    // - Run data (consecutive same bytes): negative count (two's complement) + byte
-    // - Literal data (non-consecutive): positive count + raw bytes
+    fn longest_run(data: &[u8]) -> usize {
+        data.chunk_by(|a, b| a == b)
+            .map(|run| run.len())
+            .max()
+            .unwrap_or(0)
+    }
 
-    let mut compressed = Vec::new();
+    #[test]
+    fn test_compress_tiff_group4_matches_reference() {
+        let mut state = 0x1234_5678;
+
+        for _ in 0..2000 {
+            let len = (xorshift(&mut state) % 300) as usize;
+            // Small alphabets produce runs, large ones produce literals
+            let alphabet = [2, 4, 256][(xorshift(&mut state) % 3) as usize];
+            let data: Vec<u8> = (0..len)
+                .map(|_| (xorshift(&mut state) % alphabet) as u8)
+                .collect();
+
+            if longest_run(&data) > MAX_RUN {
+                continue;
+            }
+            assert_eq!(
+                compress_tiff_group4(&data).unwrap(),
+                reference_compress(&data),
+                "Mismatch for input {:02x?}",
+                data
+            );
+        }
+    }
 
-    if data.is_empty() {
-        return Ok(compressed);
+    #[test]
+    fn test_compress_tiff_group4_round_trip() {
+        let mut state = 0x9E37_79B9;
+
+        for _ in 0..2000 {
+            let len = (xorshift(&mut state) % 600) as usize;
+            // Small alphabets produce runs (also longer than MAX_RUN)
+            let alphabet = [1, 2, 4, 256][(xorshift(&mut state) % 4) as usize];
+            let data: Vec<u8> = (0..len)
+                .map(|_| (xorshift(&mut state) % alphabet) as u8)
+                .collect();
+
+            let compressed = compress_tiff_group4(&data).unwrap();
+            assert_eq!(
+                decompress_tiff_group4(&compressed).unwrap(),
+                data,
+                "Round trip mismatch for input {:02x?}",
+                data
+            );
+        }
     }
 
-    let mut remaining = data;
+    #[test]
+    fn test_decompress_tiff_group4() {
+        // Example in the raster command reference
+        let compressed = [
+            0xED, 0x00, 0xFF, 0x22, 0x05, 0x23, 0xBA, 0xBF, 0xA2, 0x22, 0x2B,
+        ];
+        let mut expected = vec![0x00; 20];
+        expected.extend_from_slice(&[0x22, 0x22, 0x23, 0xBA, 0xBF, 0xA2, 0x22, 0x2B]);
+        assert_eq!(decompress_tiff_group4(&compressed).unwrap(), expected);
 
-    while !remaining.is_empty() {
-        let consecutive_run = take_consecutive_run(remaining);
+        // No-op header is skipped
+        assert_eq!(
+            decompress_tiff_group4(&[0x80, 0xFF, 0x01]).unwrap(),
+            vec![0x01, 0x01]
+        );
+    }
 
-        let count = if !consecutive_run.is_empty() {
-            let count = consecutive_run.len();
-            let negative_count = (256 - (count - 1)) as u8;
-            compressed.push(negative_count);
-            compressed.push(consecutive_run[0]);
-            count
-        } else {
-            let literal_run = take_literal_run(remaining);
-            let count = literal_run.len();
-            compressed.push((count - 1) as u8);
-            compressed.extend_from_slice(literal_run);
-            count
-        };
+    #[test]
+    fn test_decompress_tiff_group4_truncated() {
+        assert!(decompress_tiff_group4(&[0x03, 0x01, 0x02]).is_err());
+        assert!(decompress_tiff_group4(&[0xFE]).is_err());
+    }
 
-        remaining = &remaining[count..];
+    #[test]
+    fn test_compress_tiff_group4_into_appends() {
+        let mut buf = vec![0xAA];
+        compress_tiff_group4_into(&[0x00; 70], &mut buf);
+        assert_eq!(buf, vec![0xAA, 0xbb, 0x00]);
     }
 
-    Ok(compressed)
-}
+    #[test]
+    fn test_compress_tiff_group4_long_run() {
+        // 200 bytes exceed one PackBits run: 128 (0x81) + 72 (0xb9)
+        let result = compress_tiff_group4(&[0x00; 200]).unwrap();
+        assert_eq!(result, vec![0x81, 0x00, 0xb9, 0x00]);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Split PackBits data into (is_run, count) commands
+    fn commands(compressed: &[u8]) -> Vec<(bool, usize)> {
+        let mut result = Vec::new();
+        let mut pos = 0;
+        while pos < compressed.len() {
+            let header = compressed[pos] as i8;
+            if header < 0 {
+                result.push((true, (1 - header as isize) as usize));
+                pos += 2;
+            } else {
+                result.push((false, header as usize + 1));
+                pos += header as usize + 2;
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_compress_tiff_group4_run_crossing_boundaries() {
+        // One PackBits run command holds at most 128 bytes (header 0x81),
+        // so runs longer than 255 are also split by MAX_RUN
+        for len in [128, 129, 255, 256, 300, 600] {
+            let data = vec![0x5A; len];
+            let compressed = compress_tiff_group4(&data).unwrap();
+            let cmds = commands(&compressed);
+
+            // A single byte left over is emitted as a 1-byte literal
+            assert!(
+                cmds.iter()
+                    .all(|&(is_run, count)| count <= MAX_RUN && (is_run || count == 1))
+            );
+            assert_eq!(cmds.iter().map(|&(_, count)| count).sum::<usize>(), len);
+            assert_eq!(cmds.len(), len.div_ceil(MAX_RUN), "len {}", len);
+            assert_eq!(decompress_tiff_group4(&compressed).unwrap(), data);
+        }
+
+        // 300 = 128 + 128 + 44
+        assert_eq!(
+            compress_tiff_group4(&[0x00; 300]).unwrap(),
+            vec![0x81, 0x00, 0x81, 0x00, 0xD5, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_compress_tiff_group4_literal_crossing_boundary() {
+        // 200 bytes without repeats: 127 + 73 literal bytes
+        let data: Vec<u8> = (0..200).map(|i| i as u8).collect();
+        let compressed = compress_tiff_group4(&data).unwrap();
+
+        assert_eq!(commands(&compressed), vec![(false, 127), (false, 73)]);
+        assert_eq!(compressed[0], 126);
+        assert_eq!(compressed[128], 72);
+        assert_eq!(decompress_tiff_group4(&compressed).unwrap(), data);
+    }
 
     #[test]
     fn test_compress_tiff_group4_all_black() {
@@ -1,9 +1,176 @@
 use crate::Result;
 use crate::backend::Backend;
-use crate::printable_image::{PrintableImage, compress_tiff_group4};
+use crate::printable_image::{PrintableImage, RasterOptions, compress_tiff_group4_into};
 use crate::raster_command::{CommandMode, PageType, RasterCommand};
 use crate::status::Status;
 
+/// Compression mode of raster lines
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Compression {
+    /// TIFF (PackBits) compression
+    #[default]
+    Tiff,
+    /// No compression. For firmwares that misbehave with TIFF data
+    None,
+}
+
+/// Print quality and speed trade-off
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Quality {
+    /// Faster printing with the draft bit set
+    Draft,
+    /// Printer default
+    #[default]
+    Normal,
+    /// Priority given to print quality
+    High,
+}
+
+impl std::fmt::Display for Quality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Quality::Draft => write!(f, "draft"),
+            Quality::Normal => write!(f, "normal"),
+            Quality::High => write!(f, "high"),
+        }
+    }
+}
+
+/// When the tape is cut
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CutPolicy {
+    /// Cut after every label (every [`PrintOptions::cut_every`] labels)
+    #[default]
+    Each,
+    /// Cut only after the last label of the job
+    EndOnly,
+    /// Never cut, leaving the label in the printer for the next job
+    /// (continuous printing)
+    None,
+}
+
+impl CutPolicy {
+    /// `auto_cut` of various mode settings and `no_chain` of advanced
+    /// mode settings
+    fn mode_bits(self) -> (bool, bool) {
+        match self {
+            CutPolicy::Each => (true, true),
+            CutPolicy::EndOnly => (false, true),
+            CutPolicy::None => (false, false),
+        }
+    }
+}
+
+impl std::fmt::Display for CutPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CutPolicy::Each => write!(f, "each"),
+            CutPolicy::EndOnly => write!(f, "end"),
+            CutPolicy::None => write!(f, "none"),
+        }
+    }
+}
+
+/// Options for a print job
+#[derive(Clone, Debug)]
+pub struct PrintOptions {
+    /// When the tape is cut
+    pub cut: CutPolicy,
+    /// Cut after every N labels of a multi-page job (1 to 255). Labels
+    /// sent as jobs of their own are counted by [`PrintOptions::for_label`]
+    pub cut_every: u8,
+    /// Compression mode of raster lines
+    pub compression: Compression,
+    /// 720DPI along the tape length (image must be rendered for it)
+    pub high_resolution: bool,
+    /// Print quality and speed trade-off
+    pub quality: Quality,
+    /// Half-cut between labels (on by default)
+    pub half_cut: bool,
+    /// Mapping of the image to the pins of the print head
+    pub raster: RasterOptions,
+    /// Send raster lines in chunks while building the job instead of the
+    /// whole job at once, keeping long labels out of one large buffer
+    pub stream: bool,
+    /// Send raster lines once for [`Printer::print_repeat`] and print the
+    /// copies from the printer's buffer. Only for models that
+    /// [support it](crate::status::Status::supports_buffer_reuse)
+    pub reuse_buffer: bool,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        PrintOptions {
+            cut: CutPolicy::default(),
+            cut_every: 1,
+            compression: Compression::default(),
+            high_resolution: false,
+            quality: Quality::default(),
+            half_cut: true,
+            raster: RasterOptions::default(),
+            stream: false,
+            reuse_buffer: false,
+        }
+    }
+}
+
+impl PrintOptions {
+    /// Options for label `index` (from 0) of `count` labels sent as
+    /// jobs of their own
+    ///
+    /// The printer counts pages within a job only, so cutting at the end
+    /// or every [`cut_every`](Self::cut_every) labels is emulated by
+    /// chaining the labels in between without cutting.
+    pub fn for_label(&self, index: u32, count: u32) -> PrintOptions {
+        let last = index + 1 >= count;
+        let interval = (index + 1).is_multiple_of(self.cut_every as u32);
+        let cut = match self.cut {
+            CutPolicy::Each if !last && !interval => CutPolicy::None,
+            CutPolicy::EndOnly if !last => CutPolicy::None,
+            cut => cut,
+        };
+        PrintOptions {
+            cut,
+            // One page per job; 0 is left to be rejected
+            cut_every: self.cut_every.min(1),
+            ..self.clone()
+        }
+    }
+}
+
+/// What was sent to the printer for a label
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrintReport {
+    /// Raster lines along the tape (of all copies)
+    pub raster_lines: u32,
+    /// Size of the command sequence
+    pub bytes_sent: usize,
+}
+
+/// Check a job of `times` copies of `printable` before sending it
+///
+/// Fails if the label is longer than `max_length_mm`, and on the
+/// conditions of [`Status::check_print`] if the status is known.
+/// Returns the warnings otherwise.
+pub fn check_job(
+    printable: &PrintableImage,
+    times: u32,
+    status: Option<&Status>,
+    max_length_mm: Option<f32>,
+    strict: bool,
+) -> Result<Vec<String>> {
+    if let Some(max_length_mm) = max_length_mm {
+        printable.check_length(max_length_mm)?;
+    }
+    match status {
+        Some(status) => status.check_print(printable.length_mm() * times as f32, strict),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Size of raster line chunks sent by [`PrintOptions::stream`]
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
 pub struct Printer<B: Backend> {
     backend: B,
 }
@@ -17,9 +184,83 @@ impl<B: Backend> Printer<B> {
         self.backend.get_status()
     }
 
-    pub fn print(&mut self, printable: &PrintableImage, continuous: bool) -> Result<()> {
+    /// Human-readable status, if the backend has one
+    pub fn get_status_text(&mut self) -> Option<String> {
+        self.backend.status_text()
+    }
+
+    /// Check that the printer is a P-Touch
+    pub fn verify_device(&mut self) -> Result<()> {
+        self.backend.verify_device()
+    }
+
+    /// Print a label and tell what was sent
+    pub fn print(
+        &mut self,
+        printable: &PrintableImage,
+        options: &PrintOptions,
+    ) -> Result<PrintReport> {
+        self.print_copies(printable, options, 1)
+    }
+
+    /// Print `times` copies of a label and tell what was sent in total
+    ///
+    /// With [`PrintOptions::reuse_buffer`], raster lines are sent once
+    /// with no buffer clearing set, and the other copies only take a
+    /// print command each. Otherwise the whole job is sent for each copy.
+    pub fn print_repeat(
+        &mut self,
+        printable: &PrintableImage,
+        options: &PrintOptions,
+        times: u32,
+    ) -> Result<PrintReport> {
+        if times == 0 {
+            return Err("Number of copies must be at least 1".into());
+        }
+        if options.reuse_buffer {
+            return self.print_copies(printable, options, times);
+        }
+
+        let mut total = PrintReport {
+            raster_lines: 0,
+            bytes_sent: 0,
+        };
+        for i in 0..times {
+            let report = self.print(printable, &options.for_label(i, times))?;
+            total.raster_lines += report.raster_lines;
+            total.bytes_sent += report.bytes_sent;
+        }
+        Ok(total)
+    }
+
+    /// Send raster lines once and print them `copies` times
+    ///
+    /// The reference allows printing the buffer again only if no buffer
+    /// clearing is set with the first page: its print command (FF) keeps
+    /// the data, each further FF prints it again and the last copy ends
+    /// with print with feeding (Ctrl-Z).
+    fn print_copies(
+        &mut self,
+        printable: &PrintableImage,
+        options: &PrintOptions,
+        copies: u32,
+    ) -> Result<PrintReport> {
+        let reuse = copies > 1;
+        let (auto_cut, no_chain) = options.cut.mode_bits();
+        let high_resolution = options.high_resolution;
+        let draft = options.quality == Quality::Draft;
+        let quality_mode = options.quality == Quality::High;
+        let tiff = options.compression == Compression::Tiff;
+        let half_cut = options.half_cut;
+        if options.cut_every == 0 {
+            // Page number 0 tells the printer never to cut
+            return Err("Cut interval must be 1 to 255 labels; \
+                        use no cut (continuous printing) not to cut at all"
+                .into());
+        }
+
         // Convert to raster lines
-        let raster_lines = printable.to_raster_lines()?;
+        let raster_lines = printable.to_raster_lines_with(&options.raster)?;
         let raster_count = raster_lines.len() as u32;
         let tape_spec = printable.tape_spec();
 
@@ -29,43 +270,76 @@ impl<B: Backend> Printer<B> {
             .initialize()
             .switch_dynamic_command_mode(CommandMode::Raster)
             .print_information_command(
-                false,                    // quality_mode
+                quality_mode,             // quality_mode
                 true,                     // recover_mode
                 Some(0),                  // media_type
                 Some(tape_spec.width_mm), // media_width
                 Some(0),                  // media_length
                 raster_count,
-                PageType::LastPage,
+                if reuse {
+                    PageType::FirstPage
+                } else {
+                    PageType::LastPage
+                },
             )
-            .various_mode_settings(!continuous, false) // auto_cut=true if !continuous, mirror=false
-            .specify_page_number(1) // always 1 for single page
+            .various_mode_settings(auto_cut, false) // mirror=false
+            .specify_page_number(options.cut_every)
             .advanced_mode_settings(
-                false,       // draft
-                true,        // half_cut
-                !continuous, // no_chain: true=cut last label, false=continuous
-                false,       // special_tape
-                false,       // high_resolution
-                false,       // no_buffer_clear
+                draft,           // draft
+                half_cut,        // half_cut
+                no_chain,        // no_chain: true=cut last label, false=continuous
+                false,           // special_tape
+                high_resolution, // high_resolution: 720DPI along the tape
+                reuse,           // no_buffer_clear: keep raster data for copies
             )
             // dev manual requires 14dots min (1mm @ 360DPI, 2mm@ 1 80DPI)
             .specify_margin_amount(14)
-            .select_compression_mode(true); // TIFF compression
+            .select_compression_mode(tiff);
 
-        // Add raster lines
+        // Streaming sends the header first and raster lines in chunks
+        self.backend.begin_job()?;
+        let mut bytes_sent = 0;
+        if options.stream {
+            bytes_sent += self.send_chunk(cmd.take())?;
+        }
+
+        // Add raster lines (reusing one compression buffer)
+        let mut compressed_data = Vec::new();
         for raster_line in &raster_lines {
-            let compressed_data = compress_tiff_group4(raster_line)?;
-            cmd.raster_graphics_transfer(&compressed_data);
+            // Blank lines are sent as zero raster graphics in both modes
+            if raster_line.iter().all(|&b| b == 0) {
+                cmd.zero_raster_graphics();
+            } else if tiff {
+                compressed_data.clear();
+                compress_tiff_group4_into(raster_line, &mut compressed_data);
+                cmd.raster_graphics_transfer(&compressed_data)?;
+            } else {
+                cmd.raster_graphics_transfer(raster_line)?;
+            }
+            if options.stream && cmd.len() >= STREAM_CHUNK_SIZE {
+                bytes_sent += self.send_chunk(cmd.take())?;
+            }
         }
 
-        // Add print command
+        // Add print command, once per copy
+        for _ in 1..copies {
+            cmd.print_command();
+        }
         cmd.print_command_with_feeding();
 
-        let command_data = cmd.build();
-
         // Send to printer
-        self.backend.send_command(&command_data)?;
+        bytes_sent += self.send_chunk(cmd.build())?;
+
+        crate::info!("Print command sent successfully");
+        Ok(PrintReport {
+            raster_lines: raster_count * copies,
+            bytes_sent,
+        })
+    }
 
-        println!("Print command sent successfully");
-        Ok(())
+    /// Send a part of the command sequence and return its size
+    fn send_chunk(&mut self, data: Vec<u8>) -> Result<usize> {
+        self.backend.send_command(&data)?;
+        Ok(data.len())
     }
 }
@@ -1,3 +1,5 @@
+use crate::Result;
+
 /// Brother P-Touch raster command builder
 ///
 /// Based on Raster Command Reference (4. Printing Command Details)
@@ -197,7 +199,8 @@ impl RasterCommand {
     ///
     /// # Arguments
     /// * `draft` - Enable draft mode (faster printing)
-    /// * `half_cut` - Enable half-cut (partial cut for easy peeling)
+    /// * `half_cut` - Enable half-cut (partial cut for easy peeling).
+    ///   The cut depth is fixed by the printer; the protocol has no command to adjust it.
     /// * `no_chain` - Disable chain printing (cut after last label)
     /// * `special_tape` - Enable special tape mode
     /// * `high_resolution` - Enable high resolution mode
@@ -272,14 +275,23 @@ impl RasterCommand {
     ///
     /// # Arguments
     /// * `data` - Compressed raster line data (max 65535 bytes)
-    pub fn raster_graphics_transfer(&mut self, data: &[u8]) -> &mut Self {
+    ///
+    /// # Errors
+    /// Returns an error if `data` does not fit in the u16 length field.
+    pub fn raster_graphics_transfer(&mut self, data: &[u8]) -> Result<&mut Self> {
+        let len = u16::try_from(data.len()).map_err(|_| {
+            format!(
+                "Raster line too long: {} bytes (max {} bytes)",
+                data.len(),
+                u16::MAX
+            )
+        })?;
         self.buffer.push(0x47); // 'G'
-        let len = data.len() as u16;
         // little-endian u16
         self.buffer.push((len & 0xFF) as u8);
         self.buffer.push(((len >> 8) & 0xFF) as u8);
         self.buffer.extend_from_slice(data);
-        self
+        Ok(self)
     }
 
     /// Transfer zero raster graphics (blank line)
@@ -309,6 +321,22 @@ impl RasterCommand {
         self
     }
 
+    /// Size of the command sequence built so far
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Take the command sequence built so far, leaving the builder empty
+    ///
+    /// Lets long jobs be sent in chunks while building the rest.
+    pub fn take(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buffer)
+    }
+
     /// Build and return the complete command sequence
     ///
     /// Consumes the builder and returns the raw command bytes
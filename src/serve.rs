@@ -0,0 +1,249 @@
+//! Print server that takes jobs over a local socket
+//!
+//! The server keeps the printer open, so jobs skip opening the device
+//! (and claiming the USB interface) each time. Clients send one job per
+//! line in JSON, and the server prints the jobs one at a time and
+//! answers each line with a [`Reply`] in JSON:
+//!
+//! ```text
+//! {"layout": "Hello + qrc:example.com", "copies": 2}
+//! {"ok":true,"printed":2}
+//! {"png_base64": "iVBORw0KGgo..."}
+//! {"ok":false,"printed":0,"error":"Cannot print due to printer errors: Cover open","status":["Cover open"]}
+//! {"shutdown": true}
+//! {"ok":true,"printed":0}
+//! ```
+
+use crate::Result;
+use crate::backend::Backend;
+use crate::error::{Error, ErrorKind};
+use crate::printable_image::PrintableImage;
+use crate::printer::{PrintOptions, Printer, check_job};
+use crate::status::Status;
+use crate::tape::TapeSpec;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+
+/// Job sent by a client
+///
+/// A job has either `layout` or `png_base64`. A job of only `shutdown`
+/// stops the server without printing.
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Job {
+    /// Layout script, split into tokens as a script file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    /// PNG image made for the loaded tape
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub png_base64: Option<String>,
+    /// Number of labels to print
+    #[serde(default = "default_copies")]
+    pub copies: u32,
+    /// Stop the server after this job
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub shutdown: bool,
+}
+
+fn default_copies() -> u32 {
+    1
+}
+
+/// Answer to a job
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Reply {
+    pub ok: bool,
+    /// Number of labels printed, also on failure
+    pub printed: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Printer conditions (e.g. "Cover open") when the job failed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub status: Vec<String>,
+}
+
+/// Render a layout script into PNG for the loaded tape
+pub type Renderer = Box<dyn FnMut(&[String]) -> Result<Vec<u8>> + Send>;
+
+/// Print server over a printer kept open
+pub struct Server<B: Backend> {
+    printer: Printer<B>,
+    tape_spec: TapeSpec,
+    print_options: PrintOptions,
+    render: Renderer,
+    /// Check the printer status before each job
+    check_status: bool,
+    /// Refuse jobs longer than this in mm
+    max_length_mm: Option<f32>,
+}
+
+impl<B: Backend> Server<B> {
+    pub fn new(
+        printer: Printer<B>,
+        tape_spec: TapeSpec,
+        print_options: PrintOptions,
+        render: Renderer,
+    ) -> Self {
+        Server {
+            printer,
+            tape_spec,
+            print_options,
+            render,
+            check_status: true,
+            max_length_mm: None,
+        }
+    }
+
+    /// Refuse jobs longer than `max_length_mm` (unlimited by default)
+    pub fn with_max_length(mut self, max_length_mm: Option<f32>) -> Self {
+        self.max_length_mm = max_length_mm;
+        self
+    }
+
+    /// Check the printer status before each job (on by default)
+    ///
+    /// Turn it off for backends without status, e.g. file:PATH.
+    pub fn with_check_status(mut self, check_status: bool) -> Self {
+        self.check_status = check_status;
+        self
+    }
+
+    /// Accept clients one at a time until a job asks to shut down
+    ///
+    /// Errors of a client connection are reported to stderr and do not
+    /// stop the server.
+    pub fn serve(&mut self, listener: &TcpListener) -> Result<()> {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("Warning: Failed to accept client: {}", e);
+                    continue;
+                }
+            };
+            match self.serve_client(stream) {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(e) => eprintln!("Warning: Client connection failed: {}", e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Answer jobs of a client; returns true on a shutdown job
+    fn serve_client(&mut self, stream: TcpStream) -> Result<bool> {
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (reply, shutdown) = match serde_json::from_str::<Job>(&line) {
+                Ok(job) => (self.run(&job), job.shutdown),
+                Err(e) => (failure(0, format!("Invalid job: {}", e), None), false),
+            };
+            writeln!(writer, "{}", serde_json::to_string(&reply)?)?;
+            writer.flush()?;
+
+            if shutdown {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Print a job and tell the result
+    pub fn run(&mut self, job: &Job) -> Reply {
+        let mut printed = 0;
+        let result = self.print_job(job, &mut printed);
+
+        match result {
+            Ok(()) => Reply {
+                ok: true,
+                printed,
+                ..Default::default()
+            },
+            Err(e) => {
+                let status = if self.check_status {
+                    self.printer.get_status().ok()
+                } else {
+                    None
+                };
+                failure(printed, e.to_string(), status)
+            }
+        }
+    }
+
+    fn print_job(&mut self, job: &Job, printed: &mut u32) -> Result<()> {
+        let png_data = match (&job.layout, &job.png_base64) {
+            (Some(layout), None) => {
+                let script = crate::layout::split_script(layout)?.concat();
+                (self.render)(&script)?
+            }
+            (None, Some(png_base64)) => base64::engine::general_purpose::STANDARD
+                .decode(png_base64.trim())
+                .map_err(|e| format!("Invalid png_base64: {}", e))?,
+            (None, None) if job.shutdown => return Ok(()),
+            (None, None) => return Err("Job requires layout or png_base64".into()),
+            (Some(_), Some(_)) => return Err("Job takes either layout or png_base64".into()),
+        };
+        let printable = PrintableImage::from_png_data(png_data, self.tape_spec.clone())?;
+        let (raster, warning) = printable.dodge_masked_pins(&self.print_options.raster)?;
+        if let Some(warning) = warning {
+            eprintln!("Warning: {}", warning);
+        }
+        let print_options = PrintOptions {
+            raster,
+            ..self.print_options.clone()
+        };
+
+        for _ in 0..job.copies {
+            let status = if self.check_status {
+                Some(self.printer.get_status()?)
+            } else {
+                None
+            };
+            for warning in check_job(&printable, 1, status.as_ref(), self.max_length_mm, false)? {
+                eprintln!("Warning: {}", warning);
+            }
+            if let Some(status) = &status {
+                self.check_tape(status)?;
+            }
+            self.printer.print(&printable, &print_options)?;
+            *printed += 1;
+        }
+        Ok(())
+    }
+
+    /// Check the tape loaded in the printer
+    fn check_tape(&self, status: &Status) -> Result<()> {
+        if status.media_width_mm() != self.tape_spec.width_mm {
+            let message = format!(
+                "{} mm tape loaded, but the server was started for {} mm tape",
+                status.media_width_mm(),
+                self.tape_spec.width_mm
+            );
+            return Err(Error::new(ErrorKind::TapeMismatch, message).into());
+        }
+        Ok(())
+    }
+}
+
+fn failure(printed: u32, error: String, status: Option<Status>) -> Reply {
+    let status = status.map_or_else(Vec::new, |status| {
+        status
+            .conditions()
+            .iter()
+            .map(|condition| condition.to_string())
+            .collect()
+    });
+    Reply {
+        ok: false,
+        printed,
+        error: Some(error),
+        status,
+    }
+}
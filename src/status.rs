@@ -1,42 +1,392 @@
+use crate::Result;
+use crate::error::{Error, ErrorKind};
+
+/// Capabilities of a printer model
+struct ModelCapabilities {
+    /// Model code in status byte 4
+    model_code: u8,
+    /// Pin pitch in DPI
+    dpi: u32,
+    /// Supports 720DPI along the tape length (high resolution mode)
+    high_resolution: bool,
+    /// Has a half cutter
+    half_cut: bool,
+    /// Prints again from the raster data kept in its buffer (no buffer
+    /// clearing when printing)
+    reuse_buffer: bool,
+}
+
+#[rustfmt::skip]
+const MODEL_CAPABILITIES: &[ModelCapabilities] = &[
+    ModelCapabilities { model_code: 0x6F, dpi: 360, high_resolution: true,  half_cut: true,  reuse_buffer: true  }, // PT-P900W
+    ModelCapabilities { model_code: 0x70, dpi: 360, high_resolution: true,  half_cut: true,  reuse_buffer: true  }, // PT-P950NW
+    ModelCapabilities { model_code: 0x71, dpi: 360, high_resolution: true,  half_cut: true,  reuse_buffer: true  }, // PT-P900
+    ModelCapabilities { model_code: 0x78, dpi: 360, high_resolution: true,  half_cut: true,  reuse_buffer: true  }, // PT-P910BT
+    ModelCapabilities { model_code: 0x5A, dpi: 180, high_resolution: false, half_cut: false, reuse_buffer: false }, // PT-9200PC
+];
+
+/// Size of a standard status packet
+pub const STATUS_SIZE: usize = 32;
+
+/// First bytes of a status packet: print head mark, size (32), 'B'
+/// (Brother) and '0' (series code)
+pub const STATUS_HEADER: [u8; 4] = [0x80, 0x20, 0x42, 0x30];
+
+/// Bytes that are not a status packet
+///
+/// Carries the first 8 bytes received to tell what came instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StatusParseError {
+    /// Not [`STATUS_SIZE`] bytes long
+    Length { len: usize, head: Vec<u8> },
+    /// Print head mark, size or signature differs from [`STATUS_HEADER`]
+    Header { head: Vec<u8> },
+}
+
+impl StatusParseError {
+    fn head_of(data: &[u8]) -> Vec<u8> {
+        data[..data.len().min(8)].to_vec()
+    }
+}
+
+fn hex_dump(data: &[u8]) -> String {
+    let hex: Vec<String> = data.iter().map(|b| format!("{:02X}", b)).collect();
+    hex.join(" ")
+}
+
+impl std::fmt::Display for StatusParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatusParseError::Length { len, head } => write!(
+                f,
+                "Invalid status length: expected {} bytes, got {} (starting {})",
+                STATUS_SIZE,
+                len,
+                hex_dump(head)
+            ),
+            StatusParseError::Header { head } => write!(
+                f,
+                "Invalid status header: expected {}, got {}",
+                hex_dump(&STATUS_HEADER),
+                hex_dump(head)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StatusParseError {}
+
+/// Jobs longer than this may brown out on batteries
+pub const LONG_JOB_MM: f32 = 200.0;
+
+/// How a status condition affects printing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Shown in status output only
+    Info,
+    /// Printing may fail; shown before printing (errors with --strict)
+    Warning,
+    /// Printing is not possible
+    Fatal,
+}
+
+/// Condition in the error information bytes of a status packet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Condition {
+    NoMedia,
+    EndOfMedia,
+    CutterJam,
+    WeakBatteries,
+    PrinterInUse,
+    HighVoltageAdapter,
+    WrongMedia,
+    ExpansionBufferFull,
+    CommunicationError,
+    CommunicationBufferFull,
+    CoverOpen,
+    Overheating,
+    TapeLeaderNotDetected,
+    SystemError,
+    /// Bit not in the command reference
+    Unknown {
+        byte: usize,
+        mask: u8,
+    },
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Condition::NoMedia => write!(f, "No media"),
+            Condition::EndOfMedia => write!(f, "End of media"),
+            Condition::CutterJam => write!(f, "Cutter jam"),
+            Condition::WeakBatteries => write!(f, "Weak batteries"),
+            Condition::PrinterInUse => write!(f, "Printer in use"),
+            Condition::HighVoltageAdapter => write!(f, "High-voltage adapter"),
+            Condition::WrongMedia => write!(f, "Wrong media"),
+            Condition::ExpansionBufferFull => write!(f, "Expansion buffer full"),
+            Condition::CommunicationError => write!(f, "Communication error"),
+            Condition::CommunicationBufferFull => write!(f, "Communication buffer full"),
+            Condition::CoverOpen => write!(f, "Cover open"),
+            Condition::Overheating => write!(f, "Overheating"),
+            Condition::TapeLeaderNotDetected => write!(f, "Tape leader mark not detected"),
+            Condition::SystemError => write!(f, "System error"),
+            Condition::Unknown { byte, mask } => {
+                write!(f, "Unknown condition (byte {} bit 0x{:02X})", byte, mask)
+            }
+        }
+    }
+}
+
+impl Condition {
+    /// Unknown bits are fatal since their effect cannot be told
+    pub fn severity(&self) -> Severity {
+        CONDITIONS
+            .iter()
+            .find(|(_, _, condition, _)| condition == self)
+            .map_or(Severity::Fatal, |(_, _, _, severity)| *severity)
+    }
+}
+
+/// Conditions by (byte, bit) of error information 1 (byte 8) and 2 (byte 9)
+#[rustfmt::skip]
+const CONDITIONS: &[(usize, u8, Condition, Severity)] = &[
+    (8, 0x01, Condition::NoMedia,                 Severity::Fatal),
+    (8, 0x02, Condition::EndOfMedia,              Severity::Fatal),
+    (8, 0x04, Condition::CutterJam,               Severity::Fatal),
+    (8, 0x08, Condition::WeakBatteries,           Severity::Warning),
+    (8, 0x10, Condition::PrinterInUse,            Severity::Fatal),
+    (8, 0x40, Condition::HighVoltageAdapter,      Severity::Info),
+    (9, 0x01, Condition::WrongMedia,              Severity::Fatal),
+    (9, 0x02, Condition::ExpansionBufferFull,     Severity::Fatal),
+    (9, 0x04, Condition::CommunicationError,      Severity::Fatal),
+    (9, 0x08, Condition::CommunicationBufferFull, Severity::Fatal),
+    (9, 0x10, Condition::CoverOpen,               Severity::Fatal),
+    (9, 0x20, Condition::Overheating,             Severity::Fatal),
+    (9, 0x40, Condition::TapeLeaderNotDetected,   Severity::Fatal),
+    (9, 0x80, Condition::SystemError,             Severity::Fatal),
+];
+
+/// Status packet of the printer
+///
+/// Debug output shows the raw bytes:
+///
+/// ```
+/// use ptouch::status::Status;
+///
+/// assert_eq!(format!("{:?}", Status::new(&[0x80, 0x20])), "Status { raw_data: [128, 32] }");
+/// ```
+#[derive(Debug)]
 pub struct Status {
-    raw_data: [u8; 32],
+    raw_data: Vec<u8>,
+}
+
+/// Validate a status packet read from a printer
+impl TryFrom<&[u8]> for Status {
+    type Error = StatusParseError;
+
+    fn try_from(data: &[u8]) -> std::result::Result<Self, Self::Error> {
+        let head = StatusParseError::head_of(data);
+        if data.len() != STATUS_SIZE {
+            return Err(StatusParseError::Length {
+                len: data.len(),
+                head,
+            });
+        }
+        if !data.starts_with(&STATUS_HEADER) {
+            return Err(StatusParseError::Header { head });
+        }
+        Ok(Status::new(data))
+    }
 }
 
 impl Status {
-    pub fn new(data: [u8; 32]) -> Self {
-        Status { raw_data: data }
+    /// Create Status from raw bytes without validation
+    ///
+    /// Packets shorter or longer than [`STATUS_SIZE`] are accepted as is.
+    /// Accessors return defaults for missing bytes instead of panicking.
+    /// Bytes from a printer go through `Status::try_from` instead.
+    pub fn new(data: &[u8]) -> Self {
+        Status {
+            raw_data: data.to_vec(),
+        }
+    }
+
+    /// Parse a status packet from hex string
+    ///
+    /// Whitespace between bytes is ignored, so the hex dump shown by
+    /// `ptouch status -v` can be pasted as is.
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let digits: String = hex.chars().filter(|c| !c.is_whitespace()).collect();
+
+        if !digits.is_ascii() || digits.len() != 64 {
+            return Err(format!(
+                "Invalid status length: expected 32 bytes (64 hex digits), got {} digits",
+                digits.chars().count()
+            )
+            .into());
+        }
+
+        let mut data = [0u8; STATUS_SIZE];
+        for (i, byte) in data.iter_mut().enumerate() {
+            let pair = &digits[i * 2..i * 2 + 2];
+            *byte = u8::from_str_radix(pair, 16)
+                .map_err(|_| format!("Invalid hex byte '{}' at position {}", pair, i))?;
+        }
+
+        Ok(Status::new(&data))
     }
 
-    pub fn raw_data(&self) -> &[u8; 32] {
+    pub fn raw_data(&self) -> &[u8] {
         &self.raw_data
     }
 
+    /// Check if the packet has all bytes of a standard status packet
+    pub fn is_complete(&self) -> bool {
+        self.raw_data.len() >= STATUS_SIZE
+    }
+
+    /// Byte at `index`, or None for short packets
+    pub fn byte(&self, index: usize) -> Option<u8> {
+        self.raw_data.get(index).copied()
+    }
+
+    /// Short packets are treated as errors since "no error" cannot be confirmed
+    ///
+    /// Only fatal conditions are errors; see [`Condition::severity`].
     pub fn has_errors(&self) -> bool {
-        self.error_info1() != 0x00 || self.error_info2() != 0x00
+        !self.is_complete()
+            || self
+                .conditions()
+                .iter()
+                .any(|condition| condition.severity() == Severity::Fatal)
+    }
+
+    /// Conditions set in the error information bytes
+    pub fn conditions(&self) -> Vec<Condition> {
+        let mut conditions = Vec::new();
+        for byte in [8, 9] {
+            let value = self.byte(byte).unwrap_or(0);
+            for bit in 0..8 {
+                let mask = 1 << bit;
+                if value & mask == 0 {
+                    continue;
+                }
+                let condition = CONDITIONS
+                    .iter()
+                    .find(|(b, m, _, _)| *b == byte && *m == mask)
+                    .map_or(Condition::Unknown { byte, mask }, |(_, _, condition, _)| {
+                        *condition
+                    });
+                conditions.push(condition);
+            }
+        }
+        conditions
+    }
+
+    /// Running on the AC adapter rather than batteries
+    pub fn on_adapter(&self) -> bool {
+        self.conditions().contains(&Condition::HighVoltageAdapter)
+    }
+
+    /// Fail with the fatal conditions if the printer cannot print
+    pub fn check_errors(&self) -> Result<()> {
+        if !self.has_errors() {
+            return Ok(());
+        }
+
+        let mut errors: Vec<String> = self
+            .conditions()
+            .iter()
+            .filter(|condition| condition.severity() == Severity::Fatal)
+            .map(|condition| condition.to_string())
+            .collect();
+        if !self.is_complete() {
+            errors.push(format!(
+                "Incomplete status packet ({} of {} bytes)",
+                self.raw_data.len(),
+                STATUS_SIZE
+            ));
+        }
+        Err(Error::new(
+            ErrorKind::Printer,
+            format!("Cannot print due to printer errors: {}", errors.join(", ")),
+        )
+        .into())
+    }
+
+    /// Check conditions before printing a job of `length_mm`
+    ///
+    /// Fails on fatal conditions, and also on warnings if `strict`.
+    /// Returns the warnings otherwise.
+    pub fn check_print(&self, length_mm: f32, strict: bool) -> Result<Vec<String>> {
+        self.check_errors()?;
+
+        let mut warnings: Vec<String> = self
+            .conditions()
+            .iter()
+            .filter(|condition| condition.severity() == Severity::Warning)
+            .map(|condition| condition.to_string())
+            .collect();
+        if length_mm > LONG_JOB_MM && !self.on_adapter() {
+            warnings.push(format!(
+                "{:.0} mm job without the AC adapter may stop midway on batteries",
+                length_mm
+            ));
+        }
+
+        if strict && !warnings.is_empty() {
+            return Err(Error::new(
+                ErrorKind::Printer,
+                format!("{} (printing stopped by --strict)", warnings.join(", ")),
+            )
+            .into());
+        }
+        Ok(warnings)
     }
 
     pub fn error_info1(&self) -> u8 {
-        self.raw_data[8]
+        self.byte(8).unwrap_or(0)
     }
 
     pub fn error_info2(&self) -> u8 {
-        self.raw_data[9]
+        self.byte(9).unwrap_or(0)
     }
 
     pub fn media_width_mm(&self) -> u8 {
-        self.raw_data[10]
+        self.byte(10).unwrap_or(0)
     }
 
     pub fn media_type(&self) -> u8 {
-        self.raw_data[11]
+        self.byte(11).unwrap_or(0)
+    }
+
+    pub fn model_code(&self) -> u8 {
+        self.byte(4).unwrap_or(0)
+    }
+
+    fn capabilities(&self) -> Option<&'static ModelCapabilities> {
+        MODEL_CAPABILITIES
+            .iter()
+            .find(|c| c.model_code == self.model_code())
     }
 
     pub fn printer_dpi(&self) -> u32 {
-        match self.raw_data[4] {
-            0x6F | 0x70 | 0x71 | 0x78 => 360, // PT-P900W, PT-P950NW, PT-P900, PT-P910BT
-            0x5A => 180,                      // PT-9200PC
-            _ => 360,
-        }
+        self.capabilities().map_or(360, |c| c.dpi)
+    }
+
+    pub fn supports_high_resolution(&self) -> bool {
+        self.capabilities().is_some_and(|c| c.high_resolution)
+    }
+
+    /// Unknown models are assumed to have a half cutter like PT-P900 series
+    pub fn supports_half_cut(&self) -> bool {
+        self.capabilities().is_none_or(|c| c.half_cut)
+    }
+
+    /// Unknown models are assumed to clear the buffer after printing
+    pub fn supports_buffer_reuse(&self) -> bool {
+        self.capabilities().is_some_and(|c| c.reuse_buffer)
     }
 
     pub fn print_status_info(&self, verbose: bool) {
@@ -54,8 +404,8 @@ impl Status {
             println!("Status: OK - No errors");
         } else {
             println!("Status: ERROR");
-            self.print_error_details();
         }
+        self.print_error_details();
 
         println!("Media width: {} mm", self.media_width_mm());
         println!("Media type: 0x{:02X}", self.media_type());
@@ -66,51 +416,19 @@ impl Status {
     }
 
     fn print_error_details(&self) {
-        let error_info1 = self.error_info1();
-        let error_info2 = self.error_info2();
-
-        if error_info1 & 0x01 != 0 {
-            println!("  - No media");
-        }
-        if error_info1 & 0x02 != 0 {
-            println!("  - End of media");
-        }
-        if error_info1 & 0x04 != 0 {
-            println!("  - Cutter jam");
+        if !self.is_complete() {
+            println!(
+                "  - Incomplete status packet ({} of {} bytes)",
+                self.raw_data.len(),
+                STATUS_SIZE
+            );
         }
-        if error_info1 & 0x08 != 0 {
-            println!("  - Weak batteries");
-        }
-        if error_info1 & 0x10 != 0 {
-            println!("  - Printer in use");
-        }
-        if error_info1 & 0x40 != 0 {
-            println!("  - High-voltage adapter");
-        }
-
-        if error_info2 & 0x01 != 0 {
-            println!("  - Wrong media");
-        }
-        if error_info2 & 0x02 != 0 {
-            println!("  - Expansion buffer full");
-        }
-        if error_info2 & 0x04 != 0 {
-            println!("  - Communication error");
-        }
-        if error_info2 & 0x08 != 0 {
-            println!("  - Communication buffer full");
-        }
-        if error_info2 & 0x10 != 0 {
-            println!("  - Cover open");
-        }
-        if error_info2 & 0x20 != 0 {
-            println!("  - Overheating");
-        }
-        if error_info2 & 0x40 != 0 {
-            println!("  - Tape leader mark not detected");
-        }
-        if error_info2 & 0x80 != 0 {
-            println!("  - System error");
+        for condition in self.conditions() {
+            match condition.severity() {
+                Severity::Fatal => println!("  - {}", condition),
+                Severity::Warning => println!("  - {} (warning)", condition),
+                Severity::Info => println!("  - {} (info)", condition),
+            }
         }
     }
 
@@ -0,0 +1,91 @@
+//! Cache of the last successful printer status per host
+//!
+//! Querying status over SNMP or USB takes 0.5-2 seconds, so repeated
+//! prints to the same printer can reuse a recent result. Each host has
+//! a small text file: the time of the query in UNIX seconds and the
+//! status packet in hex.
+
+use crate::Result;
+use crate::status::{STATUS_SIZE, Status};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub struct StatusCache {
+    dir: PathBuf,
+}
+
+impl StatusCache {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        StatusCache {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// `$XDG_STATE_HOME/ptouch`, or `~/.local/state/ptouch` if it is not set
+    pub fn default_dir() -> Option<PathBuf> {
+        let state_home = std::env::var_os("XDG_STATE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::home_dir().map(|home| home.join(".local/state")))?;
+        Some(state_home.join("ptouch"))
+    }
+
+    /// Get the cached status of `host` with its age if younger than `max_age`
+    pub fn load(&self, host: &str, max_age: Duration) -> Option<(Status, Duration)> {
+        let content = fs::read_to_string(self.path(host)).ok()?;
+        let (time, hex) = content.split_once('\n')?;
+
+        let queried_at = UNIX_EPOCH + Duration::from_secs(time.trim().parse().ok()?);
+        let age = SystemTime::now().duration_since(queried_at).ok()?;
+        if age >= max_age {
+            return None;
+        }
+
+        let status = Status::from_hex(hex).ok()?;
+        Some((status, age))
+    }
+
+    /// Save status of `host` queried just now
+    ///
+    /// Only complete statuses without errors are worth reusing;
+    /// others remove the entry instead.
+    pub fn store(&self, host: &str, status: &Status) -> Result<()> {
+        if !status.is_complete() || status.has_errors() {
+            return self.remove(host);
+        }
+
+        let time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let hex: Vec<String> = status.raw_data()[..STATUS_SIZE]
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect();
+
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(host), format!("{}\n{}\n", time, hex.join(" ")))?;
+        Ok(())
+    }
+
+    /// Remove the cached status of `host` if any
+    pub fn remove(&self, host: &str) -> Result<()> {
+        match fs::remove_file(self.path(host)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn path(&self, host: &str) -> PathBuf {
+        // Host specifiers may contain ':' (port, USB vid:pid) or '/' (file:PATH)
+        let name: String = host
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("{}.status", name))
+    }
+}
@@ -1,3 +1,5 @@
+use crate::Result;
+
 #[derive(Clone, Copy, Debug)]
 pub enum Tape {
     TZe3H,
@@ -15,6 +17,25 @@ pub enum Tape {
     TZe24L,
 }
 
+impl Tape {
+    /// All tapes, from narrowest to widest at 360DPI then at 180DPI
+    pub const ALL: [Tape; 13] = [
+        Tape::TZe3H,
+        Tape::TZe6H,
+        Tape::TZe9H,
+        Tape::TZe12H,
+        Tape::TZe18H,
+        Tape::TZe24H,
+        Tape::TZe36H,
+        Tape::TZe3L,
+        Tape::TZe6L,
+        Tape::TZe9L,
+        Tape::TZe12L,
+        Tape::TZe18L,
+        Tape::TZe24L,
+    ];
+}
+
 impl std::fmt::Display for Tape {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -36,7 +57,7 @@ impl std::fmt::Display for Tape {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct TapeSpec {
     pub name: Tape,      // Tape name
     pub width_mm: u8,    // Total tape width in mm
@@ -45,6 +66,7 @@ pub struct TapeSpec {
     pub total_pins: u32, // Total printer pins
     pub right_pins: u32, // Margin pin count for raster
     pub dpi: u32,        // Printer's DPI
+    pub feed_dpi: u32,   // DPI along the tape length (raster lines per inch)
 }
 
 impl TapeSpec {
@@ -74,13 +96,63 @@ impl TapeSpec {
             total_pins,
             right_pins,
             dpi,
+            feed_dpi: dpi,
+        }
+    }
+
+    /// Override the DPI used for mm to dots conversion
+    pub fn with_dpi(mut self, dpi: u32) -> Result<Self> {
+        if dpi == 0 {
+            return Err("DPI must be positive".into());
         }
+        self.dpi = dpi;
+        self.feed_dpi = dpi;
+        Ok(self)
+    }
+
+    /// Double the raster line density along the tape length (720DPI)
+    ///
+    /// Pin pitch across the tape stays at 360DPI.
+    pub fn with_high_resolution(mut self) -> Result<Self> {
+        if self.dpi != 360 {
+            return Err(format!(
+                "High resolution requires 360DPI tape, but {} is {}DPI",
+                self.name, self.dpi
+            )
+            .into());
+        }
+        self.feed_dpi = self.dpi * 2;
+        Ok(self)
+    }
+
+    /// Check if raster lines are denser than pins (720DPI mode)
+    pub fn is_high_resolution(&self) -> bool {
+        self.feed_dpi > self.dpi
+    }
+
+    /// Tape width as written on the cassette, such as "3.5" or "12"
+    pub fn width_name(&self) -> String {
+        match self.name {
+            // 3.5mm tape is rounded to 4 in width_mm
+            Tape::TZe3H | Tape::TZe3L => "3.5".to_string(),
+            _ => self.width_mm.to_string(),
+        }
+    }
+
+    /// Non-printable width in dots on each side of the printable area
+    pub fn margin_dots(&self) -> u32 {
+        (self.width_dots - self.inner_dots) / 2
     }
 
     pub fn mm_to_dots(&self, mm: f32) -> u32 {
         ((mm * self.dpi as f32) / 25.4).round() as u32
     }
 
+    /// Convert length along the tape into raster lines
+    pub fn mm_to_feed_dots(&self, mm: f32) -> u32 {
+        ((mm * self.feed_dpi as f32) / 25.4).round() as u32
+    }
+
     pub fn from_width_dots_and_dpi(dots: u32, dpi: u32) -> Option<Self> {
         match (dots, dpi) {
             (48, 360) => Some(Self::new(Tape::TZe3H)),
@@ -101,6 +173,15 @@ impl TapeSpec {
         }
     }
 
+    /// Narrowest tape of `dpi` whose printable area fits `height` dots
+    pub fn fit_height(height: f32, dpi: u32) -> Option<Self> {
+        Tape::ALL
+            .into_iter()
+            .map(Self::new)
+            .filter(|spec| spec.dpi == dpi)
+            .find(|spec| height <= spec.inner_dots as f32)
+    }
+
     pub fn from_width_mm_and_dpi(mm: u8, dpi: u32) -> Option<Self> {
         match (mm, dpi) {
             (4, 360) => Some(Self::new(Tape::TZe3H)),
@@ -121,3 +202,93 @@ impl TapeSpec {
         }
     }
 }
+
+/// Tape width, DPI and the width in dots
+///
+/// ```
+/// use ptouch::tape::{Tape, TapeSpec};
+///
+/// assert_eq!(TapeSpec::new(Tape::TZe12H).to_string(), "12mm/360dpi, 170 dots");
+/// assert_eq!(TapeSpec::new(Tape::TZe3L).to_string(), "3.5mm/180dpi, 24 dots");
+/// ```
+impl std::fmt::Display for TapeSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}mm/{}dpi, {} dots",
+            self.width_name(),
+            self.dpi,
+            self.width_dots
+        )
+    }
+}
+
+/// Why a PNG does not fit the loaded tape
+#[derive(Clone, Debug)]
+pub enum TapeMismatch {
+    /// PNG is made for the same tape width at another DPI
+    Dpi(TapeSpec),
+    /// PNG is made for another tape width at the printer DPI
+    Width(TapeSpec),
+    /// PNG is made for another tape width at another DPI
+    WidthAndDpi(TapeSpec),
+    /// PNG height matches no tape at any DPI
+    Unsupported,
+}
+
+/// Diagnose a PNG of `png_height` pixels against the `printer` tape
+///
+/// Some heights are valid at both DPIs (170 dots is 12mm at 360DPI
+/// and 24mm at 180DPI). The PNG fits if any tape of that height is the
+/// printer tape; otherwise the closest tape is assumed: the same width at
+/// another DPI, then another width at the same DPI, and then the 360DPI
+/// tape as it is the default resolution. Returns `None` if the PNG fits
+/// the printer tape.
+///
+/// ```
+/// use ptouch::tape::{diagnose_png_height, Tape, TapeMismatch, TapeSpec};
+///
+/// let printer = TapeSpec::new(Tape::TZe12L);
+/// assert!(diagnose_png_height(84, &printer).is_none());
+/// assert!(matches!(
+///     diagnose_png_height(170, &printer),
+///     Some(TapeMismatch::Dpi(png)) if png.dpi == 360
+/// ));
+///
+/// // 170 dots is also 12mm at 360DPI, but the same width comes first
+/// let printer = TapeSpec::new(Tape::TZe24H);
+/// assert!(matches!(
+///     diagnose_png_height(170, &printer),
+///     Some(TapeMismatch::Dpi(png)) if png.dpi == 180 && png.width_mm == 24
+/// ));
+/// ```
+pub fn diagnose_png_height(png_height: u32, printer: &TapeSpec) -> Option<TapeMismatch> {
+    let candidates: Vec<TapeSpec> = Tape::ALL
+        .into_iter()
+        .map(TapeSpec::new)
+        .filter(|spec| spec.width_dots == png_height)
+        .collect();
+    let same_width = |spec: &TapeSpec| spec.width_mm == printer.width_mm;
+    let same_dpi = |spec: &TapeSpec| spec.dpi == printer.dpi;
+
+    if candidates
+        .iter()
+        .any(|spec| same_width(spec) && same_dpi(spec))
+    {
+        return None;
+    }
+    // Tape::ALL lists 360DPI tapes first, which wins ties
+    let rank = |spec: &TapeSpec| match (same_width(spec), same_dpi(spec)) {
+        (true, _) => 0,
+        (false, true) => 1,
+        (false, false) => 2,
+    };
+    let Some(png) = candidates.into_iter().min_by_key(rank) else {
+        return Some(TapeMismatch::Unsupported);
+    };
+    Some(match (same_width(&png), same_dpi(&png)) {
+        (true, _) => TapeMismatch::Dpi(png),
+        (false, true) => TapeMismatch::Width(png),
+        (false, false) => TapeMismatch::WidthAndDpi(png),
+    })
+}
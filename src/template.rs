@@ -0,0 +1,194 @@
+//! Placeholder substitution for layout scripts
+//!
+//! Placeholders are expanded in one pass in the following forms:
+//! - `{n}`: label number. A width can be given like Rust format specs:
+//!   `{n:3}` pads with spaces and `{n:03}` pads with zeros.
+//! - `{date}`, `{time}`, `{datetime}`: date and time in ISO 8601 style.
+//!   A strftime-style format can be given like `{date:%Y/%m/%d}`.
+//! - `{{` and `}}`: literal `{` and `}`
+//!
+//! Braces of unknown names (e.g. `{name}`) or placeholders without
+//! a value are kept as is.
+
+use crate::Result;
+use chrono::NaiveDateTime;
+use chrono::format::StrftimeItems;
+
+/// Label numbers for batch printing
+#[derive(Clone, Copy, Debug)]
+pub struct Counter {
+    pub start: i64,
+    pub step: i64,
+    pub count: u32,
+}
+
+impl Default for Counter {
+    fn default() -> Self {
+        Counter {
+            start: 1,
+            step: 1,
+            count: 1,
+        }
+    }
+}
+
+impl Counter {
+    /// Iterate over label numbers: start, start + step, ...
+    pub fn values(&self) -> impl Iterator<Item = i64> + use<> {
+        let Counter { start, step, count } = *self;
+        (0..count as i64).map(move |i| start + i * step)
+    }
+}
+
+/// Values of placeholders
+///
+/// The clock is given by the caller so that the output is reproducible.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Placeholders {
+    /// Label number for `{n}`
+    pub counter: Option<i64>,
+    /// Date and time for `{date}`, `{time}` and `{datetime}`
+    pub datetime: Option<NaiveDateTime>,
+}
+
+const NAMES: &[&str] = &["n", "date", "time", "datetime"];
+
+/// Expand placeholders in `template`
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use ptouch::template::{Placeholders, substitute};
+///
+/// let values = Placeholders {
+///     counter: Some(7),
+///     datetime: NaiveDate::from_ymd_opt(2025, 9, 1).unwrap().and_hms_opt(12, 34, 0),
+/// };
+/// assert_eq!(substitute("PORT-{n:03}", &values).unwrap(), "PORT-007");
+/// assert_eq!(substitute("{date} {time}", &values).unwrap(), "2025-09-01 12:34");
+/// assert_eq!(substitute("{date:%m/%d}", &values).unwrap(), "09/01");
+/// assert_eq!(substitute("{{n}} {name}", &values).unwrap(), "{n} {name}");
+/// ```
+pub fn substitute(template: &str, values: &Placeholders) -> Result<String> {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(pos) = rest.find(['{', '}']) {
+        result.push_str(&rest[..pos]);
+        let brace = &rest[pos..pos + 1];
+        let after = &rest[pos + 1..];
+
+        // Escaped "{{" or "}}"
+        if after.starts_with(brace) {
+            result.push_str(brace);
+            rest = &after[1..];
+            continue;
+        }
+        if brace == "}" {
+            result.push('}');
+            rest = after;
+            continue;
+        }
+
+        let Some(end) = after.find('}') else {
+            if NAMES
+                .iter()
+                .any(|name| after.starts_with(&format!("{}:", name)))
+            {
+                return Err(format!("Unclosed placeholder in '{}'", template).into());
+            }
+            result.push('{');
+            rest = after;
+            continue;
+        };
+
+        let (name, spec) = match after[..end].split_once(':') {
+            Some((name, spec)) => (name, Some(spec)),
+            None => (&after[..end], None),
+        };
+        match expand(name, spec, values)? {
+            Some(value) => result.push_str(&value),
+            None => result.push_str(&rest[pos..pos + end + 2]),
+        }
+        rest = &after[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Expand placeholders in every token of a layout script
+pub fn substitute_script(script: &[String], values: &Placeholders) -> Result<Vec<String>> {
+    script.iter().map(|s| substitute(s, values)).collect()
+}
+
+/// Replace `{n}` placeholders in `template` with `n`
+///
+/// ```
+/// use ptouch::template::substitute_counter;
+///
+/// assert_eq!(substitute_counter("PORT-{n:03}", 7).unwrap(), "PORT-007");
+/// assert_eq!(substitute_counter("[{n:3}]", 7).unwrap(), "[  7]");
+/// assert_eq!(substitute_counter("{x}-{n}", 12).unwrap(), "{x}-12");
+/// ```
+pub fn substitute_counter(template: &str, n: i64) -> Result<String> {
+    let values = Placeholders {
+        counter: Some(n),
+        ..Default::default()
+    };
+    substitute(template, &values)
+}
+
+/// Parse date given by user: "YYYY-MM-DD", "YYYY-MM-DD HH:MM[:SS]" or "YYYY-MM-DDTHH:MM[:SS]"
+pub fn parse_datetime(s: &str) -> Result<NaiveDateTime> {
+    for format in [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(s, format) {
+            return Ok(datetime);
+        }
+    }
+    chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map(|date| date.and_time(chrono::NaiveTime::MIN))
+        .map_err(|_| format!("Invalid date: '{}' (expected YYYY-MM-DD[THH:MM[:SS]])", s).into())
+}
+
+/// Expand a placeholder, or None if it is unknown or has no value
+fn expand(name: &str, spec: Option<&str>, values: &Placeholders) -> Result<Option<String>> {
+    let default_format = match name {
+        "n" => return values.counter.map(|n| format_counter(n, spec)).transpose(),
+        "date" => "%Y-%m-%d",
+        "time" => "%H:%M",
+        "datetime" => "%Y-%m-%d %H:%M",
+        _ => return Ok(None),
+    };
+
+    let Some(datetime) = values.datetime else {
+        return Ok(None);
+    };
+    let format = spec.unwrap_or(default_format);
+    let items = StrftimeItems::new(format)
+        .parse()
+        .map_err(|_| format!("Invalid date format: '{{{}:{}}}'", name, format))?;
+    Ok(Some(
+        datetime.format_with_items(items.into_iter()).to_string(),
+    ))
+}
+
+fn format_counter(n: i64, spec: Option<&str>) -> Result<String> {
+    let Some(spec) = spec else {
+        return Ok(n.to_string());
+    };
+    let width: usize = spec
+        .parse()
+        .map_err(|_| format!("Invalid counter format spec: '{{n:{}}}'", spec))?;
+
+    if spec.starts_with('0') {
+        Ok(format!("{:0width$}", n, width = width))
+    } else {
+        Ok(format!("{:width$}", n, width = width))
+    }
+}
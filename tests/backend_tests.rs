@@ -0,0 +1,218 @@
+use ptouch::backend::{
+    self, Backend, BulkRead, DeviceInfo, FileBackend, MalformedStatus, StatusAccumulator,
+    UsbInterfaceInfo, describe_usb_interfaces, parse_status_text, read_status,
+};
+use ptouch::status::STATUS_HEADER;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("ptouch-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn test_file_backend_forwards_exact_bytes() {
+    let path = temp_path("raw.bin");
+    // Includes bytes that must not be altered (NUL, ESC, CR/LF, 0xFF)
+    let data: Vec<u8> = vec![0x00, 0x1B, 0x40, 0x0D, 0x0A, 0xFF, 0x1B, 0x69, 0x53];
+
+    let mut backend = FileBackend::new(&path).unwrap();
+    backend.send_command(&data[..4]).unwrap();
+    backend.send_command(&data[4..]).unwrap();
+    drop(backend);
+
+    assert_eq!(std::fs::read(&path).unwrap(), data);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_file_backend_from_host() {
+    let path = temp_path("host.bin");
+    let host = format!("file:{}", path.display());
+
+    let mut backend = backend::from_host(&host).unwrap();
+    backend.send_command(b"\x1B\x40").unwrap();
+    assert!(backend.get_status().is_err());
+    drop(backend);
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"\x1B\x40");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_device_info_ptouch() {
+    let info = DeviceInfo::from_snmp(
+        Some(b"Brother NC-8300w, Firmware Ver.1.05  (14.07.04),MID 8C5-J30,FID 2"),
+        Some(b"MFG:Brother;CMD:PT-CBP;MDL:PT-P900W;CLS:PRINTER;CID:Brother PT-P900W;"),
+    );
+    assert_eq!(info.manufacturer(), Some("Brother"));
+    assert_eq!(info.model(), Some("PT-P900W"));
+    assert!(info.verify("192.168.1.100").is_ok());
+
+    // Brother without device ID is given the benefit of the doubt
+    let info = DeviceInfo::from_snmp(Some(b"Brother NC-8300w, Firmware Ver.1.05"), None);
+    assert_eq!(info.model(), None);
+    assert!(info.verify("192.168.1.100").is_ok());
+}
+
+#[test]
+fn test_device_info_other_printers() {
+    // Laser printer of another vendor
+    let info = DeviceInfo::from_snmp(
+        Some(b"HP ETHERNET MULTI-ENVIRONMENT,ROM none,JETDIRECT,JD153"),
+        None,
+    );
+    let error = info.verify("192.168.1.5").err().unwrap().to_string();
+    assert!(error.contains("192.168.1.5"), "{}", error);
+    assert!(error.contains("HP ETHERNET MULTI-ENVIRONMENT"), "{}", error);
+
+    // Brother, but not a P-Touch
+    let info = DeviceInfo::from_snmp(
+        Some(b"Brother NC-340h, Firmware Ver.1.20"),
+        Some(b"MFG:Brother;CMD:PJL,PCL,PCLXL;MDL:HL-L2350DW series;CLS:PRINTER;"),
+    );
+    let error = info.verify("192.168.1.6").err().unwrap().to_string();
+    assert!(error.contains("Brother HL-L2350DW series"), "{}", error);
+}
+
+#[test]
+fn test_device_info_no_snmp() {
+    // e.g. a web server that accepts TCP but has no SNMP agent
+    let info = DeviceInfo::from_snmp(None, Some(b"  "));
+    assert!(!info.is_brother());
+    let error = info.verify("example.com").err().unwrap().to_string();
+    assert!(error.contains("no SNMP device information"), "{}", error);
+}
+
+#[test]
+fn test_parse_status_text() {
+    assert_eq!(parse_status_text(b"READY"), Some("READY".to_string()));
+    assert_eq!(
+        parse_status_text(b"COVER OPEN      \0\0"),
+        Some("COVER OPEN".to_string())
+    );
+    assert_eq!(parse_status_text(b"  \0"), None);
+    assert_eq!(parse_status_text(b""), None);
+}
+
+#[test]
+fn test_describe_usb_interfaces() {
+    // Composite device without a printer interface: mass storage and a
+    // vendor specific interface without endpoints
+    let interfaces = [
+        UsbInterfaceInfo {
+            number: 0,
+            setting: 0,
+            class_code: 8,
+            endpoints: vec![0x81, 0x02],
+        },
+        UsbInterfaceInfo {
+            number: 1,
+            setting: 0,
+            class_code: 255,
+            endpoints: vec![],
+        },
+    ];
+    assert_eq!(
+        describe_usb_interfaces(&interfaces),
+        "interface 0.0 class 8 (mass storage): 0x81 IN, 0x02 OUT; \
+         interface 1.0 class 255 (vendor specific): no endpoints"
+    );
+    assert_eq!(describe_usb_interfaces(&[]), "no interfaces");
+
+    let printer = UsbInterfaceInfo {
+        class_code: 7,
+        endpoints: vec![0x02],
+        ..Default::default()
+    };
+    assert_eq!(
+        printer.to_string(),
+        "interface 0.0 class 7 (printer): 0x02 OUT"
+    );
+}
+
+/// 32-byte status packet of a PT-P900 (model code 0x71) with 12mm tape
+fn status_packet() -> Vec<u8> {
+    let mut data = vec![0u8; 32];
+    data[..4].copy_from_slice(&STATUS_HEADER);
+    data[4] = 0x71;
+    data[10] = 12;
+    data
+}
+
+/// Replays reads; times out once they run out
+struct ScriptedReads(VecDeque<rusb::Result<Vec<u8>>>);
+
+impl ScriptedReads {
+    fn new(reads: Vec<rusb::Result<Vec<u8>>>) -> Self {
+        ScriptedReads(reads.into())
+    }
+}
+
+impl BulkRead for ScriptedReads {
+    fn read_bulk(&mut self, buf: &mut [u8]) -> rusb::Result<usize> {
+        let data = self.0.pop_front().unwrap_or(Err(rusb::Error::Timeout))?;
+        buf[..data.len()].copy_from_slice(&data);
+        Ok(data.len())
+    }
+}
+
+#[test]
+fn test_status_accumulates_chunks() {
+    let packet = status_packet();
+    let mut accumulator = StatusAccumulator::new();
+    assert!(accumulator.push(&packet[..2]).unwrap().is_none());
+    assert!(accumulator.push(&packet[2..20]).unwrap().is_none());
+    let status = accumulator.push(&packet[20..]).unwrap().unwrap();
+    assert_eq!(status.raw_data(), packet.as_slice());
+
+    // Chunks split anywhere, with reads of nothing and timeouts between
+    let mut reader = ScriptedReads::new(vec![
+        Ok(packet[..5].to_vec()),
+        Ok(vec![]),
+        Err(rusb::Error::Timeout),
+        Ok(packet[5..].to_vec()),
+    ]);
+    let status = read_status(&mut reader, Duration::from_secs(3)).unwrap();
+    assert_eq!(status.model_code(), 0x71);
+    assert_eq!(status.media_width_mm(), 12);
+}
+
+#[test]
+fn test_status_resyncs_after_garbage() {
+    let packet = status_packet();
+    let mut garbage = vec![0x00, 0x80, 0x20, 0xFF, 0x42];
+    garbage.extend_from_slice(&packet[..3]);
+    let mut reader = ScriptedReads::new(vec![Ok(garbage), Ok(packet[3..].to_vec())]);
+    let status = read_status(&mut reader, Duration::from_secs(3)).unwrap();
+    assert_eq!(status.raw_data(), packet.as_slice());
+}
+
+#[test]
+fn test_malformed_status_carries_raw_bytes() {
+    // Header without the rest of the packet
+    let mut reader = ScriptedReads::new(vec![Ok(vec![0x80, 0x20, 0x42, 0x30, 0x71])]);
+    let error = read_status(&mut reader, Duration::from_millis(300))
+        .err()
+        .unwrap();
+    let malformed = error.downcast_ref::<MalformedStatus>().unwrap();
+    assert_eq!(malformed.raw, [0x80, 0x20, 0x42, 0x30, 0x71]);
+    assert_eq!(
+        error.to_string(),
+        "Malformed status response (5 bytes): 80 20 42 30 71"
+    );
+
+    // Endless data without a header
+    let mut accumulator = StatusAccumulator::new();
+    let error = (0..10)
+        .find_map(|_| accumulator.push(&[0xAA; 32]).err())
+        .unwrap();
+    assert!(error.downcast_ref::<MalformedStatus>().is_some());
+
+    // No response at all stays a timeout
+    let mut reader = ScriptedReads::new(vec![]);
+    let error = read_status(&mut reader, Duration::from_millis(300))
+        .err()
+        .unwrap();
+    assert!(error.downcast_ref::<MalformedStatus>().is_none());
+}
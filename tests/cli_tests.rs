@@ -0,0 +1,353 @@
+//! Exit status of the command for kinds of failures
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn ptouch(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ptouch"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Check the exit status and the message on stderr
+fn assert_failure(output: &Output, code: i32, message: &str) {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(code), "{}", stderr);
+    assert!(stderr.contains(message), "{}", stderr);
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("ptouch-cli-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn test_usage_error() {
+    // Rejected by clap
+    let output = ptouch(&["image", "--no-such-option"]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = ptouch(&["batch", "box:10x10"]);
+    assert_failure(&output, 2, "Error: Batch requires --host or --output");
+}
+
+#[test]
+fn test_render_error() {
+    let path = temp_path("render.png");
+    let output = ptouch(&["image", "-o", path.to_str().unwrap(), "[", "box:10x10"]);
+    assert_failure(&output, 3, "Error: Expected ']'");
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_communication_error() {
+    let output = ptouch(&["text", "-H", "file:/nonexistent/ptouch.bin", "Hello"]);
+    assert_failure(&output, 4, "Error: ");
+
+    // No status is an error, not a message
+    let path = temp_path("status.bin");
+    let output = ptouch(&["status", "-H", &format!("file:{}", path.display())]);
+    assert_failure(
+        &output,
+        4,
+        "Error: Status is not available for file backend",
+    );
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn test_tape_mismatch() {
+    // QR code label for 12mm tape
+    let png_path = temp_path("12mm.png");
+    let output = ptouch(&["image", "-o", png_path.to_str().unwrap(), "qrc:PORT-01"]);
+    assert!(output.status.success());
+
+    // 170 dots is also 24mm at 180DPI, so the 18mm tape makes it a width mismatch
+    let out_path = temp_path("18mm.bin");
+    let output = ptouch(&[
+        "print",
+        "-H",
+        &format!("file:{}", out_path.display()),
+        "--skip-status",
+        "-t",
+        "18",
+        png_path.to_str().unwrap(),
+    ]);
+    assert_failure(&output, 6, "Tape specification mismatch");
+
+    std::fs::remove_file(png_path).unwrap();
+    let _ = std::fs::remove_file(out_path);
+}
+
+#[test]
+fn test_resolution_mismatch() {
+    // 12mm label at 360DPI is printed on a 180DPI printer with 12mm tape
+    let png_path = temp_path("12mm-360dpi.png");
+    let output = ptouch(&["image", "-o", png_path.to_str().unwrap(), "qrc:PORT-01"]);
+    assert!(output.status.success());
+
+    let out_path = temp_path("12mm-180dpi.bin");
+    let output = ptouch(&[
+        "print",
+        "-H",
+        &format!("file:{}", out_path.display()),
+        "--skip-status",
+        "-r",
+        "180",
+        "-t",
+        "12",
+        png_path.to_str().unwrap(),
+    ]);
+    assert_failure(&output, 6, "regenerate it with -r 180");
+
+    std::fs::remove_file(png_path).unwrap();
+    let _ = std::fs::remove_file(out_path);
+
+    // No 36mm tape for 180DPI printers
+    let output = ptouch(&["image", "-o", "-", "-r", "180", "-t", "36", "box:10x10"]);
+    assert_failure(&output, 2, "use -r 360 or -r 720");
+}
+
+/// Print a QR code label to a file backend
+fn print_to_file(name: &str, options: &[&str]) -> Output {
+    let png_path = temp_path(&format!("{}.png", name));
+    let output = ptouch(&["image", "-o", png_path.to_str().unwrap(), "qrc:PORT-01"]);
+    assert!(output.status.success());
+
+    let out_path = temp_path(&format!("{}.bin", name));
+    let host = format!("file:{}", out_path.display());
+    let mut args = vec!["print", "-H", &host, "--skip-status", "-t", "12"];
+    args.extend_from_slice(options);
+    args.push(png_path.to_str().unwrap());
+    let output = ptouch(&args);
+
+    std::fs::remove_file(png_path).unwrap();
+    let _ = std::fs::remove_file(out_path);
+    output
+}
+
+#[test]
+fn test_print_json() {
+    let output = print_to_file("json", &["--json"]);
+    assert!(output.status.success());
+
+    // Progress goes to stderr, only the result to stdout
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Starting print..."), "{}", stderr);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1, "{}", stdout);
+
+    let result: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert!(result["host"].as_str().unwrap().starts_with("file:"));
+    assert_eq!(result["tape"], "12mm (360dpi)");
+    assert!(result["raster_lines"].as_u64().unwrap() > 0);
+    assert!(result["bytes_sent"].as_u64().unwrap() > 0);
+    assert!(result["length_mm"].as_f64().unwrap() > 0.0);
+    assert!(result["duration_ms"].is_u64());
+}
+
+#[test]
+fn test_print_to_several_hosts() {
+    let png_path = temp_path("hosts.png");
+    let output = ptouch(&["image", "-o", png_path.to_str().unwrap(), "qrc:PORT-01"]);
+    assert!(output.status.success());
+
+    let out_paths = [temp_path("host-a.bin"), temp_path("host-b.bin")];
+    let hosts: Vec<String> = out_paths
+        .iter()
+        .map(|path| format!("file:{}", path.display()))
+        .collect();
+    let mut args = vec![
+        "print",
+        "--skip-status",
+        "-t",
+        "12",
+        png_path.to_str().unwrap(),
+    ];
+    for host in &hosts {
+        args.extend(["-H", host]);
+    }
+    assert!(ptouch(&args).status.success());
+    let job = std::fs::read(&out_paths[0]).unwrap();
+    assert!(job.ends_with(&[0x1A]));
+    assert_eq!(std::fs::read(&out_paths[1]).unwrap(), job);
+
+    // A failing host does not stop the others
+    std::fs::remove_file(&out_paths[1]).unwrap();
+    let bad_host = "file:/nonexistent/ptouch.bin";
+    let output = ptouch(&[&args[..], &["-H", bad_host]].concat());
+    assert_failure(
+        &output,
+        4,
+        "Failed to print on 1 of 3 printers: file:/nonexistent",
+    );
+    assert_eq!(std::fs::read(&out_paths[1]).unwrap(), job);
+
+    std::fs::remove_file(png_path).unwrap();
+    for path in out_paths {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_max_length() {
+    // The QR code label is about 8 mm long
+    let output = print_to_file("max-length", &["--max-length", "5"]);
+    assert_failure(&output, 2, "longer than the maximum 5 mm");
+
+    let output = print_to_file("max-length-0", &["--max-length", "0"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_batch_cut_every() {
+    let out_path = temp_path("cut-every.bin");
+    let host = format!("file:{}", out_path.display());
+    let output = ptouch(&[
+        "batch",
+        "-H",
+        &host,
+        "--skip-status",
+        "-t",
+        "12",
+        "--count",
+        "5",
+        "--cut-every",
+        "2",
+        "box:10x10",
+    ]);
+    assert!(output.status.success());
+    let sent = std::fs::read(&out_path).unwrap();
+    std::fs::remove_file(out_path).unwrap();
+
+    // Parameters of ESC i M (auto cut 0x40) and ESC i K (no chain 0x08) per job
+    let params = |command: &[u8], mask: u8| -> Vec<u8> {
+        let windows = sent.windows(4);
+        windows
+            .filter(|w| &w[..3] == command)
+            .map(|w| w[3] & mask)
+            .collect()
+    };
+    assert_eq!(params(b"\x1B\x69\x4D", 0x40), [0, 0x40, 0, 0x40, 0x40]);
+    assert_eq!(params(b"\x1B\x69\x4B", 0x08), [0, 0x08, 0, 0x08, 0x08]);
+}
+
+#[test]
+fn test_quiet() {
+    let output = print_to_file("quiet", &["--quiet"]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(
+        output.stderr.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Global option, also before the subcommand
+    let output = ptouch(&["--quiet", "image", "qrc:PORT-01"]);
+    assert!(output.status.success());
+    assert!(output.stdout.starts_with(b"\x89PNG"));
+
+    // Warnings are still printed
+    let output = ptouch(&["--quiet", "image", "--font-size", "8", "qrc:PORT-01"]);
+    assert!(output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("Warning: Font size 8 prints capital letters 0.4mm high")
+    );
+}
+
+#[test]
+fn test_explain() {
+    let output = ptouch(&["image", "--explain", "box:10x10", "+", "gap:5"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Row at (0, 0) size 15x10 align=top padding=20\n  \
+         Box(10x10) at (0, 0) size 10x10\n  \
+         Gap(5x5) at (10, 0) size 5x5\n"
+    );
+
+    let output = ptouch(&["batch", "--explain", "box:10x10"]);
+    assert_failure(&output, 2, "Batch cannot --explain");
+}
+
+#[test]
+fn test_script_warnings() {
+    let script = ["image", "-o", "/dev/null", "box:10x10", "qrc:PORT-01 "];
+    let output = ptouch(&script);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Warning: Whitespace around qrc: payload \"PORT-01 \" at position 2"),
+        "{}",
+        stderr
+    );
+
+    let output = ptouch(&[&["--quiet"], &script[..]].concat());
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+
+    let output = ptouch(&[&["image", "--strict-script"], &script[1..]].concat());
+    assert_failure(&output, 2, "Error: Layout script has warnings: Whitespace");
+
+    // Placeholders are reported even with --quiet, and do not fail --strict-script
+    let script = ["--quiet", "image", "--lenient-assets", "--strict-script"];
+    let output = ptouch(&[&script[..], &["-o", "/dev/null", "img:missing.png"]].concat());
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Warning: Missing image: missing.png"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_timings() {
+    let output = ptouch(&["image", "--timings", "box:40x10"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for stage in [
+        "fonts",
+        "parse",
+        "measure",
+        "svg",
+        "rasterize",
+        "encode",
+        "total",
+    ] {
+        assert!(
+            stderr.contains(&format!("Timing: {} ", stage)),
+            "{}",
+            stderr
+        );
+    }
+}
+
+#[test]
+fn test_annotate_leaves_output_unchanged() {
+    let plain_path = temp_path("plain.png");
+    let output = ptouch(&["image", "-o", plain_path.to_str().unwrap(), "box:40x10"]);
+    assert!(output.status.success());
+
+    let out_path = temp_path("annotated-output.png");
+    let annotate_path = temp_path("annotated.png");
+    let output = ptouch(&[
+        "image",
+        "-o",
+        out_path.to_str().unwrap(),
+        "--annotate",
+        annotate_path.to_str().unwrap(),
+        "box:40x10",
+    ]);
+    assert!(output.status.success());
+
+    let plain = std::fs::read(&plain_path).unwrap();
+    assert_eq!(std::fs::read(&out_path).unwrap(), plain);
+    assert_ne!(std::fs::read(&annotate_path).unwrap(), plain);
+
+    for path in [plain_path, out_path, annotate_path] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
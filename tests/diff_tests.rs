@@ -0,0 +1,65 @@
+use ptouch::diff::{ImageDiff, Region, diff_png};
+
+/// Grayscale PNG with black pixels at the given positions
+fn create_png(width: u32, height: u32, inked: &[(u32, u32)]) -> Vec<u8> {
+    let mut pixels = vec![255u8; (width * height) as usize];
+    for &(x, y) in inked {
+        pixels[(y * width + x) as usize] = 0;
+    }
+
+    let mut png_data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png_data, width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .unwrap()
+        .write_image_data(&pixels)
+        .unwrap();
+    png_data
+}
+
+#[test]
+fn test_diff_identical() {
+    let a = create_png(20, 10, &[(1, 1), (5, 7)]);
+    let diff = diff_png(&a, &a.clone()).unwrap();
+    assert!(diff.is_same());
+    assert_eq!(diff.to_string(), "Same: 20x10");
+}
+
+#[test]
+fn test_diff_one_pixel() {
+    let a = create_png(20, 10, &[(1, 1), (5, 7)]);
+    let b = create_png(20, 10, &[(1, 1), (5, 7), (12, 3)]);
+    let diff = diff_png(&a, &b).unwrap();
+    assert_eq!(
+        diff,
+        ImageDiff::Pixels {
+            count: 1,
+            region: Region {
+                width: 1,
+                height: 1,
+                x: 12,
+                y: 3
+            }
+        }
+    );
+    assert_eq!(diff.to_string(), "1 pixels differ in 1x1+12+3");
+}
+
+#[test]
+fn test_diff_region_spans_changes() {
+    let a = create_png(20, 10, &[(2, 8)]);
+    let b = create_png(20, 10, &[(15, 1)]);
+    let diff = diff_png(&a, &b).unwrap();
+    assert_eq!(diff.to_string(), "2 pixels differ in 14x8+2+1");
+}
+
+#[test]
+fn test_diff_size() {
+    let a = create_png(20, 10, &[]);
+    let b = create_png(20, 12, &[]);
+    let diff = diff_png(&a, &b).unwrap();
+    assert!(!diff.is_same());
+    assert_eq!(diff.to_string(), "Size differs: 20x10 vs 20x12");
+}
@@ -0,0 +1,828 @@
+use fontdb::Database;
+use ptouch::element::{
+    AZTEC_QUIET_ZONE, Aztec, BoundingBox, Column, Element, Gap, Image, LayoutTree, Overlay, QrCode,
+    QrOptions, RenderOptions, Rotated, Rotation, Row, RowOptions, Text, TextOptions, TextTooLarge,
+    VerticalAlign, render_svg_to_pixmap,
+};
+use ptouch::label::{Label, LabelOptions, Placement};
+use ptouch::printable_image::PrintableImage;
+use ptouch::tape::{Tape, TapeSpec};
+use ptouch::{is_color_font, remove_color_fonts};
+use std::sync::Arc;
+
+#[test]
+fn test_image_from_rgba8_bbox() {
+    let (width, height) = (12, 7);
+    let data = vec![0u8; width as usize * height as usize * 4];
+    let image = Image::from_rgba8(width, height, &data).unwrap();
+
+    let bbox = image.bounding_box().unwrap();
+    assert_eq!(bbox.width, width as f32);
+    assert_eq!(bbox.height, height as f32);
+    assert_eq!(format!("{}", image), "Image(data:12x7)");
+
+    let svg = image.render().unwrap().to_string();
+    assert!(svg.contains("data:image/png;base64,"));
+}
+
+#[test]
+fn test_image_from_rgba8_length_mismatch() {
+    let result = Image::from_rgba8(4, 4, &[0u8; 10]);
+    assert!(result.is_err());
+    let error_msg = format!("{}", result.err().unwrap());
+    assert!(error_msg.contains("RGBA data length mismatch"));
+}
+
+/// Column(Box(40x20), Row(Box(10x10), Gap(6x6), Box(20x30)))
+fn create_nested_layout() -> Column {
+    let row = Row::new(
+        vec![
+            Box::new(Gap::new(10.0, 10.0, true)),
+            Box::new(Gap::new(6.0, 6.0, false)),
+            Box::new(Gap::new(20.0, 30.0, true)),
+        ],
+        RowOptions {
+            align: VerticalAlign::Center,
+            padding: 4.0,
+        },
+    );
+    Column::new(
+        vec![Box::new(Gap::new(40.0, 20.0, true)), Box::new(row)],
+        5.0,
+    )
+}
+
+fn leaves(tree: &LayoutTree) -> Vec<&LayoutTree> {
+    if tree.children.is_empty() {
+        vec![tree]
+    } else {
+        tree.children.iter().flat_map(leaves).collect()
+    }
+}
+
+#[test]
+fn test_layout_offsets() {
+    let tree = create_nested_layout().layout().unwrap();
+    let positions: Vec<(String, f32, f32)> = leaves(&tree)
+        .iter()
+        .map(|t| (t.name.clone(), t.x, t.y))
+        .collect();
+
+    assert_eq!(
+        positions,
+        vec![
+            ("Box(40x20)".to_string(), 0.0, 0.0),
+            ("Box(10x10)".to_string(), 0.0, 35.0),
+            ("Gap(6x6)".to_string(), 10.0, 37.0),
+            ("Box(20x30)".to_string(), 16.0, 25.0),
+        ]
+    );
+    assert_eq!(tree.children[1].name, "Row(Box(10x10),Gap(6x6),Box(20x30))");
+    assert_eq!((tree.children[1].x, tree.children[1].y), (0.0, 25.0));
+}
+
+#[test]
+fn test_layout_matches_rendered_pixels() {
+    let column = create_nested_layout();
+    let tree = column.layout().unwrap();
+    let bbox = column.bounding_box().unwrap();
+
+    let svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}">{}</svg>"#,
+        bbox.width,
+        bbox.height,
+        column.render_at(0.0, 0.0).unwrap()
+    );
+    let pixmap =
+        render_svg_to_pixmap(&svg, &Arc::new(Database::new()), &RenderOptions::measure()).unwrap();
+    let is_black = |x: f32, y: f32| {
+        let pixel = pixmap.pixel(x as u32, y as u32).unwrap();
+        pixel.alpha() == 255 && pixel.red() == 0
+    };
+
+    for leaf in leaves(&tree) {
+        let (x, y, w, h) = (leaf.x, leaf.y, leaf.bbox.width, leaf.bbox.height);
+        let visible = leaf.name.starts_with("Box");
+        // Corners inside the box
+        assert_eq!(is_black(x, y), visible, "{}", leaf.name);
+        assert_eq!(is_black(x + w - 1.0, y + h - 1.0), visible, "{}", leaf.name);
+        // Just outside the box
+        if y >= 1.0 {
+            assert!(!is_black(x, y - 1.0), "{}", leaf.name);
+        }
+    }
+}
+
+/// Render element at (5, 5) on a canvas with a 5px margin and return a pixel tester
+fn render_with_margin(element: &dyn Element) -> impl Fn(u32, u32) -> bool {
+    let bbox = element.bounding_box().unwrap();
+    let svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}">{}</svg>"#,
+        bbox.width + 10.0,
+        bbox.height + 10.0,
+        element.render_at(5.0, 5.0).unwrap()
+    );
+    let pixmap =
+        render_svg_to_pixmap(&svg, &Arc::new(Database::new()), &RenderOptions::measure()).unwrap();
+    move |x, y| {
+        let pixel = pixmap.pixel(x, y).unwrap();
+        pixel.alpha() == 255 && pixel.red() == 0
+    }
+}
+
+#[test]
+fn test_rotated_pixels() {
+    // Column(Box(40x10), Box(10x20)): 40x30, L-shaped
+    let create_column = || -> Box<dyn Element> {
+        Box::new(Column::new(
+            vec![
+                Box::new(Gap::new(40.0, 10.0, true)),
+                Box::new(Gap::new(10.0, 20.0, true)),
+            ],
+            0.0,
+        ))
+    };
+
+    // Expected bbox size and (black, white) sample points relative to the bbox
+    let cases = [
+        (
+            Rotation::Rot90,
+            (30.0, 40.0),
+            [(25, 35), (5, 5)],
+            [(5, 35), (15, 20)],
+        ),
+        (
+            Rotation::Rot180,
+            (40.0, 30.0),
+            [(5, 25), (35, 5)],
+            [(5, 5), (20, 15)],
+        ),
+        (
+            Rotation::Rot270,
+            (30.0, 40.0),
+            [(5, 5), (25, 35)],
+            [(25, 5), (15, 20)],
+        ),
+    ];
+
+    for (rotation, (width, height), blacks, whites) in cases {
+        let rotated = Rotated::new(create_column(), rotation);
+        assert_eq!(
+            format!("{}", rotated),
+            format!("{}(Column(Box(40x10),Box(10x20)))", rotation)
+        );
+
+        let bbox = rotated.bounding_box().unwrap();
+        assert_eq!((bbox.width, bbox.height), (width, height), "{}", rotation);
+
+        let is_black = render_with_margin(&rotated);
+        for (x, y) in blacks {
+            assert!(is_black(x + 5, y + 5), "{} ({}, {})", rotation, x, y);
+        }
+        for (x, y) in whites {
+            assert!(!is_black(x + 5, y + 5), "{} ({}, {})", rotation, x, y);
+        }
+
+        // Nothing is drawn outside the reported bbox
+        let (w, h) = (width as u32 + 10, height as u32 + 10);
+        for x in 0..w {
+            for y in 0..h {
+                if !(5..w - 5).contains(&x) || !(5..h - 5).contains(&y) {
+                    assert!(!is_black(x, y), "{} ({}, {})", rotation, x, y);
+                }
+            }
+        }
+    }
+}
+
+/// Black square drawn at (x, y) in its own coordinates, like text ink
+/// that starts off the origin
+struct Offset(BoundingBox);
+
+impl Element for Offset {
+    fn bounding_box(&self) -> ptouch::Result<BoundingBox> {
+        Ok(self.0)
+    }
+
+    fn render(&self) -> ptouch::Result<svg::node::element::Group> {
+        let rect = svg::node::element::Rectangle::new()
+            .set("x", self.0.x)
+            .set("y", self.0.y)
+            .set("width", self.0.width)
+            .set("height", self.0.height)
+            .set("fill", "black");
+        Ok(svg::node::element::Group::new().add(rect))
+    }
+}
+
+impl std::fmt::Display for Offset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Offset({})", self.0)
+    }
+}
+
+#[test]
+fn test_bounding_box_union() {
+    let a = BoundingBox::new(20.0, 20.0, 0.0, 0.0);
+    let b = BoundingBox::new(10.0, 10.0, -5.0, 15.0);
+    let expected = BoundingBox::new(25.0, 25.0, -5.0, 0.0);
+    assert_eq!(a.union(&b), expected);
+    assert_eq!(b.union(&a), expected);
+}
+
+#[test]
+fn test_overlay_layers_with_different_origins() {
+    let cases = [
+        // Later layer starts left of and above the base layer
+        (BoundingBox::new(10.0, 10.0, -5.0, -5.0), (25.0, 25.0)),
+        // Base layer starts off the origin
+        (BoundingBox::new(10.0, 10.0, 15.0, 15.0), (25.0, 25.0)),
+        (BoundingBox::new(30.0, 4.0, -8.0, 8.0), (30.0, 20.0)),
+    ];
+
+    for (offset, (width, height)) in cases {
+        let overlay = Overlay::new(vec![
+            Box::new(Gap::new(20.0, 20.0, true)),
+            Box::new(Offset(offset)),
+        ]);
+        let bbox = overlay.bounding_box().unwrap();
+        assert_eq!((bbox.width, bbox.height), (width, height), "{}", offset);
+
+        // Box and offset square keep their relative position
+        let is_black = render_with_margin(&overlay);
+        let (dx, dy) = (5.0 - bbox.x, 5.0 - bbox.y);
+        for (x, y) in [
+            (0.0, 0.0),
+            (19.0, 19.0),
+            (offset.x, offset.y),
+            (
+                offset.x + offset.width - 1.0,
+                offset.y + offset.height - 1.0,
+            ),
+        ] {
+            let (px, py) = ((x + dx) as u32, (y + dy) as u32);
+            assert!(is_black(px, py), "{} ({}, {})", offset, x, y);
+        }
+
+        // Nothing is drawn outside the reported bbox
+        let (w, h) = (width as u32 + 10, height as u32 + 10);
+        for x in 0..w {
+            for y in 0..h {
+                if !(5..w - 5).contains(&x) || !(5..h - 5).contains(&y) {
+                    assert!(!is_black(x, y), "{} ({}, {})", offset, x, y);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_overlay_of_additive_gap_keeps_padding() {
+    let row = |gap: Gap| {
+        let overlay = Overlay::new(vec![Box::new(gap), Box::new(Gap::new(10.0, 0.0, false))]);
+        Row::new(
+            vec![
+                Box::new(Gap::new(10.0, 10.0, true)),
+                Box::new(overlay),
+                Box::new(Gap::new(10.0, 10.0, true)),
+            ],
+            RowOptions {
+                align: VerticalAlign::Top,
+                padding: 4.0,
+            },
+        )
+    };
+
+    let absolute = row(Gap::new(10.0, 0.0, false));
+    let additive = row(Gap::new(10.0, 0.0, false).with_padding(true));
+    assert_eq!(absolute.bounding_box().unwrap().width, 30.0);
+    assert_eq!(additive.bounding_box().unwrap().width, 38.0);
+    assert_eq!(
+        format!("{}", additive),
+        "Row(Box(10x10),Overlay(Gap+(10x0),Gap(10x0)),Box(10x10))"
+    );
+}
+
+fn create_text_fontdb() -> Arc<Database> {
+    let mut fontdb = Database::new();
+    fontdb.load_fonts_dir("attic/fonts");
+    fontdb.load_system_fonts();
+    Arc::new(fontdb)
+}
+
+fn create_text(texts: &[String], font_size: u32) -> Text {
+    let options = TextOptions {
+        font_name: "Noto Sans JP".to_string(),
+        font_weight: "normal".to_string(),
+        font_size,
+        line_height: font_size,
+        fontdb: create_text_fontdb(),
+    };
+    Text::new(texts, options).unwrap()
+}
+
+#[test]
+fn test_columns_width() {
+    let mut fontdb = Database::new();
+    fontdb.load_fonts_dir("attic/fonts");
+    fontdb.load_system_fonts();
+    let fontdb = Arc::new(fontdb);
+
+    let text = create_text(&["0".repeat(20)], 48);
+    let options = TextOptions {
+        font_name: "Noto Sans JP".to_string(),
+        font_weight: "normal".to_string(),
+        font_size: 48,
+        line_height: 48,
+        fontdb: fontdb.clone(),
+    };
+    let cell_width = options.cell_width().unwrap();
+    assert!(cell_width > 0.0 && cell_width < 48.0);
+
+    // Measured ink of 20 digits is 20 cells without the side bearings
+    let bbox = text.bounding_box().unwrap();
+    assert!((bbox.width - 20.0 * cell_width).abs() < cell_width);
+
+    // Label of 20 columns is exactly 20 cells (plus 2 dots of slack)
+    let label_options = LabelOptions {
+        fontdb: fontdb.clone(),
+        tape_spec: TapeSpec::new(Tape::TZe24H),
+        auto_scale: false,
+        rotate: false,
+        placement: Placement::Top,
+        debug: false,
+        transparent: false,
+        preview_checkerboard: false,
+        embed_text: false,
+        length: Some(20.0 * cell_width),
+        supersample: 1,
+        border: None,
+    };
+    let png_data = Label::from_element(Box::new(text), label_options)
+        .to_png()
+        .unwrap();
+    let width = png::Decoder::new(png_data.as_slice())
+        .read_info()
+        .unwrap()
+        .info()
+        .width;
+    assert_eq!(width, (20.0 * cell_width + 2.0).round() as u32);
+}
+
+/// Ink of each vertical stroke: runs of columns with ink
+fn stroke_weights(png_data: &[u8]) -> Vec<u32> {
+    let decoder = png::Decoder::new(png_data);
+    let mut reader = decoder.read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    let (width, height) = (info.width as usize, info.height as usize);
+
+    let mut weights = Vec::new();
+    let mut weight = 0;
+    for x in 0..width {
+        let ink = (0..height)
+            .filter(|y| buf[(y * width + x) * 4] < 127)
+            .count() as u32;
+        if ink > 0 {
+            weight += ink;
+        } else if weight > 0 {
+            weights.push(weight);
+            weight = 0;
+        }
+    }
+    weights
+}
+
+fn variance(values: &[u32]) -> f32 {
+    let mean = values.iter().sum::<u32>() as f32 / values.len() as f32;
+    let sum = values
+        .iter()
+        .map(|&v| (v as f32 - mean).powi(2))
+        .sum::<f32>();
+    sum / values.len() as f32
+}
+
+#[test]
+fn test_supersample_evens_strokes() {
+    // Stems of 12px text are thinner than a dot and fall on fractional
+    // positions, so thresholding keeps some of them and drops others
+    let render = |supersample: u32| {
+        let label_options = LabelOptions {
+            fontdb: create_text_fontdb(),
+            tape_spec: TapeSpec::new(Tape::TZe12H),
+            auto_scale: false,
+            rotate: false,
+            placement: Placement::Top,
+            debug: false,
+            transparent: false,
+            preview_checkerboard: false,
+            embed_text: false,
+            length: None,
+            supersample,
+            border: None,
+        };
+        let text = create_text(&["l".repeat(20)], 12);
+        let png_data = Label::from_element(Box::new(text), label_options)
+            .to_png()
+            .unwrap();
+        stroke_weights(&png_data)
+    };
+    // Strokes thinner than half a dot may vanish: count them as no ink
+    let mut plain = render(1);
+    plain.resize(20, 0);
+    let supersampled = render(4);
+
+    assert_eq!(supersampled.len(), 20);
+    assert!(variance(&supersampled) < variance(&plain));
+}
+
+#[test]
+fn test_text_too_long_line() {
+    // 5,000 characters at 48px would need a canvas of 240k pixels wide
+    let text = create_text(&["W".repeat(5000)], 48);
+    let error = text.bounding_box().err().unwrap();
+
+    let too_large = error.downcast_ref::<TextTooLarge>().unwrap();
+    assert!(too_large.width > 16384);
+    assert!(error.to_string().contains("Wrap long lines"));
+}
+
+#[test]
+fn test_text_too_many_lines() {
+    let text = create_text(&vec!["x".to_string(); 2000], 48);
+    let error = text.bounding_box().err().unwrap();
+    assert!(error.downcast_ref::<TextTooLarge>().is_some());
+}
+
+#[test]
+fn test_text_long_narrow_line_fits() {
+    // Estimated canvas exceeds the cap, but the actual text fits in it
+    let text = create_text(&["i".repeat(1000)], 24);
+    let bbox = text.bounding_box().unwrap();
+    assert!(bbox.width > 1000.0 && bbox.width < 16384.0);
+}
+
+#[test]
+fn test_unchecked_text_fails_on_measure() {
+    let options = TextOptions::new(Arc::new(Database::new())).with_font_name("No Such Font");
+    let texts = ["Hello".to_string()];
+
+    assert!(Text::new(&texts, options.clone()).is_err());
+
+    let text = Text::new_unchecked(&texts, options);
+    assert_eq!(format!("{}", text), "Text(Hello)");
+    let error = text.bounding_box().unwrap_err().to_string();
+    assert_eq!(error, "Font 'No Such Font' not found.");
+}
+
+fn baseline_row(elements: Vec<Box<dyn Element>>) -> Row {
+    Row::new(
+        elements,
+        RowOptions {
+            align: VerticalAlign::Baseline,
+            padding: 0.0,
+        },
+    )
+}
+
+#[test]
+fn test_row_baseline_mixed_font_sizes() {
+    let row = baseline_row(vec![
+        Box::new(create_text(&["Small".to_string()], 24)),
+        Box::new(create_text(&["Large".to_string()], 48)),
+    ]);
+
+    let offsets = row.child_offsets().unwrap();
+    let baselines: Vec<f32> = offsets
+        .iter()
+        .map(|(elm, _, y)| y + elm.baseline().unwrap().unwrap())
+        .collect();
+    assert_eq!(baselines[0], baselines[1]);
+    // Larger text reaches the top
+    assert_eq!(offsets[1].2, 0.0);
+    assert!(offsets[0].2 > 0.0);
+    assert_eq!(row.baseline().unwrap(), Some(baselines[0]));
+}
+
+#[test]
+fn test_row_baseline_without_text() {
+    // Bottoms of boxes are put on the baseline
+    let row = baseline_row(vec![
+        Box::new(Gap::new(10.0, 10.0, true)),
+        Box::new(Gap::new(10.0, 30.0, true)),
+    ]);
+
+    let offsets: Vec<f32> = row
+        .child_offsets()
+        .unwrap()
+        .iter()
+        .map(|(_, _, y)| *y)
+        .collect();
+    assert_eq!(offsets, [20.0, 0.0]);
+    assert_eq!(row.bounding_box().unwrap().height, 30.0);
+    assert_eq!(row.baseline().unwrap(), None);
+}
+
+#[test]
+fn test_monochrome_emoji_coverage() {
+    let mut fontdb = Database::new();
+    fontdb.load_fonts_dir("attic/fonts");
+    fontdb.load_system_fonts();
+    remove_color_fonts(&mut fontdb);
+    assert!(fontdb.faces().all(|face| !is_color_font(&fontdb, face.id)));
+    let fontdb = Arc::new(fontdb);
+
+    let options = TextOptions {
+        font_name: "Noto Sans JP".to_string(),
+        font_weight: "normal".to_string(),
+        font_size: 64,
+        line_height: 64,
+        fontdb: fontdb.clone(),
+    };
+    let text = Text::new(&["\u{263a}".to_string()], options).unwrap();
+    let bbox = text.bounding_box().unwrap();
+
+    let tape_spec = TapeSpec::new(Tape::TZe24H);
+    let label = Label::from_element(
+        Box::new(text),
+        LabelOptions::new(fontdb).with_tape_spec(tape_spec.clone()),
+    );
+    let printable = PrintableImage::from_png_data(label.to_png().unwrap(), tape_spec).unwrap();
+    let black_dots: u32 = printable
+        .to_raster_lines()
+        .unwrap()
+        .iter()
+        .flatten()
+        .map(|byte| byte.count_ones())
+        .sum();
+
+    // Outline of a face: neither vanished nor filled solid
+    let area = bbox.width * bbox.height;
+    assert!(black_dots as f32 > area * 0.1, "{} of {}", black_dots, area);
+    assert!(
+        (black_dots as f32) < area * 0.9,
+        "{} of {}",
+        black_dots,
+        area
+    );
+}
+
+/// Extent (min x, min y, max x, max y) of non-white pixels
+fn ink_extent(pixmap: &resvg::tiny_skia::Pixmap) -> Option<(u32, u32, u32, u32)> {
+    let mut extent: Option<(u32, u32, u32, u32)> = None;
+    for y in 0..pixmap.height() {
+        for x in 0..pixmap.width() {
+            let pixel = pixmap.pixel(x, y).unwrap();
+            if pixel.alpha() > 0 && pixel.red() < 255 {
+                extent = Some(match extent {
+                    None => (x, y, x, y),
+                    Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+                });
+            }
+        }
+    }
+    extent
+}
+
+#[test]
+fn test_measured_bbox_contains_antialiased_ink() {
+    for glyphs in ["Ag", "jy", "W", "|", "日本", "(Q)"] {
+        let text = create_text(&[glyphs.to_string()], 48);
+        let bbox = text.bounding_box().unwrap();
+
+        // Measured without antialiasing, rendered with it
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}">{}</svg>"#,
+            bbox.width + 10.0,
+            bbox.height + 10.0,
+            text.render_at(5.0, 5.0).unwrap()
+        );
+        let fontdb = create_text_fontdb();
+        let pixmap = render_svg_to_pixmap(&svg, &fontdb, &RenderOptions::output()).unwrap();
+        let (x0, y0, x1, y1) = ink_extent(&pixmap).unwrap();
+
+        // 1px tolerance for partially covered pixels
+        let (left, top) = (5.0 - 1.0, 5.0 - 1.0);
+        let (right, bottom) = (5.0 + bbox.width, 5.0 + bbox.height);
+        assert!(
+            x0 as f32 >= left && y0 as f32 >= top,
+            "{}: {:?}",
+            glyphs,
+            (x0, y0)
+        );
+        assert!(
+            x1 as f32 <= right && y1 as f32 <= bottom,
+            "{}: {:?}",
+            glyphs,
+            (x1, y1)
+        );
+    }
+}
+
+#[test]
+fn test_render_dpi_scale() {
+    let svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="30" height="20">{}</svg>"#,
+        Gap::new(10.0, 10.0, true).render_at(5.0, 5.0).unwrap()
+    );
+    let fontdb = Arc::new(Database::new());
+    let options = RenderOptions {
+        dpi_scale: 2.0,
+        ..RenderOptions::measure()
+    };
+    let pixmap = render_svg_to_pixmap(&svg, &fontdb, &options).unwrap();
+    assert_eq!((pixmap.width(), pixmap.height()), (60, 40));
+    assert_eq!(ink_extent(&pixmap), Some((10, 10, 29, 29)));
+
+    let options = RenderOptions {
+        dpi_scale: 0.0,
+        ..RenderOptions::measure()
+    };
+    assert!(render_svg_to_pixmap(&svg, &fontdb, &options).is_err());
+}
+
+#[test]
+fn test_physical_text_size_warning() {
+    // Without the font, capital letters are taken as 0.7 em
+    let options = TextOptions::new(Arc::new(Database::new()));
+    let warns = |size: u32, tape: Tape| {
+        options
+            .clone()
+            .with_font_size(size)
+            .check_physical_size(&TapeSpec::new(tape), 1.5)
+    };
+
+    // 1.5mm is 21.3 dots at 360 DPI and 10.6 dots at 180 DPI
+    assert!(warns(30, Tape::TZe12H).unwrap().contains("below 1.5mm"));
+    assert!(warns(31, Tape::TZe12H).is_none());
+    assert!(warns(15, Tape::TZe12L).unwrap().contains("below 1.5mm"));
+    assert!(warns(16, Tape::TZe12L).is_none());
+
+    // Capital letters taller than the printable width
+    assert!(warns(214, Tape::TZe12H).is_none());
+    assert!(
+        warns(215, Tape::TZe12H)
+            .unwrap()
+            .contains("printable width")
+    );
+    assert!(warns(100, Tape::TZe12L).is_none());
+    assert!(
+        warns(101, Tape::TZe12L)
+            .unwrap()
+            .contains("printable width")
+    );
+
+    // 0 disables the lower bound
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    let tiny = options.with_font_size(4);
+    assert!(tiny.check_physical_size(&tape_spec, 0.0).is_none());
+}
+
+#[test]
+fn test_qr_code_bbox_matches_render() {
+    // Ink of the rendered modules spans exactly the bounding box
+    let ink_size = |qr: &QrCode| {
+        let bbox = qr.bounding_box().unwrap();
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{0}">{1}</svg>"#,
+            bbox.width + 10.0,
+            qr.render_at(5.0, 5.0).unwrap()
+        );
+        let fontdb = Arc::new(Database::new());
+        let pixmap = render_svg_to_pixmap(&svg, &fontdb, &RenderOptions::measure()).unwrap();
+        let (left, top, right, bottom) = ink_extent(&pixmap).unwrap();
+        (
+            bbox.width,
+            (right - left + 1) as f32,
+            (bottom - top + 1) as f32,
+        )
+    };
+
+    let mut qr = QrCode::new("PORT-01".to_string()).unwrap();
+    let expected = qrcode::QrCode::new("PORT-01").unwrap().width() as f32 * 5.0;
+    assert_eq!(ink_size(&qr), (expected, expected, expected));
+
+    // Changing the data encodes the symbol again
+    qr.set_data("https://example.com/".repeat(4)).unwrap();
+    let (width, ink_width, ink_height) = ink_size(&qr);
+    assert!(width > expected);
+    assert_eq!((ink_width, ink_height), (width, width));
+}
+
+#[test]
+fn test_aztec_known_size() {
+    // 5 bytes fit the smallest compact symbol of 15x15 modules
+    let aztec = Aztec::new("HELLO".to_string()).unwrap();
+    assert_eq!(aztec.symbol_size(), 15);
+    assert_eq!(aztec.ec_percent(), 33);
+
+    // Bounding box includes the quiet zone, ink only the symbol
+    let bbox = aztec.bounding_box().unwrap();
+    let expected = (15 + AZTEC_QUIET_ZONE * 2) as f32 * 5.0;
+    assert_eq!((bbox.width, bbox.height), (expected, expected));
+
+    let svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{0}">{1}</svg>"#,
+        bbox.width,
+        aztec.render_at(0.0, 0.0).unwrap()
+    );
+    let fontdb = Arc::new(Database::new());
+    let pixmap = render_svg_to_pixmap(&svg, &fontdb, &RenderOptions::measure()).unwrap();
+    let quiet = (AZTEC_QUIET_ZONE * 5) as u32;
+    assert_eq!(
+        ink_extent(&pixmap).unwrap(),
+        (quiet, quiet, quiet + 74, quiet + 74)
+    );
+}
+
+#[test]
+fn test_aztec_capacity() {
+    // More error correction takes more layers
+    let data = "TRIP-0042-SEAT-17A".to_string();
+    let small = Aztec::new(data.clone()).unwrap();
+    let large = Aztec::with_options(data.clone(), &QrOptions::default(), 80).unwrap();
+    assert!(large.symbol_size() > small.symbol_size());
+
+    // Full-range symbols take over from compact ones
+    let full = Aztec::new("x".repeat(200)).unwrap();
+    assert!(full.symbol_size() > 27);
+
+    let error = Aztec::new("x".repeat(4000)).err().unwrap();
+    assert_eq!(
+        error.to_string(),
+        "Data too large for Aztec code: 4000 bytes with 33% error correction"
+    );
+}
+
+/// Widest of elements of any one type, boxed or not
+fn widest<E: Element>(elements: &[E]) -> f32 {
+    elements
+        .iter()
+        .map(|element| element.bounding_box().unwrap().width)
+        .fold(0.0, f32::max)
+}
+
+/// Row of elements of any one type
+fn row_of<E: Element + 'static>(elements: Vec<E>) -> Row {
+    let elements = elements
+        .into_iter()
+        .map(|element| Box::new(element) as Box<dyn Element>)
+        .collect();
+    Row::new(elements, RowOptions::default())
+}
+
+#[test]
+fn test_boxed_elements_in_generic_code() {
+    let mixed = || -> Vec<Box<dyn Element>> {
+        vec![
+            Box::new(Gap::new(30.0, 10.0, true)),
+            Box::new(QrCode::new("PORT-01".to_string()).unwrap()),
+            Box::new(Gap::new(5.0, 5.0, false)),
+        ]
+    };
+    let qr_width = QrCode::new("PORT-01".to_string())
+        .unwrap()
+        .bounding_box()
+        .unwrap()
+        .width;
+    assert_eq!(widest(&mixed()), qr_width);
+
+    // The row sees through the boxes: same layout as the elements boxed once
+    let row = row_of(mixed());
+    let direct = Row::new(mixed(), RowOptions::default());
+    assert_eq!(row.bounding_box().unwrap(), direct.bounding_box().unwrap());
+    assert_eq!(
+        row.render().unwrap().to_string(),
+        direct.render().unwrap().to_string()
+    );
+    let layout = row.layout().unwrap();
+    assert_eq!(layout.children[1].name, "QrCode(PORT-01)");
+    assert!(!layout.children[2].visible);
+}
+
+#[test]
+fn test_text_from_lines_verbatim() {
+    let options = TextOptions::new(create_text_fontdb())
+        .with_font_name("Noto Sans JP")
+        .with_font_size(24)
+        .with_line_height(30);
+    let bbox = |lines: &[&str]| {
+        let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        Text::from_lines(&lines, options.clone())
+            .unwrap()
+            .bounding_box()
+            .unwrap()
+    };
+
+    // A blank middle line keeps its line height
+    let two = bbox(&["Ag", "Ag"]);
+    let three = bbox(&["Ag", "", "Ag"]);
+    assert_eq!(three.height - two.height, 30.0);
+    assert_eq!(three.width, two.width);
+
+    // Leading spaces are kept
+    assert!(bbox(&["  Ag"]).width > bbox(&["Ag"]).width);
+
+    let lines = vec!["one\ntwo".to_string()];
+    assert!(Text::from_lines(&lines, options.clone()).is_err());
+}
@@ -0,0 +1,48 @@
+use ptouch::escp_command::EscpCommand;
+
+#[test]
+fn test_escp_hello() {
+    let mut cmd = EscpCommand::new();
+    cmd.initialize()
+        .select_character_size(32)
+        .text("HELLO")
+        .unwrap()
+        .form_feed();
+
+    assert_eq!(
+        cmd.build(),
+        b"\x1B\x40\x1B\x69\x61\x00\x1B\x58\x00\x20\x00HELLO\x0C"
+    );
+}
+
+#[test]
+fn test_escp_multiline_large_size() {
+    let mut cmd = EscpCommand::new();
+    cmd.select_character_size(300).text("A-1\nB 2\n").unwrap();
+
+    // 300 = 0x012C in little-endian, LF becomes CR LF
+    assert_eq!(cmd.build(), b"\x1B\x58\x00\x2C\x01A-1\r\nB 2\r\n");
+}
+
+#[test]
+fn test_escp_invalidate() {
+    let mut cmd = EscpCommand::new();
+    cmd.invalidate().initialize();
+    let data = cmd.build();
+
+    assert_eq!(data.len(), 206);
+    assert!(data[..200].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn test_escp_rejects_non_ascii() {
+    let mut cmd = EscpCommand::new();
+    cmd.text("OK").unwrap();
+
+    let error = cmd.text("ラベル").err().unwrap().to_string();
+    assert!(error.contains("ASCII only"), "{}", error);
+    assert!(cmd.text("tab\there").is_err());
+
+    // Nothing is added on error
+    assert_eq!(cmd.build(), b"OK");
+}
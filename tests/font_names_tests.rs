@@ -0,0 +1,69 @@
+use fontdb::{Database, Source, Style, Weight};
+use ptouch::{get_font_names, select_face, sort_font_names};
+
+#[test]
+fn test_sort_font_names_ignores_input_order() {
+    let names = ["b Font", "A Font", "B font", "a font", "C Font"];
+    let mut reversed = names;
+    reversed.reverse();
+
+    let expected = ["A Font", "B font", "C Font"];
+    assert_eq!(sort_font_names(names.map(String::from)), expected);
+    assert_eq!(sort_font_names(reversed.map(String::from)), expected);
+}
+
+#[test]
+fn test_sort_font_names_case_insensitive_order() {
+    let names = ["noto", "DejaVu", "Liberation"];
+    assert_eq!(
+        sort_font_names(names.map(String::from)),
+        ["DejaVu", "Liberation", "noto"]
+    );
+}
+
+#[test]
+fn test_get_font_names_is_stable() {
+    let first = get_font_names(&[]);
+    let second = get_font_names(&[]);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn test_select_face_ignores_load_order() {
+    // Two copies of a test font make two faces of the same family
+    let mut test_fonts = Database::new();
+    test_fonts.load_fonts_dir("attic/fonts");
+    let (path, family) = test_fonts
+        .faces()
+        .find_map(|face| match &face.source {
+            Source::File(path) => Some((path.clone(), face.families[0].0.clone())),
+            _ => None,
+        })
+        .expect("no test fonts in attic/fonts");
+    let dir = std::env::temp_dir().join(format!("ptouch-{}-same-family", std::process::id()));
+    let file_name = path.file_name().unwrap();
+    for copy in ["a", "b"] {
+        std::fs::create_dir_all(dir.join(copy)).unwrap();
+        std::fs::copy(&path, dir.join(copy).join(file_name)).unwrap();
+    }
+
+    let selected_path = |order: [&str; 2]| {
+        let mut fontdb = Database::new();
+        for copy in order {
+            fontdb
+                .load_font_file(dir.join(copy).join(file_name))
+                .unwrap();
+        }
+        let id = select_face(&fontdb, &family, Weight::NORMAL, Style::Normal).unwrap();
+        match &fontdb.face(id).unwrap().source {
+            Source::File(path) => path.clone(),
+            _ => unreachable!(),
+        }
+    };
+    let forward = selected_path(["a", "b"]);
+    let backward = selected_path(["b", "a"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(forward, backward);
+    assert_eq!(forward, dir.join("a").join(file_name));
+}
@@ -0,0 +1,208 @@
+//! Rendering regression tests against committed golden images, and
+//! golden text of `layout::explain` to keep its format stable
+//!
+//! Regenerate the files after an intended change with:
+//! UPDATE_GOLDEN=1 cargo test --test golden_tests
+
+use fontdb::Database;
+use ptouch::diff::{ImageDiff, diff_png};
+use ptouch::element::{RenderOptions, TextOptions, render_svg_to_pixmap};
+use ptouch::label::{Label, LabelOptions, rasterize_label_svg};
+use ptouch::layout::{LayoutOptions, explain, parse_layout_script};
+use ptouch::tape::{Tape, TapeSpec};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Differing pixels allowed to absorb font rasterizer jitter
+const TOLERANCE: usize = 40;
+
+fn create_fontdb() -> Arc<Database> {
+    let mut fontdb = Database::new();
+    fontdb.load_fonts_dir("attic/fonts");
+    fontdb.load_system_fonts();
+    Arc::new(fontdb)
+}
+
+fn render(script: &str) -> Vec<u8> {
+    create_label(script, false).to_png().unwrap()
+}
+
+fn create_label(script: &str, embed_text: bool) -> Label {
+    create_label_with(script, |options| LabelOptions {
+        embed_text,
+        ..options
+    })
+}
+
+/// Label of `script` with the label options changed by `with`
+fn create_label_with(script: &str, with: impl FnOnce(LabelOptions) -> LabelOptions) -> Label {
+    let fontdb = create_fontdb();
+    let layout_options = LayoutOptions {
+        // Shipped with most Linux distributions
+        text: TextOptions {
+            font_name: "DejaVu Sans".to_string(),
+            font_weight: "normal".to_string(),
+            font_size: 48,
+            line_height: 48,
+            fontdb: fontdb.clone(),
+        },
+        ..LayoutOptions::new(fontdb.clone())
+    };
+    let script: Vec<String> = script.split_whitespace().map(String::from).collect();
+    let element = parse_layout_script(&script, &layout_options).unwrap();
+
+    let label_options = LabelOptions::new(fontdb).with_tape_spec(TapeSpec::new(Tape::TZe12H));
+    Label::from_element(element, with(label_options))
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "tests", "golden", name]
+        .iter()
+        .collect()
+}
+
+fn assert_golden(name: &str, script: &str) {
+    let path = golden_path(name);
+    let png_data = render(script);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, &png_data).unwrap();
+        return;
+    }
+
+    let golden = std::fs::read(&path).unwrap_or_else(|e| {
+        panic!(
+            "{}: {} (run with UPDATE_GOLDEN=1 to create)",
+            path.display(),
+            e
+        )
+    });
+    match diff_png(&golden, &png_data).unwrap() {
+        ImageDiff::Same { .. } => {}
+        ImageDiff::Pixels { count, .. } if count <= TOLERANCE => {}
+        diff => panic!("{} differs from the rendered label: {}", name, diff),
+    }
+}
+
+#[test]
+fn test_golden_text() {
+    assert_golden("text.png", "Hello");
+}
+
+#[test]
+fn test_golden_qr() {
+    assert_golden("qr.png", "qrc:https://example.com");
+}
+
+#[test]
+fn test_golden_overlay() {
+    assert_golden(
+        "overlay.png",
+        "[ gap:10 + box:60x20 ] / [ box:4x60 + gap:30 ]",
+    );
+}
+
+#[test]
+fn test_embedded_text_renders_without_fonts() {
+    let script = "Hello + qrc:https://example.com";
+    let svg_data = create_label(script, true).to_svg().unwrap();
+    assert!(!svg_data.contains("<text"));
+
+    // The SVG source is sized in mm, so render both at the same scale
+    let options = RenderOptions {
+        dpi_scale: 360.0 / 96.0,
+        ..RenderOptions::output()
+    };
+    let no_fonts = Arc::new(Database::new());
+    let pixmap = render_svg_to_pixmap(&svg_data, &no_fonts, &options).unwrap();
+    let svg_data = create_label(script, false).to_svg().unwrap();
+    let expected = render_svg_to_pixmap(&svg_data, &create_fontdb(), &options).unwrap();
+    assert_eq!(
+        (pixmap.width(), pixmap.height()),
+        (expected.width(), expected.height())
+    );
+
+    // Outlines are written with limited precision, which slightly shifts
+    // the gray of a few edge pixels, so compare the dots to be printed
+    let ink = |pixmap: &resvg::tiny_skia::Pixmap| -> Vec<bool> {
+        pixmap.pixels().iter().map(|p| p.red() < 128).collect()
+    };
+    assert_eq!(ink(&pixmap), ink(&expected));
+}
+
+#[test]
+fn test_embedded_text_prints_like_source() {
+    let script = "Hello + qrc:https://example.com";
+    let no_fonts = Arc::new(Database::new());
+    let rasterize = |label: &Label| {
+        let svg_data = label.to_svg().unwrap();
+        rasterize_label_svg(&svg_data, &no_fonts, 360, false).unwrap()
+    };
+
+    // The tape and size come from the root of the SVG as without --embed-text
+    let checkered = create_label_with(script, |options| LabelOptions {
+        embed_text: true,
+        preview_checkerboard: true,
+        ..options
+    });
+    let (pixmap, tape_spec) = rasterize(&checkered);
+    assert_eq!(tape_spec.name.to_string(), Tape::TZe12H.to_string());
+    let expected = create_label(script, false).to_pixmap().unwrap();
+    assert_eq!(
+        (pixmap.width(), pixmap.height()),
+        (expected.width(), expected.height())
+    );
+
+    // The checkered background is left out as a preview mark
+    let (plain, _) = rasterize(&create_label(script, true));
+    assert_eq!(pixmap.data(), plain.data());
+    let ink = |pixmap: &resvg::tiny_skia::Pixmap| -> Vec<bool> {
+        pixmap.pixels().iter().map(|p| p.red() < 128).collect()
+    };
+    assert_eq!(ink(&pixmap), ink(&expected));
+}
+
+fn assert_golden_explain(name: &str, script: &str) {
+    let path = golden_path(name);
+    let label = create_label(script, false);
+    let tree = explain(label.element()).unwrap();
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, &tree).unwrap();
+        return;
+    }
+
+    let golden = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "{}: {} (run with UPDATE_GOLDEN=1 to create)",
+            path.display(),
+            e
+        )
+    });
+    assert_eq!(tree, golden, "{} differs from the explained layout", name);
+}
+
+#[test]
+fn test_golden_explain_nested() {
+    assert_golden_explain(
+        "nested.txt",
+        "box:30x10 + [p=4 box:20x20 box:10x5 ] + gap:15 + qrc:PORT-01",
+    );
+}
+
+#[test]
+fn test_golden_explain_overlay() {
+    assert_golden_explain(
+        "overlay.txt",
+        "[ gap:10 + box:60x20 ] / [ box:4x60 + gap:30 ]",
+    );
+}
+
+#[test]
+fn test_golden_explain_rotated() {
+    assert_golden_explain(
+        "rotated.txt",
+        "rot90: box:40x10 + rot180: [ box:5x5 + box:8x8 ]",
+    );
+}
@@ -0,0 +1,620 @@
+use fontdb::Database;
+use ptouch::element::{Gap, Overlay};
+use ptouch::label::{
+    Border, Label, LabelOptions, Placement, RenderMetrics, rasterize_label_svg, write_atomically,
+};
+use ptouch::printable_image::PrintableImage;
+use ptouch::tape::{Tape, TapeSpec};
+use std::sync::Arc;
+
+fn create_label_options() -> LabelOptions {
+    LabelOptions {
+        fontdb: Arc::new(Database::new()),
+        tape_spec: TapeSpec::new(Tape::TZe12H),
+        auto_scale: false,
+        rotate: false,
+        placement: Placement::Top,
+        debug: false,
+        transparent: false,
+        preview_checkerboard: false,
+        embed_text: false,
+        length: None,
+        supersample: 1,
+        border: None,
+    }
+}
+
+fn create_box_label(options: LabelOptions) -> Label {
+    Label::from_element(Box::new(Gap::new(40.0, 20.0, true)), options)
+}
+
+#[test]
+fn test_svg_has_white_background() {
+    let svg = create_box_label(create_label_options()).to_svg().unwrap();
+    assert!(svg.contains(r#"class="background""#));
+    assert!(!svg.contains(r#"fill="none""#));
+}
+
+#[test]
+fn test_svg_has_physical_size() {
+    let svg_header = |tape_spec: TapeSpec| {
+        let options = LabelOptions {
+            tape_spec,
+            ..create_label_options()
+        };
+        let svg = create_box_label(options).to_svg().unwrap();
+        let (declaration, rest) = svg.split_once('\n').unwrap();
+        assert_eq!(declaration, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        rest[..rest.find('>').unwrap()].to_string()
+    };
+
+    // 42 raster lines: 40 units wide box with 1 unit of padding on each side
+    let header = svg_header(TapeSpec::new(Tape::TZe12H));
+    assert!(header.contains(r#" width="2.96mm""#), "{}", header);
+    assert!(header.contains(r#" height="12mm""#), "{}", header);
+    assert!(header.contains(r#"viewBox="0 0 42 170""#), "{}", header);
+    assert!(
+        header.contains(r#"data-ptouch-tape="12mm (360dpi)""#),
+        "{}",
+        header
+    );
+
+    let header = svg_header(TapeSpec::new(Tape::TZe12L));
+    assert!(header.contains(r#" width="5.93mm""#), "{}", header);
+    assert!(header.contains(r#" height="12mm""#), "{}", header);
+    assert!(header.contains(r#"viewBox="0 0 42 84""#), "{}", header);
+
+    // PNG stays in dots
+    let png_data = create_box_label(create_label_options()).to_png().unwrap();
+    assert_eq!(png_width(&png_data), 42);
+}
+
+#[test]
+fn test_rasterize_label_svg_prints_as_png() {
+    let fontdb = Arc::new(Database::new());
+    for tape in [Tape::TZe12H, Tape::TZe12L] {
+        let options = LabelOptions {
+            tape_spec: TapeSpec::new(tape),
+            transparent: true,
+            ..create_label_options()
+        };
+        let label = create_box_label(options);
+        let png = PrintableImage::from_png_data(label.to_png().unwrap(), TapeSpec::new(tape));
+
+        // The DPI is only for SVG without the tape
+        let (pixmap, tape_spec) =
+            rasterize_label_svg(&label.to_svg().unwrap(), &fontdb, 180, false).unwrap();
+        assert_eq!(tape_spec.name.to_string(), tape.to_string());
+        let svg = PrintableImage::from_png_data(pixmap.encode_png().unwrap(), tape_spec);
+
+        assert_eq!(
+            svg.unwrap().to_raster_lines().unwrap(),
+            png.unwrap().to_raster_lines().unwrap(),
+            "{}",
+            tape
+        );
+    }
+
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 40 170"><text font-family="No Such Font">A</text></svg>"#;
+    let error = rasterize_label_svg(svg, &fontdb, 360, false).err().unwrap();
+    assert_eq!(error.to_string(), "Font 'No Such Font' not found.");
+}
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("ptouch-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_save_png_replaces_file() {
+    let dir = temp_dir("save-png");
+    let path = dir.join("label.png");
+    std::fs::write(&path, b"old").unwrap();
+
+    let label = create_box_label(create_label_options());
+    label.save_png(&path).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), label.to_png().unwrap());
+    label.save_svg(dir.join("label.svg")).unwrap();
+
+    // Only the saved files, no temporary files left behind
+    let mut names: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["label.png", "label.svg"]);
+
+    assert!(!label.save_png_if_changed(&path).unwrap());
+    std::fs::write(&path, b"old").unwrap();
+    assert!(label.save_png_if_changed(&path).unwrap());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_save_png_keeps_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = temp_dir("save-png-mode");
+    let path = dir.join("label.png");
+    std::fs::write(&path, b"old").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+    create_box_label(create_label_options())
+        .save_png(&path)
+        .unwrap();
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o640);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_failed_write_leaves_no_partial_file() {
+    let dir = temp_dir("save-png-fail");
+    // A directory in the way makes the final rename fail after the data
+    // was written (read-only directories do not stop root)
+    let path = dir.join("label.png");
+    std::fs::create_dir(&path).unwrap();
+
+    let error = write_atomically(&path, b"data").unwrap_err();
+    assert!(error.to_string().contains("label.png"), "{}", error);
+    assert!(error.downcast_ref::<std::io::Error>().is_some());
+
+    let names: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(names, ["label.png"]);
+    assert!(path.is_dir());
+
+    // Missing directory: nothing is created
+    let error = write_atomically(&dir.join("missing/label.png"), b"data").unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<std::io::Error>().unwrap().kind(),
+        std::io::ErrorKind::NotFound
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_transparent_svg_lacks_background() {
+    let options = LabelOptions {
+        transparent: true,
+        ..create_label_options()
+    };
+    let svg = create_box_label(options).to_svg().unwrap();
+    assert!(!svg.contains(r#"class="background""#));
+    assert!(svg.contains(r#"class="margin""#));
+    assert!(!svg.contains(r#"fill="white""#));
+}
+
+#[test]
+fn test_transparent_png_stays_white() {
+    let options = LabelOptions {
+        transparent: true,
+        ..create_label_options()
+    };
+    let png_data = create_box_label(options).to_png().unwrap();
+
+    let decoder = png::Decoder::new(png_data.as_slice());
+    let mut reader = decoder.read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut buf).unwrap();
+
+    // Top-left pixel is in the margin: must be opaque white
+    assert_eq!(&buf[..4], &[255, 255, 255, 255]);
+}
+
+#[test]
+fn test_lint_hidden_overlay_layer() {
+    let overlay = Overlay::new(vec![
+        Box::new(Gap::new(10.0, 10.0, true)),
+        Box::new(Gap::new(20.0, 20.0, true)),
+        // Invisible gaps hide nothing
+        Box::new(Gap::new(30.0, 30.0, false)),
+    ]);
+    let label = Label::from_element(Box::new(overlay), create_label_options());
+    let warnings = label.lint().unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("Layer 1"));
+    assert!(warnings[0].contains("opaque layer 2"));
+
+    // Good layout has no warnings
+    assert!(
+        create_box_label(create_label_options())
+            .lint()
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_lint_margin_overflow() {
+    // 12mm tape: 150 printable dots in the middle of 170
+    let label = Label::from_element(
+        Box::new(Gap::new(40.0, 160.0, true)),
+        create_label_options(),
+    );
+    let warnings = label.lint().unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("0 dots at the top, 10 dots at the bottom"));
+
+    // Auto-scaled contents always fit
+    let options = LabelOptions {
+        auto_scale: true,
+        ..create_label_options()
+    };
+    let label = Label::from_element(Box::new(Gap::new(40.0, 160.0, true)), options);
+    assert!(label.lint().unwrap().is_empty());
+}
+
+fn png_width(png_data: &[u8]) -> u32 {
+    png::Decoder::new(png_data)
+        .read_info()
+        .unwrap()
+        .info()
+        .width
+}
+
+#[test]
+fn test_fixed_length_pads_and_cuts() {
+    // 40 units wide box on a 100 units label: padded with blank
+    let options = LabelOptions {
+        length: Some(100.0),
+        ..create_label_options()
+    };
+    let png_data = create_box_label(options).to_png().unwrap();
+    assert_eq!(png_width(&png_data), 102);
+
+    // On a 10 units label: cut off
+    let options = LabelOptions {
+        length: Some(10.0),
+        ..create_label_options()
+    };
+    let png_data = create_box_label(options).to_png().unwrap();
+    assert_eq!(png_width(&png_data), 12);
+}
+
+#[test]
+fn test_png_records_tape_dpi() {
+    // Pixels per meter of the pHYs chunk: 360DPI = 14173, 180DPI = 7087
+    let pixel_dims = |tape_spec: TapeSpec| {
+        let options = LabelOptions {
+            tape_spec,
+            ..create_label_options()
+        };
+        let png_data = create_box_label(options).to_png().unwrap();
+        let reader = png::Decoder::new(png_data.as_slice()).read_info().unwrap();
+        let dims = reader.info().pixel_dims.unwrap();
+        assert_eq!(dims.unit, png::Unit::Meter);
+        (dims.xppu, dims.yppu)
+    };
+
+    assert_eq!(pixel_dims(TapeSpec::new(Tape::TZe12H)), (14173, 14173));
+    assert_eq!(pixel_dims(TapeSpec::new(Tape::TZe24L)), (7087, 7087));
+    // Raster lines along the tape are doubled in high resolution
+    let high_resolution = TapeSpec::new(Tape::TZe12H).with_high_resolution().unwrap();
+    assert_eq!(pixel_dims(high_resolution), (28346, 14173));
+}
+
+/// Render a 10mm long label and count its raster lines
+fn raster_lines_of_10mm_label(tape_spec: TapeSpec) -> (usize, u32) {
+    let length = tape_spec.mm_to_dots(10.0) as f32;
+    let expected = tape_spec.mm_to_feed_dots(10.0);
+    let options = LabelOptions {
+        tape_spec: tape_spec.clone(),
+        ..create_label_options()
+    };
+    let label = Label::from_element(Box::new(Gap::new(length, 20.0, true)), options);
+    let printable = PrintableImage::from_png_data(label.to_png().unwrap(), tape_spec).unwrap();
+
+    (printable.to_raster_lines().unwrap().len(), expected)
+}
+
+#[test]
+fn test_high_resolution_doubles_raster_lines() {
+    let (lines_360, expected_360) = raster_lines_of_10mm_label(TapeSpec::new(Tape::TZe12H));
+    let (lines_720, expected_720) =
+        raster_lines_of_10mm_label(TapeSpec::new(Tape::TZe12H).with_high_resolution().unwrap());
+
+    assert_eq!(expected_360, 142);
+    assert_eq!(expected_720, 283);
+    // Label adds 2 dots of horizontal slack
+    assert!(lines_360.abs_diff(expected_360 as usize) <= 3);
+    assert!(lines_720.abs_diff(expected_720 as usize) <= 3);
+}
+
+#[test]
+fn test_high_resolution_requires_360dpi() {
+    let result = TapeSpec::new(Tape::TZe12L).with_high_resolution();
+    assert!(result.is_err());
+    let error_msg = format!("{}", result.err().unwrap());
+    assert!(error_msg.contains("High resolution requires 360DPI tape"));
+}
+
+#[test]
+fn test_supersample_keeps_size_and_bilevel() {
+    let decode = |png_data: Vec<u8>| {
+        let decoder = png::Decoder::new(png_data.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).unwrap();
+        (info.width, info.height, buf)
+    };
+    let plain = decode(create_box_label(create_label_options()).to_png().unwrap());
+    let options = LabelOptions {
+        supersample: 3,
+        ..create_label_options()
+    };
+    let supersampled = decode(create_box_label(options).to_png().unwrap());
+
+    assert_eq!((supersampled.0, supersampled.1), (plain.0, plain.1));
+    assert_eq!(plain.1, TapeSpec::new(Tape::TZe12H).width_dots);
+    assert!(supersampled.2.iter().all(|&v| v == 0 || v == 255));
+
+    // Box aligned to the pixel grid comes out the same
+    assert_eq!(supersampled.2, plain.2);
+}
+
+#[test]
+fn test_dimensions_match_png() {
+    let png_size = |png_data: &[u8]| {
+        let reader = png::Decoder::new(png_data).read_info().unwrap();
+        (reader.info().width, reader.info().height)
+    };
+    let variants = [
+        create_label_options(),
+        LabelOptions {
+            rotate: true,
+            ..create_label_options()
+        },
+        LabelOptions {
+            auto_scale: true,
+            ..create_label_options()
+        },
+        LabelOptions {
+            rotate: true,
+            auto_scale: true,
+            ..create_label_options()
+        },
+        LabelOptions {
+            tape_spec: TapeSpec::new(Tape::TZe12H).with_high_resolution().unwrap(),
+            ..create_label_options()
+        },
+    ];
+
+    for options in variants {
+        let label = create_box_label(options);
+        let dimensions = label.dimensions().unwrap();
+        assert_eq!(dimensions, png_size(&label.to_png().unwrap()));
+    }
+    assert_eq!(
+        create_box_label(create_label_options())
+            .dimensions()
+            .unwrap(),
+        (42, TapeSpec::new(Tape::TZe12H).width_dots)
+    );
+}
+
+/// First row with ink in the middle column of the label
+fn top_of_contents(label: &Label) -> u32 {
+    let pixmap = label.to_pixmap().unwrap();
+    let x = pixmap.width() / 2;
+    (0..pixmap.height())
+        .find(|&y| pixmap.pixel(x, y).unwrap().red() < 128)
+        .unwrap()
+}
+
+#[test]
+fn test_placement_offset() {
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    let m = tape_spec.margin_dots();
+    let free = tape_spec.inner_dots - 20;
+    let placed = |placement: &str| {
+        let options = LabelOptions {
+            placement: placement.parse().unwrap(),
+            ..create_label_options()
+        };
+        top_of_contents(&create_box_label(options))
+    };
+
+    assert_eq!(placed("top"), m);
+    assert_eq!(placed("0%"), m);
+    assert_eq!(placed("25%"), m + (free as f32 * 0.25).round() as u32);
+    assert_eq!(placed("50%"), placed("center"));
+    assert_eq!(placed("100%"), placed("bottom"));
+    assert_eq!(placed("30"), m + 30);
+
+    assert_eq!(
+        "25%".parse::<Placement>().unwrap(),
+        Placement::Fraction(0.25)
+    );
+    assert!("150%".parse::<Placement>().is_err());
+    assert!("-3".parse::<Placement>().is_err());
+    assert!("middle".parse::<Placement>().is_err());
+}
+
+#[test]
+fn test_fitting_tape() {
+    let fitted = |width: f32, height: f32, rotate: bool| {
+        let options = LabelOptions {
+            rotate,
+            ..create_label_options()
+        };
+        options
+            .with_fitting_tape(&Gap::new(width, height, true))
+            .map(|options| options.tape_spec.width_mm)
+    };
+
+    let short = fitted(200.0, 60.0, false).unwrap();
+    let tall = fitted(200.0, 200.0, false).unwrap();
+    assert_eq!((short, tall), (6, 18));
+    assert!(tall > short);
+
+    // Length becomes the height when rotated
+    assert_eq!(fitted(60.0, 10.0, true).unwrap(), 6);
+    assert!(fitted(10.0, 600.0, false).is_err());
+
+    // DPI override is kept
+    let options = LabelOptions {
+        tape_spec: TapeSpec::new(Tape::TZe12H).with_dpi(720).unwrap(),
+        ..create_label_options()
+    };
+    let options = options
+        .with_fitting_tape(&Gap::new(10.0, 200.0, true))
+        .unwrap();
+    assert_eq!(options.tape_spec.width_mm, 18);
+    assert_eq!(options.tape_spec.dpi, 720);
+}
+
+#[test]
+fn test_annotated_png() {
+    // Taller than the printable area: bleeds into the bottom margin
+    let label = Label::from_element(
+        Box::new(Gap::new(40.0, 200.0, true)),
+        create_label_options(),
+    );
+    let png_data = label.to_png().unwrap();
+    let annotated = label.to_annotated_png().unwrap();
+    assert_eq!(label.to_png().unwrap(), png_data);
+    assert!(!label.to_svg().unwrap().contains(r#"class="baseline""#));
+
+    let decoder = png::Decoder::new(annotated.as_slice());
+    let mut reader = decoder.read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Grayscale);
+
+    // Caption strip below the tape, widened for the short label
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    assert!(info.height > tape_spec.width_dots);
+    assert!(info.width > png_width(&png_data));
+
+    // Margins are shaded, and contents bleeding in show through
+    let pixel = |x: u32, y: u32| buf[(y * info.width + x) as usize];
+    let blank_margin = pixel(20, 0);
+    let bleeding = pixel(20, tape_spec.width_dots - 1);
+    assert!(blank_margin < 255 && bleeding > 0 && bleeding < blank_margin);
+}
+
+#[test]
+fn test_preview_checkerboard() {
+    // Blank label: the background is all there is to see
+    let options = create_label_options().with_preview_checkerboard(true);
+    let label = Label::from_element(Box::new(Gap::new(100.0, 10.0, true)), options);
+    let plain = Label::from_element(
+        Box::new(Gap::new(100.0, 10.0, true)),
+        create_label_options(),
+    );
+
+    // Printing output is left white
+    assert_eq!(label.to_png().unwrap(), plain.to_png().unwrap());
+    assert!(label.to_svg().unwrap().contains("url(#checkerboard)"));
+    assert!(!plain.to_svg().unwrap().contains("checkerboard"));
+
+    let pixels = |png_data: Vec<u8>| {
+        let decoder = png::Decoder::new(png_data.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).unwrap();
+        // Rows of the printable area, above the caption
+        let tape_spec = TapeSpec::new(Tape::TZe12H);
+        let (start, end) = (
+            tape_spec.margin_dots(),
+            tape_spec.width_dots - tape_spec.margin_dots(),
+        );
+        buf[(start * info.width) as usize..(end * info.width) as usize].to_vec()
+    };
+    // Light squares cover about half of the blank area; the white
+    // preview has only the outline of the bounding box in gray
+    let gray = |pixels: &[u8]| pixels.iter().filter(|&&p| p > 0 && p < 255).count();
+    let checkered = pixels(label.to_annotated_png().unwrap());
+    let white = pixels(plain.to_annotated_png().unwrap());
+    assert!(checkered.contains(&255));
+    assert!(gray(&checkered) > gray(&white) + checkered.len() / 4);
+
+    // SVG source printed as is prints white too
+    let fontdb = Arc::new(Database::new());
+    let rasterize = |label: &Label| {
+        let (pixmap, _) =
+            rasterize_label_svg(&label.to_svg().unwrap(), &fontdb, 360, false).unwrap();
+        pixmap.encode_png().unwrap()
+    };
+    assert_eq!(rasterize(&label), rasterize(&plain));
+}
+
+#[test]
+fn test_render_metrics() {
+    let label = create_box_label(LabelOptions {
+        supersample: 2,
+        ..create_label_options()
+    });
+    let mut metrics = RenderMetrics::default();
+    let png_data = label.to_png_with_metrics(&mut metrics).unwrap();
+    assert_eq!(png_data, label.to_png().unwrap());
+
+    // Label fills the rendering stages only
+    assert!(metrics.fonts.is_zero() && metrics.parse.is_zero());
+    assert!(!metrics.rasterize.is_zero());
+    assert!(!metrics.encode.is_zero());
+    let sum = metrics.measure + metrics.svg + metrics.rasterize + metrics.encode;
+    assert_eq!(metrics.total(), sum);
+
+    // Stages add up over labels
+    let first = metrics;
+    label.to_svg_with_metrics(&mut metrics).unwrap();
+    assert!(metrics.svg > first.svg);
+    assert_eq!(metrics.rasterize, first.rasterize);
+    assert_eq!(metrics.to_string().lines().count(), 7);
+}
+
+#[test]
+fn test_border_frames_printable_area() {
+    // 1mm is 14 dots and 0.5mm is 7 dots at 360 DPI; the 12mm tape has
+    // 150 printable dots below a 10-dot margin
+    let border = Border::new(1.0).with_width(0.5).with_radius(1.0);
+    let options = create_label_options().with_border(Some(border));
+    let label = Label::from_element(Box::new(Gap::new(100.0, 10.0, true)), options);
+    let svg = label.to_svg().unwrap();
+    let (length, _) = label.dimensions().unwrap();
+    let rect = format!(
+        r#"<rect class="border" fill="none" height="115" rx="14" ry="14" stroke="black" stroke-width="7" width="{}" x="17.5" y="27.5"/>"#,
+        length - 35
+    );
+    assert!(svg.contains(&rect), "{}", svg);
+
+    // Printed unless preview only
+    let plain = Label::from_element(
+        Box::new(Gap::new(100.0, 10.0, true)),
+        create_label_options(),
+    );
+    assert_ne!(label.to_png().unwrap(), plain.to_png().unwrap());
+    let preview = Label::from_element(
+        Box::new(Gap::new(100.0, 10.0, true)),
+        create_label_options().with_border(Some(border.with_preview_only(true))),
+    );
+    assert_eq!(preview.to_png().unwrap(), plain.to_png().unwrap());
+    assert!(
+        preview
+            .to_svg()
+            .unwrap()
+            .contains(r#"class="border preview""#)
+    );
+
+    // Nor from SVG source
+    let fontdb = Arc::new(Database::new());
+    let rasterize = |label: &Label| {
+        let (pixmap, _) =
+            rasterize_label_svg(&label.to_svg().unwrap(), &fontdb, 360, false).unwrap();
+        pixmap.encode_png().unwrap()
+    };
+    assert_eq!(rasterize(&preview), rasterize(&plain));
+    assert_ne!(rasterize(&label), rasterize(&plain));
+
+    // Too deep an inset leaves no room for the outline
+    let options = create_label_options().with_border(Some(Border::new(6.0)));
+    let label = Label::from_element(Box::new(Gap::new(100.0, 10.0, true)), options);
+    assert!(label.to_svg().is_err());
+}
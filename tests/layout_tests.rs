@@ -1,9 +1,19 @@
 use fontdb::Database;
-use ptouch::element::{RowOptions, TextOptions, VerticalAlign};
-use ptouch::layout::parse_layout_script;
+use ptouch::element::{
+    Element, Gap, QrCode, QrEcLevel, QrOptions, RowOptions, Text, TextMeasure, TextOptions,
+    TextRenderer, VerticalAlign, measure_text, measure_text_with,
+};
+use ptouch::layout::{
+    LayoutOptions, ParseWarning, ParseWarningKind, WarningSummary, parse_layout_script,
+    parse_layout_script_lenient, parse_layout_script_with_warnings, read_script_file,
+    read_script_paragraphs, split_script, split_to_fit,
+};
+use ptouch::normalize::Normalization;
+use ptouch::payload::IdAlgorithm;
+use ptouch::unescape_shell_string;
 use std::sync::Arc;
 
-fn create_test_options() -> (TextOptions, Arc<Database>) {
+fn create_test_options() -> LayoutOptions {
     let mut fontdb = Database::new();
     fontdb.load_fonts_dir("attic/fonts");
     fontdb.load_system_fonts();
@@ -14,15 +24,18 @@ fn create_test_options() -> (TextOptions, Arc<Database>) {
         font_weight: "normal".to_string(),
         font_size: 24,
         line_height: 30,
+        fontdb: fontdb.clone(),
     };
 
-    (text_options, fontdb)
-}
-
-fn create_test_row_options() -> RowOptions {
-    RowOptions {
-        align: VerticalAlign::default(),
-        padding: 5.0,
+    LayoutOptions {
+        text: text_options,
+        row: RowOptions {
+            align: VerticalAlign::default(),
+            padding: 5.0,
+        },
+        // Structure does not depend on installed fonts; measuring does
+        check_fonts: false,
+        ..LayoutOptions::new(fontdb)
     }
 }
 
@@ -32,9 +45,7 @@ fn script_from_str(input: &str) -> Vec<String> {
 
 fn parse_test_script(input: &str) -> ptouch::Result<Box<dyn ptouch::element::Element>> {
     let script = script_from_str(input);
-    let (options, fontdb) = create_test_options();
-    let row_options = create_test_row_options();
-    parse_layout_script(&script, &options, &row_options, fontdb)
+    parse_layout_script(&script, &create_test_options())
 }
 
 fn assert_parse_result(input: &str, expected: &str) {
@@ -77,6 +88,183 @@ fn test_qr_code() {
     assert_parse_result("qrc:example.com", "QrCode(example.com)");
 }
 
+#[test]
+fn test_qr_code_layout_defaults() {
+    let qr = QrOptions {
+        module_size: 6.0,
+        ec_level: QrEcLevel::H,
+    };
+    let options = LayoutOptions {
+        qr: qr.clone(),
+        ..create_test_options()
+    };
+    let script = script_from_str("qrc:example.com + qrc:example.org");
+    let element = parse_layout_script(&script, &options).unwrap();
+
+    // Both codes take the module size and the (larger) high EC version
+    let expected = QrCode::with_options("example.com".to_string(), &qr)
+        .unwrap()
+        .bounding_box()
+        .unwrap();
+    let default = QrCode::new("example.com".to_string())
+        .unwrap()
+        .bounding_box()
+        .unwrap();
+    assert_eq!(expected.width, 25.0 * 6.0);
+    assert_eq!(default.width, 21.0 * 5.0);
+
+    let tree = element.layout().unwrap();
+    assert_eq!(tree.children.len(), 2);
+    for child in &tree.children {
+        assert_eq!(child.bbox.width, expected.width);
+        assert_eq!(child.bbox.height, expected.height);
+    }
+}
+
+#[test]
+fn test_aztec_layout() {
+    assert_parse_result("aztec:TRIP-0042", "Aztec(TRIP-0042)");
+    assert_parse_result(
+        "Ticket aztec@50:TRIP-0042",
+        "Column(Text(Ticket),Aztec(TRIP-0042))",
+    );
+    // Not an EC percentage, so just text
+    assert_parse_result("aztec@home:42", "Text(aztec@home:42)");
+
+    // The EC percentage may make a larger symbol of the same data
+    let size = |input: &str| {
+        let element = parse_test_script(input).unwrap();
+        element.bounding_box().unwrap().width
+    };
+    assert_eq!(
+        size("aztec:TRIP-0042-SEAT-17A"),
+        size("aztec@33%:TRIP-0042-SEAT-17A")
+    );
+    assert!(size("aztec@90:TRIP-0042-SEAT-17A") > size("aztec:TRIP-0042-SEAT-17A"));
+
+    let error = parse_test_script("aztec@0:TRIP-0042").err().unwrap();
+    assert_eq!(
+        error.to_string(),
+        "Aztec error correction must be 1 to 95%: 0%"
+    );
+    let error = parse_test_script("aztec@:TRIP-0042").err().unwrap();
+    assert_eq!(error.to_string(), "Invalid Aztec error correction ''");
+    let error = parse_test_script("aztec:").err().unwrap();
+    assert_eq!(error.to_string(), "Empty data in Aztec code");
+}
+
+#[test]
+fn test_wifi_qr_code_redacts_password() {
+    assert_parse_result("wifi:home:secret", "Wifi(home)");
+    assert_parse_result(
+        "wifi:home:pass:word:WEP + SSID",
+        "Row(Wifi(home),Text(SSID))",
+    );
+    assert_parse_result("wifi:guest", "Wifi(guest)");
+}
+
+#[test]
+fn test_wifi_qr_code_errors() {
+    let result = parse_test_script("wifi::secret");
+    assert!(result.is_err());
+    let error_msg = format!("{}", result.err().unwrap());
+    assert!(error_msg.contains("Empty SSID"));
+    assert!(!error_msg.contains("secret"));
+
+    let result = parse_test_script("wifi:home::WPA");
+    assert!(result.is_err());
+    let error_msg = format!("{}", result.err().unwrap());
+    assert!(error_msg.contains("Password required for WPA"));
+}
+
+#[test]
+fn test_missing_image_strict_error() {
+    let result = parse_test_script("img:no-such-file.png:40:40");
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_missing_image_lenient_placeholder() {
+    let script = script_from_str("img:no-such-file.png:40:30");
+    let (element, warnings) = parse_layout_script_lenient(&script, &create_test_options()).unwrap();
+
+    assert_eq!(format!("{}", element), "Image(missing:no-such-file.png)");
+    let bbox = element.bounding_box().unwrap();
+    assert_eq!((bbox.width, bbox.height), (40.0, 30.0));
+    assert_eq!(
+        warnings,
+        vec![ParseWarning {
+            position: 1,
+            kind: ParseWarningKind::MissingImage("no-such-file.png".to_string()),
+        }]
+    );
+    assert_eq!(
+        warnings[0].to_string(),
+        "Missing image: no-such-file.png at position 1"
+    );
+    assert!(element.render().is_ok());
+}
+
+fn parse_warnings(script: &[&str]) -> Vec<ParseWarning> {
+    let script: Vec<String> = script.iter().map(|s| s.to_string()).collect();
+    parse_layout_script_with_warnings(&script, &create_test_options())
+        .unwrap()
+        .1
+}
+
+#[test]
+fn test_parse_warnings() {
+    let warning = |position, kind| ParseWarning { position, kind };
+
+    assert_eq!(
+        parse_warnings(&["A", "txt:", "B"]),
+        [warning(2, ParseWarningKind::EmptyText)]
+    );
+    assert_eq!(
+        parse_warnings(&[
+            "box:10", "+", "gap:5", "+", "gap:5", "+", "box:10", "/", "gap:5", "+", "gap:10"
+        ]),
+        [warning(9, ParseWarningKind::InvisibleRow)]
+    );
+    assert_eq!(
+        parse_warnings(&["box:10", "qrc:SN-0001\n"]),
+        [warning(
+            2,
+            ParseWarningKind::QrWhitespace("SN-0001\n".to_string())
+        )]
+    );
+    assert_eq!(
+        parse_warnings(&["Shelf", "A", "A", "txt:A"]),
+        [
+            warning(3, ParseWarningKind::DuplicateText("A".to_string())),
+            warning(4, ParseWarningKind::DuplicateText("A".to_string())),
+        ]
+    );
+
+    // Positions count the tokens of the whole script
+    assert_eq!(
+        parse_warnings(&["[", "rot90:qrc:x ", "]", "Serial", "No."]),
+        [warning(2, ParseWarningKind::QrWhitespace("x ".to_string()))]
+    );
+    assert!(parse_warnings(&["A", "B", "+", "gap:5", "+", "A"]).is_empty());
+}
+
+#[test]
+fn test_warning_summary() {
+    let mut summary = WarningSummary::new();
+    assert!(summary.is_empty());
+
+    summary.add(1, vec!["a".to_string(), "b".to_string()]);
+    summary.add(2, vec![]);
+    summary.add(3, vec!["c".to_string()]);
+    assert_eq!(summary.count(), 3);
+    assert_eq!(summary.label_count(), 2);
+    assert_eq!(
+        summary.to_string(),
+        "3 warning(s) in 2 label(s)\n  Label 1: a\n  Label 1: b\n  Label 3: c"
+    );
+}
+
 // Tests for nested bracket syntax
 #[test]
 fn test_simple_nested_layout() {
@@ -177,6 +365,12 @@ fn test_gap_element_square() {
     assert_parse_result("gap:25", "Gap(25x25)");
 }
 
+#[test]
+fn test_additive_gap_element() {
+    assert_parse_result("gap+:30x40", "Gap+(30x40)");
+    assert_parse_result("qrc:a + gap+:5 + B", "Row(QrCode(a),Gap+(5x5),Text(B))");
+}
+
 #[test]
 fn test_gap_in_horizontal_layout() {
     assert_parse_result(
@@ -248,10 +442,7 @@ fn test_column_padding_with_gap() {
     let bbox1 = without_gap.bounding_box().unwrap();
     let bbox2 = with_gap.bounding_box().unwrap();
 
-    // bbox2 should be 3.0 units taller (5.0 → 8.0)
-    // Actually: without_gap uses default column padding 5.0, with_gap uses 8.0
-    // But gap replaces the whole column, so difference should be 8.0 - 5.0 = 3.0
-    // However, the actual calculation shows -12 difference, so let's check the logic
+    // Column padding is 20.0 (row padding is 5.0 only in Row), gap replaces it
     assert_eq!(bbox2.height, bbox1.height - 12.0);
 }
 
@@ -277,6 +468,142 @@ fn test_gap_eliminates_double_padding() {
     assert_eq!(bbox.width, expected_width);
 }
 
+fn width_of(script: &str) -> f32 {
+    parse_test_script(script)
+        .unwrap()
+        .bounding_box()
+        .unwrap()
+        .width
+}
+
+#[test]
+fn test_row_padding_with_additive_gap() {
+    // Row test padding is 5.0, gap+ is added to it on both sides
+    let qrc_width = width_of("qrc:hello") + width_of("qrc:world");
+
+    assert_eq!(width_of("qrc:hello + qrc:world"), qrc_width + 5.0);
+    assert_eq!(
+        width_of("qrc:hello + gap+:10x0 + qrc:world"),
+        qrc_width + 5.0 + 10.0 + 5.0
+    );
+    assert_eq!(
+        width_of("qrc:hello + gap+:10x0 + gap+:10x0 + qrc:world"),
+        qrc_width + 5.0 + 10.0 + 5.0 + 10.0 + 5.0
+    );
+}
+
+#[test]
+fn test_column_padding_with_additive_gap() {
+    let height_of = |script: &str| {
+        parse_test_script(script)
+            .unwrap()
+            .bounding_box()
+            .unwrap()
+            .height
+    };
+    let qrc_height = height_of("qrc:hello") + height_of("qrc:world");
+
+    // Column padding is 20.0
+    assert_eq!(
+        height_of("qrc:hello gap+:0x8 qrc:world"),
+        qrc_height + 20.0 + 8.0 + 20.0
+    );
+}
+
+#[test]
+fn test_mixed_gaps_padding() {
+    let qrc_width = width_of("qrc:hello") + width_of("qrc:world");
+
+    // Padding only between gap+ and the next QR code
+    assert_eq!(
+        width_of("qrc:hello + gap:5x0 + gap+:10x0 + qrc:world"),
+        qrc_width + 5.0 + 10.0 + 5.0
+    );
+    assert_eq!(
+        width_of("qrc:hello + gap+:10x0 + gap:5x0 + qrc:world"),
+        qrc_width + 5.0 + 10.0 + 5.0
+    );
+    // Padding before gap+ at the start, none before gap
+    assert_eq!(
+        width_of("gap+:10x0 + qrc:hello + qrc:world"),
+        qrc_width + 10.0 + 5.0 + 5.0
+    );
+    assert_eq!(
+        width_of("gap:10x0 + qrc:hello + qrc:world"),
+        qrc_width + 10.0 + 5.0
+    );
+}
+
+#[test]
+fn test_rotated_additive_gap() {
+    let qrc_width = width_of("qrc:hello") + width_of("qrc:world");
+
+    // Rotation keeps gap+ additive
+    assert_eq!(
+        width_of("qrc:hello + rot90:gap+:0x10 + qrc:world"),
+        qrc_width + 5.0 + 10.0 + 5.0
+    );
+    assert_eq!(
+        width_of("qrc:hello + rot90:gap:0x10 + qrc:world"),
+        qrc_width + 10.0
+    );
+}
+
+fn height_of(script: &str, options: &LayoutOptions) -> f32 {
+    let script = script_from_str(script);
+    parse_layout_script(&script, options)
+        .unwrap()
+        .bounding_box()
+        .unwrap()
+        .height
+}
+
+#[test]
+fn test_column_padding_option() {
+    let options = create_test_options();
+    // Default column padding stays 20.0
+    assert_eq!(height_of("box:10 box:10", &options), 40.0);
+
+    let options = LayoutOptions {
+        column_padding: 4.0,
+        ..options
+    };
+    assert_eq!(height_of("box:10 box:10", &options), 24.0);
+    assert_eq!(height_of("box:10 box:10 box:10", &options), 38.0);
+}
+
+#[test]
+fn test_bracket_column_padding() {
+    let options = create_test_options();
+
+    assert_eq!(height_of("[p=8 box:10 box:10 ]", &options), 28.0);
+    assert_eq!(height_of("[p=0 box:10 box:10 box:10 ]", &options), 30.0);
+    // Nested brackets inherit the override
+    assert_eq!(
+        height_of("[p=8 box:10 [ box:10 box:10 ] ]", &options),
+        10.0 + 8.0 + 28.0
+    );
+    // Override ends at the closing bracket
+    assert_eq!(
+        height_of("[p=0 box:10 box:10 ] box:10", &options),
+        20.0 + 20.0 + 10.0
+    );
+    assert_parse_result("[p=8 box:1 box:2 ]", "Column(Box(1x1),Box(2x2))");
+}
+
+#[test]
+fn test_invalid_bracket_column_padding() {
+    for (script, expected) in [
+        ("[p=x box:1 ]", "Invalid column padding '[p=x'"),
+        ("[p=-1 box:1 ]", "Invalid column padding '[p=-1'"),
+        ("[p=8 box:1", "Expected ']'"),
+    ] {
+        let result = parse_test_script(script);
+        let error_msg = format!("{}", result.err().unwrap());
+        assert!(error_msg.contains(expected), "{}: {}", script, error_msg);
+    }
+}
+
 #[test]
 fn test_box_element_with_dimensions() {
     assert_parse_result("box:30x40", "Box(30x40)");
@@ -287,6 +614,38 @@ fn test_box_element_square() {
     assert_parse_result("box:25", "Box(25x25)");
 }
 
+#[test]
+fn test_rotated_element() {
+    assert_parse_result("rot90:box:20x5", "Rot90(Box(20x5))");
+    assert_parse_result(
+        "qrc:example.com + rot270:[ box:10 gap:5 ]",
+        "Row(QrCode(example.com),Rot270(Column(Box(10x10),Gap(5x5))))",
+    );
+    assert_parse_result(
+        "box:10 rot180: box:4 box:6",
+        "Column(Box(10x10),Rot180(Box(4x4)),Box(6x6))",
+    );
+    assert_parse_result("rot90:rot90:box:3", "Rot90(Rot90(Box(3x3)))");
+
+    let element = parse_test_script("rot90:box:20x5").unwrap();
+    let bbox = element.bounding_box().unwrap();
+    assert_eq!((bbox.width, bbox.height), (5.0, 20.0));
+}
+
+#[test]
+fn test_rotated_element_without_target() {
+    for script in ["rot90:", "box:10 rot270:", "[ rot180:] ]", "rot90:+ box:1"] {
+        let result = parse_test_script(script);
+        assert!(result.is_err(), "'{}' should fail", script);
+        let error_msg = format!("{}", result.err().unwrap());
+        assert!(
+            error_msg.contains("Expected element after"),
+            "{}",
+            error_msg
+        );
+    }
+}
+
 #[test]
 fn test_box_in_horizontal_layout() {
     // Box test padding is 5.0, box should replace it
@@ -477,3 +836,385 @@ fn test_invalid_line_height() {
     let error_msg = format!("{}", result.err().unwrap());
     assert!(error_msg.contains("Invalid line height: 'invalid'"));
 }
+
+// Line height tests
+#[test]
+fn test_tight_line_height_shorter_bbox() {
+    // Default line height in tests is 30 (font size 24)
+    let normal = parse_test_script("A B C").unwrap();
+    let tight = parse_test_script("fnt::::10 A B C").unwrap();
+
+    let bbox1 = normal.bounding_box().unwrap();
+    let bbox2 = tight.bounding_box().unwrap();
+
+    // Two line advances shrink by 20 each
+    assert_eq!(bbox2.height, bbox1.height - 40.0);
+}
+
+#[test]
+fn test_loose_line_height_not_clipped() {
+    // 20 lines advancing 72 each do not fit in 20 * font_size
+    let script = ["fnt::::72"]
+        .into_iter()
+        .chain(std::iter::repeat_n("A", 20))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let element = parse_test_script(&script).unwrap();
+    let bbox = element.bounding_box().unwrap();
+
+    assert!(bbox.height > 19.0 * 72.0);
+}
+
+#[test]
+fn test_qrc_id() {
+    assert_parse_result(
+        "qrc+id:https://example.com",
+        "Column(QrCode(https://example.com),Text(20381b))",
+    );
+    assert_parse_result(
+        "Asset qrc+id:SN-0001 + Shelf",
+        "Row(Column(Text(Asset),Column(QrCode(SN-0001),Text(7x0j2a))),Text(Shelf))",
+    );
+
+    let options = LayoutOptions {
+        id_algorithm: IdAlgorithm::Crc32,
+        ..create_test_options()
+    };
+    let element = parse_layout_script(&script_from_str("qrc+id:SN-0001"), &options).unwrap();
+    assert_eq!(
+        format!("{}", element),
+        "Column(QrCode(SN-0001),Text(v658e1))"
+    );
+}
+
+#[test]
+fn test_parse_without_font_check() {
+    let script = script_from_str("Hello + qrc:example.com");
+    let options = LayoutOptions {
+        check_fonts: false,
+        ..LayoutOptions::new(Arc::new(Database::new()))
+    };
+    let element = parse_layout_script(&script, &options).unwrap();
+    assert_eq!(
+        format!("{}", element),
+        "Row(Text(Hello),QrCode(example.com))"
+    );
+
+    // Missing fonts are reported when checked
+    let options = LayoutOptions {
+        check_fonts: true,
+        ..options
+    };
+    assert!(parse_layout_script(&script, &options).is_err());
+}
+
+#[test]
+fn test_split_script_comments() {
+    let script = "# Title\nHello # greeting\n'# quoted' \"# double\" C#\n";
+    assert_eq!(
+        split_script(script).unwrap(),
+        [vec!["Hello", "# quoted", "# double", "C#"]]
+    );
+}
+
+#[test]
+fn test_split_script_quotes_and_escapes() {
+    let script = r#"'Serial No.' "say \"hi\"" a\ b 'it''s' "" C:\dir '\x'"#;
+    assert_eq!(
+        split_script(script).unwrap(),
+        [vec![
+            "Serial No.",
+            "say \"hi\"",
+            "a b",
+            "its",
+            "",
+            "C:\\dir",
+            "\\x"
+        ]]
+    );
+
+    // Same escapes as unescape_shell_string()
+    let token = r#"a\ b\x"c\"d"'e\'"#;
+    assert_eq!(
+        split_script(token).unwrap(),
+        [vec![unescape_shell_string(token)]]
+    );
+    assert!(split_script("'open").is_err());
+    assert!(split_script("\"open\\\"").is_err());
+}
+
+#[test]
+fn test_split_script_lines_and_paragraphs() {
+    // Lines are joined, blank lines (also with spaces) separate paragraphs
+    let script = "one two\nthree\n\n  \n\nfour \\\nfi\\\nve\n# comment\n\nsix\n";
+    assert_eq!(
+        split_script(script).unwrap(),
+        [
+            vec!["one", "two", "three"],
+            vec!["four", "five"],
+            vec!["six"]
+        ]
+    );
+    assert!(split_script("\n# only comments\n\n").unwrap().is_empty());
+}
+
+#[test]
+fn test_split_script_crlf_and_bom() {
+    let script = "\u{feff}# CRLF\r\nqrc:A \\\r\n+ B\r\n\r\nC\r\n";
+    assert_eq!(
+        split_script(script).unwrap(),
+        [vec!["qrc:A", "+", "B"], vec!["C"]]
+    );
+}
+
+#[test]
+fn test_read_script_file() {
+    let path = std::env::temp_dir().join(format!("ptouch-{}-script.ptl", std::process::id()));
+    std::fs::write(&path, "Hello\n\nqrc:example.com + World\n").unwrap();
+
+    let script = read_script_file(&path).unwrap();
+    assert_eq!(script, ["Hello", "qrc:example.com", "+", "World"]);
+    assert_eq!(read_script_paragraphs(&path).unwrap().len(), 2);
+    let element = parse_layout_script(&script, &create_test_options()).unwrap();
+    assert_eq!(
+        format!("{}", element),
+        "Row(Column(Text(Hello),QrCode(example.com)),Text(World))"
+    );
+
+    std::fs::remove_file(&path).unwrap();
+    let error = read_script_file(&path).err().unwrap();
+    assert!(error.to_string().contains("script.ptl"));
+}
+
+/// First element of type T in the tree, depth first
+fn find_mut<T: Element + 'static>(element: &mut dyn Element) -> Option<&mut T> {
+    if element.downcast_ref::<T>().is_some() {
+        return element.downcast_mut::<T>();
+    }
+    element
+        .children_mut()
+        .into_iter()
+        .find_map(|child| find_mut::<T>(child.as_mut()))
+}
+
+#[test]
+fn test_edit_parsed_elements() {
+    let mut element = parse_test_script("[ box:10x20 + rot90:Hello ] / qrc:abc").unwrap();
+
+    let text = find_mut::<Text>(element.as_mut()).unwrap();
+    assert_eq!(text.texts(), ["Hello"]);
+    text.options_mut().font_size *= 2;
+    text.set_texts(&["Hello".to_string(), "World".to_string()]);
+
+    let gap = find_mut::<Gap>(element.as_mut()).unwrap();
+    assert_eq!(gap.size(), (10.0, 20.0));
+    gap.set_size(30.0, 20.0);
+
+    let qr_code = find_mut::<QrCode>(element.as_mut()).unwrap();
+    assert_eq!(qr_code.data(), "abc");
+    qr_code.set_data("xyz".to_string()).unwrap();
+
+    assert_eq!(
+        element.to_string(),
+        "Overlay(Row(Box(30x20),Rot90(Text(Hello,World))),QrCode(xyz))"
+    );
+    let text = find_mut::<Text>(element.as_mut()).unwrap();
+    assert_eq!(text.options().font_size, 48);
+}
+
+#[test]
+fn test_edit_font_size_enlarges_bbox() {
+    let mut element = parse_test_script("box:10x10 + Hello").unwrap();
+    let before = element.bounding_box().unwrap();
+
+    let text = find_mut::<Text>(element.as_mut()).unwrap();
+    text.options_mut().font_size *= 2;
+    text.options_mut().line_height *= 2;
+
+    let after = element.bounding_box().unwrap();
+    assert!(after.width > before.width && after.height > before.height);
+}
+
+#[test]
+fn test_normalize_full_width_text() {
+    let script = script_from_str("ＳＮ－００１２ + ｶﾅ + qrc:ＳＮ");
+    let parse = |normalization: Normalization| {
+        let options = LayoutOptions {
+            normalization,
+            ..create_test_options()
+        };
+        parse_layout_script(&script, &options).unwrap().to_string()
+    };
+
+    assert_eq!(
+        parse(Normalization::None),
+        "Row(Text(ＳＮ－００１２),Text(ｶﾅ),QrCode(ＳＮ))"
+    );
+    // Text is folded, QR code payloads are kept
+    assert_eq!(
+        parse(Normalization::Nfkc),
+        "Row(Text(SN-0012),Text(カナ),QrCode(ＳＮ))"
+    );
+    assert_eq!(
+        parse(Normalization::Nfc),
+        "Row(Text(ＳＮ－００１２),Text(ｶﾅ),QrCode(ＳＮ))"
+    );
+    assert_eq!(
+        "NFKC".parse::<Normalization>().unwrap(),
+        Normalization::Nfkc
+    );
+    assert!("nfd".parse::<Normalization>().is_err());
+}
+
+#[test]
+fn test_combining_accent_bbox() {
+    let options = create_test_options();
+    let measure = |text: &str| measure_text(&[text.to_string()], &options.text);
+
+    // An accent shares the cell of its base character
+    let composed = measure("Café").unwrap();
+    let decomposed = measure("Cafe\u{301}").unwrap();
+    assert_eq!(composed.width, decomposed.width);
+    assert!((composed.height - decomposed.height).abs() <= 2.0);
+
+    let normalized = measure(&Normalization::Nfc.apply("Cafe\u{301}")).unwrap();
+    assert_eq!(normalized, composed);
+}
+
+#[test]
+fn test_logical_measure_agrees_with_pixel() {
+    let options = create_test_options();
+    let texts = ["Hello, World!".to_string(), "gyp 0123".to_string()];
+    let measure = |mode| {
+        measure_text_with(&texts, &options.text, mode, TextRenderer::default(), false).unwrap()
+    };
+
+    let pixel = measure(TextMeasure::Pixel);
+    let logical = measure(TextMeasure::Logical);
+    assert_eq!(logical.x, 0.0);
+    assert!(
+        (pixel.y - logical.y).abs() <= 2.0,
+        "{:?} {:?}",
+        pixel,
+        logical
+    );
+    assert!(
+        (pixel.width - logical.width).abs() <= 2.0,
+        "{:?} {:?}",
+        pixel,
+        logical
+    );
+    assert!(
+        (pixel.height - logical.height).abs() <= 2.0,
+        "{:?} {:?}",
+        pixel,
+        logical
+    );
+
+    assert_eq!(
+        "Logical".parse::<TextMeasure>().unwrap(),
+        TextMeasure::Logical
+    );
+    assert!("ink".parse::<TextMeasure>().is_err());
+}
+
+#[test]
+fn test_resvg_renderer_hangs_text_from_top() {
+    let options = create_test_options();
+    let texts = vec!["Hello".to_string(), "gyp".to_string()];
+    let measure = |renderer| {
+        measure_text_with(&texts, &options.text, TextMeasure::Pixel, renderer, false).unwrap()
+    };
+
+    let compat = measure(TextRenderer::ImageMagickCompat);
+    let resvg = measure(TextRenderer::Resvg);
+    assert!(resvg.y < compat.y, "{:?} {:?}", resvg, compat);
+    assert!((resvg.width - compat.width).abs() <= 1.0);
+    assert!((resvg.height - compat.height).abs() <= 1.0);
+
+    // Baselines in the bbox agree, so rows align the same way
+    let baseline = |renderer| {
+        Text::new(&texts, options.text.clone())
+            .unwrap()
+            .with_renderer(renderer)
+            .baseline()
+            .unwrap()
+            .unwrap()
+    };
+    let compat = baseline(TextRenderer::ImageMagickCompat);
+    let resvg = baseline(TextRenderer::Resvg);
+    assert!((resvg - compat).abs() <= 1.0, "{} {}", resvg, compat);
+}
+
+#[test]
+fn test_trim_bearing_cuts_leading_space() {
+    let options = create_test_options();
+    let measure = |text: &str, trim_bearing| {
+        let texts = [text.to_string()];
+        measure_text_with(
+            &texts,
+            &options.text,
+            TextMeasure::Pixel,
+            TextRenderer::default(),
+            trim_bearing,
+        )
+        .unwrap()
+    };
+
+    // Leading spaces are kept by default
+    let kept = measure("  Hello", false);
+    assert_eq!(kept.x, 0.0);
+    assert!(kept.width > measure("Hello", false).width);
+
+    // Trimming starts the box at the ink of "H" and keeps its right edge
+    let trimmed = measure("  Hello", true);
+    assert!(trimmed.x > 0.0);
+    assert_eq!(trimmed.x + trimmed.width, kept.width);
+    assert!((trimmed.width - measure("Hello", true).width).abs() <= 1.0);
+}
+
+#[test]
+fn test_split_to_fit_long_text() {
+    let tokens: Vec<String> = (1..=10).map(|i| format!("line{}", i)).collect();
+    let labels = split_to_fit(&tokens, |label| Ok(label.len() <= 4)).unwrap();
+    assert!(labels.len() > 1);
+    assert!(labels.iter().all(|label| label.len() <= 4));
+
+    // Continuation marks go between labels only
+    assert_eq!(labels[0], ["line1", "line2", "line3", "…"]);
+    assert_eq!(labels[1], ["…", "line4", "line5", "…"]);
+    assert_eq!(labels.last().unwrap().last().unwrap(), "line10");
+    let lines: Vec<&String> = labels.iter().flatten().filter(|t| *t != "…").collect();
+    assert_eq!(lines, tokens.iter().collect::<Vec<_>>());
+
+    // Text that fits stays on one label
+    let labels = split_to_fit(&tokens, |_| Ok(true)).unwrap();
+    assert_eq!(labels, vec![tokens]);
+}
+
+#[test]
+fn test_split_to_fit_errors() {
+    let tokens: Vec<String> = ["short", "far too long"].map(String::from).into();
+    let result = split_to_fit(&tokens, |label| Ok(label.iter().all(|t| t.len() < 8)));
+    assert!(result.unwrap_err().to_string().contains("far too long"));
+
+    let tokens: Vec<String> = ["A", "+", "B"].map(String::from).into();
+    let result = split_to_fit(&tokens, |_| Ok(true));
+    assert!(result.unwrap_err().to_string().contains("'+'"));
+
+    // Rotations prefixed to a token count as structure, as alone
+    for token in [
+        "rot90:Hello",
+        "rot180:",
+        "rot270:qrc:x",
+        "[p=4",
+        "fnt:default",
+    ] {
+        let tokens: Vec<String> = ["A", token, "B"].map(String::from).into();
+        let result = split_to_fit(&tokens, |_| Ok(true));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            format!("Cannot split layout structure: '{}'", token)
+        );
+    }
+}
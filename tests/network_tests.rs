@@ -0,0 +1,203 @@
+use ptouch::Result;
+use ptouch::backend::{
+    Backend, FileBackend, NetworkBackend, OID_BROTHER_DEVICE_ID, OID_BROTHER_STATUS, SnmpSource,
+};
+use ptouch::printable_image::PrintableImage;
+use ptouch::printer::{PrintOptions, Printer};
+use ptouch::tape::TapeSpec;
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// SNMP values of a PT-P900 with 12mm tape
+fn canned_snmp() -> HashMap<&'static str, Vec<u8>> {
+    let mut status = vec![0u8; 32];
+    status[..5].copy_from_slice(&[0x80, 0x20, 0x42, 0x30, 0x71]);
+    status[10] = 12; // media width
+    status[11] = 0x01; // laminated tape
+
+    HashMap::from([
+        (OID_BROTHER_STATUS, status),
+        (
+            OID_BROTHER_DEVICE_ID,
+            b"MFG:Brother;CMD:PT-CBP;MDL:PT-P900;CLS:PRINTER;".to_vec(),
+        ),
+    ])
+}
+
+struct CannedSnmp(HashMap<&'static str, Vec<u8>>);
+
+impl SnmpSource for CannedSnmp {
+    fn get(&self, oid: &str) -> Result<Vec<u8>> {
+        self.0
+            .get(oid)
+            .cloned()
+            .ok_or_else(|| format!("No such OID: {}", oid).into())
+    }
+}
+
+/// Fake printer on a free local port that records one print job
+fn start_fake_printer() -> (String, JoinHandle<Vec<u8>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut job = Vec::new();
+        stream.read_to_end(&mut job).unwrap();
+        job
+    });
+    (address, handle)
+}
+
+/// Grayscale PNG for `tape_spec`: 8 columns with a black bar
+fn create_png(tape_spec: &TapeSpec) -> Vec<u8> {
+    let (width, height) = (8, tape_spec.width_dots);
+    let mut pixels = vec![255u8; (width * height) as usize];
+    for y in 40..120 {
+        pixels[(y * width + 3) as usize] = 0;
+    }
+
+    let mut png_data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png_data, width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .unwrap()
+        .write_image_data(&pixels)
+        .unwrap();
+    png_data
+}
+
+#[test]
+fn test_network_print_job() {
+    let (address, fake_printer) = start_fake_printer();
+    let backend = NetworkBackend::with_snmp(&address, Box::new(CannedSnmp(canned_snmp()))).unwrap();
+    let mut printer = Printer::new(backend);
+
+    // Same steps as `ptouch print`
+    printer.verify_device().unwrap();
+    let status = printer.get_status().unwrap();
+    assert!(!status.has_errors());
+    let tape_spec =
+        TapeSpec::from_width_mm_and_dpi(status.media_width_mm(), status.printer_dpi()).unwrap();
+    assert_eq!(tape_spec.width_mm, 12);
+
+    let printable = PrintableImage::from_png_data(create_png(&tape_spec), tape_spec).unwrap();
+    printer.print(&printable, &PrintOptions::default()).unwrap();
+    drop(printer);
+    let job = fake_printer.join().unwrap();
+
+    // Golden job written by the file backend
+    let path = std::env::temp_dir().join(format!("ptouch-{}-golden.bin", std::process::id()));
+    let mut printer = Printer::new(FileBackend::new(&path).unwrap());
+    printer.print(&printable, &PrintOptions::default()).unwrap();
+    drop(printer);
+    let golden = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(job, golden);
+    assert!(job.starts_with(&[0x00; 200]));
+    assert_eq!(job.last(), Some(&0x1A));
+}
+
+#[test]
+fn test_network_reconnects_dropped_connection() {
+    // Fake printer that drops the first connection while idle
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let (dropped, wait_dropped) = mpsc::channel();
+    let fake_printer = thread::spawn(move || {
+        drop(listener.accept().unwrap());
+        dropped.send(()).unwrap();
+
+        // No job (instead of hanging) if the client never reconnects
+        listener.set_nonblocking(true).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut job = Vec::new();
+        while Instant::now() < deadline {
+            if let Ok((mut stream, _)) = listener.accept() {
+                stream.set_nonblocking(false).unwrap();
+                stream.read_to_end(&mut job).unwrap();
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        job
+    });
+
+    let backend = NetworkBackend::with_snmp(&address, Box::new(CannedSnmp(canned_snmp()))).unwrap();
+    let mut printer = Printer::new(backend);
+    let status = printer.get_status().unwrap();
+    wait_dropped.recv().unwrap();
+
+    let tape_spec =
+        TapeSpec::from_width_mm_and_dpi(status.media_width_mm(), status.printer_dpi()).unwrap();
+    let printable = PrintableImage::from_png_data(create_png(&tape_spec), tape_spec).unwrap();
+    let report = printer.print(&printable, &PrintOptions::default()).unwrap();
+    drop(printer);
+
+    // The whole job arrives on the new connection
+    let job = fake_printer.join().unwrap();
+    assert_eq!(job.len(), report.bytes_sent);
+    assert_eq!(job.last(), Some(&0x1A));
+}
+
+#[test]
+fn test_network_fails_on_connection_dropped_mid_job() {
+    // Fake printer that drops the connection after the first chunk
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let (dropped, wait_dropped) = mpsc::channel();
+    let fake_printer = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut header = [0u8; 2];
+        stream.read_exact(&mut header).unwrap();
+        drop(stream);
+        dropped.send(()).unwrap();
+
+        // The rest of the job must not come on a new connection
+        thread::sleep(Duration::from_millis(200));
+        listener.set_nonblocking(true).unwrap();
+        listener.accept().is_err()
+    });
+
+    let mut backend =
+        NetworkBackend::with_snmp(&address, Box::new(CannedSnmp(canned_snmp()))).unwrap();
+    backend.begin_job().unwrap();
+    backend.send_command(b"\x1B\x40").unwrap();
+    wait_dropped.recv().unwrap();
+
+    let error = backend.send_command(b"\x0C").unwrap_err();
+    assert!(
+        error.to_string().contains("closed the connection"),
+        "{}",
+        error
+    );
+    assert!(
+        fake_printer.join().unwrap(),
+        "reconnected in the middle of the job"
+    );
+}
+
+#[test]
+fn test_network_rejects_other_device() {
+    let (address, fake_printer) = start_fake_printer();
+    let mut snmp = canned_snmp();
+    snmp.insert(
+        OID_BROTHER_DEVICE_ID,
+        b"MFG:Brother;MDL:HL-L2350DW;".to_vec(),
+    );
+    let backend = NetworkBackend::with_snmp(&address, Box::new(CannedSnmp(snmp))).unwrap();
+
+    let mut printer = Printer::new(backend);
+    let error = printer.verify_device().unwrap_err().to_string();
+    assert!(error.contains("not a Brother P-Touch: Brother HL-L2350DW"));
+
+    drop(printer);
+    assert!(fake_printer.join().unwrap().is_empty());
+}
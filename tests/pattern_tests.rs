@@ -0,0 +1,95 @@
+use fontdb::Database;
+use ptouch::label::{Label, LabelOptions};
+use ptouch::pattern::{
+    BLACK_MM, PATTERN_LENGTH_MM, PIN_LINES_MM, PIN_RULER_MM, TOTAL_LENGTH_MM, test_pattern,
+};
+use ptouch::tape::{Tape, TapeSpec};
+use resvg::tiny_skia::Pixmap;
+use std::sync::Arc;
+
+fn render_pattern(tape_spec: &TapeSpec) -> Pixmap {
+    let fontdb = Arc::new(Database::new());
+    let options = LabelOptions::new(fontdb).with_tape_spec(tape_spec.clone());
+    Label::from_element(test_pattern(tape_spec, None), options)
+        .to_pixmap()
+        .unwrap()
+}
+
+fn is_inked(pixmap: &Pixmap, x: u32, y: u32) -> bool {
+    pixmap.pixel(x, y).unwrap().red() < 128
+}
+
+#[test]
+fn test_pattern_bars_and_ticks() {
+    for tape in [Tape::TZe12H, Tape::TZe24H, Tape::TZe12L] {
+        let tape_spec = TapeSpec::new(tape);
+        let pixmap = render_pattern(&tape_spec);
+        let top = (tape_spec.width_dots - tape_spec.inner_dots) / 2;
+        let bottom = top + tape_spec.inner_dots - 1;
+        let middle = top + tape_spec.inner_dots / 2;
+
+        for mm in 0..=PATTERN_LENGTH_MM {
+            let x = tape_spec.mm_to_dots(mm as f32);
+            // Ticks on both edges of the printable area every 1mm
+            assert!(is_inked(&pixmap, x, top), "{}: tick at {}mm", tape, mm);
+            assert!(is_inked(&pixmap, x, bottom), "{}: tick at {}mm", tape, mm);
+            // Full-height bars every 5mm
+            assert_eq!(
+                is_inked(&pixmap, x, middle),
+                mm % 5 == 0,
+                "{}: {}mm",
+                tape,
+                mm
+            );
+            // Nothing in the margins
+            assert!(!is_inked(&pixmap, x, top - 1));
+            assert!(!is_inked(&pixmap, x, bottom + 1));
+        }
+
+        // Blank between ticks
+        let x = (tape_spec.mm_to_dots(1.0) + tape_spec.mm_to_dots(2.0)) / 2;
+        assert!(!is_inked(&pixmap, x, top));
+    }
+}
+
+#[test]
+fn test_pattern_dimensions() {
+    for tape in [Tape::TZe3H, Tape::TZe12H, Tape::TZe36H, Tape::TZe24L] {
+        let tape_spec = TapeSpec::new(tape);
+        let pixmap = render_pattern(&tape_spec);
+
+        // Label adds 2 dots of horizontal slack
+        let length = tape_spec.mm_to_dots(TOTAL_LENGTH_MM as f32);
+        assert_eq!(pixmap.width(), length + 2, "{}", tape);
+        assert_eq!(pixmap.height(), tape_spec.width_dots, "{}", tape);
+    }
+}
+
+#[test]
+fn test_pattern_head_check() {
+    for tape in [Tape::TZe12H, Tape::TZe24L] {
+        let tape_spec = TapeSpec::new(tape);
+        let pixmap = render_pattern(&tape_spec);
+        let top = tape_spec.margin_dots();
+        let rows = top..top + tape_spec.inner_dots;
+        let pin = |y: u32| tape_spec.right_pins + y - top;
+
+        // Every pin inks the black section
+        let x = tape_spec.mm_to_dots(BLACK_MM as f32 + 2.5);
+        assert!(rows.clone().all(|y| is_inked(&pixmap, x, y)), "{}", tape);
+
+        // Lines of even pins, then odd pins
+        for (mm, parity) in [(PIN_LINES_MM + 2, 0), (PIN_LINES_MM + 7, 1)] {
+            let x = tape_spec.mm_to_dots(mm as f32);
+            for y in rows.clone() {
+                assert_eq!(is_inked(&pixmap, x, y), pin(y) % 2 == parity, "{}", tape);
+            }
+        }
+
+        // Ruler ticks on every 8th pin
+        let x = tape_spec.mm_to_dots(PIN_RULER_MM as f32);
+        for y in rows.clone() {
+            assert_eq!(is_inked(&pixmap, x, y), pin(y) % 8 == 0, "{}", tape);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use ptouch::payload::{
+    IdAlgorithm, WifiAuth, mailto, mecard, payload_id, payload_id_with, tel, wifi,
+};
+
+#[test]
+fn test_wifi_payload() {
+    assert_eq!(
+        wifi("home", "secret", WifiAuth::Wpa),
+        "WIFI:T:WPA;S:home;P:secret;;"
+    );
+    assert_eq!(
+        wifi("office", "12345", WifiAuth::Wep),
+        "WIFI:T:WEP;S:office;P:12345;;"
+    );
+}
+
+#[test]
+fn test_wifi_payload_nopass_omits_password() {
+    assert_eq!(
+        wifi("guest", "ignored", WifiAuth::NoPass),
+        "WIFI:T:nopass;S:guest;;"
+    );
+}
+
+#[test]
+fn test_wifi_payload_escapes_special_characters() {
+    assert_eq!(
+        wifi("my;net", r#"pa"ss:w,o\rd"#, WifiAuth::Wpa),
+        r#"WIFI:T:WPA;S:my\;net;P:pa\"ss\:w\,o\\rd;;"#
+    );
+}
+
+#[test]
+fn test_wifi_payload_unicode() {
+    assert_eq!(
+        wifi("自宅ネット", "パスワード", WifiAuth::Wpa),
+        "WIFI:T:WPA;S:自宅ネット;P:パスワード;;"
+    );
+}
+
+#[test]
+fn test_wifi_auth_from_str() {
+    assert_eq!("WPA".parse::<WifiAuth>().unwrap(), WifiAuth::Wpa);
+    assert_eq!("wep".parse::<WifiAuth>().unwrap(), WifiAuth::Wep);
+    assert_eq!("nopass".parse::<WifiAuth>().unwrap(), WifiAuth::NoPass);
+    assert!("WPA3-SAE".parse::<WifiAuth>().is_err());
+}
+
+#[test]
+fn test_mailto_and_tel_payload() {
+    assert_eq!(mailto("nom@example.com"), "mailto:nom@example.com");
+    assert_eq!(tel("03-1234-5678"), "tel:0312345678");
+}
+
+#[test]
+fn test_mecard_payload() {
+    assert_eq!(
+        mecard("Nomura", "0312345678", "nom@example.com"),
+        "MECARD:N:Nomura;TEL:0312345678;EMAIL:nom@example.com;;"
+    );
+    assert_eq!(mecard("Foo; Bar", "", ""), r"MECARD:N:Foo\; Bar;;");
+}
+
+#[test]
+fn test_payload_id_sha256() {
+    // Leading 30 bits of SHA-256 in Crockford's base32
+    assert_eq!(payload_id(""), "werc8g");
+    assert_eq!(payload_id("https://example.com"), "20381b");
+    assert_eq!(payload_id("SN-0001"), "7x0j2a");
+}
+
+#[test]
+fn test_payload_id_crc32() {
+    let crc32 = |payload| payload_id_with(payload, IdAlgorithm::Crc32);
+    assert_eq!(crc32(""), "000000");
+    assert_eq!(crc32("https://example.com"), "zwmnsp");
+    assert_eq!(crc32("SN-0001"), "v658e1");
+}
+
+#[test]
+fn test_id_algorithm_from_str() {
+    assert_eq!(
+        "SHA256".parse::<IdAlgorithm>().unwrap(),
+        IdAlgorithm::Sha256
+    );
+    assert_eq!("crc32".parse::<IdAlgorithm>().unwrap(), IdAlgorithm::Crc32);
+    assert!("md5".parse::<IdAlgorithm>().is_err());
+}
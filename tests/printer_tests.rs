@@ -0,0 +1,817 @@
+use ptouch::Result;
+use ptouch::backend::Backend;
+use ptouch::error::{ErrorKind, kind_of};
+use ptouch::printable_image::{PrintableImage, RasterOptions};
+use ptouch::printer::{Compression, CutPolicy, PrintOptions, Printer, Quality, check_job};
+use ptouch::raster_command::RasterCommand;
+use ptouch::status::Status;
+use ptouch::tape::{Tape, TapeSpec};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Backend that records every command sent to the printer
+#[derive(Clone, Default)]
+struct RecordingBackend {
+    sent: Rc<RefCell<Vec<u8>>>,
+}
+
+impl Backend for RecordingBackend {
+    fn send_command(&mut self, data: &[u8]) -> Result<()> {
+        self.sent.borrow_mut().extend_from_slice(data);
+        Ok(())
+    }
+
+    fn get_status(&mut self) -> Result<Status> {
+        Ok(Status::new(&[0; 32]))
+    }
+}
+
+/// Raster command after the compression mode command
+#[derive(Debug, PartialEq)]
+enum Line {
+    Graphics(Vec<u8>),
+    Zero,
+}
+
+/// Grayscale PNG for 12mm tape: 4 columns, 2nd column is blank
+fn create_test_png(tape_spec: &TapeSpec) -> Vec<u8> {
+    create_png(tape_spec, 4, &[0, 2, 3])
+}
+
+/// Grayscale PNG with ink only in the given columns
+fn create_png(tape_spec: &TapeSpec, width: u32, inked_columns: &[u32]) -> Vec<u8> {
+    let height = tape_spec.width_dots;
+    let mut pixels = vec![255u8; (width * height) as usize];
+    for y in 20..100 {
+        for &x in inked_columns {
+            pixels[(y * width + x) as usize] = 0;
+        }
+    }
+
+    let mut png_data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png_data, width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .unwrap()
+        .write_image_data(&pixels)
+        .unwrap();
+    png_data
+}
+
+fn print_job(compression: Compression) -> Vec<u8> {
+    print_job_with_options(&PrintOptions {
+        compression,
+        ..Default::default()
+    })
+}
+
+fn print_job_with_options(options: &PrintOptions) -> Vec<u8> {
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    let printable = PrintableImage::from_png_data(create_test_png(&tape_spec), tape_spec).unwrap();
+
+    let backend = RecordingBackend::default();
+    let mut printer = Printer::new(backend.clone());
+    let report = printer.print(&printable, options).unwrap();
+
+    let sent = backend.sent.take();
+    assert_eq!(report.bytes_sent, sent.len());
+    assert_eq!(report.raster_lines, 4);
+    sent
+}
+
+/// Parameter byte of "advanced mode settings" (ESC i K n)
+fn advanced_mode_param(job: &[u8]) -> u8 {
+    let pos = job
+        .windows(3)
+        .position(|w| w == b"\x1B\x69\x4B")
+        .expect("advanced mode settings not found");
+    job[pos + 3]
+}
+
+/// Split job dump into compression mode byte and raster lines
+fn parse_raster_section(job: &[u8]) -> (u8, Vec<Line>) {
+    // Compression mode command follows "specify margin amount" (ESC i d n1 n2)
+    let pos = job
+        .windows(3)
+        .position(|w| w == b"\x1B\x69\x64")
+        .expect("margin command not found");
+    let mut rest = &job[pos + 5..];
+
+    assert_eq!(rest[0], b'M');
+    let mode = rest[1];
+    rest = &rest[2..];
+
+    let mut lines = Vec::new();
+    loop {
+        match rest[0] {
+            b'G' => {
+                let len = u16::from_le_bytes([rest[1], rest[2]]) as usize;
+                lines.push(Line::Graphics(rest[3..3 + len].to_vec()));
+                rest = &rest[3 + len..];
+            }
+            b'Z' => {
+                lines.push(Line::Zero);
+                rest = &rest[1..];
+            }
+            0x1A => break,
+            other => panic!("Unexpected command 0x{:02X}", other),
+        }
+    }
+    assert_eq!(rest, [0x1A], "Print command must terminate the job");
+
+    (mode, lines)
+}
+
+#[test]
+fn test_print_tiff_compression() {
+    let (mode, lines) = parse_raster_section(&print_job(Compression::Tiff));
+
+    assert_eq!(mode, 0x02);
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[1], Line::Zero);
+    for line in [&lines[0], &lines[2], &lines[3]] {
+        let Line::Graphics(data) = line else {
+            panic!("Expected graphics line");
+        };
+        // 560 pins = 70 bytes, compressed well below that
+        assert!(data.len() < 70);
+    }
+}
+
+#[test]
+fn test_print_no_compression() {
+    let (mode, lines) = parse_raster_section(&print_job(Compression::None));
+
+    assert_eq!(mode, 0x00);
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[1], Line::Zero);
+    for line in [&lines[0], &lines[2], &lines[3]] {
+        let Line::Graphics(data) = line else {
+            panic!("Expected graphics line");
+        };
+        assert_eq!(data.len(), 70);
+    }
+}
+
+#[test]
+fn test_print_sparse_label_zero_lines() {
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    let png_data = create_png(&tape_spec, 12, &[0, 3, 4, 11]);
+
+    for compression in [Compression::Tiff, Compression::None] {
+        let printable = PrintableImage::from_png_data(png_data.clone(), tape_spec.clone()).unwrap();
+        let backend = RecordingBackend::default();
+        let options = PrintOptions {
+            compression,
+            ..Default::default()
+        };
+        Printer::new(backend.clone())
+            .print(&printable, &options)
+            .unwrap();
+
+        let (_, lines) = parse_raster_section(&backend.sent.take());
+        let zeros = lines.iter().filter(|line| **line == Line::Zero).count();
+        assert_eq!(lines.len(), 12, "{:?}", compression);
+        assert_eq!(zeros, 8, "{:?}", compression);
+    }
+}
+
+#[test]
+fn test_print_modes_share_header() {
+    let tiff = print_job(Compression::Tiff);
+    let none = print_job(Compression::None);
+
+    // Everything before the compression mode command is identical
+    let pos = tiff.windows(3).position(|w| w == b"\x1B\x69\x64").unwrap() + 5;
+    assert_eq!(tiff[..pos], none[..pos]);
+    assert_eq!(tiff[pos], b'M');
+    assert_ne!(tiff[pos + 1], none[pos + 1]);
+}
+
+#[test]
+fn test_print_high_resolution_flag() {
+    let normal = print_job_with_options(&PrintOptions::default());
+    let high = print_job_with_options(&PrintOptions {
+        high_resolution: true,
+        ..Default::default()
+    });
+
+    assert_eq!(advanced_mode_param(&normal) & 0x40, 0);
+    assert_eq!(advanced_mode_param(&high) & 0x40, 0x40);
+}
+
+#[test]
+fn test_print_half_cut_flag() {
+    let default = print_job_with_options(&PrintOptions::default());
+    let no_half_cut = print_job_with_options(&PrintOptions {
+        half_cut: false,
+        ..Default::default()
+    });
+
+    assert_eq!(advanced_mode_param(&default) & 0x04, 0x04);
+    assert_eq!(advanced_mode_param(&no_half_cut) & 0x04, 0);
+}
+
+#[test]
+fn test_advanced_mode_settings_bytes() {
+    let mut cmd = RasterCommand::new();
+    cmd.advanced_mode_settings(false, true, true, false, false, false);
+    assert_eq!(cmd.build(), b"\x1B\x69\x4B\x0C");
+
+    let mut cmd = RasterCommand::new();
+    cmd.advanced_mode_settings(true, false, false, true, true, true);
+    assert_eq!(cmd.build(), b"\x1B\x69\x4B\xD1");
+}
+
+/// Parameter byte of "specify page number" (ESC i A n)
+fn page_number_param(job: &[u8]) -> u8 {
+    let pos = job
+        .windows(3)
+        .position(|w| w == b"\x1B\x69\x41")
+        .expect("specify page number not found");
+    job[pos + 3]
+}
+
+#[test]
+fn test_print_cut_every() {
+    assert_eq!(
+        page_number_param(&print_job_with_options(&PrintOptions::default())),
+        1
+    );
+    for cut_every in [2, 4, 255] {
+        let job = print_job_with_options(&PrintOptions {
+            cut_every,
+            ..Default::default()
+        });
+        assert_eq!(page_number_param(&job), cut_every);
+    }
+}
+
+/// Parameter byte of "various mode settings" (ESC i M n)
+fn various_mode_param(job: &[u8]) -> u8 {
+    let pos = job
+        .windows(3)
+        .position(|w| w == b"\x1B\x69\x4D")
+        .expect("various mode settings not found");
+    job[pos + 3]
+}
+
+#[test]
+fn test_print_cut_policy() {
+    // (policy, auto_cut 0x40 of ESC i M, no_chain 0x08 of ESC i K)
+    for (cut, auto_cut, no_chain) in [
+        (CutPolicy::Each, 0x40, 0x08),
+        (CutPolicy::EndOnly, 0, 0x08),
+        (CutPolicy::None, 0, 0),
+    ] {
+        let job = print_job_with_options(&PrintOptions {
+            cut,
+            ..Default::default()
+        });
+        assert_eq!(various_mode_param(&job) & 0x40, auto_cut, "{}", cut);
+        assert_eq!(advanced_mode_param(&job) & 0x08, no_chain, "{}", cut);
+    }
+    assert_eq!(PrintOptions::default().cut, CutPolicy::Each);
+}
+
+#[test]
+fn test_print_repeat_cut_every() {
+    // (auto_cut 0x40 of ESC i M, no_chain 0x08 of ESC i K) of each job
+    let cut_bits = |options: &PrintOptions, times| {
+        print_repeat_jobs(options, times)
+            .iter()
+            .map(|job| {
+                assert_eq!(page_number_param(job), 1);
+                (
+                    various_mode_param(job) & 0x40,
+                    advanced_mode_param(job) & 0x08,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let (cut, chain) = ((0x40, 0x08), (0, 0));
+
+    // Labels in between are chained, the last one is always cut
+    let options = PrintOptions {
+        cut_every: 2,
+        ..Default::default()
+    };
+    assert_eq!(cut_bits(&options, 5), [chain, cut, chain, cut, cut]);
+    assert_eq!(cut_bits(&options, 1), [cut]);
+
+    let options = PrintOptions {
+        cut: CutPolicy::EndOnly,
+        ..Default::default()
+    };
+    assert_eq!(cut_bits(&options, 3), [chain, chain, (0, 0x08)]);
+
+    let options = PrintOptions {
+        cut: CutPolicy::None,
+        cut_every: 2,
+        ..Default::default()
+    };
+    assert_eq!(cut_bits(&options, 2), [chain, chain]);
+}
+
+#[test]
+fn test_print_cut_every_zero_rejected() {
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    let printable = PrintableImage::from_png_data(create_test_png(&tape_spec), tape_spec).unwrap();
+    let backend = RecordingBackend::default();
+    let mut printer = Printer::new(backend.clone());
+
+    let options = PrintOptions {
+        cut_every: 0,
+        ..Default::default()
+    };
+    let error = printer.print(&printable, &options).unwrap_err();
+    assert!(error.to_string().contains("continuous"), "{}", error);
+    assert!(backend.sent.take().is_empty());
+}
+
+/// Flag byte of "print information command" (ESC i z n1)
+fn print_information_flag(job: &[u8]) -> u8 {
+    let pos = job
+        .windows(3)
+        .position(|w| w == b"\x1B\x69\x7A")
+        .expect("print information command not found");
+    job[pos + 3]
+}
+
+/// Print `times` copies and return what was sent per send_command call
+fn print_repeat_jobs(options: &PrintOptions, times: u32) -> Vec<Vec<u8>> {
+    #[derive(Clone, Default)]
+    struct CallBackend {
+        calls: Rc<RefCell<Vec<Vec<u8>>>>,
+    }
+    impl Backend for CallBackend {
+        fn send_command(&mut self, data: &[u8]) -> Result<()> {
+            self.calls.borrow_mut().push(data.to_vec());
+            Ok(())
+        }
+        fn get_status(&mut self) -> Result<Status> {
+            Ok(Status::new(&[0; 32]))
+        }
+    }
+
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    let printable = PrintableImage::from_png_data(create_test_png(&tape_spec), tape_spec).unwrap();
+    let backend = CallBackend::default();
+    let mut printer = Printer::new(backend.clone());
+    let report = printer.print_repeat(&printable, options, times).unwrap();
+
+    let calls = backend.calls.take();
+    assert_eq!(report.bytes_sent, calls.iter().map(Vec::len).sum::<usize>());
+    assert_eq!(report.raster_lines, 4 * times);
+    calls
+}
+
+#[test]
+fn test_print_repeat_reuse_buffer() {
+    let single = print_job_with_options(&PrintOptions::default());
+    let options = PrintOptions {
+        reuse_buffer: true,
+        ..Default::default()
+    };
+    let jobs = print_repeat_jobs(&options, 3);
+    assert_eq!(jobs.len(), 1);
+    let job = &jobs[0];
+
+    // Header and raster lines are sent once with no buffer clearing
+    assert_eq!(advanced_mode_param(job) & 0x80, 0x80);
+    assert_eq!(advanced_mode_param(&single) & 0x80, 0);
+    let pos = job.windows(3).position(|w| w == b"\x1B\x69\x7A").unwrap();
+    assert_eq!(job[pos + 11], 0, "first page");
+    assert_eq!(job.windows(2).filter(|w| w == b"\x1B\x40").count(), 1);
+
+    // Then the buffer is printed again: FF per copy, Ctrl-Z for the last
+    assert!(
+        job.ends_with(b"\x0C\x0C\x1A"),
+        "{:02X?}",
+        &job[job.len() - 4..]
+    );
+    assert_eq!(job.len(), single.len() + 2);
+    let one_copy = [&job[..job.len() - 3], b"\x1A"].concat();
+    assert_eq!(
+        parse_raster_section(&one_copy),
+        parse_raster_section(&single)
+    );
+
+    // A single copy is the plain job
+    assert_eq!(print_repeat_jobs(&options, 1), [single]);
+}
+
+#[test]
+fn test_print_repeat_resends_without_reuse() {
+    let single = print_job_with_options(&PrintOptions::default());
+    let jobs = print_repeat_jobs(&PrintOptions::default(), 3);
+    assert_eq!(jobs, [single.clone(), single.clone(), single]);
+
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    let printable = PrintableImage::from_png_data(create_test_png(&tape_spec), tape_spec).unwrap();
+    let mut printer = Printer::new(RecordingBackend::default());
+    assert!(
+        printer
+            .print_repeat(&printable, &PrintOptions::default(), 0)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_print_quality_flags() {
+    let job_with_quality = |quality| {
+        print_job_with_options(&PrintOptions {
+            quality,
+            ..Default::default()
+        })
+    };
+
+    // (quality, draft bit 0x01 of ESC i K, quality_mode bit 0x40 of ESC i z)
+    for (quality, draft, quality_mode) in [
+        (Quality::Draft, 0x01, 0x00),
+        (Quality::Normal, 0x00, 0x00),
+        (Quality::High, 0x00, 0x40),
+    ] {
+        let job = job_with_quality(quality);
+        assert_eq!(advanced_mode_param(&job) & 0x01, draft, "{}", quality);
+        assert_eq!(
+            print_information_flag(&job) & 0x40,
+            quality_mode,
+            "{}",
+            quality
+        );
+    }
+}
+
+#[test]
+fn test_raster_graphics_transfer_length() {
+    let mut cmd = RasterCommand::new();
+    cmd.raster_graphics_transfer(&[0xAA; 0x0102]).unwrap();
+    let data = cmd.build();
+    assert_eq!(data[..3], [b'G', 0x02, 0x01]);
+    assert_eq!(data.len(), 3 + 0x0102);
+
+    let mut cmd = RasterCommand::new();
+    assert!(cmd.raster_graphics_transfer(&[0; 65535]).is_ok());
+}
+
+#[test]
+fn test_raster_graphics_transfer_oversized() {
+    let mut cmd = RasterCommand::new();
+    let result = cmd.raster_graphics_transfer(&vec![0; 65536]);
+    let error_msg = format!("{}", result.err().unwrap());
+    assert!(error_msg.contains("Raster line too long: 65536 bytes"));
+
+    // Nothing is written on error
+    assert!(cmd.build().is_empty());
+}
+
+/// Set pins of the first raster line
+fn set_pins(printable: &PrintableImage, v_offset: i32) -> Vec<usize> {
+    let options = RasterOptions {
+        v_offset,
+        ..Default::default()
+    };
+    let lines = printable.to_raster_lines_with(&options).unwrap();
+    (0..lines[0].len() * 8)
+        .filter(|pin| lines[0][pin / 8] & (0x80 >> (pin % 8)) != 0)
+        .collect()
+}
+
+#[test]
+fn test_v_offset_shifts_pins() {
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    let printable = PrintableImage::from_png_data(create_test_png(&tape_spec), tape_spec).unwrap();
+
+    // Ink at y = 20..100 lands on right_pins (213) + y - margin (10)
+    let pins = set_pins(&printable, 0);
+    assert_eq!(pins, (223..303).collect::<Vec<_>>());
+    assert_eq!(printable.to_raster_lines().unwrap()[0].len(), 70);
+
+    // Positive offsets move ink downward to higher pins
+    assert_eq!(set_pins(&printable, 5), (228..308).collect::<Vec<_>>());
+    assert_eq!(set_pins(&printable, -5), (218..298).collect::<Vec<_>>());
+
+    // Pins off the print head are dropped
+    assert_eq!(set_pins(&printable, -300), (0..3).collect::<Vec<_>>());
+    assert_eq!(set_pins(&printable, 300), (523..560).collect::<Vec<_>>());
+    assert_eq!(set_pins(&printable, 600), Vec::<usize>::new());
+}
+
+#[test]
+fn test_v_offset_in_print_job() {
+    let shifted = print_job_with_options(&PrintOptions {
+        raster: RasterOptions {
+            v_offset: 8,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    assert_ne!(shifted, print_job_with_options(&PrintOptions::default()));
+}
+
+#[test]
+fn test_mask_pins_never_set() {
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    let printable = PrintableImage::from_png_data(create_test_png(&tape_spec), tape_spec).unwrap();
+    let count = |lines: &[Vec<u8>]| -> u32 { lines[0].iter().map(|byte| byte.count_ones()).sum() };
+    // Ink on pins 223..303
+    let mask_pins = [223, 250, 302];
+    let masked = |v_offset| {
+        let options = RasterOptions {
+            v_offset,
+            mask_pins: mask_pins.to_vec(),
+            negative: false,
+        };
+        printable.to_raster_lines_with(&options).unwrap()
+    };
+
+    for v_offset in [0, 5, -5] {
+        for line in &masked(v_offset) {
+            for &pin in &mask_pins {
+                let pin = pin as usize;
+                assert_eq!(line[pin / 8] & (0x80 >> (pin % 8)), 0, "pin {}", pin);
+            }
+        }
+    }
+
+    // Other pins are untouched
+    assert_eq!(count(&masked(0)), 80 - 3);
+    assert_eq!(count(&printable.to_raster_lines().unwrap()), 80);
+}
+
+#[test]
+fn test_dodge_pins() {
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    let printable = PrintableImage::from_png_data(create_test_png(&tape_spec), tape_spec).unwrap();
+
+    // Ink on pins 223..303 with 10 blank rows above and 60 below
+    assert_eq!(printable.dodge_pins(0, &[]).unwrap(), Some(0));
+    assert_eq!(printable.dodge_pins(0, &[200, 400]).unwrap(), Some(0));
+    assert_eq!(printable.dodge_pins(0, &[224]).unwrap(), Some(2));
+    assert_eq!(printable.dodge_pins(0, &[250]).unwrap(), Some(28));
+    assert_eq!(printable.dodge_pins(0, &[300]).unwrap(), Some(-3));
+    // Same place on the head with another offset
+    assert_eq!(printable.dodge_pins(5, &[250]).unwrap(), Some(28));
+
+    // Ink does not fit between masked pins within the printable area
+    assert_eq!(printable.dodge_pins(0, &[230, 300]).unwrap(), None);
+
+    // Options are moved, or left with the reason
+    let dodge = |mask_pins: &[u32], negative| {
+        let options = RasterOptions {
+            v_offset: 0,
+            mask_pins: mask_pins.to_vec(),
+            negative,
+        };
+        printable.dodge_masked_pins(&options).unwrap()
+    };
+    let (options, warning) = dodge(&[250], false);
+    assert_eq!((options.v_offset, warning), (28, None));
+    let (options, warning) = dodge(&[230, 300], false);
+    assert_eq!(options.v_offset, 0);
+    assert!(warning.unwrap().contains("overlap masked pins 230,300"));
+    let (options, warning) = dodge(&[250], true);
+    assert_eq!(options.v_offset, 0);
+    assert!(warning.unwrap().contains("in the negative"));
+}
+
+#[test]
+fn test_mask_pins_in_print_job() {
+    let masked = print_job_with_options(&PrintOptions {
+        raster: RasterOptions {
+            mask_pins: vec![250],
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    assert_ne!(masked, print_job_with_options(&PrintOptions::default()));
+}
+
+#[test]
+fn test_negative_inverts_printable_area() {
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    let (first_pin, inner) = (tape_spec.right_pins as usize, tape_spec.inner_dots as usize);
+    let printable =
+        PrintableImage::from_png_data(create_test_png(&tape_spec), tape_spec.clone()).unwrap();
+    let pin = |line: &[u8], pin: usize| line[pin / 8] & (0x80 >> (pin % 8)) != 0;
+    let to_negative = |mask_pins: &[u32]| {
+        let options = RasterOptions {
+            v_offset: 0,
+            mask_pins: mask_pins.to_vec(),
+            negative: true,
+        };
+        printable.to_raster_lines_with(&options).unwrap()
+    };
+
+    let positive = printable.to_raster_lines().unwrap();
+    let negative = to_negative(&[]);
+    assert_eq!(negative.len(), positive.len());
+    for (positive, negative) in positive.iter().zip(&negative) {
+        for p in 0..tape_spec.total_pins as usize {
+            if (first_pin..first_pin + inner).contains(&p) {
+                assert_ne!(pin(negative, p), pin(positive, p), "pin {}", p);
+            } else {
+                assert!(!pin(negative, p), "margin pin {}", p);
+            }
+        }
+    }
+
+    // Masked pins stay unset in the background
+    let masked = to_negative(&[300]);
+    assert!(masked.iter().all(|line| !pin(line, 300)));
+
+    let job = print_job_with_options(&PrintOptions {
+        raster: RasterOptions {
+            negative: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    assert_ne!(job, print_job_with_options(&PrintOptions::default()));
+}
+
+/// Backend that counts the commands sent to the printer
+#[derive(Clone, Default)]
+struct CountingBackend {
+    sends: Rc<RefCell<usize>>,
+}
+
+impl Backend for CountingBackend {
+    fn send_command(&mut self, _data: &[u8]) -> Result<()> {
+        *self.sends.borrow_mut() += 1;
+        Ok(())
+    }
+
+    fn get_status(&mut self) -> Result<Status> {
+        Ok(Status::new(&[0; 32]))
+    }
+}
+
+#[test]
+fn test_stream_sends_same_bytes() {
+    // Long label of every other line inked, about 200 kB uncompressed
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    let width = 20000;
+    let inked: Vec<u32> = (0..width).step_by(2).collect();
+    let printable =
+        PrintableImage::from_png_data(create_png(&tape_spec, width, &inked), tape_spec).unwrap();
+
+    let print_to_file = |name: &str, stream: bool| {
+        let path =
+            std::env::temp_dir().join(format!("ptouch-stream-{}-{}", std::process::id(), name));
+        let options = PrintOptions {
+            compression: Compression::None,
+            stream,
+            ..Default::default()
+        };
+        let backend = ptouch::backend::FileBackend::new(&path).unwrap();
+        let report = Printer::new(backend).print(&printable, &options).unwrap();
+        let sent = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(report.bytes_sent, sent.len());
+        sent
+    };
+    let batched = print_to_file("batched.bin", false);
+    assert!(batched.len() > 150_000);
+    assert_eq!(print_to_file("streamed.bin", true), batched);
+
+    let sends = |stream: bool| {
+        let backend = CountingBackend::default();
+        let options = PrintOptions {
+            compression: Compression::None,
+            stream,
+            ..Default::default()
+        };
+        Printer::new(backend.clone())
+            .print(&printable, &options)
+            .unwrap();
+        backend.sends.take()
+    };
+    assert_eq!(sends(false), 1);
+    assert!(sends(true) > 2);
+}
+
+/// RGBA PNG padded with transparent columns on both sides, as screenshots
+fn create_transparent_padded_png(tape_spec: &TapeSpec, left: u32, right: u32) -> Vec<u8> {
+    let width = left + 4 + right;
+    let height = tape_spec.width_dots;
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    for y in 0..height {
+        for x in left..left + 4 {
+            // Opaque white with ink in the middle rows
+            let i = ((y * width + x) * 4) as usize;
+            let value = if (20..100).contains(&y) { 0 } else { 255 };
+            pixels[i..i + 4].copy_from_slice(&[value, value, value, 255]);
+        }
+    }
+
+    let mut png_data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png_data, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .unwrap()
+        .write_image_data(&pixels)
+        .unwrap();
+    png_data
+}
+
+#[test]
+fn test_trimmed() {
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    let trim = |png_data: Vec<u8>| {
+        let printable = PrintableImage::from_png_data(png_data, tape_spec.clone()).unwrap();
+        let (trimmed, trim) = printable.trimmed().unwrap();
+        (trimmed.length_px(), trim.leading, trim.trailing)
+    };
+
+    // White padding at the start, the end and both
+    assert_eq!(trim(create_png(&tape_spec, 10, &[3, 4])), (2, 3, 5));
+    assert_eq!(trim(create_png(&tape_spec, 10, &[0, 4])), (5, 0, 5));
+    assert_eq!(trim(create_png(&tape_spec, 10, &[6, 9])), (4, 6, 0));
+    assert_eq!(trim(create_png(&tape_spec, 4, &[0, 3])), (4, 0, 0));
+
+    // Transparent padding of screenshots
+    assert_eq!(
+        trim(create_transparent_padded_png(&tape_spec, 7, 11)),
+        (4, 7, 11)
+    );
+
+    // Blank lines inside the label are kept
+    let png_data = create_png(&tape_spec, 10, &[2, 7]);
+    let printable = PrintableImage::from_png_data(png_data, tape_spec.clone()).unwrap();
+    let (trimmed, _) = printable.trimmed().unwrap();
+    let lines = trimmed.to_raster_lines().unwrap();
+    assert_eq!(lines.len(), 6);
+    assert!(lines[1..5].iter().all(|line| line.iter().all(|&b| b == 0)));
+    assert_eq!(trimmed.tape_spec().width_dots, tape_spec.width_dots);
+}
+
+#[test]
+fn test_trimmed_blank_image() {
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    let png_data = create_png(&tape_spec, 10, &[]);
+    let printable = PrintableImage::from_png_data(png_data, tape_spec).unwrap();
+    let error = printable.trimmed().err().unwrap();
+    assert_eq!(error.to_string(), "Nothing to print: the image is blank");
+}
+
+#[test]
+fn test_check_length() {
+    // 500 mm is 3543.3 raster lines at 180DPI and 7086.6 at 360DPI
+    for (tape, longest) in [(Tape::TZe24L, 3543), (Tape::TZe12H, 7086)] {
+        let tape_spec = TapeSpec::new(tape);
+        let printable = |length: u32| {
+            let png_data = create_png(&tape_spec, length, &[0]);
+            PrintableImage::from_png_data(png_data, tape_spec.clone()).unwrap()
+        };
+
+        assert!(printable(longest).check_length(500.0).is_ok());
+        let error = printable(longest + 1).check_length(500.0).unwrap_err();
+        assert!(
+            error.to_string().contains(&format!(
+                "{} raster lines at {}DPI",
+                longest + 1,
+                tape_spec.dpi
+            )),
+            "{}",
+            error
+        );
+    }
+}
+
+#[test]
+fn test_check_job() {
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    let printable = |length: u32| {
+        let png_data = create_png(&tape_spec, length, &[0]);
+        PrintableImage::from_png_data(png_data, tape_spec.clone()).unwrap()
+    };
+    let kind = |result: Result<Vec<String>>| kind_of(result.unwrap_err().as_ref());
+    let cover_open = Status::from_hex(&format!("{:0<18}10{:0<44}", "", "")).unwrap();
+
+    // 100 mm at 360DPI
+    let label = printable(1417);
+    assert!(
+        check_job(&label, 1, None, Some(100.0), false)
+            .unwrap()
+            .is_empty()
+    );
+    let result = check_job(&printable(1418), 1, None, Some(100.0), false);
+    assert_eq!(kind(result), Some(ErrorKind::Usage));
+
+    // The length is checked before the printer conditions
+    let result = check_job(&printable(1418), 1, Some(&cover_open), Some(100.0), false);
+    assert_eq!(kind(result), Some(ErrorKind::Usage));
+    let result = check_job(&label, 1, Some(&cover_open), Some(100.0), false);
+    assert_eq!(kind(result), Some(ErrorKind::Printer));
+
+    // The maximum holds for a label, not for the copies in total
+    let status = Status::from_hex(&format!("{:0<64}", "")).unwrap();
+    let warnings = check_job(&label, 3, Some(&status), Some(100.0), false).unwrap();
+    assert!(warnings[0].contains("300 mm job"), "{:?}", warnings);
+}
@@ -0,0 +1,211 @@
+use base64::Engine;
+use fontdb::Database;
+use ptouch::Result;
+use ptouch::backend::Backend;
+use ptouch::label::{Label, LabelOptions};
+use ptouch::layout::{LayoutOptions, parse_layout_script};
+use ptouch::printer::{PrintOptions, Printer};
+use ptouch::serve::{Job, Renderer, Reply, Server};
+use ptouch::status::Status;
+use ptouch::tape::{Tape, TapeSpec};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// Backend in memory, shared with the test across the server thread
+#[derive(Clone)]
+struct MemoryBackend {
+    sent: Arc<Mutex<Vec<Vec<u8>>>>,
+    status: Arc<Mutex<[u8; 32]>>,
+}
+
+impl MemoryBackend {
+    fn new(tape_spec: &TapeSpec) -> Self {
+        let mut status = [0; 32];
+        status[10] = tape_spec.width_mm;
+        MemoryBackend {
+            sent: Arc::default(),
+            status: Arc::new(Mutex::new(status)),
+        }
+    }
+
+    /// Number of labels printed: the printer sends a label at once
+    fn printed(&self) -> usize {
+        self.sent.lock().unwrap().len()
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn send_command(&mut self, data: &[u8]) -> Result<()> {
+        self.sent.lock().unwrap().push(data.to_vec());
+        Ok(())
+    }
+
+    fn get_status(&mut self) -> Result<Status> {
+        Ok(Status::new(&*self.status.lock().unwrap()))
+    }
+}
+
+/// Render layouts without fonts: boxes and QR codes only
+fn create_renderer(tape_spec: TapeSpec) -> Renderer {
+    let fontdb = Arc::new(Database::new());
+    Box::new(move |script| {
+        let element = parse_layout_script(script, &LayoutOptions::new(fontdb.clone()))?;
+        let options = LabelOptions::new(fontdb.clone()).with_tape_spec(tape_spec.clone());
+        Label::from_element(element, options).to_png()
+    })
+}
+
+fn start_server(backend: &MemoryBackend) -> (SocketAddr, JoinHandle<()>) {
+    let tape_spec = TapeSpec::new(Tape::TZe12H);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut server = Server::new(
+        Printer::new(backend.clone()),
+        tape_spec.clone(),
+        PrintOptions::default(),
+        create_renderer(tape_spec),
+    );
+    let handle = std::thread::spawn(move || server.serve(&listener).unwrap());
+    (addr, handle)
+}
+
+/// Client sending one line at a time
+struct Client {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Client {
+    fn connect(addr: SocketAddr) -> Self {
+        let writer = TcpStream::connect(addr).unwrap();
+        let reader = BufReader::new(writer.try_clone().unwrap());
+        Client { reader, writer }
+    }
+
+    fn send(&mut self, line: &str) -> Reply {
+        writeln!(self.writer, "{}", line).unwrap();
+        let mut reply = String::new();
+        self.reader.read_line(&mut reply).unwrap();
+        serde_json::from_str(&reply).unwrap()
+    }
+
+    fn shutdown(mut self) {
+        let reply = self.send(r#"{"shutdown": true}"#);
+        assert!(reply.ok);
+    }
+}
+
+fn create_png(width: u32, height: u32) -> Vec<u8> {
+    let pixels = vec![0u8; (width * height) as usize];
+    let mut png_data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png_data, width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .unwrap()
+        .write_image_data(&pixels)
+        .unwrap();
+    png_data
+}
+
+#[test]
+fn test_job_defaults() {
+    let job: Job = serde_json::from_str(r#"{"layout": "box:10x10"}"#).unwrap();
+    assert_eq!(job.layout.as_deref(), Some("box:10x10"));
+    assert_eq!(job.copies, 1);
+    assert!(!job.shutdown);
+
+    assert!(serde_json::from_str::<Job>(r#"{"layuot": "box:10x10"}"#).is_err());
+}
+
+#[test]
+fn test_serve_layout_and_png() {
+    let backend = MemoryBackend::new(&TapeSpec::new(Tape::TZe12H));
+    let (addr, handle) = start_server(&backend);
+    let mut client = Client::connect(addr);
+
+    let reply = client.send(r#"{"layout": "box:10x10 + qrc:SN-0001", "copies": 2}"#);
+    assert_eq!(
+        reply,
+        Reply {
+            ok: true,
+            printed: 2,
+            ..Default::default()
+        }
+    );
+    assert_eq!(backend.printed(), 2);
+
+    let height = TapeSpec::new(Tape::TZe12H).width_dots;
+    let png_base64 = base64::engine::general_purpose::STANDARD.encode(create_png(20, height));
+    let reply = client.send(&format!(r#"{{"png_base64": "{}"}}"#, png_base64));
+    assert!(reply.ok, "{:?}", reply.error);
+    assert_eq!(reply.printed, 1);
+    assert_eq!(backend.printed(), 3);
+
+    client.shutdown();
+    handle.join().unwrap();
+}
+
+#[test]
+fn test_serve_invalid_jobs() {
+    let backend = MemoryBackend::new(&TapeSpec::new(Tape::TZe12H));
+    let (addr, handle) = start_server(&backend);
+    let mut client = Client::connect(addr);
+
+    let reply = client.send("not json");
+    assert!(!reply.ok);
+    assert!(reply.error.unwrap().starts_with("Invalid job"));
+
+    let reply = client.send(r#"{"copies": 1}"#);
+    assert_eq!(
+        reply.error.as_deref(),
+        Some("Job requires layout or png_base64")
+    );
+
+    // PNG for another tape width
+    let png_base64 = base64::engine::general_purpose::STANDARD.encode(create_png(20, 10));
+    let reply = client.send(&format!(r#"{{"png_base64": "{}"}}"#, png_base64));
+    assert!(!reply.ok);
+    assert_eq!(reply.printed, 0);
+
+    // The server keeps serving after failed jobs
+    let reply = client.send(r#"{"layout": "box:10x10"}"#);
+    assert!(reply.ok, "{:?}", reply.error);
+    assert_eq!(backend.printed(), 1);
+
+    client.shutdown();
+    handle.join().unwrap();
+}
+
+#[test]
+fn test_serve_reports_printer_status() {
+    let backend = MemoryBackend::new(&TapeSpec::new(Tape::TZe12H));
+    let (addr, handle) = start_server(&backend);
+    let mut client = Client::connect(addr);
+
+    backend.status.lock().unwrap()[9] = 0x10;
+    let reply = client.send(r#"{"layout": "box:10x10"}"#);
+    assert!(!reply.ok);
+    assert_eq!(reply.status, ["Cover open"]);
+    assert_eq!(backend.printed(), 0);
+
+    // Another tape loaded while serving
+    backend.status.lock().unwrap()[9] = 0;
+    backend.status.lock().unwrap()[10] = 24;
+    let reply = client.send(r#"{"layout": "box:10x10"}"#);
+    assert!(!reply.ok);
+    assert!(reply.error.unwrap().contains("24 mm tape loaded"));
+    drop(client);
+
+    // Jobs from the next client
+    backend.status.lock().unwrap()[10] = 12;
+    let mut client = Client::connect(addr);
+    let reply = client.send(r#"{"layout": "box:10x10"}"#);
+    assert!(reply.ok, "{:?}", reply.error);
+
+    client.shutdown();
+    handle.join().unwrap();
+}
@@ -0,0 +1,104 @@
+use ptouch::status::Status;
+use ptouch::status_cache::StatusCache;
+use std::time::Duration;
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+fn temp_cache(name: &str) -> (StatusCache, std::path::PathBuf) {
+    let dir = std::env::temp_dir().join(format!("ptouch-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    (StatusCache::new(&dir), dir)
+}
+
+/// PT-P900 with 12mm tape
+fn ok_status() -> Status {
+    Status::from_hex(
+        "80 20 42 30 71 30 00 00 00 00 0C 01 00 00 00 00 \
+         00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00",
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_status_cache_round_trip() {
+    let (cache, dir) = temp_cache("cache-round-trip");
+    assert!(cache.load("ptouch.local", HOUR).is_none());
+
+    cache.store("ptouch.local", &ok_status()).unwrap();
+    let (status, age) = cache.load("ptouch.local", HOUR).unwrap();
+    assert_eq!(status.raw_data(), ok_status().raw_data());
+    assert!(age < HOUR);
+
+    // Entries are per host
+    assert!(cache.load("04f9:2085", HOUR).is_none());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_status_cache_expired() {
+    let (cache, dir) = temp_cache("cache-expired");
+    cache.store("ptouch.local", &ok_status()).unwrap();
+    assert!(cache.load("ptouch.local", Duration::ZERO).is_none());
+
+    // Written an hour ago
+    let path = std::fs::read_dir(&dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let content = std::fs::read_to_string(&path).unwrap();
+    let (time, hex) = content.split_once('\n').unwrap();
+    let old = time.parse::<u64>().unwrap() - 3600;
+    std::fs::write(&path, format!("{}\n{}", old, hex)).unwrap();
+
+    assert!(cache.load("ptouch.local", HOUR).is_none());
+    assert!(cache.load("ptouch.local", 2 * HOUR).is_some());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_status_cache_skips_errors() {
+    let (cache, dir) = temp_cache("cache-errors");
+    cache.store("ptouch.local", &ok_status()).unwrap();
+
+    // Error status removes the previous entry
+    let mut data = ok_status().raw_data().to_vec();
+    data[8] = 0x01; // No media
+    cache.store("ptouch.local", &Status::new(&data)).unwrap();
+    assert!(cache.load("ptouch.local", HOUR).is_none());
+
+    // So does a truncated one
+    cache.store("ptouch.local", &ok_status()).unwrap();
+    cache
+        .store("ptouch.local", &Status::new(&data[..10]))
+        .unwrap();
+    assert!(cache.load("ptouch.local", HOUR).is_none());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_status_cache_remove_and_corrupt() {
+    let (cache, dir) = temp_cache("cache-remove");
+    cache.remove("ptouch.local").unwrap();
+
+    cache.store("file:/tmp/out.bin", &ok_status()).unwrap();
+    cache.remove("file:/tmp/out.bin").unwrap();
+    assert!(cache.load("file:/tmp/out.bin", HOUR).is_none());
+
+    // Broken files are ignored
+    cache.store("ptouch.local", &ok_status()).unwrap();
+    let path = std::fs::read_dir(&dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    std::fs::write(&path, "garbage").unwrap();
+    assert!(cache.load("ptouch.local", HOUR).is_none());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
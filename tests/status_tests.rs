@@ -0,0 +1,258 @@
+use ptouch::error::{ErrorKind, kind_of};
+use ptouch::status::{Condition, STATUS_HEADER, STATUS_SIZE, Severity, Status, StatusParseError};
+
+fn status_with_model(model_code: u8) -> Status {
+    let mut data = [0u8; 32];
+    data[0] = 0x80;
+    data[1] = 0x20;
+    data[4] = model_code;
+    Status::new(&data)
+}
+
+#[test]
+fn test_model_capabilities() {
+    let p900 = status_with_model(0x71);
+    assert_eq!(p900.printer_dpi(), 360);
+    assert!(p900.supports_high_resolution());
+    assert!(p900.supports_half_cut());
+    assert!(p900.supports_buffer_reuse());
+
+    let pt9200 = status_with_model(0x5A);
+    assert_eq!(pt9200.printer_dpi(), 180);
+    assert!(!pt9200.supports_high_resolution());
+    assert!(!pt9200.supports_half_cut());
+    assert!(!pt9200.supports_buffer_reuse());
+}
+
+#[test]
+fn test_unknown_model_capabilities() {
+    let unknown = status_with_model(0x00);
+    assert_eq!(unknown.printer_dpi(), 360);
+    assert!(!unknown.supports_high_resolution());
+    assert!(unknown.supports_half_cut());
+    assert!(!unknown.supports_buffer_reuse());
+}
+
+#[test]
+fn test_decode_status_ok_packet() {
+    // PT-P900 with 12mm laminated tape, no errors
+    let status = Status::from_hex(
+        "80 20 42 30 71 30 00 00 00 00 0C 01 00 00 00 00 \
+         00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00",
+    )
+    .unwrap();
+
+    assert!(!status.has_errors());
+    assert_eq!(status.media_width_mm(), 12);
+    assert_eq!(status.media_type(), 0x01);
+    assert_eq!(status.printer_dpi(), 360);
+}
+
+#[test]
+fn test_decode_status_error_packet() {
+    // No media and cover open
+    let status =
+        Status::from_hex("8020423071300000011000000000000000000000000000000000000000000000")
+            .unwrap();
+
+    assert!(status.has_errors());
+    assert_eq!(status.error_info1(), 0x01);
+    assert_eq!(status.error_info2(), 0x10);
+    assert_eq!(status.media_width_mm(), 0);
+}
+
+#[test]
+fn test_decode_status_invalid_length() {
+    let result = Status::from_hex("80 20 42");
+    assert!(result.is_err());
+    let error_msg = format!("{}", result.err().unwrap());
+    assert!(error_msg.contains("expected 32 bytes"));
+}
+
+#[test]
+fn test_decode_status_invalid_hex() {
+    let result =
+        Status::from_hex("ZZ20423071300000011000000000000000000000000000000000000000000000");
+    assert!(result.is_err());
+    let error_msg = format!("{}", result.err().unwrap());
+    assert!(error_msg.contains("Invalid hex byte 'ZZ' at position 0"));
+}
+
+#[test]
+fn test_short_status_packet() {
+    // Truncated right after the model code
+    let status = Status::new(&[0x80, 0x20, 0x42, 0x30, 0x71]);
+
+    assert!(!status.is_complete());
+    assert!(status.has_errors());
+    assert_eq!(status.model_code(), 0x71);
+    assert_eq!(status.error_info1(), 0);
+    assert_eq!(status.media_width_mm(), 0);
+    assert_eq!(status.byte(10), None);
+    status.print_status_info(true);
+}
+
+#[test]
+fn test_empty_status_packet() {
+    let status = Status::new(&[]);
+
+    assert!(status.has_errors());
+    assert_eq!(status.printer_dpi(), 360);
+    assert!(!status.supports_high_resolution());
+}
+
+#[test]
+fn test_long_status_packet() {
+    // Extended response: standard 32 bytes followed by extra data
+    let mut data = vec![0u8; 48];
+    data[4] = 0x71;
+    data[10] = 24;
+    data[40] = 0xFF;
+    let status = Status::new(&data);
+
+    assert!(status.is_complete());
+    assert!(!status.has_errors());
+    assert_eq!(status.media_width_mm(), 24);
+    assert_eq!(status.raw_data().len(), 48);
+    assert_eq!(status.byte(40), Some(0xFF));
+}
+
+fn status_with_error_info(error_info1: u8, error_info2: u8) -> Status {
+    let mut data = [0u8; 32];
+    data[4] = 0x71;
+    data[8] = error_info1;
+    data[9] = error_info2;
+    data[10] = 36;
+    Status::new(&data)
+}
+
+#[test]
+fn test_condition_severity() {
+    let status = status_with_error_info(0xFF, 0xFF);
+    let conditions = status.conditions();
+    assert_eq!(conditions.len(), 16);
+
+    let severity = |condition: &Condition| condition.severity();
+    assert_eq!(severity(&Condition::HighVoltageAdapter), Severity::Info);
+    assert_eq!(severity(&Condition::WeakBatteries), Severity::Warning);
+    assert_eq!(severity(&Condition::CoverOpen), Severity::Fatal);
+    assert_eq!(severity(&Condition::NoMedia), Severity::Fatal);
+    // Undocumented bits
+    assert!(conditions.contains(&Condition::Unknown {
+        byte: 8,
+        mask: 0x20
+    }));
+    assert_eq!(
+        severity(&Condition::Unknown {
+            byte: 8,
+            mask: 0x80
+        }),
+        Severity::Fatal
+    );
+}
+
+#[test]
+fn test_adapter_is_not_an_error() {
+    let status = status_with_error_info(0x40, 0x00);
+    assert!(!status.has_errors());
+    assert!(status.on_adapter());
+    assert!(status.check_print(500.0, true).unwrap().is_empty());
+}
+
+#[test]
+fn test_weak_batteries_warning() {
+    let status = status_with_error_info(0x48, 0x00);
+    assert!(!status.has_errors());
+    assert_eq!(status.check_print(50.0, false).unwrap(), ["Weak batteries"]);
+
+    let error = status.check_print(50.0, true).unwrap_err().to_string();
+    assert!(error.contains("Weak batteries"));
+    assert!(error.contains("--strict"));
+}
+
+#[test]
+fn test_long_job_on_batteries_warning() {
+    let status = status_with_error_info(0x00, 0x00);
+    assert!(status.check_print(200.0, false).unwrap().is_empty());
+
+    let warnings = status.check_print(250.0, false).unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("250 mm"));
+    assert!(status.check_print(250.0, true).is_err());
+}
+
+#[test]
+fn test_fatal_condition_blocks_print() {
+    let status = status_with_error_info(0x00, 0x10);
+    assert!(status.has_errors());
+
+    let error = status.check_print(10.0, false).unwrap_err();
+    assert_eq!(kind_of(&*error), Some(ErrorKind::Printer));
+    assert_eq!(
+        error.to_string(),
+        "Cannot print due to printer errors: Cover open"
+    );
+}
+
+fn status_packet() -> Vec<u8> {
+    let mut data = vec![0u8; STATUS_SIZE];
+    data[..4].copy_from_slice(&STATUS_HEADER);
+    data[4] = 0x71;
+    data[10] = 12;
+    data
+}
+
+#[test]
+fn test_status_try_from_good_packet() {
+    let data = status_packet();
+    let status = Status::try_from(data.as_slice()).unwrap();
+    assert_eq!(status.raw_data(), data.as_slice());
+    assert_eq!(status.model_code(), 0x71);
+    assert_eq!(status.media_width_mm(), 12);
+}
+
+#[test]
+fn test_status_try_from_short_packet() {
+    let data = status_packet();
+    let error = Status::try_from(&data[..10]).err().unwrap();
+    assert_eq!(
+        error,
+        StatusParseError::Length {
+            len: 10,
+            head: data[..8].to_vec()
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "Invalid status length: expected 32 bytes, got 10 (starting 80 20 42 30 71 00 00 00)"
+    );
+    assert!(matches!(
+        Status::try_from(&[][..]),
+        Err(StatusParseError::Length { len: 0, .. })
+    ));
+}
+
+#[test]
+fn test_status_try_from_corrupted_header() {
+    // Print head mark, size byte and each signature byte
+    for index in 0..4 {
+        let mut data = status_packet();
+        data[index] ^= 0xFF;
+        let error = Status::try_from(data.as_slice()).err().unwrap();
+        assert_eq!(
+            error,
+            StatusParseError::Header {
+                head: data[..8].to_vec()
+            }
+        );
+    }
+
+    // e.g. a wrong OID answering text
+    let error = Status::try_from(&b"Brother PT-P900W series printer."[..])
+        .err()
+        .unwrap();
+    assert_eq!(
+        error.to_string(),
+        "Invalid status header: expected 80 20 42 30, got 42 72 6F 74 68 65 72 20"
+    );
+}
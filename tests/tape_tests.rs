@@ -0,0 +1,153 @@
+use ptouch::element::{Element, QrCode, QrOptions};
+use ptouch::tape::{Tape, TapeMismatch, TapeSpec, diagnose_png_height};
+
+#[test]
+fn test_padding_scales_with_dpi_override() {
+    let native = TapeSpec::new(Tape::TZe12H);
+    let doubled = TapeSpec::new(Tape::TZe12H).with_dpi(720).unwrap();
+
+    // Row padding is 1.4mm in dots
+    assert_eq!(native.mm_to_dots(1.4), 20);
+    assert_eq!(doubled.mm_to_dots(1.4), 40);
+    assert_eq!(doubled.width_dots, native.width_dots);
+}
+
+#[test]
+fn test_margin_dots() {
+    let tapes = [
+        Tape::TZe3H,
+        Tape::TZe6H,
+        Tape::TZe9H,
+        Tape::TZe12H,
+        Tape::TZe18H,
+        Tape::TZe24H,
+        Tape::TZe36H,
+        Tape::TZe3L,
+        Tape::TZe6L,
+        Tape::TZe9L,
+        Tape::TZe12L,
+        Tape::TZe18L,
+        Tape::TZe24L,
+    ];
+    for tape in tapes {
+        let spec = TapeSpec::new(tape);
+        assert_eq!(
+            spec.margin_dots(),
+            (spec.width_dots - spec.inner_dots) / 2,
+            "{}",
+            tape
+        );
+        assert!(
+            2 * spec.margin_dots() + spec.inner_dots <= spec.width_dots,
+            "{}",
+            tape
+        );
+    }
+    assert_eq!(TapeSpec::new(Tape::TZe12H).margin_dots(), 10);
+}
+
+#[test]
+fn test_dpi_override_must_be_positive() {
+    let result = TapeSpec::new(Tape::TZe12H).with_dpi(0);
+    assert!(result.is_err());
+    let error_msg = format!("{}", result.err().unwrap());
+    assert!(error_msg.contains("DPI must be positive"));
+}
+
+#[test]
+fn test_qr_module_size_option() {
+    let default = QrCode::new("example.com".to_string()).unwrap();
+    let doubled = QrCode::with_options(
+        "example.com".to_string(),
+        &QrOptions {
+            module_size: QrOptions::default().module_size * 2.0,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let bbox1 = default.bounding_box().unwrap();
+    let bbox2 = doubled.bounding_box().unwrap();
+    assert_eq!(bbox2.width, bbox1.width * 2.0);
+}
+
+#[test]
+fn test_fit_height() {
+    let fit = |height, dpi| TapeSpec::fit_height(height, dpi).map(|spec| spec.width_mm);
+    assert_eq!(fit(10.0, 360), Some(4));
+    assert_eq!(fit(150.0, 360), Some(12));
+    assert_eq!(fit(151.0, 360), Some(18));
+    assert_eq!(fit(454.0, 360), Some(36));
+    assert_eq!(fit(455.0, 360), None);
+
+    // Tapes of 180DPI printers are considered separately
+    assert_eq!(fit(120.0, 180), Some(24));
+    assert_eq!(fit(129.0, 180), None);
+}
+
+#[test]
+fn test_diagnose_png_height_matrix() {
+    // Every PNG made for a tape against every loaded tape
+    for png in Tape::ALL.map(TapeSpec::new) {
+        for printer in Tape::ALL.map(TapeSpec::new) {
+            let found = match diagnose_png_height(png.width_dots, &printer) {
+                None => {
+                    assert_eq!(png.width_dots, printer.width_dots);
+                    continue;
+                }
+                Some(TapeMismatch::Dpi(found)) => {
+                    assert_eq!(found.width_mm, printer.width_mm);
+                    assert_ne!(found.dpi, printer.dpi);
+                    found
+                }
+                Some(TapeMismatch::Width(found)) => {
+                    assert_ne!(found.width_mm, printer.width_mm);
+                    assert_eq!(found.dpi, printer.dpi);
+                    found
+                }
+                Some(TapeMismatch::WidthAndDpi(found)) => {
+                    assert_ne!(found.width_mm, printer.width_mm);
+                    assert_ne!(found.dpi, printer.dpi);
+                    found
+                }
+                Some(TapeMismatch::Unsupported) => panic!("{} on {}", png, printer),
+            };
+            assert_eq!(found.width_dots, png.width_dots, "{} on {}", png, printer);
+            assert_ne!(png.width_dots, printer.width_dots, "{} on {}", png, printer);
+        }
+    }
+}
+
+#[test]
+fn test_diagnose_png_height_ambiguous() {
+    // 170 dots is 12mm at 360DPI and 24mm at 180DPI
+    let diagnose = |tape| diagnose_png_height(170, &TapeSpec::new(tape));
+    assert!(diagnose(Tape::TZe12H).is_none());
+    assert!(diagnose(Tape::TZe24L).is_none());
+
+    // The same width comes first, then the same DPI
+    assert!(matches!(diagnose(Tape::TZe12L), Some(TapeMismatch::Dpi(png)) if png.dpi == 360));
+    assert!(matches!(
+        diagnose(Tape::TZe24H),
+        Some(TapeMismatch::Dpi(png)) if png.width_mm == 24 && png.dpi == 180
+    ));
+    assert!(matches!(
+        diagnose(Tape::TZe18L),
+        Some(TapeMismatch::Width(png)) if png.width_mm == 24
+    ));
+    assert!(matches!(
+        diagnose(Tape::TZe18H),
+        Some(TapeMismatch::Width(png)) if png.width_mm == 12
+    ));
+
+    // 64 dots exists only at 180DPI
+    let printer = TapeSpec::new(Tape::TZe24H);
+    assert!(matches!(
+        diagnose_png_height(64, &printer),
+        Some(TapeMismatch::WidthAndDpi(png)) if png.width_mm == 9
+    ));
+    assert!(matches!(
+        diagnose_png_height(100, &printer),
+        Some(TapeMismatch::Unsupported)
+    ));
+}
@@ -0,0 +1,152 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use fontdb::Database;
+use ptouch::layout::{LayoutOptions, parse_layout_script};
+use ptouch::template::{
+    Counter, Placeholders, parse_datetime, substitute, substitute_counter, substitute_script,
+};
+use std::sync::Arc;
+
+#[test]
+fn test_counter_plain() {
+    assert_eq!(substitute_counter("PORT-{n}", 1).unwrap(), "PORT-1");
+    assert_eq!(substitute_counter("{n}/{n}", 48).unwrap(), "48/48");
+    assert_eq!(
+        substitute_counter("no placeholder", 3).unwrap(),
+        "no placeholder"
+    );
+}
+
+#[test]
+fn test_counter_zero_padding() {
+    assert_eq!(substitute_counter("PORT-{n:03}", 1).unwrap(), "PORT-001");
+    assert_eq!(substitute_counter("PORT-{n:03}", 48).unwrap(), "PORT-048");
+    assert_eq!(
+        substitute_counter("PORT-{n:03}", 1234).unwrap(),
+        "PORT-1234"
+    );
+    assert_eq!(substitute_counter("PORT-{n:3}", 7).unwrap(), "PORT-  7");
+}
+
+#[test]
+fn test_counter_keeps_other_braces() {
+    assert_eq!(substitute_counter("{name}-{n}", 2).unwrap(), "{name}-2");
+    assert_eq!(substitute_counter("{}", 2).unwrap(), "{}");
+}
+
+#[test]
+fn test_counter_invalid_spec() {
+    assert!(substitute_counter("{n:x}", 1).is_err());
+    assert!(substitute_counter("{n:03", 1).is_err());
+}
+
+#[test]
+fn test_counter_values_custom_step() {
+    let counter = Counter {
+        start: 10,
+        step: 5,
+        count: 4,
+    };
+    assert_eq!(counter.values().collect::<Vec<_>>(), vec![10, 15, 20, 25]);
+
+    let countdown = Counter {
+        start: 3,
+        step: -1,
+        count: 3,
+    };
+    assert_eq!(countdown.values().collect::<Vec<_>>(), vec![3, 2, 1]);
+}
+
+#[test]
+fn test_counter_in_qr_code_payload() {
+    let script = vec!["qrc:https://example.com/port/{n:03}".to_string()];
+    let options = LayoutOptions::new(Arc::new(Database::new()));
+
+    for n in [1, 2] {
+        let values = Placeholders {
+            counter: Some(n),
+            ..Default::default()
+        };
+        let script = substitute_script(&script, &values).unwrap();
+        let element = parse_layout_script(&script, &options).unwrap();
+        assert_eq!(
+            format!("{}", element),
+            format!("QrCode(https://example.com/port/00{})", n)
+        );
+    }
+}
+
+fn fixed_clock() -> Placeholders {
+    Placeholders {
+        counter: None,
+        datetime: NaiveDate::from_ymd_opt(2025, 9, 1)
+            .unwrap()
+            .and_hms_opt(8, 5, 30),
+    }
+}
+
+#[test]
+fn test_date_placeholders() {
+    let values = fixed_clock();
+    assert_eq!(substitute("{date}", &values).unwrap(), "2025-09-01");
+    assert_eq!(substitute("{time}", &values).unwrap(), "08:05");
+    assert_eq!(
+        substitute("{datetime}", &values).unwrap(),
+        "2025-09-01 08:05"
+    );
+    assert_eq!(
+        substitute("Opened: {date:%Y/%m/%d %H:%M:%S}", &values).unwrap(),
+        "Opened: 2025/09/01 08:05:30"
+    );
+}
+
+#[test]
+fn test_date_placeholder_invalid_format() {
+    assert!(substitute("{date:%Q}", &fixed_clock()).is_err());
+}
+
+#[test]
+fn test_placeholders_without_values_are_kept() {
+    let values = Placeholders::default();
+    assert_eq!(substitute("{n} {date}", &values).unwrap(), "{n} {date}");
+}
+
+#[test]
+fn test_escaped_braces() {
+    let values = Placeholders {
+        counter: Some(3),
+        ..fixed_clock()
+    };
+    assert_eq!(substitute("{{date}}", &values).unwrap(), "{date}");
+    assert_eq!(substitute("{{{n}}}", &values).unwrap(), "{3}");
+    assert_eq!(substitute("a{{b", &values).unwrap(), "a{b");
+}
+
+#[test]
+fn test_date_and_counter_in_one_pass() {
+    let values = Placeholders {
+        counter: Some(12),
+        ..fixed_clock()
+    };
+    assert_eq!(
+        substitute("qrc:{date:%Y%m%d}-{n:04}", &values).unwrap(),
+        "qrc:20250901-0012"
+    );
+}
+
+#[test]
+fn test_parse_datetime() {
+    let expect = |s: &str| -> NaiveDateTime { s.parse().unwrap() };
+    assert_eq!(
+        parse_datetime("2025-09-01").unwrap(),
+        expect("2025-09-01T00:00:00")
+    );
+    assert_eq!(
+        parse_datetime("2025-09-01T08:05").unwrap(),
+        expect("2025-09-01T08:05:00")
+    );
+    assert_eq!(
+        parse_datetime("2025-09-01 08:05:30").unwrap(),
+        expect("2025-09-01T08:05:30")
+    );
+    assert!(parse_datetime("09/01/2025").is_err());
+}